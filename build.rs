@@ -1,4 +1,6 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // the generated code is only needed by the binaries
+    #[cfg(feature = "binaries")]
     tonic_build::configure()
         .build_server(true)
         .out_dir("src/")
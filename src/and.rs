@@ -0,0 +1,122 @@
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// Answers for two independent secrets under one shared challenge,
+/// x1 behind y1 = alpha^x1 and x2 behind y2 = beta^x2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AndProof {
+    pub s1: BigUint,
+    pub s2: BigUint,
+}
+
+impl ZKP {
+    /// AND of two Schnorr proofs, unlike `solve` the secrets don't have to match:
+    /// s1 = k1 - c * x1 mod q
+    /// s2 = k2 - c * x2 mod q
+    pub fn prove_and(
+        &self,
+        k1: &BigUint,
+        k2: &BigUint,
+        c: &BigUint,
+        x1: &BigUint,
+        x2: &BigUint,
+    ) -> AndProof {
+        AndProof {
+            s1: self.solve(k1, c, x1),
+            s2: self.solve(k2, c, x2),
+        }
+    }
+
+    /// r1 = alpha^s1 * y1^c
+    /// r2 = beta^s2 * y2^c
+    pub fn verify_and(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        proof: &AndProof,
+    ) -> bool {
+        let AndProof { s1, s2 } = proof;
+        if self.validate_challenge(c).is_err() || *s1 >= self.q || *s2 >= self.q {
+            return false;
+        }
+
+        let sol1 = ZKP::exponentiate(&self.alpha, s1, &self.p) * ZKP::exponentiate(y1, c, &self.p)
+            % &self.p;
+        let sol2 = ZKP::exponentiate(&self.beta, s2, &self.p) * ZKP::exponentiate(y2, c, &self.p)
+            % &self.p;
+        *r1 == sol1 && *r2 == sol2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_and_toy_group() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let [x1, x2, k1, k2, c] = [6u32, 3, 7, 2, 4].map(BigUint::from);
+
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x1, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x2, &zkp.p);
+        assert_eq!((&y1, &y2), (&BigUint::from(2u32), &BigUint::from(16u32)));
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k1, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k2, &zkp.p);
+        assert_eq!((&r1, &r2), (&BigUint::from(8u32), &BigUint::from(12u32)));
+
+        let proof = zkp.prove_and(&k1, &k2, &c, &x1, &x2);
+        assert_eq!(
+            proof,
+            AndProof {
+                s1: BigUint::from(5u32),
+                s2: BigUint::from(1u32),
+            }
+        );
+        assert!(zkp.verify_and(&r1, &r2, &y1, &y2, &c, &proof));
+
+        // the equality proof can't vouch for two different secrets
+        for s in [&proof.s1, &proof.s2] {
+            assert!(!zkp.verify(&r1, &r2, &y1, &y2, &c, s));
+        }
+
+        let swapped = AndProof {
+            s1: proof.s2.clone(),
+            s2: proof.s1.clone(),
+        };
+        assert!(!zkp.verify_and(&r1, &r2, &y1, &y2, &c, &swapped));
+        let other_c = BigUint::from(5u32);
+        assert!(!zkp.verify_and(&r1, &r2, &y1, &y2, &other_c, &proof));
+        let wrapped = AndProof {
+            s1: &proof.s1 + &zkp.q,
+            ..proof.clone()
+        };
+        assert!(!zkp.verify_and(&r1, &r2, &y1, &y2, &c, &wrapped));
+    }
+
+    #[test]
+    fn test_and_random_secrets() {
+        let zkp = ZKP::new();
+        let [x1, x2, k1, k2] = [(); 4].map(|_| ZKP::generate_random_below(&zkp.q));
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x1, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x2, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k1, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k2, &zkp.p);
+        let c = ZKP::generate_random_below(&zkp.q);
+
+        let proof = zkp.prove_and(&k1, &k2, &c, &x1, &x2);
+        assert!(zkp.verify_and(&r1, &r2, &y1, &y2, &c, &proof));
+
+        // knowing only one of the secrets isn't enough
+        let half = zkp.prove_and(&k1, &k2, &c, &x1, &(&x2 + 1u32));
+        assert!(!zkp.verify_and(&r1, &r2, &y1, &y2, &c, &half));
+    }
+}
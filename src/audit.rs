@@ -0,0 +1,111 @@
+use std::{
+    fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Security relevant event reported by the server, e.g. for an IDS or fail2ban
+/// to block whoever keeps failing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditRecord {
+    /// An answer to a challenge was rejected
+    AuthFailure {
+        at: SystemTime,
+        /// Where the request came from, unknown for in-process calls
+        peer: Option<IpAddr>,
+        /// Empty when the auth_id didn't lead to a user
+        user: String,
+        /// Reason sent back to the client, e.g. INVALID_PROOF
+        reason: String,
+    },
+}
+
+impl fmt::Display for AuditRecord {
+    /// One line of key=value pairs, the user name is quoted so it can't forge fields
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditRecord::AuthFailure {
+                at,
+                peer,
+                user,
+                reason,
+            } => {
+                let at = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                let peer = peer.map_or("-".to_string(), |peer| peer.to_string());
+                write!(
+                    f,
+                    "auth_failure at={} peer={} user={:?} reason={}",
+                    at, peer, user, reason
+                )
+            }
+        }
+    }
+}
+
+/// Where the server sends its audit records.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, record: AuditRecord);
+}
+
+/// Drops every record.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoAudit;
+
+impl AuditSink for NoAudit {
+    fn record(&self, _: AuditRecord) {}
+}
+
+/// Writes each record as a line on stderr.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StderrAudit;
+
+impl AuditSink for StderrAudit {
+    fn record(&self, record: AuditRecord) {
+        eprintln!("{}", record);
+    }
+}
+
+/// Keeps records in memory. Clones share them, so a test can keep one and hand
+/// the copy to the server.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryAudit {
+    records: Arc<Mutex<Vec<AuditRecord>>>,
+}
+
+impl MemoryAudit {
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl AuditSink for MemoryAudit {
+    fn record(&self, record: AuditRecord) {
+        self.records.lock().unwrap().push(record);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_auth_failure_line() {
+        let audit = MemoryAudit::default();
+        let handed_out = audit.clone();
+        handed_out.record(AuditRecord::AuthFailure {
+            at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            peer: Some(IpAddr::from([203, 0, 113, 7])),
+            user: "peggy reason=OK".to_string(),
+            reason: "INVALID_PROOF".to_string(),
+        });
+
+        let records = audit.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].to_string(),
+            "auth_failure at=1700000000 peer=203.0.113.7 user=\"peggy reason=OK\" reason=INVALID_PROOF"
+        );
+    }
+}
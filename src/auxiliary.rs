@@ -0,0 +1,214 @@
+use num_bigint::BigUint;
+use sha2::Sha256;
+
+use crate::{VerifyError, ZKP};
+
+/// Put before C and r3 in the challenge, apart from the user names hashed into
+/// proofs of possession
+const LINK_LABEL: &[u8] = b"zkp-chaum-pedersen/auxiliary";
+
+/// Auxiliary Pedersen commitment C = `commit_key(x, r)` carried next to a
+/// Chaum-Pedersen proof, with what links it to the proven x: r3 = alpha^k * h^m
+/// sent with r1 and r2, k their nonce and m a fresh one, and the answer
+/// u = m - c * r mod q sent with s.
+///
+/// As s answers r1, r2 and r3 at once, the x inside C is the x behind y1 and
+/// y2, so a larger protocol can go on from C knowing it holds the
+/// authenticated secret. That only holds if C and r3 were fixed before c: with
+/// c in hand anyone can pick s and u and solve r3 = alpha^s * h^u * C^c for any
+/// C. So c is the Fiat-Shamir hash of the whole transcript, C and r3 included,
+/// see `ZKP::linked_challenge`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Auxiliary {
+    pub commitment: BigUint,
+    pub r3: BigUint,
+    pub u: BigUint,
+}
+
+impl ZKP {
+    /// r3 = alpha^k * h^m mod p, for the k behind r1 and r2. The answer is
+    /// u = `solve(m, c, r)`.
+    pub fn commit_auxiliary(&self, k: &BigUint, m: &BigUint) -> BigUint {
+        self.commit_key(k, m)
+    }
+
+    /// c = H(p, q, alpha, beta, y1, y2, r1, r2, C, r3) mod q, with SHA-256 as
+    /// in `fiat_shamir_challenge`
+    pub fn linked_challenge(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        r1: &BigUint,
+        r2: &BigUint,
+        commitment: &BigUint,
+        r3: &BigUint,
+    ) -> BigUint {
+        let mut label = LINK_LABEL.to_vec();
+        for n in [commitment, r3] {
+            let bytes = n.to_bytes_be();
+            label.extend((bytes.len() as u32).to_be_bytes());
+            label.extend(bytes);
+        }
+        self.labelled_challenge::<Sha256>(y1, y2, r1, r2, Some(&label))
+    }
+
+    /// c is `linked_challenge`, `verify_checked` holds, and r3 = alpha^s * h^u
+    /// * C^c with the same c and s
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_linked(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+        auxiliary: &Auxiliary,
+    ) -> Result<(), VerifyError> {
+        self.check_linked_challenge(r1, r2, y1, y2, c, auxiliary)?;
+        self.verify_checked(r1, r2, y1, y2, c, s)?;
+        self.check_link(c, s, auxiliary)
+    }
+
+    pub(crate) fn check_linked_challenge(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        auxiliary: &Auxiliary,
+    ) -> Result<(), VerifyError> {
+        let expected = self.linked_challenge(y1, y2, r1, r2, &auxiliary.commitment, &auxiliary.r3);
+        if *c != expected {
+            return Err(VerifyError::ChallengeMismatch);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_link(
+        &self,
+        c: &BigUint,
+        s: &BigUint,
+        auxiliary: &Auxiliary,
+    ) -> Result<(), VerifyError> {
+        let Auxiliary { commitment, r3, u } = auxiliary;
+        // C is the prover's choice, outside the subgroup it could hide a
+        // component that isn't a power of alpha or h
+        for (name, element) in [("commitment", commitment), ("r3", r3)] {
+            if !self.is_in_subgroup(element) {
+                return Err(VerifyError::NotInSubgroup(name));
+            }
+        }
+        if *u >= self.q {
+            return Err(VerifyError::OutOfRange("u"));
+        }
+        let expected = self.commit_key(s, u) * ZKP::exponentiate(commitment, c, &self.p) % &self.p;
+        if expected != *r3 {
+            return Err(VerifyError::AuxiliaryMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Gate, Verifier};
+
+    /// Proof for y = (alpha^x, beta^x) carrying `commit_key(committed, r)`,
+    /// answered with x
+    fn linked_proof(zkp: &ZKP, x: &BigUint, committed: &BigUint) -> ([BigUint; 6], Auxiliary) {
+        let [k, m, r] = [(); 3].map(|_| ZKP::generate_random_below(&zkp.q));
+        let (r1, r2) = (
+            ZKP::exponentiate(&zkp.alpha, &k, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &k, &zkp.p),
+        );
+        let (y1, y2) = (
+            ZKP::exponentiate(&zkp.alpha, x, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, x, &zkp.p),
+        );
+        let commitment = zkp.commit_key(committed, &r);
+        let r3 = zkp.commit_auxiliary(&k, &m);
+        let c = zkp.linked_challenge(&y1, &y2, &r1, &r2, &commitment, &r3);
+        let auxiliary = Auxiliary {
+            commitment,
+            r3,
+            u: zkp.solve(&m, &c, &r),
+        };
+        let s = zkp.solve(&k, &c, x);
+        ([r1, r2, y1, y2, c, s], auxiliary)
+    }
+
+    #[test]
+    fn test_auxiliary_commitment_is_linked_to_the_secret() {
+        // a toy group would let a mismatch through one time in q
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+
+        let ([r1, r2, y1, y2, c, s], auxiliary) = linked_proof(&zkp, &x, &x);
+        assert_eq!(
+            zkp.verify_linked(&r1, &r2, &y1, &y2, &c, &s, &auxiliary),
+            Ok(())
+        );
+        let verifier = Verifier::new(ZKP::new());
+        assert_eq!(
+            verifier.verify_linked(&r1, &r2, &y1, &y2, &c, &s, &auxiliary),
+            Ok(())
+        );
+
+        // a commitment to another value, or swapped in from another proof
+        let other = (&x + 1u32) % &zkp.q;
+        let ([r1, r2, y1, y2, c, s], mismatched) = linked_proof(&zkp, &x, &other);
+        assert_eq!(zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+        assert_eq!(
+            zkp.verify_linked(&r1, &r2, &y1, &y2, &c, &s, &mismatched),
+            Err(VerifyError::AuxiliaryMismatch)
+        );
+        // swapped in from another proof, c no longer hashes it
+        let swapped = Auxiliary {
+            commitment: zkp.commit_key(&x, &ZKP::generate_random_below(&zkp.q)),
+            ..auxiliary
+        };
+        assert_eq!(
+            verifier.verify_linked(&r1, &r2, &y1, &y2, &c, &s, &swapped),
+            Err(VerifyError::ChallengeMismatch)
+        );
+
+        let outside = Auxiliary {
+            commitment: &zkp.p - 1u32,
+            ..swapped
+        };
+        let c = zkp.linked_challenge(&y1, &y2, &r1, &r2, &outside.commitment, &outside.r3);
+        assert_eq!(
+            zkp.check_link(&c, &s, &outside),
+            Err(VerifyError::NotInSubgroup("commitment"))
+        );
+    }
+
+    #[test]
+    fn test_r3_built_after_the_challenge_is_rejected() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let ([r1, r2, y1, y2, c, s], _) = linked_proof(&zkp, &x, &x);
+
+        // knowing c, a C committing to anything gets an r3 solved to match it
+        let other = (&x + 1u32) % &zkp.q;
+        let commitment = zkp.commit_key(&other, &ZKP::generate_random_below(&zkp.q));
+        let u = ZKP::generate_random_below(&zkp.q);
+        let r3 = zkp.commit_key(&s, &u) * ZKP::exponentiate(&commitment, &c, &zkp.p) % &zkp.p;
+        let forged = Auxiliary { commitment, r3, u };
+        assert_eq!(zkp.check_link(&c, &s, &forged), Ok(()));
+
+        assert_eq!(
+            zkp.verify_linked(&r1, &r2, &y1, &y2, &c, &s, &forged),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        let verifier = Verifier::new(ZKP::new());
+        assert_eq!(
+            verifier.verify_linked(&r1, &r2, &y1, &y2, &c, &s, &forged),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        assert_eq!(verifier.rejections().get(Gate::Equation), 1);
+    }
+}
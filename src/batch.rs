@@ -0,0 +1,304 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use digest::Digest;
+use num_bigint::BigUint;
+use sha2::Sha256;
+
+use crate::ZKP;
+
+const CHECKPOINT_HEADER: &str = "zkp-batch-checkpoint v1";
+
+/// One proof of a batch, checked with `verify_checked`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchItem {
+    pub r1: BigUint,
+    pub r2: BigUint,
+    pub y1: BigUint,
+    pub y2: BigUint,
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+/// Why a checkpoint couldn't be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckpointError {
+    Io(String),
+    /// Not something written by `BatchVerifier::checkpoint`, from this line on
+    Corrupt(usize),
+    /// Written for the group with this descriptor
+    WrongGroup(String),
+    /// The proofs before the checkpoint aren't the ones it was written for
+    BatchMismatch,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(reason) => write!(f, "could not read the checkpoint: {}", reason),
+            CheckpointError::Corrupt(line) => write!(f, "corrupt checkpoint line {}", line),
+            CheckpointError::WrongGroup(group) => {
+                write!(f, "the checkpoint is for the group {}", group)
+            }
+            CheckpointError::BatchMismatch => {
+                f.write_str("the checkpoint was written for another batch")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err.to_string())
+    }
+}
+
+/// Verifies a large batch a slice at a time, keeping the outcome of every proof
+/// checked so far. `checkpoint` writes that progress out and `resume` picks it
+/// up again, so an interrupted run only redoes the proofs checked after the
+/// last checkpoint.
+///
+/// A checkpoint holds the results and a SHA-256 of the proofs they are for, a
+/// resumed run refuses a batch that doesn't start with the same proofs.
+pub struct BatchVerifier {
+    zkp: ZKP,
+    results: Vec<bool>,
+    /// Hash of the proofs in `results`
+    hasher: Sha256,
+    /// Hash read from a checkpoint, compared with the batch on the next call
+    expected: Option<Vec<u8>>,
+}
+
+impl BatchVerifier {
+    pub fn new(zkp: ZKP) -> Self {
+        BatchVerifier {
+            zkp,
+            results: Vec::new(),
+            hasher: Sha256::new(),
+            expected: None,
+        }
+    }
+
+    /// Continues from a checkpoint written for the same group
+    pub fn resume(zkp: ZKP, mut checkpoint: impl Read) -> Result<Self, CheckpointError> {
+        let mut text = String::new();
+        checkpoint.read_to_string(&mut text)?;
+        let mut lines = text.lines();
+        if lines.next() != Some(CHECKPOINT_HEADER) {
+            return Err(CheckpointError::Corrupt(1));
+        }
+        let mut field = |line: usize, name: &str| {
+            lines
+                .next()
+                .and_then(|text| text.strip_prefix(name))
+                .and_then(|text| text.strip_prefix(' '))
+                .ok_or(CheckpointError::Corrupt(line))
+        };
+        let group = field(2, "group")?;
+        if group != zkp.descriptor() {
+            return Err(CheckpointError::WrongGroup(group.to_string()));
+        }
+        let results = field(3, "results")?
+            .chars()
+            .map(|result| match result {
+                '1' => Ok(true),
+                '0' => Ok(false),
+                _ => Err(CheckpointError::Corrupt(3)),
+            })
+            .collect::<Result<_, _>>()?;
+        let digest = field(4, "digest")?;
+        let expected = (0..digest.len())
+            .step_by(2)
+            .map(|i| {
+                digest
+                    .get(i..i + 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .filter(|expected| expected.len() == 32)
+            .ok_or(CheckpointError::Corrupt(4))?;
+        Ok(BatchVerifier {
+            zkp,
+            results,
+            hasher: Sha256::new(),
+            expected: Some(expected),
+        })
+    }
+
+    /// Checks up to `count` of the proofs in `batch` not checked yet, returning
+    /// how many it checked. `batch` is the whole batch every time.
+    pub fn verify_next(
+        &mut self,
+        batch: &[BatchItem],
+        count: usize,
+    ) -> Result<usize, CheckpointError> {
+        let done = self.results.len();
+        if let Some(expected) = self.expected.take() {
+            let checked = batch.get(..done).ok_or(CheckpointError::BatchMismatch)?;
+            checked.iter().for_each(|item| self.hash(item));
+            if self.hasher.clone().finalize().as_slice() != expected {
+                self.expected = Some(expected);
+                self.hasher = Sha256::new();
+                return Err(CheckpointError::BatchMismatch);
+            }
+        }
+        let next = &batch[done.min(batch.len())..];
+        let next = &next[..count.min(next.len())];
+        for item in next {
+            let BatchItem {
+                r1,
+                r2,
+                y1,
+                y2,
+                c,
+                s,
+            } = item;
+            let valid = self.zkp.verify_checked(r1, r2, y1, y2, c, s).is_ok();
+            self.results.push(valid);
+            self.hash(item);
+        }
+        Ok(next.len())
+    }
+
+    fn hash(&mut self, item: &BatchItem) {
+        for n in [&item.r1, &item.r2, &item.y1, &item.y2, &item.c, &item.s] {
+            let bytes = n.to_bytes_be();
+            self.hasher.update((bytes.len() as u32).to_be_bytes());
+            self.hasher.update(bytes);
+        }
+    }
+
+    /// Writes the progress so far, for `resume`
+    pub fn checkpoint(&self, mut writer: impl Write) -> io::Result<()> {
+        let digest = match &self.expected {
+            Some(expected) => expected.clone(),
+            None => self.hasher.clone().finalize().to_vec(),
+        };
+        let results: String = self
+            .results
+            .iter()
+            .map(|valid| if *valid { '1' } else { '0' })
+            .collect();
+        let digest: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        write!(
+            writer,
+            "{}\ngroup {}\nresults {}\ndigest {}\n",
+            CHECKPOINT_HEADER,
+            self.zkp.descriptor(),
+            results,
+            digest
+        )?;
+        writer.flush()
+    }
+
+    /// Outcome of each proof checked so far, in batch order
+    pub fn results(&self) -> &[bool] {
+        &self.results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn toy_zkp() -> ZKP {
+        ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        }
+    }
+
+    /// Every third proof has a wrong s
+    fn batch(zkp: &ZKP, len: u32) -> Vec<BatchItem> {
+        (0..len)
+            .map(|i| {
+                let (x, k, c) = (
+                    BigUint::from(i % 11),
+                    BigUint::from(7u32),
+                    BigUint::from(i % 5),
+                );
+                let s = zkp.solve(&k, &c, &x);
+                BatchItem {
+                    r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p),
+                    r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p),
+                    y1: ZKP::exponentiate(&zkp.alpha, &x, &zkp.p),
+                    y2: ZKP::exponentiate(&zkp.beta, &x, &zkp.p),
+                    s: if i % 3 == 2 { (s + 1u32) % &zkp.q } else { s },
+                    c,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resumed_batch_matches_a_single_pass() {
+        let batch = batch(&toy_zkp(), 20);
+        let mut single = BatchVerifier::new(toy_zkp());
+        assert_eq!(single.verify_next(&batch, usize::MAX), Ok(20));
+
+        // interrupted after 7, then after 7 more, with a checkpoint each time
+        let mut checkpoint = Vec::new();
+        let mut first = BatchVerifier::new(toy_zkp());
+        assert_eq!(first.verify_next(&batch, 7), Ok(7));
+        first.checkpoint(&mut checkpoint).unwrap();
+        drop(first);
+
+        let mut second = BatchVerifier::resume(toy_zkp(), checkpoint.as_slice()).unwrap();
+        assert_eq!(second.verify_next(&batch, 7), Ok(7));
+        checkpoint.clear();
+        second.checkpoint(&mut checkpoint).unwrap();
+
+        let mut third = BatchVerifier::resume(toy_zkp(), checkpoint.as_slice()).unwrap();
+        assert_eq!(third.verify_next(&batch, usize::MAX), Ok(6));
+        assert_eq!(third.verify_next(&batch, usize::MAX), Ok(0));
+        assert_eq!(third.results(), single.results());
+        assert_eq!(single.results().iter().filter(|valid| !**valid).count(), 6);
+
+        // the same checkpoint for a batch that starts differently
+        let mut other = batch.clone();
+        other.swap(0, 1);
+        let mut resumed = BatchVerifier::resume(toy_zkp(), checkpoint.as_slice()).unwrap();
+        assert_eq!(
+            resumed.verify_next(&other, 1),
+            Err(CheckpointError::BatchMismatch)
+        );
+        assert_eq!(
+            resumed.verify_next(&batch[..3], 1),
+            Err(CheckpointError::BatchMismatch)
+        );
+        assert_eq!(resumed.verify_next(&batch, 1), Ok(1));
+    }
+
+    #[test]
+    fn test_bad_checkpoints_are_refused() {
+        let mut checkpoint = Vec::new();
+        BatchVerifier::new(toy_zkp())
+            .checkpoint(&mut checkpoint)
+            .unwrap();
+        let text = String::from_utf8(checkpoint).unwrap();
+
+        assert!(BatchVerifier::resume(toy_zkp(), text.as_bytes()).is_ok());
+        assert!(matches!(
+            BatchVerifier::resume(ZKP::new(), text.as_bytes()),
+            Err(CheckpointError::WrongGroup(_))
+        ));
+        let cases = [
+            (text.replacen("v1", "v2", 1), 1),
+            (text.replacen("results ", "results 2", 1), 3),
+            (text.replacen("digest ", "digest 0", 1), 4),
+            (text.lines().take(3).collect::<Vec<_>>().join("\n"), 4),
+        ];
+        for (bad, line) in cases {
+            assert_eq!(
+                BatchVerifier::resume(toy_zkp(), bad.as_bytes()).err(),
+                Some(CheckpointError::Corrupt(line))
+            );
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use digest::Digest;
+use num_bigint::BigUint;
+use num_traits::One;
+use sha2::Sha256;
+
+use crate::ZKP;
+
+/// A registered key rerandomized for one session: (alpha^t, beta^t, y1^t, y2^t)
+/// for a fresh t. Under DDH it looks random, two blindings of one key can't be
+/// told apart from blindings of two keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindedKey {
+    pub a1: BigUint,
+    pub a2: BigUint,
+    pub b1: BigUint,
+    pub b2: BigUint,
+}
+
+/// Proof that the prover knows x with b1 = a1^x and b2 = a2^x, and that the
+/// blinded key is a blinding of one of the keys in a ring without saying which.
+///
+/// The second part is a Cramer-Damgard-Schoenmakers OR of one statement per
+/// ring key, log_alpha a1 = log_beta a2 = log_y1 b1 = log_y2 b2, with a
+/// challenge and answer each. All challenges come from one Fiat-Shamir hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindedProof {
+    pub key: BlindedKey,
+    pub c: BigUint,
+    pub s: BigUint,
+    pub ring_c: Vec<BigUint>,
+    pub ring_s: Vec<BigUint>,
+}
+
+impl ZKP {
+    /// Blinds the prover's key, which must be `ring[index]`, and proves it.
+    /// `context` is hashed in, e.g. a nonce from the verifier so the proof
+    /// can't be replayed in another session.
+    pub fn prove_blinded(
+        &self,
+        x: &BigUint,
+        ring: &[(BigUint, BigUint)],
+        index: usize,
+        context: &[u8],
+    ) -> BlindedProof {
+        assert!(index < ring.len(), "the prover's key must be in the ring");
+        let t = ZKP::generate_random_below(&(&self.q - 1u32)) + 1u32;
+        let key = BlindedKey {
+            a1: ZKP::exponentiate(&self.alpha, &t, &self.p),
+            a2: ZKP::exponentiate(&self.beta, &t, &self.p),
+            b1: ZKP::exponentiate(&ring[index].0, &t, &self.p),
+            b2: ZKP::exponentiate(&ring[index].1, &t, &self.p),
+        };
+
+        let k = ZKP::generate_random_below(&self.q);
+        let r1 = ZKP::exponentiate(&key.a1, &k, &self.p);
+        let r2 = ZKP::exponentiate(&key.a2, &k, &self.p);
+
+        // every other member's statement is simulated from a chosen (c_i, s_i)
+        let w = ZKP::generate_random_below(&self.q);
+        let mut ring_c = Vec::with_capacity(ring.len());
+        let mut ring_s = Vec::with_capacity(ring.len());
+        let mut commitments = Vec::with_capacity(ring.len());
+        for (i, member) in ring.iter().enumerate() {
+            if i == index {
+                ring_c.push(BigUint::default());
+                ring_s.push(BigUint::default());
+                commitments.push([
+                    ZKP::exponentiate(&self.alpha, &w, &self.p),
+                    ZKP::exponentiate(&self.beta, &w, &self.p),
+                    ZKP::exponentiate(&member.0, &w, &self.p),
+                    ZKP::exponentiate(&member.1, &w, &self.p),
+                ]);
+            } else {
+                let c_i = ZKP::generate_random_below(&self.q);
+                let s_i = ZKP::generate_random_below(&self.q);
+                commitments.push(self.ring_commitment(member, &key, &c_i, &s_i));
+                ring_c.push(c_i);
+                ring_s.push(s_i);
+            }
+        }
+
+        let c = self.blinded_challenge(ring, &key, &r1, &r2, &commitments, context);
+        let others = ring_c.iter().fold(BigUint::default(), |sum, c_i| sum + c_i);
+        ring_c[index] = (&c + &self.q - others % &self.q) % &self.q;
+        ring_s[index] = self.solve(&w, &ring_c[index], &t);
+        BlindedProof {
+            s: self.solve(&k, &c, x),
+            key,
+            c,
+            ring_c,
+            ring_s,
+        }
+    }
+
+    /// r1 = a1^s * b1^c, r2 = a2^s * b2^c, every ring commitment rebuilt from
+    /// its (c_i, s_i), the c_i summing to c mod q and c the hash of it all
+    pub fn verify_blinded(
+        &self,
+        ring: &[(BigUint, BigUint)],
+        proof: &BlindedProof,
+        context: &[u8],
+    ) -> bool {
+        let BlindedProof {
+            key,
+            c,
+            s,
+            ring_c,
+            ring_s,
+        } = proof;
+        if ring.is_empty() || ring_c.len() != ring.len() || ring_s.len() != ring.len() {
+            return false;
+        }
+        let challenges = std::iter::once(c).chain(ring_c);
+        if challenges
+            .into_iter()
+            .any(|c| self.validate_challenge(c).is_err())
+        {
+            return false;
+        }
+        if std::iter::once(s).chain(ring_s).any(|s| *s >= self.q) {
+            return false;
+        }
+        // a1 = 1 would be t = 0, and the blinded key the identity
+        let elements = [&key.a1, &key.a2, &key.b1, &key.b2];
+        if elements
+            .iter()
+            .any(|n| n.is_one() || !self.is_in_subgroup(n))
+        {
+            return false;
+        }
+        let sum = ring_c.iter().fold(BigUint::default(), |sum, c_i| sum + c_i);
+        if sum % &self.q != *c {
+            return false;
+        }
+
+        let r1 = ZKP::exponentiate(&key.a1, s, &self.p) * ZKP::exponentiate(&key.b1, c, &self.p)
+            % &self.p;
+        let r2 = ZKP::exponentiate(&key.a2, s, &self.p) * ZKP::exponentiate(&key.b2, c, &self.p)
+            % &self.p;
+        let commitments: Vec<[BigUint; 4]> = ring
+            .iter()
+            .zip(ring_c.iter().zip(ring_s))
+            .map(|(member, (c_i, s_i))| self.ring_commitment(member, key, c_i, s_i))
+            .collect();
+        self.blinded_challenge(ring, key, &r1, &r2, &commitments, context) == *c
+    }
+
+    /// What an honest commitment of one ring statement must have been for
+    /// the answer s_i to challenge c_i
+    fn ring_commitment(
+        &self,
+        (y1, y2): &(BigUint, BigUint),
+        key: &BlindedKey,
+        c_i: &BigUint,
+        s_i: &BigUint,
+    ) -> [BigUint; 4] {
+        let pair = |base: &BigUint, power: &BigUint| {
+            ZKP::exponentiate(base, s_i, &self.p) * ZKP::exponentiate(power, c_i, &self.p) % &self.p
+        };
+        [
+            pair(&self.alpha, &key.a1),
+            pair(&self.beta, &key.a2),
+            pair(y1, &key.b1),
+            pair(y2, &key.b2),
+        ]
+    }
+
+    /// SHA-256 of the group, ring, blinded key, every commitment and the
+    /// context, each length-prefixed, mod q
+    fn blinded_challenge(
+        &self,
+        ring: &[(BigUint, BigUint)],
+        key: &BlindedKey,
+        r1: &BigUint,
+        r2: &BigUint,
+        commitments: &[[BigUint; 4]],
+        context: &[u8],
+    ) -> BigUint {
+        let mut hasher = Sha256::new();
+        let mut update = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(bytes);
+        };
+        for n in [&self.p, &self.q, &self.alpha, &self.beta] {
+            update(&n.to_bytes_be());
+        }
+        update(&(ring.len() as u32).to_be_bytes());
+        for (y1, y2) in ring {
+            update(&y1.to_bytes_be());
+            update(&y2.to_bytes_be());
+        }
+        for n in [&key.a1, &key.a2, &key.b1, &key.b2, r1, r2] {
+            update(&n.to_bytes_be());
+        }
+        for n in commitments.iter().flatten() {
+            update(&n.to_bytes_be());
+        }
+        update(context);
+        BigUint::from_bytes_be(&hasher.finalize()) % &self.q
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Group;
+
+    fn key(zkp: &ZKP, x: &BigUint) -> (BigUint, BigUint) {
+        (
+            ZKP::exponentiate(&zkp.alpha, x, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, x, &zkp.p),
+        )
+    }
+
+    #[test]
+    fn test_blinded_sessions_are_unlinkable_and_verify() {
+        let zkp = Group::Rfc5114_1024_160.zkp();
+        let secrets: Vec<BigUint> = (0..3).map(|_| ZKP::generate_random_below(&zkp.q)).collect();
+        let ring: Vec<_> = secrets.iter().map(|x| key(&zkp, x)).collect();
+
+        let first = zkp.prove_blinded(&secrets[1], &ring, 1, b"session 1");
+        let second = zkp.prove_blinded(&secrets[1], &ring, 1, b"session 2");
+        assert!(zkp.verify_blinded(&ring, &first, b"session 1"));
+        assert!(zkp.verify_blinded(&ring, &second, b"session 2"));
+
+        // nothing the two sessions show repeats, nor is the registered key
+        let shown = |proof: &BlindedProof| {
+            let BlindedKey { a1, a2, b1, b2 } = proof.key.clone();
+            [a1, a2, b1, b2]
+        };
+        for element in shown(&first) {
+            assert!(!shown(&second).contains(&element));
+            assert!(!ring.iter().any(|(y1, y2)| *y1 == element || *y2 == element));
+        }
+
+        // bound to its session
+        assert!(!zkp.verify_blinded(&ring, &first, b"session 2"));
+    }
+
+    #[test]
+    fn test_blinded_proof_needs_a_ring_secret() {
+        // a toy group would let a bad proof through one time in q
+        let zkp = ZKP::new();
+        let ring = vec![key(&zkp, &3u32.into()), key(&zkp, &6u32.into())];
+        let proof = zkp.prove_blinded(&6u32.into(), &ring, 1, b"");
+        assert!(zkp.verify_blinded(&ring, &proof, b""));
+
+        // a secret whose key isn't where it claims to be
+        let outsider = zkp.prove_blinded(&7u32.into(), &ring, 0, b"");
+        assert!(!zkp.verify_blinded(&ring, &outsider, b""));
+
+        // a ring that doesn't hold the key
+        let other_ring = vec![ring[0].clone(), key(&zkp, &5u32.into())];
+        assert!(!zkp.verify_blinded(&other_ring, &proof, b""));
+
+        let mut tampered = proof.clone();
+        tampered.ring_c.swap(0, 1);
+        assert!(!zkp.verify_blinded(&ring, &tampered, b""));
+        let mut truncated = proof;
+        truncated.ring_s.pop();
+        assert!(!zkp.verify_blinded(&ring, &truncated, b""));
+    }
+}
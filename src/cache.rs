@@ -0,0 +1,179 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use num_bigint::BigUint;
+
+use crate::{Reducer, Reduction, VerifyError, ZKP};
+
+/// Entries a `Verifier` built with `with_power_cache` keeps by default
+pub const DEFAULT_POWER_CACHE_SIZE: usize = 256;
+
+/// Least recently used cache of (alpha^s, beta^s) keyed by s, so a retried
+/// answer skips two of the four exponentiations.
+///
+/// The cached powers only depend on s and the group, which are both public, so
+/// a hit gives the same outcome as recomputing and never stands in for any check
+/// the caller makes on the auth_id or challenge. A cache must only be used with
+/// the group it was first filled for.
+pub struct PowerCache {
+    capacity: usize,
+    inner: Mutex<Lru>,
+}
+
+#[derive(Default)]
+struct Lru {
+    /// Bumped on every lookup, entries remember the tick of their last use
+    tick: u64,
+    entries: HashMap<BigUint, (BigUint, BigUint, u64)>,
+}
+
+impl PowerCache {
+    /// Keeps at most `capacity` values of s, a capacity of 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        PowerCache {
+            capacity,
+            inner: Mutex::new(Lru::default()),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets every entry, e.g. when the group changes
+    pub fn clear(&self) {
+        let lru = &mut *self.inner.lock().unwrap();
+        lru.entries.clear();
+    }
+
+    /// (alpha^s, beta^s) mod p, from the cache or computed and remembered
+    pub fn powers(&self, zkp: &ZKP, s: &BigUint) -> (BigUint, BigUint) {
+        {
+            let lru = &mut *self.inner.lock().unwrap();
+            lru.tick += 1;
+            if let Some((alpha_s, beta_s, last_used)) = lru.entries.get_mut(s) {
+                *last_used = lru.tick;
+                return (alpha_s.clone(), beta_s.clone());
+            }
+        }
+
+        // computed without holding the lock, a concurrent miss on the same s
+        // only costs a duplicate computation
+        let alpha_s = ZKP::exponentiate(&zkp.alpha, s, &zkp.p);
+        let beta_s = ZKP::exponentiate(&zkp.beta, s, &zkp.p);
+        if self.capacity > 0 {
+            let lru = &mut *self.inner.lock().unwrap();
+            if lru.entries.len() >= self.capacity && !lru.entries.contains_key(s) {
+                let oldest = lru
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, _, last_used))| *last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    lru.entries.remove(&oldest);
+                }
+            }
+            let tick = lru.tick;
+            lru.entries
+                .insert(s.clone(), (alpha_s.clone(), beta_s.clone(), tick));
+        }
+        (alpha_s, beta_s)
+    }
+}
+
+impl ZKP {
+    /// Same as `verify_checked`, taking alpha^s and beta^s from `cache`. Inputs are
+    /// range checked first, so out of range values of s never reach the cache.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_cached(
+        &self,
+        cache: &PowerCache,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        self.check_ranges(r1, r2, y1, y2, c, s)?;
+        let powers = cache.powers(self, s);
+        self.verify_with_powers(
+            &Reducer::new(&self.p, Reduction::Plain),
+            r1,
+            r2,
+            y1,
+            y2,
+            c,
+            powers,
+        )
+        .into_result()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cached_and_uncached_agree() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let cache = PowerCache::new(4);
+        let (y1, y2) = (BigUint::from(2u32), BigUint::from(3u32));
+        let c = BigUint::from(4u32);
+        // every s twice, through a cache too small to hold them all
+        for _ in 0..2 {
+            for r1 in [8u32, 9] {
+                for s in 0..11u32 {
+                    let (r1, r2, s) = (r1.into(), 4u32.into(), s.into());
+                    assert_eq!(
+                        zkp.verify_cached(&cache, &r1, &r2, &y1, &y2, &c, &s),
+                        zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s)
+                    );
+                }
+            }
+        }
+        assert_eq!(cache.len(), 4);
+
+        let s = BigUint::from(11u32);
+        assert_eq!(
+            zkp.verify_cached(&cache, &y1, &y2, &y1, &y2, &c, &s),
+            Err(VerifyError::OutOfRange("s"))
+        );
+        assert_eq!(
+            cache.powers(&zkp, &BigUint::from(5u32)),
+            (BigUint::from(12u32), BigUint::from(8u32))
+        );
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let zkp = ZKP::new();
+        let cache = PowerCache::new(2);
+        let [a, b, c] = [1u32, 2, 3].map(BigUint::from);
+        cache.powers(&zkp, &a);
+        cache.powers(&zkp, &b);
+        // touching a makes b the oldest
+        cache.powers(&zkp, &a);
+        cache.powers(&zkp, &c);
+
+        let entries = &cache.inner.lock().unwrap().entries;
+        assert!(entries.contains_key(&a) && entries.contains_key(&c));
+        assert!(!entries.contains_key(&b));
+
+        let disabled = PowerCache::new(0);
+        disabled.powers(&zkp, &a);
+        assert!(disabled.is_empty());
+    }
+}
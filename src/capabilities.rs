@@ -0,0 +1,170 @@
+use std::fmt;
+
+use digest::Digest;
+use sha2::Sha256;
+
+use crate::ElementEncoding;
+
+/// Version of the protocol spoken by this build. A server and client agree on
+/// the lower of their two versions.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// SHA-256, used for commitment hashes and Fiat-Shamir challenges
+pub const HASH_SHA256: &str = "sha256";
+
+impl ElementEncoding {
+    /// Name of the encoding in a capability list
+    pub fn name(&self) -> &'static str {
+        match self {
+            ElementEncoding::Padded => "padded",
+            ElementEncoding::Varint => "varint",
+        }
+    }
+}
+
+/// Settings one side can work with, each list in its own order of preference.
+/// Groups are given by their `descriptor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub protocol_version: u32,
+    pub groups: Vec<String>,
+    pub encodings: Vec<String>,
+    pub hashes: Vec<String>,
+}
+
+/// What both sides settled on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiated {
+    pub protocol_version: u32,
+    pub group: String,
+    pub encoding: String,
+    pub hash: String,
+}
+
+/// Why two sides have no settings in common.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NegotiationError {
+    /// The other side announced a version below 1, or none at all
+    UnsupportedVersion(u32),
+    /// No group, encoding or hash appears in both lists, named by the field
+    NothingInCommon(&'static str),
+}
+
+impl fmt::Display for NegotiationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NegotiationError::UnsupportedVersion(version) => {
+                write!(f, "protocol version {} is not supported", version)
+            }
+            NegotiationError::NothingInCommon(field) => write!(f, "no {} in common", field),
+        }
+    }
+}
+
+impl std::error::Error for NegotiationError {}
+
+impl Capabilities {
+    /// Everything this build supports, for the given groups
+    pub fn new(groups: Vec<String>) -> Self {
+        Capabilities {
+            protocol_version: PROTOCOL_VERSION,
+            groups,
+            encodings: [ElementEncoding::Padded, ElementEncoding::Varint]
+                .iter()
+                .map(|encoding| encoding.name().to_string())
+                .collect(),
+            hashes: vec![HASH_SHA256.to_string()],
+        }
+    }
+
+    /// Hex SHA-256 of the version and every list, in order: two sides with
+    /// the same fingerprint announce the same settings
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.protocol_version.to_be_bytes());
+        for list in [&self.groups, &self.encodings, &self.hashes] {
+            hasher.update((list.len() as u32).to_be_bytes());
+            for value in list {
+                hasher.update((value.len() as u32).to_be_bytes());
+                hasher.update(value);
+            }
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Picks, for each setting, the first of ours the other side supports too
+    pub fn negotiate(&self, theirs: &Capabilities) -> Result<Negotiated, NegotiationError> {
+        let protocol_version = self.protocol_version.min(theirs.protocol_version);
+        if protocol_version == 0 {
+            return Err(NegotiationError::UnsupportedVersion(
+                theirs.protocol_version,
+            ));
+        }
+        let pick = |ours: &[String], theirs: &[String], field| {
+            ours.iter()
+                .find(|value| theirs.contains(value))
+                .cloned()
+                .ok_or(NegotiationError::NothingInCommon(field))
+        };
+        Ok(Negotiated {
+            protocol_version,
+            group: pick(&self.groups, &theirs.groups, "group")?,
+            encoding: pick(&self.encodings, &theirs.encodings, "encoding")?,
+            hash: pick(&self.hashes, &theirs.hashes, "hash")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Group;
+
+    #[test]
+    fn test_negotiate_picks_our_first_common_choice() {
+        let server = Capabilities::new(vec![
+            Group::Rfc5114_2048_256.name().to_string(),
+            Group::Rfc5114_1024_160.name().to_string(),
+        ]);
+        let client = Capabilities {
+            protocol_version: PROTOCOL_VERSION + 1,
+            groups: vec![
+                "p=17;q=b;alpha=4;beta=9".to_string(),
+                Group::Rfc5114_1024_160.name().to_string(),
+            ],
+            encodings: vec!["varint".to_string(), "padded".to_string()],
+            hashes: vec!["sha3-256".to_string(), HASH_SHA256.to_string()],
+        };
+        assert_eq!(
+            client.negotiate(&server),
+            Ok(Negotiated {
+                protocol_version: PROTOCOL_VERSION,
+                group: Group::Rfc5114_1024_160.name().to_string(),
+                encoding: "varint".to_string(),
+                hash: HASH_SHA256.to_string(),
+            })
+        );
+        assert_eq!(server.negotiate(&client).unwrap().encoding, "padded");
+
+        let no_hash = Capabilities {
+            hashes: vec!["sha3-256".to_string()],
+            ..client.clone()
+        };
+        assert_eq!(
+            no_hash.negotiate(&server),
+            Err(NegotiationError::NothingInCommon("hash"))
+        );
+        let unversioned = Capabilities {
+            protocol_version: 0,
+            ..server
+        };
+        assert_eq!(
+            client.negotiate(&unversioned),
+            Err(NegotiationError::UnsupportedVersion(0))
+        );
+    }
+}
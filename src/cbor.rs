@@ -0,0 +1,222 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+use crate::{DecodeError, ElementEncoding, ZKP};
+
+/// A proof on the wire: a map from the names of r1, r2, y1, y2, c and s to
+/// their padded elements. The fields are declared in the deterministic order
+/// of RFC 8949, shorter keys first, which is the order they are written in.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CborProof {
+    c: ByteBuf,
+    s: ByteBuf,
+    r1: ByteBuf,
+    r2: ByteBuf,
+    y1: ByteBuf,
+    y2: ByteBuf,
+}
+
+/// Why bytes didn't decode into a CBOR proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CborError {
+    /// The input ended inside a data item
+    Truncated,
+    /// Not well-formed CBOR
+    Malformed,
+    /// Well-formed, but not a map of exactly the six proof entries to byte
+    /// strings, with why as `ciborium` put it
+    NotAProof(String),
+    /// A length not written in its shortest form, an indefinite length, or
+    /// keys out of their deterministic order, which would give the same proof
+    /// a second encoding
+    NonCanonical,
+    /// The value of a key is not a padded element below its modulus
+    Element(&'static str, DecodeError),
+    /// Bytes left over after the map
+    TrailingBytes,
+}
+
+impl fmt::Display for CborError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CborError::Truncated => f.write_str("input ends inside a data item"),
+            CborError::Malformed => f.write_str("input is not well-formed CBOR"),
+            CborError::NotAProof(reason) => write!(f, "not a proof: {}", reason),
+            CborError::NonCanonical => f.write_str("proof is not deterministically encoded"),
+            CborError::Element(name, err) => write!(f, "{}: {}", name, err),
+            CborError::TrailingBytes => f.write_str("bytes left after the proof"),
+        }
+    }
+}
+
+impl std::error::Error for CborError {}
+
+impl From<ciborium::de::Error<std::io::Error>> for CborError {
+    fn from(err: ciborium::de::Error<std::io::Error>) -> Self {
+        match err {
+            ciborium::de::Error::Io(_) => CborError::Truncated,
+            ciborium::de::Error::Syntax(_) => CborError::Malformed,
+            ciborium::de::Error::Semantic(_, reason) => CborError::NotAProof(reason),
+            ciborium::de::Error::RecursionLimitExceeded => CborError::Malformed,
+        }
+    }
+}
+
+impl ZKP {
+    /// r1, r2, y1, y2, c and s as a CBOR map from their names to byte strings,
+    /// each element padded as `ElementEncoding::Padded`, r1 to y2 to the length
+    /// of p and c and s to the length of q. The map is written in the
+    /// deterministic encoding of RFC 8949, so every proof has exactly one.
+    pub fn encode_proof_cbor(&self, transcript: [&BigUint; 6]) -> Vec<u8> {
+        let [r1, r2, y1, y2, c, s] = transcript;
+        let element =
+            |n: &BigUint, modulus| ByteBuf::from(ElementEncoding::Padded.encode(n, modulus));
+        let proof = CborProof {
+            c: element(c, &self.q),
+            s: element(s, &self.q),
+            r1: element(r1, &self.p),
+            r2: element(r2, &self.p),
+            y1: element(y1, &self.p),
+            y2: element(y2, &self.p),
+        };
+        let mut out = Vec::new();
+        ciborium::into_writer(&proof, &mut out).expect("writing to a Vec can't fail");
+        out
+    }
+
+    /// Inverse of `encode_proof_cbor`, giving r1, r2, y1, y2, c and s. Only
+    /// the deterministic encoding is accepted, every element range checked
+    /// against its modulus and the whole input consumed.
+    pub fn decode_proof_cbor(&self, bytes: &[u8]) -> Result<[BigUint; 6], CborError> {
+        let mut reader = bytes;
+        let proof: CborProof = ciborium::from_reader(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(CborError::TrailingBytes);
+        }
+        let mut transcript: [BigUint; 6] = Default::default();
+        let fields = [
+            ("r1", &proof.r1, &self.p),
+            ("r2", &proof.r2, &self.p),
+            ("y1", &proof.y1, &self.p),
+            ("y2", &proof.y2, &self.p),
+            ("c", &proof.c, &self.q),
+            ("s", &proof.s, &self.q),
+        ];
+        for (i, (name, element, modulus)) in fields.into_iter().enumerate() {
+            let (value, rest) = ElementEncoding::Padded
+                .decode(element, modulus)
+                .map_err(|err| CborError::Element(name, err))?;
+            if !rest.is_empty() {
+                return Err(CborError::Element(name, DecodeError::TrailingBytes));
+            }
+            transcript[i] = value;
+        }
+        // ciborium reads any encoding of the map, only one is the proof's
+        if self.encode_proof_cbor(transcript.each_ref()) != bytes {
+            return Err(CborError::NonCanonical);
+        }
+        Ok(transcript)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CompactProof, Group};
+
+    fn toy_zkp() -> ZKP {
+        ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        }
+    }
+
+    #[test]
+    fn test_cbor_proof_round_trip() {
+        let zkp = toy_zkp();
+        let transcript = [8u32, 4, 2, 3, 10, 0].map(BigUint::from);
+        let bytes = zkp.encode_proof_cbor(transcript.each_ref());
+        assert_eq!(
+            bytes,
+            [
+                0xa6, 0x61, b'c', 0x41, 10, 0x61, b's', 0x41, 0, 0x62, b'r', b'1', 0x41, 8, 0x62,
+                b'r', b'2', 0x41, 4, 0x62, b'y', b'1', 0x41, 2, 0x62, b'y', b'2', 0x41, 3,
+            ]
+        );
+        assert_eq!(zkp.decode_proof_cbor(&bytes), Ok(transcript));
+
+        // a real proof in a 2048-bit group, the byte strings need two byte lengths
+        let zkp = Group::Rfc5114_2048_256.zkp();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+        let CompactProof { c, s } = zkp.prove_compact(&k, &x);
+        let bytes = zkp.encode_proof_cbor([&r1, &r2, &y1, &y2, &c, &s]);
+        let [r1, r2, y1, y2, c, s] = zkp.decode_proof_cbor(&bytes).unwrap();
+        assert!(zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_cbor_is_rejected() {
+        let zkp = toy_zkp();
+        let bytes = zkp.encode_proof_cbor([8u32, 4, 2, 3, 10, 0].map(BigUint::from).each_ref());
+        let decode = |bytes: &[u8]| zkp.decode_proof_cbor(bytes);
+
+        for len in 0..bytes.len() {
+            assert_eq!(decode(&bytes[..len]), Err(CborError::Truncated));
+        }
+        assert_eq!(
+            decode(&[&bytes[..], &[0]].concat()),
+            Err(CborError::TrailingBytes)
+        );
+        // a reserved initial byte
+        assert_eq!(decode(&[0x1c]), Err(CborError::Malformed));
+        // an array, and a map of five entries
+        let not_a_proof = |result| matches!(result, Err(CborError::NotAProof(_)));
+        assert!(not_a_proof(decode(&[&[0x86], &bytes[1..]].concat())));
+        assert!(not_a_proof(decode(&[&[0xa5], &bytes[1..]].concat())));
+        // the map's length written in two bytes, or left indefinite
+        assert_eq!(
+            decode(&[&[0xb8, 6], &bytes[1..]].concat()),
+            Err(CborError::NonCanonical)
+        );
+        assert_eq!(
+            decode(&[&[0xbf], &bytes[1..], &[0xff]].concat()),
+            Err(CborError::NonCanonical)
+        );
+
+        // s before c, and a key that isn't part of a proof
+        let mut swapped = bytes.clone();
+        swapped.swap(2, 6);
+        swapped.swap(4, 8);
+        assert_eq!(decode(&swapped), Err(CborError::NonCanonical));
+        let mut unknown = bytes.clone();
+        unknown[2] = b'x';
+        assert!(not_a_proof(decode(&unknown)));
+
+        // c as text, c of two bytes, and c not below q
+        let mut text = bytes.clone();
+        text[3] = 0x61;
+        assert!(not_a_proof(decode(&text)));
+        let long = [&bytes[..3], &[0x42, 0, 10], &bytes[5..]].concat();
+        assert_eq!(
+            decode(&long),
+            Err(CborError::Element("c", DecodeError::TrailingBytes))
+        );
+        let mut big = bytes.clone();
+        big[4] = 11;
+        assert_eq!(
+            decode(&big),
+            Err(CborError::Element("c", DecodeError::OutOfRange))
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::{fmt, sync::Mutex};
+
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// Challenges below a q shorter than this are guessed too often to matter,
+/// once in 2^q_bits proofs.
+pub const MIN_CHALLENGE_BITS: u64 = 64;
+
+/// q has fewer than `MIN_CHALLENGE_BITS` bits, so a cheater guessing c in
+/// advance passes a proof with probability about 2^-q_bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmallChallengeSpace {
+    pub q_bits: u64,
+}
+
+impl fmt::Display for SmallChallengeSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "q has {} bits, below the {} needed for challenges a cheater can't guess",
+            self.q_bits, MIN_CHALLENGE_BITS
+        )
+    }
+}
+
+impl std::error::Error for SmallChallengeSpace {}
+
+impl ZKP {
+    /// Whether challenges in [0, q) are numerous enough, i.e. q has at least
+    /// `MIN_CHALLENGE_BITS` bits. Named groups always pass, custom ones built
+    /// from their parameters do under `strict`.
+    pub fn check_challenge_space(&self) -> Result<(), SmallChallengeSpace> {
+        match self.q.bits() {
+            q_bits if q_bits < MIN_CHALLENGE_BITS => Err(SmallChallengeSpace { q_bits }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where the verifier draws its challenges c from.
+pub trait ChallengeSource: Send + Sync {
+    /// output = c in [0, q)
+    fn challenge(&self, q: &BigUint) -> BigUint;
+}
+
+/// Uniformly random challenges, what a real verifier must use.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomChallenges;
+
+impl ChallengeSource for RandomChallenges {
+    fn challenge(&self, q: &BigUint) -> BigUint {
+        ZKP::generate_random_below(q)
+    }
+}
+
+/// Uniformly random challenges of at most `bits` bits, trading soundness
+/// (a cheater wins with probability 2^-bits) for smaller challenges.
+#[derive(Debug, Clone, Copy)]
+pub struct ShortChallenges {
+    pub bits: u64,
+}
+
+impl ShortChallenges {
+    /// The challenges are uniform in [0, bound), bound = min(2^bits, q)
+    pub fn bound(&self, q: &BigUint) -> BigUint {
+        (BigUint::from(1u32) << self.bits.min(q.bits())).min(q.clone())
+    }
+}
+
+impl ChallengeSource for ShortChallenges {
+    fn challenge(&self, q: &BigUint) -> BigUint {
+        ZKP::generate_challenge(q, self.bits)
+    }
+}
+
+/// Replays a fixed sequence of challenges (reduced mod q), starting over once
+/// exhausted. Only meant for reproducible tests.
+#[derive(Debug)]
+pub struct FixedChallenges {
+    values: Vec<BigUint>,
+    next: Mutex<usize>,
+}
+
+impl FixedChallenges {
+    pub fn new(values: Vec<BigUint>) -> Self {
+        assert!(!values.is_empty(), "at least one challenge is needed");
+        FixedChallenges {
+            values,
+            next: Mutex::new(0),
+        }
+    }
+}
+
+impl ChallengeSource for FixedChallenges {
+    fn challenge(&self, q: &BigUint) -> BigUint {
+        let mut next = self.next.lock().unwrap();
+        let c = &self.values[*next] % q;
+        *next = (*next + 1) % self.values.len();
+        c
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fixed_challenges_repeat_in_order() {
+        let q = BigUint::from(11u32);
+        let source = FixedChallenges::new(vec![
+            BigUint::from(4u32),
+            BigUint::from(7u32),
+            BigUint::from(13u32),
+        ]);
+
+        let challenges: Vec<_> = (0..4).map(|_| source.challenge(&q)).collect();
+        assert_eq!(challenges, [4u32, 7, 2, 4].map(BigUint::from).to_vec());
+    }
+
+    #[test]
+    fn test_random_challenges_below_q() {
+        let q = BigUint::from(11u32);
+        for _ in 0..100 {
+            assert!(RandomChallenges.challenge(&q) < q);
+        }
+    }
+
+    #[test]
+    fn test_challenge_space_of_tiny_q_is_too_small() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        assert_eq!(
+            zkp.check_challenge_space(),
+            Err(SmallChallengeSpace { q_bits: 4 })
+        );
+        for group in crate::Group::ALL {
+            assert_eq!(group.zkp().check_challenge_space(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_short_challenges() {
+        let q = ZKP::new().q;
+        let source = ShortChallenges { bits: 16 };
+        for _ in 0..100 {
+            assert!(source.challenge(&q).bits() <= 16);
+        }
+        let source = ShortChallenges { bits: 1024 };
+        for _ in 0..100 {
+            assert!(source.challenge(&q) < q);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Offline tools, no server involved.
+//!
+//! `zkp verify <group file> <proof file> [--format json|padded|varint]` checks
+//! a stored Fiat-Shamir proof. The group file holds a named group or a
+//! `p=..;q=..;alpha=..[;beta=..]` descriptor. The proof is a JSON object with
+//! r1, r2, y1, y2, c and s as hex strings, as in `tests/vectors.json`, or the
+//! bytes of `ZKP::encode_transcript`. The format defaults to json for a `.json`
+//! file and padded otherwise.
+//!
+//! Exits with 0 for a valid proof, 1 for an invalid one and 2 when the files
+//! can't be read or parsed.
+
+use std::{collections::HashMap, env, fs, path::Path, process::ExitCode};
+
+use num_bigint::BigUint;
+use zkp_chaum_pedersen::{ElementEncoding, Verifier, ZKP};
+
+const USAGE: &str = "usage: zkp verify <group file> <proof file> [--format json|padded|varint]";
+
+/// Field names of a JSON proof, in transcript order
+const FIELDS: [&str; 6] = ["r1", "r2", "y1", "y2", "c", "s"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Binary(ElementEncoding),
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(Ok(())) => {
+            println!("valid");
+            ExitCode::SUCCESS
+        }
+        Ok(Err(reason)) => {
+            println!("invalid: {}", reason);
+            ExitCode::from(1)
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// The outcome of the proof, or why it couldn't be checked
+fn run(args: &[String]) -> Result<Result<(), String>, String> {
+    let (group_path, proof_path, format) = match args {
+        [command, group, proof] if command == "verify" => (group, proof, None),
+        [command, group, proof, flag, format] if command == "verify" && flag == "--format" => {
+            (group, proof, Some(format.as_str()))
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+    let format = match format {
+        Some("json") => Format::Json,
+        Some("padded") => Format::Binary(ElementEncoding::Padded),
+        Some("varint") => Format::Binary(ElementEncoding::Varint),
+        Some(other) => return Err(format!("unknown format {}\n{}", other, USAGE)),
+        None if Path::new(proof_path)
+            .extension()
+            .is_some_and(|ext| ext == "json") =>
+        {
+            Format::Json
+        }
+        None => Format::Binary(ElementEncoding::Padded),
+    };
+
+    let group = fs::read_to_string(group_path)
+        .map_err(|err| format!("could not read {}: {}", group_path, err))?;
+    let zkp: ZKP = group
+        .trim()
+        .parse()
+        .map_err(|err| format!("invalid group in {}: {}", group_path, err))?;
+    let proof =
+        fs::read(proof_path).map_err(|err| format!("could not read {}: {}", proof_path, err))?;
+    let [r1, r2, y1, y2, c, s] = match format {
+        Format::Json => String::from_utf8(proof)
+            .map_err(|_| "the proof is not UTF-8".to_string())
+            .and_then(|json| parse_json(&json)),
+        Format::Binary(encoding) => zkp
+            .decode_transcript(encoding, &proof)
+            .map_err(|err| err.to_string()),
+    }
+    .map_err(|err| format!("invalid proof in {}: {}", proof_path, err))?;
+
+    let verifier = Verifier::new(zkp);
+    Ok(verifier
+        .verify_fiat_shamir(&r1, &r2, &y1, &y2, &c, &s)
+        .map_err(|err| err.to_string()))
+}
+
+/// A flat object of string fields, every proof field hex. Other fields, such
+/// as the group, x and k of a test vector, are ignored.
+fn parse_json(json: &str) -> Result<[BigUint; 6], String> {
+    let body = json
+        .trim()
+        .strip_prefix('{')
+        .and_then(|body| body.strip_suffix('}'))
+        .ok_or("not a JSON object")?;
+    let unquote = |text: &str| {
+        let text = text.trim();
+        text.strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+            .filter(|text| !text.contains(['"', '\\']))
+            .map(str::to_string)
+            .ok_or_else(|| format!("{} is not a plain string", text))
+    };
+    let mut fields = HashMap::new();
+    for entry in body.split(',').filter(|entry| !entry.trim().is_empty()) {
+        let (name, value) = entry
+            .split_once(':')
+            .ok_or_else(|| format!("{} is not a field", entry.trim()))?;
+        fields.insert(unquote(name)?, unquote(value)?);
+    }
+    let mut transcript: [BigUint; 6] = Default::default();
+    for (n, name) in transcript.iter_mut().zip(FIELDS) {
+        let hex = fields
+            .get(name)
+            .ok_or_else(|| format!("{} is missing", name))?;
+        *n = BigUint::parse_bytes(hex.as_bytes(), 16)
+            .ok_or_else(|| format!("{} is not hex", name))?;
+    }
+    Ok(transcript)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A Fiat-Shamir proof as JSON and padded bytes, with a tampered copy of each
+    fn write_proofs(dir: &Path, zkp: &ZKP) {
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let proof = zkp.prove_compact(&k, &x);
+        let transcript = [
+            ZKP::exponentiate(&zkp.alpha, &k, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &k, &zkp.p),
+            ZKP::exponentiate(&zkp.alpha, &x, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &x, &zkp.p),
+            proof.c,
+            proof.s,
+        ];
+        let mut tampered = transcript.clone();
+        tampered[5] = (&tampered[5] + 1u32) % &zkp.q;
+
+        for (name, transcript) in [("good", &transcript), ("tampered", &tampered)] {
+            let fields: Vec<String> = FIELDS
+                .iter()
+                .zip(transcript)
+                .map(|(field, n)| format!("\"{}\": \"{:x}\"", field, n))
+                .collect();
+            let json = format!("{{\n  {}\n}}\n", fields.join(",\n  "));
+            fs::write(dir.join(format!("{}.json", name)), json).unwrap();
+            let [r1, r2, y1, y2, c, s] = transcript;
+            let bytes = zkp.encode_transcript(ElementEncoding::Padded, [r1, r2, y1, y2, c, s]);
+            fs::write(dir.join(format!("{}.bin", name)), bytes).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_verify_good_and_tampered_proof_files() {
+        let dir = env::temp_dir().join(format!("zkp-cli-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("group"), "rfc5114-1024-160\n").unwrap();
+        write_proofs(&dir, &ZKP::new());
+
+        let verify = |proof: &str, format: &[&str]| {
+            let mut args = vec!["verify".to_string()];
+            for path in ["group", proof] {
+                args.push(dir.join(path).to_str().unwrap().to_string());
+            }
+            args.extend(format.iter().map(|arg| arg.to_string()));
+            run(&args)
+        };
+        assert_eq!(verify("good.json", &[]), Ok(Ok(())));
+        assert_eq!(verify("good.bin", &[]), Ok(Ok(())));
+        assert_eq!(verify("good.bin", &["--format", "padded"]), Ok(Ok(())));
+        assert!(matches!(verify("tampered.json", &[]), Ok(Err(_))));
+        assert!(matches!(verify("tampered.bin", &[]), Ok(Err(_))));
+
+        // files that can't be checked at all
+        assert!(verify("good.bin", &["--format", "varint"]).is_err());
+        assert!(verify("good.json", &["--format", "padded"]).is_err());
+        assert!(verify("missing.json", &[]).is_err());
+        assert!(run(&["verify".to_string()]).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,19 +1,103 @@
 pub mod zkp_auth {
     include!("./zkp_auth.rs");
 }
+mod retry;
+mod trace;
 
-use std::{env, io::stdin};
+use std::{
+    env, fs,
+    io::stdin,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use num_bigint::BigUint;
-use zkp_auth::{auth_client::AuthClient, AuthAnswerRequest, AuthChallengeRequest, RegisterRequest};
-use zkp_chaum_pedersen::ZKP;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use zkp_auth::{
+    auth_client::AuthClient, AuthAnswerRequest, AuthChallengeRequest, GetParamsRequest,
+    HandshakeRequest, RegisterRequest,
+};
+use zkp_chaum_pedersen::{
+    capabilities::Capabilities,
+    commit::hash_elements,
+    error_details::{error_reason, ErrorReason},
+    Commitment, DeviceRng, ElementEncoding, Entropy, ParamsCache, ParamsSource, Prover, Response,
+    SigmaProver, ZKP,
+};
+
+use retry::{retry, RetryPolicy};
+use trace::Traced;
+
+const CACHE_DIR: &str = "zkp-chaum-pedersen";
+const DEFAULT_PARAMS_TTL_SECS: u64 = 3600;
+
+/// With `--trace-json`, writes `message` to stderr as one JSON line
+fn trace(enabled: bool, message: &impl Traced) {
+    if enabled {
+        eprintln!("{}", message.to_trace_json());
+    }
+}
+
+/// `CLIENT_PARAMS_CACHE`, by default `zkp-chaum-pedersen` under `$XDG_CACHE_HOME`
+/// or `~/.cache`, for `CLIENT_PARAMS_TTL` seconds
+fn params_cache() -> ParamsCache {
+    let dir = env::var_os("CLIENT_PARAMS_CACHE")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("XDG_CACHE_HOME").map(|dir| PathBuf::from(dir).join(CACHE_DIR)))
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join(CACHE_DIR))
+        })
+        .unwrap_or_else(|| env::temp_dir().join(CACHE_DIR));
+    let ttl = env::var("CLIENT_PARAMS_TTL")
+        .map(|ttl| ttl.parse().expect("invalid CLIENT_PARAMS_TTL"))
+        .unwrap_or(DEFAULT_PARAMS_TTL_SECS);
+    ParamsCache::new(dir, Duration::from_secs(ttl))
+}
+
+async fn get_params(
+    client: &mut AuthClient<Channel>,
+    trace_json: bool,
+) -> Result<Capabilities, tonic::Status> {
+    let request = GetParamsRequest {};
+    trace(trace_json, &request);
+    let response = client.get_params(request).await?;
+    trace(trace_json, response.get_ref());
+    let response = response.into_inner();
+    Ok(Capabilities {
+        protocol_version: response.protocol_version,
+        groups: response.groups,
+        encodings: response.encodings,
+        hashes: response.hashes,
+    })
+}
+
+/// Channel to `addr`, over TLS when `CLIENT_TLS_CA` names the PEM CA of the
+/// server, presenting `CLIENT_TLS_CERT` and `CLIENT_TLS_KEY` as the client
+/// certificate when both are set
+async fn connect(addr: String) -> Result<Channel, Box<dyn std::error::Error>> {
+    let mut endpoint = Endpoint::from_shared(addr)?;
+    if let Some(ca) = env::var_os("CLIENT_TLS_CA") {
+        let mut tls = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(fs::read(ca)?));
+        if let (Some(cert), Some(key)) = (
+            env::var_os("CLIENT_TLS_CERT"),
+            env::var_os("CLIENT_TLS_KEY"),
+        ) {
+            tls = tls.identity(Identity::from_pem(fs::read(cert)?, fs::read(key)?));
+        }
+        endpoint = endpoint.tls_config(tls)?;
+    }
+    Ok(endpoint.connect().await?)
+}
 
 #[tokio::main]
 async fn main() {
+    let trace_json = env::args().skip(1).any(|arg| arg == "--trace-json");
     let addr = env::var("CLIENT_ADDRESS").unwrap_or("http://127.0.0.1:50051".to_string());
-    let mut client = AuthClient::connect(addr)
-        .await
-        .expect("could not connect to the client");
+    let mut client = AuthClient::new(
+        connect(addr.clone())
+            .await
+            .expect("could not connect to the client"),
+    );
     println!("Connected to the server");
 
     let mut buf = String::new();
@@ -31,46 +115,182 @@ async fn main() {
     let password = BigUint::from_bytes_be(buf.trim().as_bytes());
     buf.clear();
 
-    let zkp = ZKP::new();
-    let y1 = ZKP::exponentiate(&zkp.alpha, &password, &zkp.p);
-    let y2 = ZKP::exponentiate(&zkp.beta, &password, &zkp.p);
+    let zkp: ZKP = env::var("CLIENT_GROUP")
+        .map(|group| group.parse().expect("invalid CLIENT_GROUP"))
+        .unwrap_or_default();
+    let group = zkp.descriptor();
+
+    let ours = Capabilities {
+        groups: vec![group.clone()],
+        encodings: vec![ElementEncoding::Padded.name().to_string()],
+        ..Capabilities::new(Vec::new())
+    };
+    let cache = params_cache();
+    let now = SystemTime::now();
+    // servers from before GetParams don't announce anything, go ahead and find out
+    match cache
+        .get_or_fetch(&addr, now, get_params(&mut client, trace_json))
+        .await
+    {
+        Ok((server, source)) => {
+            let mut negotiated = ours.negotiate(&server);
+            // the server may have changed its settings since they were cached
+            if negotiated.is_err() && source == ParamsSource::Cache {
+                cache.invalidate(&addr);
+                negotiated = match cache
+                    .get_or_fetch(&addr, now, get_params(&mut client, trace_json))
+                    .await
+                {
+                    Ok((server, _)) => ours.negotiate(&server),
+                    Err(status) => panic!("could not get the server's parameters: {}", status),
+                };
+            }
+            if let Err(err) = negotiated {
+                println!("The server can't be used with these settings: {}", err);
+                return;
+            }
+        }
+        Err(status) if status.code() == tonic::Code::Unimplemented => {}
+        Err(status) => panic!("could not get the server's parameters: {}", status),
+    }
+
+    // a server reading elements differently would only fail the proof later on
+    let request = HandshakeRequest {
+        element: ElementEncoding::Padded.encode(&zkp.handshake_element(), &zkp.p),
+        group: group.clone(),
+    };
+    match client.handshake(request).await {
+        Ok(response) => {
+            let echoed = response.into_inner().element;
+            if let Err(err) = zkp.check_handshake(ElementEncoding::Padded, &echoed) {
+                println!("{}", err);
+                return;
+            }
+        }
+        Err(status) if status.code() == tonic::Code::Unimplemented => {}
+        Err(status) => panic!("could not check the encoding with the server: {}", status),
+    }
+
+    let context = env::var("CLIENT_CONTEXT").unwrap_or_default();
+    let policy = RetryPolicy::from_env();
+    // a retried answer could log in twice, see `retry`
+    let verify_policy = match env::var("CLIENT_RETRY_VERIFY") {
+        Ok(_) => policy,
+        Err(_) => RetryPolicy::none(),
+    };
+    let mut prover = Prover::new(zkp, password);
+    if let Ok(path) = env::var("CLIENT_ENTROPY_DEVICE") {
+        prover = prover.with_entropy(Entropy::new(
+            DeviceRng::open(path).expect("could not open CLIENT_ENTROPY_DEVICE"),
+        ));
+    }
+    let (y1, y2) = prover.public_key();
+    let possession = prover.prove_possession(&user);
     let request = RegisterRequest {
         user: user.clone(),
         y1: y1.to_bytes_be(),
         y2: y2.to_bytes_be(),
+        group,
+        pop_c: possession.c.to_bytes_be(),
+        pop_s: possession.s.to_bytes_be(),
     };
 
-    let _response = client
-        .register(request)
-        .await
-        .expect("could not register response");
+    trace(trace_json, &request);
+    let _response = retry(&policy, || {
+        let (mut client, request) = (client.clone(), request.clone());
+        async move { client.register(request).await }
+    })
+    .await
+    .expect("could not register response");
     println!("{:?}", _response);
+    trace(trace_json, _response.get_ref());
+    let key_handle = _response.into_inner().key_handle;
 
-    let k = ZKP::generate_random_below(&zkp.q);
-    let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
-    let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+    log_in(
+        &client,
+        &prover,
+        key_handle,
+        context,
+        policy,
+        verify_policy,
+        trace_json,
+    )
+    .await;
+}
+
+/// One round of `prover`'s protocol with the server, which plays the verifier:
+/// the commitment hash for the challenge, then r1, r2 and the answer. The key
+/// handle stands in for the user name and public key.
+async fn log_in<P>(
+    client: &AuthClient<Channel>,
+    prover: &P,
+    key_handle: String,
+    context: String,
+    policy: RetryPolicy,
+    verify_policy: RetryPolicy,
+    trace_json: bool,
+) where
+    P: SigmaProver<Commitment = Commitment, Challenge = BigUint, Response = Response>,
+{
+    // only H(r1 || r2) goes out now, r1 and r2 follow with the answer
+    let (commitment, nonce) = prover.commit();
     let request = AuthChallengeRequest {
-        user,
-        r1: r1.to_bytes_be(),
-        r2: r2.to_bytes_be(),
+        commitment_hash: hash_elements(&commitment.r1, &commitment.r2).to_vec(),
+        context: context.clone(),
+        key_handle,
+        ..Default::default()
     };
 
-    let response = client
-        .create_auth_challenge(request)
-        .await
-        .expect("could not request challenge")
-        .into_inner();
-    println!("{:?}", _response);
+    trace(trace_json, &request);
+    let response = retry(&policy, || {
+        let (mut client, request) = (client.clone(), request.clone());
+        async move { client.create_auth_challenge(request).await }
+    })
+    .await
+    .expect("could not request challenge")
+    .into_inner();
+    println!("{:?}", response);
+    trace(trace_json, &response);
 
-    let s = zkp.solve(&k, &BigUint::from_bytes_be(&response.c), &password);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    if now >= response.expires_at {
+        println!("The challenge expired before it could be answered, try again");
+        return;
+    }
+
+    let answer = prover.respond(nonce, &BigUint::from_bytes_be(&response.c));
     let request = AuthAnswerRequest {
         auth_id: response.auth_id,
-        s: s.to_bytes_be(),
+        s: answer.s.to_bytes_be(),
+        context,
+        r1: commitment.r1.to_bytes_be(),
+        r2: commitment.r2.to_bytes_be(),
     };
-    let response = client
-        .verify_auth(request)
-        .await
-        .expect("could not authenticate in server")
-        .into_inner();
-    println!("You logged in! session_id = {:?}", response.session_id)
+    trace(trace_json, &request);
+    let response = match retry(&verify_policy, || {
+        let (mut client, request) = (client.clone(), request.clone());
+        async move { client.verify_auth(request).await }
+    })
+    .await
+    {
+        Ok(response) => response.into_inner(),
+        Err(status) => {
+            match error_reason(&status) {
+                Some(ErrorReason::ChallengeExpired) => {
+                    println!("The challenge expired before it could be answered, try again")
+                }
+                Some(ErrorReason::InvalidProof) => println!("Wrong password"),
+                _ => println!("could not authenticate in server: {}", status.message()),
+            }
+            return;
+        }
+    };
+    trace(trace_json, &response);
+    println!(
+        "You logged in! session_id = {:?}, expires_at = {}",
+        response.session_id, response.expires_at
+    )
 }
@@ -0,0 +1,168 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::ZKP;
+
+/// Commit-and-open variant: the prover first sends H(r1), H(r2) instead of r1, r2
+/// and only opens them together with the answer s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashCommitment {
+    pub h1: [u8; 32],
+    pub h2: [u8; 32],
+}
+
+impl HashCommitment {
+    pub fn new(r1: &BigUint, r2: &BigUint) -> Self {
+        HashCommitment {
+            h1: hash_element(r1),
+            h2: hash_element(r2),
+        }
+    }
+
+    /// true if (r1, r2) is the opening of this commitment
+    pub fn opens_to(&self, r1: &BigUint, r2: &BigUint) -> bool {
+        *self == HashCommitment::new(r1, r2)
+    }
+}
+
+/// output = SHA-256 of the big-endian bytes of n
+pub fn hash_element(n: &BigUint) -> [u8; 32] {
+    Sha256::digest(n.to_bytes_be()).into()
+}
+
+/// output = H(r1 || r2), a single 32 byte commitment to both elements for the
+/// wire. r1 is length-prefixed so the split between the two can't be moved.
+pub fn hash_elements(r1: &BigUint, r2: &BigUint) -> [u8; 32] {
+    let r1 = r1.to_bytes_be();
+    Sha256::new()
+        .chain_update((r1.len() as u64).to_be_bytes())
+        .chain_update(&r1)
+        .chain_update(r2.to_bytes_be())
+        .finalize()
+        .into()
+}
+
+impl ZKP {
+    /// r1 = alpha^k mod p
+    /// r2 = beta^k mod p
+    /// output = (H(r1), H(r2)), r1, r2
+    pub fn prove_committed(&self, k: &BigUint) -> (HashCommitment, BigUint, BigUint) {
+        let r1 = ZKP::exponentiate(&self.alpha, k, &self.p);
+        let r2 = ZKP::exponentiate(&self.beta, k, &self.p);
+        (HashCommitment::new(&r1, &r2), r1, r2)
+    }
+
+    /// Checks that (r1, r2) opens the commitment and then runs the usual verification
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_committed(
+        &self,
+        commitment: &HashCommitment,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> bool {
+        commitment.opens_to(r1, r2) && self.verify(r1, r2, y1, y2, c, s)
+    }
+
+    /// Same as `verify_committed` for a commitment made with `hash_elements`
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_hashed(
+        &self,
+        commitment: &[u8; 32],
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> bool {
+        hash_elements(r1, r2) == *commitment && self.verify(r1, r2, y1, y2, c, s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn toy_zkp() -> ZKP {
+        ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        }
+    }
+
+    #[test]
+    fn test_commit_and_open() {
+        let zkp = toy_zkp();
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+        let c = BigUint::from(4u32);
+
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+
+        let (commitment, r1, r2) = zkp.prove_committed(&k);
+        assert_eq!(r1, BigUint::from(8u32));
+        assert_eq!(r2, BigUint::from(4u32));
+
+        let s = zkp.solve(&k, &c, &x);
+        assert!(zkp.verify_committed(&commitment, &r1, &r2, &y1, &y2, &c, &s));
+    }
+
+    #[test]
+    fn test_mismatched_opening_is_rejected() {
+        let zkp = toy_zkp();
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+        let c = BigUint::from(4u32);
+
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+
+        let (commitment, _, _) = zkp.prove_committed(&k);
+
+        // the prover opens a different nonce k' after seeing c, the algebra holds
+        // but the opening does not match what was committed
+        let k_other = BigUint::from(3u32);
+        let (_, r1, r2) = zkp.prove_committed(&k_other);
+        let s = zkp.solve(&k_other, &c, &x);
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+        assert!(!commitment.opens_to(&r1, &r2));
+        assert!(!zkp.verify_committed(&commitment, &r1, &r2, &y1, &y2, &c, &s));
+    }
+
+    #[test]
+    fn test_hashed_commitment() {
+        let zkp = toy_zkp();
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+        let c = BigUint::from(4u32);
+
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let (_, r1, r2) = zkp.prove_committed(&k);
+        let commitment = hash_elements(&r1, &r2);
+        let s = zkp.solve(&k, &c, &x);
+        assert!(zkp.verify_hashed(&commitment, &r1, &r2, &y1, &y2, &c, &s));
+
+        // a wrong opening fails the hash check even though its algebra holds
+        let k_other = BigUint::from(3u32);
+        let (_, r1_other, r2_other) = zkp.prove_committed(&k_other);
+        let s_other = zkp.solve(&k_other, &c, &x);
+        assert!(zkp.verify(&r1_other, &r2_other, &y1, &y2, &c, &s_other));
+        assert!(!zkp.verify_hashed(&commitment, &r1_other, &r2_other, &y1, &y2, &c, &s_other));
+
+        // moving bytes between r1 and r2 changes the hash
+        let shifted = (BigUint::from(0x0102u32), BigUint::from(0x03u32));
+        let other = (BigUint::from(0x01u32), BigUint::from(0x0203u32));
+        assert_ne!(
+            hash_elements(&shifted.0, &shifted.1),
+            hash_elements(&other.0, &other.1)
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use digest::Digest;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use sha2::Sha256;
+
+use crate::ZKP;
+
+/// Non-interactive proof that only carries (c, s), the verifier recomputes
+/// r1 = alpha^s * y1^c and r2 = beta^s * y2^c and checks c is their hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactProof {
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+impl ZKP {
+    /// Fiat-Shamir challenge, c = SHA-256(p, q, alpha, beta, y1, y2, r1, r2) mod q
+    /// with every number length-prefixed
+    pub fn fiat_shamir_challenge(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> BigUint {
+        self.fiat_shamir_challenge_with::<Sha256>(y1, y2, r1, r2)
+    }
+
+    /// `fiat_shamir_challenge` hashed with `D` instead of SHA-256. The whole
+    /// digest is reduced mod q, so a digest wider than q keeps c close to uniform.
+    pub fn fiat_shamir_challenge_with<D: Digest>(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> BigUint {
+        self.labelled_challenge::<D>(y1, y2, r1, r2, None)
+    }
+
+    /// Same hash, with `label` length-prefixed after r2 when given
+    pub(crate) fn labelled_challenge<D: Digest>(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        r1: &BigUint,
+        r2: &BigUint,
+        label: Option<&[u8]>,
+    ) -> BigUint {
+        let mut hasher = D::new();
+        for n in [&self.p, &self.q, &self.alpha, &self.beta, y1, y2, r1, r2] {
+            let bytes = n.to_bytes_be();
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(bytes);
+        }
+        if let Some(label) = label {
+            hasher.update((label.len() as u32).to_be_bytes());
+            hasher.update(label);
+        }
+        BigUint::from_bytes_be(&hasher.finalize()) % &self.q
+    }
+
+    /// r1 = alpha^k mod p
+    /// r2 = beta^k mod p
+    /// c = H(..., r1, r2)
+    /// s = k - c * x mod q
+    pub fn prove_compact(&self, k: &BigUint, x: &BigUint) -> CompactProof {
+        self.prove_labelled::<Sha256>(k, x, None)
+    }
+
+    /// `prove_compact` with the challenge hashed by `D`, e.g. `Sha512`
+    pub fn prove_compact_with<D: Digest>(&self, k: &BigUint, x: &BigUint) -> CompactProof {
+        self.prove_labelled::<D>(k, x, None)
+    }
+
+    /// Proof of possession of x for registering (y1, y2) as `user_name`. The
+    /// name goes into the challenge, so the proof can't be replayed to register
+    /// the same key under another name.
+    pub fn prove_possession(&self, k: &BigUint, x: &BigUint, user_name: &str) -> CompactProof {
+        self.prove_labelled::<Sha256>(k, x, Some(user_name.as_bytes()))
+    }
+
+    pub(crate) fn prove_labelled<D: Digest>(
+        &self,
+        k: &BigUint,
+        x: &BigUint,
+        label: Option<&[u8]>,
+    ) -> CompactProof {
+        let y1 = ZKP::exponentiate(&self.alpha, x, &self.p);
+        let y2 = ZKP::exponentiate(&self.beta, x, &self.p);
+        let r1 = ZKP::exponentiate(&self.alpha, k, &self.p);
+        let r2 = ZKP::exponentiate(&self.beta, k, &self.p);
+
+        let c = self.labelled_challenge::<D>(&y1, &y2, &r1, &r2, label);
+        let s = self.solve(k, &c, x);
+        CompactProof { c, s }
+    }
+
+    /// Recomputes r1, r2 from the proof and checks they hash back to c
+    pub fn verify_compact(&self, y1: &BigUint, y2: &BigUint, proof: &CompactProof) -> bool {
+        self.verify_labelled::<Sha256>(y1, y2, proof, None)
+    }
+
+    /// Checks a proof made by `prove_compact_with` for the same `D`
+    pub fn verify_compact_with<D: Digest>(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &CompactProof,
+    ) -> bool {
+        self.verify_labelled::<D>(y1, y2, proof, None)
+    }
+
+    /// Checks a proof made by `prove_possession` for the same user name.
+    /// alpha and beta are hashed ahead of y1 and y2, so the proof ties y1 to alpha
+    /// and y2 to beta: the same key submitted as (y2, y1) is rejected, even with a
+    /// proof made for swapped generators.
+    pub fn verify_possession(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &CompactProof,
+        user_name: &str,
+    ) -> bool {
+        self.verify_labelled::<Sha256>(y1, y2, proof, Some(user_name.as_bytes()))
+    }
+
+    pub(crate) fn verify_labelled<D: Digest>(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &CompactProof,
+        label: Option<&[u8]>,
+    ) -> bool {
+        let CompactProof { c, s } = proof;
+        if self.validate_challenge(c).is_err() || *s >= self.q {
+            return false;
+        }
+        if [y1, y2].iter().any(|y| y.is_zero() || **y >= self.p) {
+            return false;
+        }
+
+        let r1 = ZKP::exponentiate(&self.alpha, s, &self.p) * ZKP::exponentiate(y1, c, &self.p)
+            % &self.p;
+        let r2 =
+            ZKP::exponentiate(&self.beta, s, &self.p) * ZKP::exponentiate(y2, c, &self.p) % &self.p;
+        *c == self.labelled_challenge::<D>(y1, y2, &r1, &r2, label)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compact_round_trip() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+
+        let proof = zkp.prove_compact(&k, &x);
+        assert!(zkp.verify_compact(&y1, &y2, &proof));
+
+        // the recomputed commitments are the prover's
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+        assert_eq!(proof.c, zkp.fiat_shamir_challenge(&y1, &y2, &r1, &r2));
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &proof.c, &proof.s));
+    }
+
+    #[test]
+    fn test_compact_tampering_is_rejected() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let proof = zkp.prove_compact(&k, &x);
+
+        let tampered_c = CompactProof {
+            c: (&proof.c + 1u32) % &zkp.q,
+            ..proof.clone()
+        };
+        assert!(!zkp.verify_compact(&y1, &y2, &tampered_c));
+
+        let tampered_s = CompactProof {
+            s: (&proof.s + 1u32) % &zkp.q,
+            ..proof.clone()
+        };
+        assert!(!zkp.verify_compact(&y1, &y2, &tampered_s));
+
+        // same algebra, but not the canonical answer
+        let wrapped_s = CompactProof {
+            s: &proof.s + &zkp.q,
+            ..proof.clone()
+        };
+        assert!(!zkp.verify_compact(&y1, &y2, &wrapped_s));
+
+        // a proof for one public key says nothing about another
+        let y1_other = ZKP::exponentiate(&zkp.alpha, &(&x + 1u32), &zkp.p);
+        assert!(!zkp.verify_compact(&y1_other, &y2, &proof));
+    }
+
+    #[test]
+    fn test_possession_is_bound_to_the_name() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+
+        let proof = zkp.prove_possession(&k, &x, "peggy");
+        assert!(zkp.verify_possession(&y1, &y2, &proof, "peggy"));
+        assert!(!zkp.verify_possession(&y1, &y2, &proof, "mallory"));
+        assert!(!zkp.verify_compact(&y1, &y2, &proof));
+        // and an unlabelled proof isn't a proof of possession
+        assert!(!zkp.verify_possession(&y1, &y2, &zkp.prove_compact(&k, &x), ""));
+    }
+
+    #[test]
+    fn test_compact_with_other_digests() {
+        use sha2::{Sha384, Sha512};
+
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+
+        // the default is the SHA-256 instance
+        assert_eq!(
+            zkp.prove_compact_with::<Sha256>(&k, &x),
+            zkp.prove_compact(&k, &x)
+        );
+
+        let sha384 = zkp.prove_compact_with::<Sha384>(&k, &x);
+        let sha512 = zkp.prove_compact_with::<Sha512>(&k, &x);
+        assert_eq!(
+            sha512.c,
+            zkp.fiat_shamir_challenge_with::<Sha512>(&y1, &y2, &r1, &r2)
+        );
+        assert!(zkp.verify_compact_with::<Sha384>(&y1, &y2, &sha384));
+        assert!(zkp.verify_compact_with::<Sha512>(&y1, &y2, &sha512));
+
+        // a proof only verifies under the digest it was made with
+        assert_ne!(sha384.c, sha512.c);
+        assert!(!zkp.verify_compact_with::<Sha512>(&y1, &y2, &sha384));
+        assert!(!zkp.verify_compact(&y1, &y2, &sha512));
+    }
+
+    #[test]
+    fn test_possession_rejects_swapped_keys() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let proof = zkp.prove_possession(&k, &x, "peggy");
+        assert!(!zkp.verify_possession(&y2, &y1, &proof, "peggy"));
+
+        // (beta^x, alpha^x) with a valid proof under swapped generators
+        let swapped = ZKP {
+            alpha: zkp.beta.clone(),
+            beta: zkp.alpha.clone(),
+            ..ZKP::new()
+        };
+        let swapped_proof = swapped.prove_possession(&k, &x, "peggy");
+        assert!(swapped.verify_possession(&y2, &y1, &swapped_proof, "peggy"));
+        assert!(!zkp.verify_possession(&y2, &y1, &swapped_proof, "peggy"));
+    }
+}
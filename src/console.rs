@@ -0,0 +1,46 @@
+//! `tokio-console` support through `console-subscriber`, with the `console`
+//! feature.
+//!
+//! tokio only emits the task and resource instrumentation the console reads
+//! when compiled with `RUSTFLAGS="--cfg tokio_unstable"`. Without it the
+//! console still connects, but sees no tasks.
+
+use console_subscriber::ConsoleLayer;
+use tracing_subscriber::{prelude::*, util::TryInitError};
+
+/// Installs the console layer as the global subscriber and starts its gRPC
+/// server on a background thread, on 127.0.0.1:6669 unless the
+/// `TOKIO_CONSOLE_*` variables say otherwise
+pub fn init() -> Result<(), TryInitError> {
+    let layer = ConsoleLayer::builder().with_default_env().spawn();
+    tracing_subscriber::registry().with(layer).try_init()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_console_init() {
+        assert!(init().is_ok());
+        // only one global subscriber per process
+        assert!(init().is_err());
+    }
+
+    #[test]
+    fn test_console_layer_sees_tasks() {
+        let layer = ConsoleLayer::builder()
+            .server_addr(([127, 0, 0, 1], 0))
+            .spawn();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            // a task spawned and polled with the layer in place
+            let answer = runtime.block_on(async { tokio::spawn(async { 42 }).await.unwrap() });
+            assert_eq!(answer, 42);
+        });
+    }
+}
@@ -0,0 +1,314 @@
+//! Verifiable-credential style JSON envelope around a proof of possession.
+//!
+//! The envelope follows the shape of a W3C verifiable credential (`@context`,
+//! `type`, `issuer`, `issuanceDate`, `credentialSubject`, `proof`) so it can pass
+//! through tooling built for them. Only the proof is cryptographic: it is the
+//! `prove_possession` proof for the subject's id, and the issuer and date are
+//! carried as they are.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::{CompactProof, ParamError, ZKP};
+
+pub const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+pub const CREDENTIAL_TYPE: &str = "ChaumPedersenCredential";
+/// `type` of the proof, a `CompactProof` labelled with the subject's id
+pub const PROOF_TYPE: &str = "ChaumPedersenPossessionProof";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credential {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    #[serde(rename = "type")]
+    pub kind: Vec<String>,
+    pub issuer: String,
+    /// UTC, as `YYYY-MM-DDTHH:MM:SSZ`
+    #[serde(rename = "issuanceDate")]
+    pub issuance_date: String,
+    #[serde(rename = "credentialSubject")]
+    pub subject: CredentialSubject,
+    pub proof: CredentialProof,
+}
+
+/// Who the credential is about and their public key, numbers in lowercase hex
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialSubject {
+    pub id: String,
+    /// Descriptor of the group, see `ZKP::descriptor`
+    pub group: String,
+    pub y1: String,
+    pub y2: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialProof {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub c: String,
+    pub s: String,
+}
+
+/// Why a credential couldn't be read or verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialError {
+    Json(String),
+    /// Holds the credential or proof type that isn't supported
+    Unsupported(String),
+    /// Holds the issuance date that isn't a `YYYY-MM-DDTHH:MM:SSZ` date
+    Date(String),
+    Group(ParamError),
+    /// Holds the field that isn't a hex number
+    Encoding(&'static str),
+    InvalidProof,
+}
+
+impl fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialError::Json(err) => write!(f, "invalid credential JSON: {}", err),
+            CredentialError::Unsupported(kind) => write!(f, "unsupported type {:?}", kind),
+            CredentialError::Date(date) => write!(f, "invalid issuance date {:?}", date),
+            CredentialError::Group(err) => write!(f, "invalid group: {}", err),
+            CredentialError::Encoding(field) => write!(f, "{} is not a hex number", field),
+            CredentialError::InvalidProof => f.write_str("the proof does not verify"),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {}
+
+impl Credential {
+    /// Envelope for `proof`, made by `prove_possession` with `subject` as the user name
+    pub fn from_proof(
+        zkp: &ZKP,
+        issuer: &str,
+        subject: &str,
+        issued_at: SystemTime,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &CompactProof,
+    ) -> Credential {
+        Credential {
+            context: vec![CREDENTIALS_CONTEXT.to_string()],
+            kind: vec![
+                "VerifiableCredential".to_string(),
+                CREDENTIAL_TYPE.to_string(),
+            ],
+            issuer: issuer.to_string(),
+            issuance_date: format_date(issued_at),
+            subject: CredentialSubject {
+                id: subject.to_string(),
+                group: zkp.descriptor(),
+                y1: y1.to_str_radix(16),
+                y2: y2.to_str_radix(16),
+            },
+            proof: CredentialProof {
+                kind: PROOF_TYPE.to_string(),
+                c: proof.c.to_str_radix(16),
+                s: proof.s.to_str_radix(16),
+            },
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("credentials always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Credential, CredentialError> {
+        serde_json::from_str(json).map_err(|err| CredentialError::Json(err.to_string()))
+    }
+
+    pub fn issued_at(&self) -> Result<SystemTime, CredentialError> {
+        parse_date(&self.issuance_date)
+            .ok_or_else(|| CredentialError::Date(self.issuance_date.clone()))
+    }
+}
+
+/// Checks the envelope is well formed and its proof shows the subject knows
+/// the x behind y1 and y2
+pub fn verify_credential(credential: &Credential) -> Result<(), CredentialError> {
+    if let Some(kind) = ["VerifiableCredential", CREDENTIAL_TYPE]
+        .into_iter()
+        .find(|kind| !credential.kind.iter().any(|k| k == kind))
+    {
+        return Err(CredentialError::Unsupported(kind.to_string()));
+    }
+    if credential.proof.kind != PROOF_TYPE {
+        return Err(CredentialError::Unsupported(credential.proof.kind.clone()));
+    }
+    credential.issued_at()?;
+
+    let subject = &credential.subject;
+    let zkp: ZKP = subject.group.parse().map_err(CredentialError::Group)?;
+    let hex = |field: &'static str, value: &str| {
+        BigUint::parse_bytes(value.as_bytes(), 16).ok_or(CredentialError::Encoding(field))
+    };
+    let y1 = hex("y1", &subject.y1)?;
+    let y2 = hex("y2", &subject.y2)?;
+    let proof = CompactProof {
+        c: hex("c", &credential.proof.c)?,
+        s: hex("s", &credential.proof.s)?,
+    };
+    if !zkp.verify_possession(&y1, &y2, &proof, &subject.id) {
+        return Err(CredentialError::InvalidProof);
+    }
+    Ok(())
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ`, times before the epoch are written as the epoch
+fn format_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // days to a civil date, after Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let (era, doe) = (z / 146_097, z % 146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+fn parse_date(date: &str) -> Option<SystemTime> {
+    let bytes = date.as_bytes();
+    if bytes.len() != 20 || !date.is_ascii() {
+        return None;
+    }
+    let separators = [
+        (4, b'-'),
+        (7, b'-'),
+        (10, b'T'),
+        (13, b':'),
+        (16, b':'),
+        (19, b'Z'),
+    ];
+    if separators.iter().any(|&(i, c)| bytes[i] != c) {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| date[range].parse::<u64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    // the inverse, `days_from_civil`
+    let year = year - u64::from(month <= 2);
+    let (era, yoe) = (year / 400, year % 400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+
+    let time =
+        UNIX_EPOCH + Duration::from_secs(days * 86_400 + hour * 3_600 + minute * 60 + second);
+    // rejects days past the end of their month, e.g. February 30th
+    (format_date(time) == date).then_some(time)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_credential_round_trip() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let proof = zkp.prove_possession(&k, &x, "did:example:peggy");
+        let issued_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let credential = Credential::from_proof(
+            &zkp,
+            "https://issuer.example",
+            "did:example:peggy",
+            issued_at,
+            &y1,
+            &y2,
+            &proof,
+        );
+        assert_eq!(credential.issuance_date, "2023-11-14T22:13:20Z");
+
+        let json = credential.to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["@context"][0], CREDENTIALS_CONTEXT);
+        assert_eq!(value["credentialSubject"]["id"], "did:example:peggy");
+        assert_eq!(value["proof"]["type"], PROOF_TYPE);
+
+        let parsed = Credential::from_json(&json).unwrap();
+        assert_eq!(parsed, credential);
+        assert_eq!(parsed.issued_at(), Ok(issued_at));
+        assert_eq!(verify_credential(&parsed), Ok(()));
+
+        // the proof is bound to the subject
+        let mut other_subject = parsed.clone();
+        other_subject.subject.id = "did:example:mallory".to_string();
+        assert_eq!(
+            verify_credential(&other_subject),
+            Err(CredentialError::InvalidProof)
+        );
+
+        let mut bad_hex = parsed.clone();
+        bad_hex.proof.s = "xyz".to_string();
+        assert_eq!(
+            verify_credential(&bad_hex),
+            Err(CredentialError::Encoding("s"))
+        );
+
+        let mut bad_date = parsed;
+        bad_date.issuance_date = "2023-02-30T00:00:00Z".to_string();
+        assert!(matches!(
+            verify_credential(&bad_date),
+            Err(CredentialError::Date(_))
+        ));
+        assert!(matches!(
+            Credential::from_json("{}"),
+            Err(CredentialError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_dates() {
+        for (secs, date) in [
+            (0, "1970-01-01T00:00:00Z"),
+            (951_782_400, "2000-02-29T00:00:00Z"),
+            (4_102_444_799, "2099-12-31T23:59:59Z"),
+        ] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(format_date(time), date);
+            assert_eq!(parse_date(date), Some(time));
+        }
+        for date in [
+            "1969-12-31T23:59:59Z",
+            "2023-13-01T00:00:00Z",
+            "2023-01-01 00:00:00Z",
+        ] {
+            assert_eq!(parse_date(date), None, "{}", date);
+        }
+    }
+}
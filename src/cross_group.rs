@@ -0,0 +1,232 @@
+use num_bigint::BigUint;
+use num_traits::One;
+
+use crate::ZKP;
+
+/// Bits k is wider than c * x, so that s = k + c * x leaks nothing about x
+/// beyond a 2^-80 statistical distance
+pub const CROSS_GROUP_SLACK_BITS: u64 = 80;
+
+/// Answer showing y1 = alpha^x mod p1 and y2 = gamma^x mod p2 share x, where
+/// gamma is the alpha of another group with a different order. The two orders
+/// leave no common modulus to reduce in, so s is an integer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossGroupProof {
+    pub s: BigUint,
+}
+
+/// Sizes of x and c in a cross-group proof. s is only bound to one x if it
+/// can't wrap around either order, otherwise the prover can solve for it by
+/// CRT with different exponents in each group. Both are bounded so that k,
+/// and so s, stay below 2^`nonce_bits`, itself below min(q1, q2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrossGroupBounds {
+    /// x is below 2^witness_bits
+    pub witness_bits: u64,
+    /// c is below 2^challenge_bits, a cheating prover gets through with
+    /// probability 2^-challenge_bits
+    pub challenge_bits: u64,
+}
+
+impl CrossGroupBounds {
+    /// witness_bits + challenge_bits + `CROSS_GROUP_SLACK_BITS`, the size of
+    /// k and the bound on s
+    pub fn nonce_bits(&self) -> u64 {
+        self.witness_bits + self.challenge_bits + CROSS_GROUP_SLACK_BITS
+    }
+
+    /// Whether s stays below the order of both groups
+    pub fn fit(&self, zkp1: &ZKP, zkp2: &ZKP) -> bool {
+        self.nonce_bits() < zkp1.cross_group_order(zkp2).bits()
+    }
+
+    /// Random k below 2^`nonce_bits`
+    pub fn nonce(&self) -> BigUint {
+        ZKP::generate_random_below(&(BigUint::one() << self.nonce_bits()))
+    }
+
+    /// Random c below 2^challenge_bits
+    pub fn challenge(&self) -> BigUint {
+        ZKP::generate_random_below(&(BigUint::one() << self.challenge_bits))
+    }
+}
+
+impl ZKP {
+    /// min(q1, q2), every bound of `CrossGroupBounds` has to stay below it
+    pub fn cross_group_order(&self, other: &ZKP) -> BigUint {
+        self.q.clone().min(other.q.clone())
+    }
+
+    /// r1 = alpha^k mod p1
+    /// r2 = gamma^k mod p2
+    pub fn cross_group_commit(&self, other: &ZKP, k: &BigUint) -> (BigUint, BigUint) {
+        (
+            ZKP::exponentiate(&self.alpha, k, &self.p),
+            ZKP::exponentiate(&other.alpha, k, &other.p),
+        )
+    }
+
+    /// s = k + c * x, with k from `CrossGroupBounds::nonce`. None, with
+    /// probability below 2^-`CROSS_GROUP_SLACK_BITS`, when s reaches
+    /// 2^`nonce_bits` and would be rejected, the prover commits to a new k
+    /// and tries again.
+    pub fn prove_cross_group(
+        &self,
+        other: &ZKP,
+        bounds: &CrossGroupBounds,
+        k: &BigUint,
+        c: &BigUint,
+        x: &BigUint,
+    ) -> Option<CrossGroupProof> {
+        assert!(bounds.fit(self, other), "bounds don't fit the group orders");
+        assert!(x.bits() <= bounds.witness_bits, "x is too large");
+        assert!(c.bits() <= bounds.challenge_bits, "c is too large");
+        let s = k + c * x;
+        (s.bits() <= bounds.nonce_bits()).then_some(CrossGroupProof { s })
+    }
+
+    /// alpha^s = r1 * y1^c mod p1
+    /// gamma^s = r2 * y2^c mod p2
+    /// with s below 2^`nonce_bits`
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_cross_group(
+        &self,
+        other: &ZKP,
+        bounds: &CrossGroupBounds,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        proof: &CrossGroupProof,
+    ) -> bool {
+        let CrossGroupProof { s } = proof;
+        if !bounds.fit(self, other) {
+            return false;
+        }
+        if c.bits() > bounds.challenge_bits || s.bits() > bounds.nonce_bits() {
+            return false;
+        }
+        // outside its subgroup, y could carry a component the challenge can't see
+        if !self.is_in_subgroup(y1) || !other.is_in_subgroup(y2) {
+            return false;
+        }
+        if !self.is_in_subgroup(r1) || !other.is_in_subgroup(r2) {
+            return false;
+        }
+
+        let sol1 = r1 * ZKP::exponentiate(y1, c, &self.p) % &self.p;
+        let sol2 = r2 * ZKP::exponentiate(y2, c, &other.p) % &other.p;
+        ZKP::exponentiate(&self.alpha, s, &self.p) == sol1
+            && ZKP::exponentiate(&other.alpha, s, &other.p) == sol2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Group;
+
+    const BOUNDS: CrossGroupBounds = CrossGroupBounds {
+        witness_bits: 40,
+        challenge_bits: 32,
+    };
+
+    #[test]
+    fn test_cross_group() {
+        let zkp1 = Group::Rfc5114_1024_160.zkp();
+        let zkp2 = Group::Rfc5114_2048_224.zkp();
+        assert_eq!(zkp1.cross_group_order(&zkp2), zkp1.q);
+        assert!(BOUNDS.fit(&zkp1, &zkp2));
+        let too_wide = CrossGroupBounds {
+            witness_bits: 48,
+            ..BOUNDS
+        };
+        assert!(!too_wide.fit(&zkp1, &zkp2));
+
+        let x = ZKP::generate_random_below(&(BigUint::one() << BOUNDS.witness_bits));
+        let y1 = ZKP::exponentiate(&zkp1.alpha, &x, &zkp1.p);
+        let y2 = ZKP::exponentiate(&zkp2.alpha, &x, &zkp2.p);
+        // y2 for another exponent of the same size
+        let y2_other = ZKP::exponentiate(&zkp2.alpha, &(&x ^ BigUint::one()), &zkp2.p);
+
+        for _ in 0..4 {
+            let k = BOUNDS.nonce();
+            let c = BOUNDS.challenge();
+            let (r1, r2) = zkp1.cross_group_commit(&zkp2, &k);
+            let Some(proof) = zkp1.prove_cross_group(&zkp2, &BOUNDS, &k, &c, &x) else {
+                continue;
+            };
+            let verify = |y2: &BigUint, c: &BigUint, proof: &CrossGroupProof| {
+                zkp1.verify_cross_group(&zkp2, &BOUNDS, &r1, &r2, &y1, y2, c, proof)
+            };
+            assert!(verify(&y2, &c, &proof));
+            assert!(!verify(&y2_other, &c, &proof));
+            assert!(!verify(&y2, &c, &CrossGroupProof { s: &proof.s + 1u32 }));
+            // a challenge wider than the bounds
+            assert!(!verify(&y2, &(&c + (BigUint::one() << 32u32)), &proof));
+            // the groups aren't interchangeable
+            assert!(!zkp2.verify_cross_group(&zkp1, &BOUNDS, &r2, &r1, &y1, &y2, &c, &proof));
+        }
+    }
+
+    #[test]
+    fn test_crt_forgery_is_rejected() {
+        // s solved by CRT for x1 = 3 in the order 11 group and x2 = 5 in the
+        // order 23 one, which their equations alone accept
+        let zkp1 = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let zkp2 = ZKP {
+            p: BigUint::from(47u32),
+            q: BigUint::from(23u32),
+            alpha: BigUint::from(2u32),
+            beta: BigUint::from(4u32),
+        };
+        let y1 = ZKP::exponentiate(&zkp1.alpha, &BigUint::from(3u32), &zkp1.p);
+        let y2 = ZKP::exponentiate(&zkp2.alpha, &BigUint::from(5u32), &zkp2.p);
+        let (k, c) = (BigUint::from(7u32), BigUint::from(2u32));
+        let (r1, r2) = zkp1.cross_group_commit(&zkp2, &k);
+        // s = 13 mod 11 and 17 mod 23
+        let s = (0..253u32)
+            .map(BigUint::from)
+            .find(|s| s % 11u32 == BigUint::from(2u32) && s % 23u32 == BigUint::from(17u32))
+            .unwrap();
+        assert_eq!(
+            ZKP::exponentiate(&zkp1.alpha, &s, &zkp1.p),
+            &r1 * ZKP::exponentiate(&y1, &c, &zkp1.p) % &zkp1.p
+        );
+        assert_eq!(
+            ZKP::exponentiate(&zkp2.alpha, &s, &zkp2.p),
+            &r2 * ZKP::exponentiate(&y2, &c, &zkp2.p) % &zkp2.p
+        );
+        // no bounds fit an order of 11, so nothing is accepted in these groups
+        let bounds = CrossGroupBounds {
+            witness_bits: 2,
+            challenge_bits: 2,
+        };
+        let proof = CrossGroupProof { s };
+        assert!(!zkp1.verify_cross_group(&zkp2, &bounds, &r1, &r2, &y1, &y2, &c, &proof));
+
+        // in real groups the forger's s is as large as q1 and fails the bound:
+        // x1 + q1 is x1 in the first group and another exponent in the second
+        let zkp1 = Group::Rfc5114_1024_160.zkp();
+        let zkp2 = Group::Rfc5114_2048_224.zkp();
+        let x1 = BigUint::from(3u32);
+        let x2 = &x1 + &zkp1.q;
+        let y1 = ZKP::exponentiate(&zkp1.alpha, &x1, &zkp1.p);
+        let y2 = ZKP::exponentiate(&zkp2.alpha, &x2, &zkp2.p);
+        let k = BOUNDS.nonce();
+        let c = BigUint::from(0x8000_0001u32);
+        let (r1, r2) = zkp1.cross_group_commit(&zkp2, &k);
+        let forged = CrossGroupProof { s: &k + &c * &x2 };
+        assert_eq!(
+            ZKP::exponentiate(&zkp1.alpha, &forged.s, &zkp1.p),
+            &r1 * ZKP::exponentiate(&y1, &c, &zkp1.p) % &zkp1.p
+        );
+        assert!(!zkp1.verify_cross_group(&zkp2, &BOUNDS, &r1, &r2, &y1, &y2, &c, &forged));
+    }
+}
@@ -0,0 +1,62 @@
+use num_bigint::BigUint;
+use subtle::{Choice, ConstantTimeEq};
+
+use crate::ZKP;
+
+impl ZKP {
+    /// Same check as `verify`, but r1 and r2 are compared in constant time and
+    /// both equations are always evaluated. The exponentiations themselves are
+    /// not constant time, they only involve public values, and neither is the
+    /// `validate_challenge` check of the public c.
+    pub fn verify_ct(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Choice {
+        let sol1 = ZKP::exponentiate(&self.alpha, s, &self.p) * ZKP::exponentiate(y1, c, &self.p)
+            % &self.p;
+        let sol2 =
+            ZKP::exponentiate(&self.beta, s, &self.p) * ZKP::exponentiate(y2, c, &self.p) % &self.p;
+
+        let padded = |n: &BigUint| ZKP::to_padded_bytes(n, &self.p);
+        let in_range = Choice::from(u8::from(self.validate_challenge(c).is_ok()));
+        in_range & padded(r1).ct_eq(&padded(&sol1)) & padded(r2).ct_eq(&padded(&sol2))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verify_ct_matches_verify() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let (y1, y2) = (BigUint::from(2u32), BigUint::from(3u32));
+        let c = BigUint::from(4u32);
+        // every (r1, r2, s) in range, including the honest transcript r = (8, 4), s = 5
+        for r1 in 1..23u32 {
+            for r2 in [4u32, 5] {
+                for s in 0..11u32 {
+                    let (r1, r2, s) = (r1.into(), r2.into(), s.into());
+                    assert_eq!(
+                        bool::from(zkp.verify_ct(&r1, &r2, &y1, &y2, &c, &s)),
+                        zkp.verify(&r1, &r2, &y1, &y2, &c, &s)
+                    );
+                }
+            }
+        }
+        let honest = [8u32, 4, 5].map(BigUint::from);
+        assert!(bool::from(
+            zkp.verify_ct(&honest[0], &honest[1], &y1, &y2, &c, &honest[2])
+        ));
+    }
+}
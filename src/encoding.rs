@@ -0,0 +1,301 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// How elements are written as bytes in `encode_transcript`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ElementEncoding {
+    /// Big-endian, left padded with zeros to the byte length of the modulus,
+    /// as `ZKP::to_padded_bytes`
+    #[default]
+    Padded,
+    /// LEB128 byte count followed by the big-endian bytes without leading
+    /// zeros, zero is the single byte 0. Smaller for values well below the
+    /// modulus, such as short challenges.
+    Varint,
+}
+
+/// Why bytes didn't decode into elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended inside an element
+    Truncated,
+    /// An overlong byte count or a leading zero byte, which would give the
+    /// same value a second encoding
+    NonCanonical,
+    /// The value is not below its modulus
+    OutOfRange,
+    /// Bytes left over after the last element
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => f.write_str("input ends inside an element"),
+            DecodeError::NonCanonical => f.write_str("element is not minimally encoded"),
+            DecodeError::OutOfRange => f.write_str("element is not below its modulus"),
+            DecodeError::TrailingBytes => f.write_str("bytes left after the last element"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn width(modulus: &BigUint) -> usize {
+    modulus.bits().div_ceil(8) as usize
+}
+
+impl ElementEncoding {
+    /// Appends n, which must be below modulus
+    pub fn encode_into(self, n: &BigUint, modulus: &BigUint, out: &mut Vec<u8>) {
+        debug_assert!(n < modulus);
+        match self {
+            ElementEncoding::Padded => out.extend(ZKP::to_padded_bytes(n, modulus)),
+            ElementEncoding::Varint => {
+                let bytes = match n.bits() {
+                    0 => Vec::new(),
+                    _ => n.to_bytes_be(),
+                };
+                let mut len = bytes.len();
+                loop {
+                    let low = (len & 0x7f) as u8;
+                    len >>= 7;
+                    if len == 0 {
+                        out.push(low);
+                        break;
+                    }
+                    out.push(low | 0x80);
+                }
+                out.extend(bytes);
+            }
+        }
+    }
+
+    pub fn encode(self, n: &BigUint, modulus: &BigUint) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(n, modulus, &mut out);
+        out
+    }
+
+    /// Reads one element below modulus off the front of bytes, returning it
+    /// with the bytes after it
+    pub fn decode<'a>(
+        self,
+        bytes: &'a [u8],
+        modulus: &BigUint,
+    ) -> Result<(BigUint, &'a [u8]), DecodeError> {
+        let width = width(modulus);
+        let (value, rest) = match self {
+            ElementEncoding::Padded => {
+                if bytes.len() < width {
+                    return Err(DecodeError::Truncated);
+                }
+                bytes.split_at(width)
+            }
+            ElementEncoding::Varint => {
+                let mut len = 0usize;
+                let mut read = 0;
+                loop {
+                    let byte = *bytes.get(read).ok_or(DecodeError::Truncated)?;
+                    read += 1;
+                    len |= ((byte & 0x7f) as usize) << (7 * (read - 1));
+                    if byte & 0x80 == 0 {
+                        if byte == 0 && read > 1 {
+                            return Err(DecodeError::NonCanonical);
+                        }
+                        break;
+                    }
+                    // more bytes than the modulus could ever need
+                    if len > width || read >= 4 {
+                        return Err(DecodeError::OutOfRange);
+                    }
+                }
+                if len > width {
+                    return Err(DecodeError::OutOfRange);
+                }
+                let rest = &bytes[read..];
+                if rest.len() < len {
+                    return Err(DecodeError::Truncated);
+                }
+                let (value, rest) = rest.split_at(len);
+                if value.first() == Some(&0) {
+                    return Err(DecodeError::NonCanonical);
+                }
+                (value, rest)
+            }
+        };
+        let value = BigUint::from_bytes_be(value);
+        if value >= *modulus {
+            return Err(DecodeError::OutOfRange);
+        }
+        Ok((value, rest))
+    }
+}
+
+impl ZKP {
+    /// r1, r2, y1 and y2 below p, then c and s below q, one after the other
+    pub fn encode_transcript(
+        &self,
+        encoding: ElementEncoding,
+        transcript: [&BigUint; 6],
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (i, n) in transcript.into_iter().enumerate() {
+            let modulus = if i < 4 { &self.p } else { &self.q };
+            encoding.encode_into(n, modulus, &mut out);
+        }
+        out
+    }
+
+    /// Inverse of `encode_transcript`, every element range checked against its
+    /// modulus and the whole input consumed
+    pub fn decode_transcript(
+        &self,
+        encoding: ElementEncoding,
+        mut bytes: &[u8],
+    ) -> Result<[BigUint; 6], DecodeError> {
+        let mut transcript: [BigUint; 6] = Default::default();
+        for (i, n) in transcript.iter_mut().enumerate() {
+            let modulus = if i < 4 { &self.p } else { &self.q };
+            (*n, bytes) = encoding.decode(bytes, modulus)?;
+        }
+        if !bytes.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(transcript)
+    }
+
+    /// `verify_checked` on an encoded transcript, false if it doesn't decode
+    pub fn verify_encoded(&self, encoding: ElementEncoding, bytes: &[u8]) -> bool {
+        match self.decode_transcript(encoding, bytes) {
+            Ok([r1, r2, y1, y2, c, s]) => self.verify_checked(&r1, &r2, &y1, &y2, &c, &s).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Group;
+
+    #[test]
+    fn test_elements_round_trip() {
+        let q = Group::Rfc5114_2048_256.zkp().q;
+        let mut values: Vec<BigUint> = (0..q.bits())
+            .step_by(7)
+            .map(|bits| BigUint::from(1u32) << bits)
+            .collect();
+        values.extend([
+            BigUint::default(),
+            BigUint::from(1u32),
+            BigUint::from(0xffu32),
+            &q - 1u32,
+        ]);
+        for n in values {
+            for encoding in [ElementEncoding::Padded, ElementEncoding::Varint] {
+                let mut bytes = encoding.encode(&n, &q);
+                bytes.push(0xaa);
+                assert_eq!(encoding.decode(&bytes, &q), Ok((n.clone(), &[0xaa][..])));
+            }
+            let varint = ElementEncoding::Varint.encode(&n, &q);
+            assert_eq!(varint.len(), 1 + n.bits().div_ceil(8) as usize);
+        }
+        assert_eq!(ElementEncoding::Varint.encode(&BigUint::default(), &q), [0]);
+        assert_eq!(
+            ElementEncoding::Padded
+                .encode(&BigUint::default(), &q)
+                .len(),
+            32
+        );
+    }
+
+    #[test]
+    fn test_varint_length_takes_several_bytes() {
+        let p = Group::Rfc5114_2048_256.zkp().p;
+        let n = &p - 1u32;
+        let bytes = ElementEncoding::Varint.encode(&n, &p);
+        // 256 bytes, 0x80 0x02 in LEB128
+        assert_eq!(bytes[..2], [0x80, 0x02]);
+        assert_eq!(ElementEncoding::Varint.decode(&bytes, &p), Ok((n, &[][..])));
+    }
+
+    #[test]
+    fn test_varint_rejects_ambiguous_or_bad_input() {
+        let q = BigUint::from(0x1_0001u32);
+        let varint = ElementEncoding::Varint;
+        assert_eq!(varint.decode(&[], &q), Err(DecodeError::Truncated));
+        assert_eq!(varint.decode(&[2, 1], &q), Err(DecodeError::Truncated));
+        assert_eq!(varint.decode(&[0x80], &q), Err(DecodeError::Truncated));
+        // leading zero byte, and a length with an empty last group
+        assert_eq!(
+            varint.decode(&[2, 0, 4], &q),
+            Err(DecodeError::NonCanonical)
+        );
+        assert_eq!(
+            varint.decode(&[0x81, 0, 4], &q),
+            Err(DecodeError::NonCanonical)
+        );
+        // longer than q could be, and q itself
+        assert_eq!(
+            varint.decode(&[4, 1, 0, 0, 0], &q),
+            Err(DecodeError::OutOfRange)
+        );
+        assert_eq!(
+            varint.decode(&[0xff, 0xff, 0xff, 0xff, 0x0f], &q),
+            Err(DecodeError::OutOfRange)
+        );
+        assert_eq!(
+            varint.decode(&[3, 1, 0, 1], &q),
+            Err(DecodeError::OutOfRange)
+        );
+        assert_eq!(
+            varint.decode(&[3, 1, 0, 0], &q),
+            Ok((BigUint::from(0x1_0000u32), &[][..]))
+        );
+
+        let padded = ElementEncoding::Padded;
+        assert_eq!(padded.decode(&[0, 4], &q), Err(DecodeError::Truncated));
+        assert_eq!(padded.decode(&[1, 0, 1], &q), Err(DecodeError::OutOfRange));
+    }
+
+    #[test]
+    fn test_verify_encoded() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let [r1, r2, y1, y2, c, s] = [8u32, 4, 2, 3, 4, 5].map(BigUint::from);
+        for encoding in [ElementEncoding::Padded, ElementEncoding::Varint] {
+            let bytes = zkp.encode_transcript(encoding, [&r1, &r2, &y1, &y2, &c, &s]);
+            assert_eq!(
+                zkp.decode_transcript(encoding, &bytes),
+                Ok([
+                    r1.clone(),
+                    r2.clone(),
+                    y1.clone(),
+                    y2.clone(),
+                    c.clone(),
+                    s.clone()
+                ])
+            );
+            assert!(zkp.verify_encoded(encoding, &bytes));
+
+            let mut trailing = bytes.clone();
+            trailing.push(0);
+            assert_eq!(
+                zkp.decode_transcript(encoding, &trailing),
+                Err(DecodeError::TrailingBytes)
+            );
+            assert!(!zkp.verify_encoded(encoding, &bytes[..bytes.len() - 1]));
+
+            let wrong_s = zkp.encode_transcript(encoding, [&r1, &r2, &y1, &y2, &c, &(&s + 1u32)]);
+            assert!(!zkp.verify_encoded(encoding, &wrong_s));
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Encryption at rest for the public keys a store holds.
+//!
+//! Records are sealed with XChaCha20-Poly1305, whose 192 bit nonces are long
+//! enough to draw at random for every write.
+
+use std::{fmt, str::FromStr};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use num_bigint::BigUint;
+use rand::RngCore;
+
+use crate::{
+    store::{StoreError, UserAuthInfo, UserStore},
+    AuthId,
+};
+
+pub const STORE_KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+
+/// 256 bit key records are sealed under.
+#[derive(Clone, PartialEq, Eq)]
+pub struct StoreKey([u8; STORE_KEY_LEN]);
+
+impl StoreKey {
+    pub fn new(bytes: [u8; STORE_KEY_LEN]) -> Self {
+        StoreKey(bytes)
+    }
+
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; STORE_KEY_LEN];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        StoreKey(bytes)
+    }
+}
+
+impl fmt::Debug for StoreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StoreKey(..)")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidStoreKey;
+
+impl fmt::Display for InvalidStoreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} hex characters", 2 * STORE_KEY_LEN)
+    }
+}
+
+impl std::error::Error for InvalidStoreKey {}
+
+impl FromStr for StoreKey {
+    type Err = InvalidStoreKey;
+
+    /// 64 hex characters
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        if hex.len() != 2 * STORE_KEY_LEN || !hex.is_ascii() {
+            return Err(InvalidStoreKey);
+        }
+        let mut bytes = [0u8; STORE_KEY_LEN];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| InvalidStoreKey)?;
+        }
+        Ok(StoreKey(bytes))
+    }
+}
+
+/// Store decorator that seals y1, y2 and the certificate subject of every
+/// user before handing them to the inner store, and opens them on the way out.
+///
+/// The sealed record goes in `UserAuthInfo::sealed`, with y1 and y2 zero and
+/// no certificate subject. The user name is its associated data, so records
+/// swapped between users fail to open. Names, key handles, groups and pending
+/// challenges stay in the clear, the store needs them for lookups and they
+/// expire anyway.
+pub struct EncryptedStore<S: UserStore> {
+    inner: S,
+    cipher: XChaCha20Poly1305,
+}
+
+impl<S: UserStore> EncryptedStore<S> {
+    pub fn new(inner: S, key: StoreKey) -> Self {
+        EncryptedStore {
+            inner,
+            cipher: XChaCha20Poly1305::new(&key.0.into()),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn seal(&self, mut user: UserAuthInfo) -> UserAuthInfo {
+        let mut plaintext = Vec::new();
+        for field in [user.y1.to_bytes_be(), user.y2.to_bytes_be()] {
+            plaintext.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            plaintext.extend_from_slice(&field);
+        }
+        plaintext.extend_from_slice(user.cert_subject.as_bytes());
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: &plaintext,
+            aad: user.user_name.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .expect("a record is far below the cipher's length limit");
+
+        user.sealed = [&nonce[..], &ciphertext].concat();
+        user.y1 = BigUint::default();
+        user.y2 = BigUint::default();
+        user.cert_subject = String::new();
+        user
+    }
+
+    fn open(&self, mut user: UserAuthInfo) -> Result<UserAuthInfo, StoreError> {
+        let failed =
+            || StoreError::Internal(format!("record of {} failed to open", user.user_name));
+        let (nonce, ciphertext) = user.sealed.split_at_checked(NONCE_LEN).ok_or_else(failed)?;
+        let payload = Payload {
+            msg: ciphertext,
+            aad: user.user_name.as_bytes(),
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| failed())?;
+
+        let mut rest = plaintext.as_slice();
+        let mut fields = [BigUint::default(), BigUint::default()];
+        for field in fields.iter_mut() {
+            let (len, tail) = rest.split_at_checked(4).ok_or_else(failed)?;
+            let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+            let (bytes, tail) = tail.split_at_checked(len).ok_or_else(failed)?;
+            *field = BigUint::from_bytes_be(bytes);
+            rest = tail;
+        }
+        let cert_subject = String::from_utf8(rest.to_vec()).map_err(|_| failed())?;
+
+        let [y1, y2] = fields;
+        user.y1 = y1;
+        user.y2 = y2;
+        user.cert_subject = cert_subject;
+        user.sealed = Vec::new();
+        Ok(user)
+    }
+}
+
+impl<S: UserStore> UserStore for EncryptedStore<S> {
+    fn put_user(&self, user: UserAuthInfo) -> Result<(), StoreError> {
+        self.inner.put_user(self.seal(user))
+    }
+
+    fn get_user(&self, user_name: &str) -> Result<Option<UserAuthInfo>, StoreError> {
+        self.inner
+            .get_user(user_name)?
+            .map(|user| self.open(user))
+            .transpose()
+    }
+
+    fn users(&self) -> Result<Vec<UserAuthInfo>, StoreError> {
+        self.inner
+            .users()?
+            .into_iter()
+            .map(|user| self.open(user))
+            .collect()
+    }
+
+    fn put_auth_id(&self, auth_id: AuthId, user_name: String) -> Result<(), StoreError> {
+        self.inner.put_auth_id(auth_id, user_name)
+    }
+
+    fn get_auth_id(&self, auth_id: &AuthId) -> Result<Option<String>, StoreError> {
+        self.inner.get_auth_id(auth_id)
+    }
+
+    fn put_key_handle(&self, key_handle: String, user_name: String) -> Result<(), StoreError> {
+        self.inner.put_key_handle(key_handle, user_name)
+    }
+
+    fn get_key_handle(&self, key_handle: &str) -> Result<Option<String>, StoreError> {
+        self.inner.get_key_handle(key_handle)
+    }
+
+    fn put_registration(&self, user: UserAuthInfo) -> Result<(), StoreError> {
+        self.inner.put_registration(self.seal(user))
+    }
+
+    fn put_challenge(&self, user: UserAuthInfo, auth_id: AuthId) -> Result<(), StoreError> {
+        self.inner.put_challenge(self.seal(user), auth_id)
+    }
+
+    fn flush(&self) -> Result<(), StoreError> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MemoryStore;
+
+    fn user() -> UserAuthInfo {
+        UserAuthInfo {
+            user_name: "peggy".to_string(),
+            key_handle: "handle".to_string(),
+            y1: BigUint::from(0x1234_5678u32),
+            y2: BigUint::from(9u32),
+            cert_subject: "CN=peggy".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_encrypted_store_round_trip() {
+        let store = EncryptedStore::new(MemoryStore::default(), StoreKey::generate());
+        store.put_registration(user()).unwrap();
+        assert_eq!(store.get_user("peggy"), Ok(Some(user())));
+        assert_eq!(store.users(), Ok(vec![user()]));
+        assert_eq!(
+            store.get_key_handle("handle"),
+            Ok(Some("peggy".to_string()))
+        );
+
+        let at_rest = store.inner().get_user("peggy").unwrap().unwrap();
+        assert_eq!(at_rest.y1, BigUint::default());
+        assert_eq!(at_rest.y2, BigUint::default());
+        assert_eq!(at_rest.cert_subject, "");
+        assert!(!at_rest.sealed.is_empty());
+
+        // a fresh nonce every time the record is written
+        store.put_user(user()).unwrap();
+        let rewritten = store.inner().get_user("peggy").unwrap().unwrap();
+        assert_ne!(rewritten.sealed[..NONCE_LEN], at_rest.sealed[..NONCE_LEN]);
+    }
+
+    #[test]
+    fn test_encrypted_store_rejects_tampering() {
+        let key = StoreKey::generate();
+        let store = EncryptedStore::new(MemoryStore::default(), key.clone());
+        store.put_user(user()).unwrap();
+        let sealed = store.inner().get_user("peggy").unwrap().unwrap();
+
+        let mut flipped = sealed.clone();
+        flipped.sealed[NONCE_LEN + 2] ^= 1;
+        store.inner().put_user(flipped).unwrap();
+        assert!(matches!(
+            store.get_user("peggy"),
+            Err(StoreError::Internal(_))
+        ));
+        let mut truncated = sealed.clone();
+        truncated.sealed.truncate(NONCE_LEN - 1);
+        store.inner().put_user(truncated).unwrap();
+        assert!(store.get_user("peggy").is_err());
+
+        // moved under another name
+        let moved = UserAuthInfo {
+            user_name: "mallory".to_string(),
+            ..sealed.clone()
+        };
+        store.inner().put_user(moved).unwrap();
+        assert!(store.get_user("mallory").is_err());
+
+        // opened with another key
+        let other = EncryptedStore::new(MemoryStore::default(), StoreKey::generate());
+        other.inner().put_user(sealed.clone()).unwrap();
+        assert!(other.get_user("peggy").is_err());
+        let same = EncryptedStore::new(MemoryStore::default(), key);
+        same.inner().put_user(sealed).unwrap();
+        assert_eq!(same.get_user("peggy"), Ok(Some(user())));
+    }
+
+    #[test]
+    fn test_store_key_from_hex() {
+        let hex = "00".repeat(31) + "ff";
+        let key: StoreKey = hex.parse().unwrap();
+        assert_eq!(key.0[31], 0xff);
+        assert_eq!("00".parse::<StoreKey>(), Err(InvalidStoreKey));
+        assert!(("zz".repeat(32)).parse::<StoreKey>().is_err());
+        assert_eq!(format!("{:?}", key), "StoreKey(..)");
+    }
+}
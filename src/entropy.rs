@@ -0,0 +1,280 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::{distributions::Alphanumeric, CryptoRng, Rng, RngCore};
+
+use crate::{ChallengeSource, IdGenerator};
+
+/// A CSPRNG that can move between threads, any `RngCore + CryptoRng + Send`
+pub trait SecureRng: RngCore + CryptoRng + Send {}
+
+impl<R: RngCore + CryptoRng + Send> SecureRng for R {}
+
+/// Random number generator behind an `Entropy`
+pub type BoxedRng = Box<dyn SecureRng>;
+
+/// Shared handle to a user-provided CSPRNG, e.g. a hardware RNG, for everything
+/// that would otherwise draw from the thread's CSPRNG: a prover's nonces with
+/// `Prover::with_entropy` and the sigma provers' `with_entropy`, challenges as
+/// a `ChallengeSource` and ids as an `IdGenerator`. The `ZKP` generators take
+/// one in their `_with` variants. Clones draw from the same generator, in turn.
+#[derive(Clone)]
+pub struct Entropy {
+    rng: Arc<Mutex<BoxedRng>>,
+}
+
+impl Entropy {
+    pub fn new(rng: impl RngCore + CryptoRng + Send + 'static) -> Self {
+        Entropy::from_boxed(Box::new(rng))
+    }
+
+    pub fn from_boxed(rng: BoxedRng) -> Self {
+        Entropy {
+            rng: Arc::new(Mutex::new(rng)),
+        }
+    }
+
+    /// Uniform in [0, bound)
+    pub fn below(&self, bound: &BigUint) -> BigUint {
+        self.rng.lock().unwrap().gen_biguint_below(bound)
+    }
+
+    /// Runs `f` on the generator, holding it for the whole call so the draws
+    /// come in one run
+    pub(crate) fn with_rng<T>(&self, f: impl FnOnce(&mut dyn SecureRng) -> T) -> T {
+        f(&mut **self.rng.lock().unwrap())
+    }
+
+    /// `len` ASCII alphanumerics
+    pub fn string(&self, len: usize) -> String {
+        let mut rng = self.rng.lock().unwrap();
+        (&mut **rng)
+            .sample_iter(Alphanumeric)
+            .take(len)
+            .map(char::from)
+            .collect()
+    }
+}
+
+impl fmt::Debug for Entropy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Entropy")
+    }
+}
+
+/// Bytes read from a device such as `/dev/hwrng`, trusted to be
+/// cryptographically random.
+#[derive(Debug)]
+pub struct DeviceRng {
+    device: File,
+}
+
+impl DeviceRng {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(DeviceRng {
+            device: File::open(path)?,
+        })
+    }
+}
+
+impl RngCore for DeviceRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Panics if the device can't be read, there is no weaker source to fall
+    /// back to
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("could not read the entropy device")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.device.read_exact(dest).map_err(rand::Error::new)
+    }
+}
+
+impl CryptoRng for DeviceRng {}
+
+impl ChallengeSource for Entropy {
+    fn challenge(&self, q: &BigUint) -> BigUint {
+        self.below(q)
+    }
+}
+
+impl IdGenerator for Entropy {
+    fn generate(&self, len: usize) -> String {
+        self.string(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ChaumPedersen, OkamotoProver, Prover, SchnorrProver, SigmaProver, SigmaVerifier, Verifier,
+        ZKP,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Seeded generator that counts the words it hands out, standing in for a
+    /// hardware RNG
+    struct CountingRng {
+        inner: StdRng,
+        draws: Arc<AtomicUsize>,
+    }
+
+    impl RngCore for CountingRng {
+        fn next_u32(&mut self) -> u32 {
+            self.draws.fetch_add(1, Ordering::Relaxed);
+            self.inner.next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.draws.fetch_add(1, Ordering::Relaxed);
+            self.inner.next_u64()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            self.draws.fetch_add(1, Ordering::Relaxed);
+            self.inner.fill_bytes(dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.draws.fetch_add(1, Ordering::Relaxed);
+            self.inner.try_fill_bytes(dest)
+        }
+    }
+
+    impl CryptoRng for CountingRng {}
+
+    /// Source seeded with `seed`, and its count of draws
+    fn counting(seed: u64) -> (Entropy, Arc<AtomicUsize>) {
+        let draws = Arc::new(AtomicUsize::new(0));
+        let entropy = Entropy::new(CountingRng {
+            inner: StdRng::seed_from_u64(seed),
+            draws: draws.clone(),
+        });
+        (entropy, draws)
+    }
+
+    /// Id, commitment and challenge of one login, and how many draws it took
+    fn login(seed: u64) -> (String, BigUint, BigUint, usize) {
+        let (entropy, draws) = counting(seed);
+        let zkp = ZKP::new();
+        let verifier = Verifier::with_challenge_source(ZKP::new(), entropy.clone());
+        let prover = Prover::new(ZKP::new(), entropy.below(&zkp.q)).with_entropy(entropy.clone());
+        let (y1, y2) = prover.public_key();
+
+        let auth_id = entropy.generate(12);
+        let (commitment, nonce) = prover.commit();
+        let c = verifier.challenge();
+        let response = prover.respond(nonce, &c);
+        assert_eq!(
+            verifier.verify(&commitment.r1, &commitment.r2, &y1, &y2, &c, &response.s),
+            Ok(())
+        );
+        (auth_id, commitment.r1, c, draws.load(Ordering::Relaxed))
+    }
+
+    /// Everything random a client sends: its proof of possession, a login
+    /// answered to a short challenge, the sigma provers' commitments, an OR
+    /// proof and a group of its own. Also how many draws they took.
+    fn client_proofs(seed: u64) -> (Vec<BigUint>, usize) {
+        let (entropy, draws) = counting(seed);
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below_with(&zkp.q, &entropy);
+        let r = ZKP::generate_random_below_with(&zkp.q, &entropy);
+        let prover = Prover::new(ZKP::new(), x.clone()).with_entropy(entropy.clone());
+        let (y1, y2) = prover.public_key();
+        let possession = prover.prove_possession("peggy");
+        assert!(zkp.verify_possession(&y1, &y2, &possession, "peggy"));
+
+        let (commitment, nonce) = SigmaProver::commit(&prover);
+        let c = ZKP::generate_challenge_with(&zkp.q, 80, &entropy);
+        let response = SigmaProver::respond(&prover, nonce, &c);
+        let protocol = ChaumPedersen(ZKP::new());
+        assert_eq!(
+            protocol.verify(&(y1, y2), &commitment, &c, &response),
+            Ok(())
+        );
+
+        let (t, _) = SchnorrProver::new(ZKP::new(), x.clone())
+            .with_entropy(entropy.clone())
+            .commit();
+        let (u, _) = OkamotoProver::new(ZKP::new(), x.clone(), r)
+            .with_entropy(entropy.clone())
+            .commit();
+        let y = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let or = zkp.prove_or_with(&zkp.alpha, [&y, &zkp.beta], 0, &x, b"ctx", &entropy);
+        assert!(zkp.verify_or(&zkp.alpha, [&y, &zkp.beta], &or, b"ctx"));
+        let group = ZKP::generate_safe_group_with(32, &entropy, |_| {});
+
+        let [c0, c1] = or.c;
+        let values = vec![
+            possession.c,
+            commitment.r1,
+            c,
+            response.s,
+            t,
+            u,
+            c0,
+            c1,
+            group.p,
+            group.alpha,
+        ];
+        (values, draws.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn test_device_rng_reads_the_device() {
+        let path = std::env::temp_dir().join(format!(
+            "zkp-entropy-{}",
+            crate::ZKP::generate_random_string(12)
+        ));
+        std::fs::write(&path, [1, 0, 0, 0, 2]).unwrap();
+        let mut rng = DeviceRng::open(&path).unwrap();
+        assert_eq!(rng.next_u32(), 1);
+        // one byte left, not enough for another word
+        assert!(rng.try_fill_bytes(&mut [0u8; 4]).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_whole_flow_draws_from_the_entropy_source() {
+        let (auth_id, r1, c, draws) = login(7);
+        assert_eq!(auth_id.len(), 12);
+        assert!(auth_id.chars().all(|c| c.is_ascii_alphanumeric()));
+        // x, k and c take at least a word each, the id one per character
+        assert!(draws >= 15, "only {} draws", draws);
+
+        // nothing came from anywhere else, so the same seed replays the login
+        assert_eq!(login(7), (auth_id, r1.clone(), c, draws));
+        assert_ne!(login(8).1, r1);
+    }
+
+    #[test]
+    fn test_client_proofs_draw_only_from_the_entropy_source() {
+        let (values, draws) = client_proofs(7);
+        // x, r, five nonces, the OR proof's three and the short challenge
+        assert!(draws >= 11, "only {} draws", draws);
+        assert_eq!(client_proofs(7), (values.clone(), draws));
+        let (others, _) = client_proofs(8);
+        assert!(values.iter().zip(&others).all(|(a, b)| a != b));
+    }
+}
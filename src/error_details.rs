@@ -0,0 +1,189 @@
+//! gRPC rich error model: failures carry a `google.rpc.ErrorInfo` in the status
+//! details, so clients can match on a reason instead of parsing the message.
+
+use std::collections::HashMap;
+
+use prost::Message;
+use tonic::{codegen::Bytes, Code, Status};
+
+pub const ERROR_DOMAIN: &str = "zkp-auth";
+const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+
+/// google.protobuf.Any
+#[derive(Clone, PartialEq, Message)]
+pub struct Any {
+    #[prost(string, tag = "1")]
+    pub type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: Vec<u8>,
+}
+
+/// google.rpc.Status, what goes in the `grpc-status-details-bin` trailer
+#[derive(Clone, PartialEq, Message)]
+pub struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(message, repeated, tag = "3")]
+    pub details: Vec<Any>,
+}
+
+/// google.rpc.ErrorInfo
+#[derive(Clone, PartialEq, Message)]
+pub struct ErrorInfo {
+    #[prost(string, tag = "1")]
+    pub reason: String,
+    #[prost(string, tag = "2")]
+    pub domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Machine-readable reason of a failed RPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorReason {
+    UserNotFound,
+    KeyHandleNotFound,
+    KeyNotRegistered,
+    UsernameNotAllowed,
+    InvalidPossessionProof,
+    InvalidGroup,
+    WrongGroup,
+    RetiredGroup,
+    AuthIdNotFound,
+    ChallengeExpired,
+    ContextMismatch,
+    ChallengeMismatch,
+    CommitmentMismatch,
+    CommitmentReused,
+    AnswerReplayed,
+    ClientCertMismatch,
+    MalformedProof,
+    MalformedElement,
+    InvalidProof,
+    SessionNotFound,
+    SessionExpired,
+    AdminRequired,
+    StoreUnavailable,
+    StoreError,
+}
+
+impl ErrorReason {
+    pub const ALL: [ErrorReason; 24] = [
+        ErrorReason::UserNotFound,
+        ErrorReason::KeyHandleNotFound,
+        ErrorReason::KeyNotRegistered,
+        ErrorReason::UsernameNotAllowed,
+        ErrorReason::InvalidPossessionProof,
+        ErrorReason::InvalidGroup,
+        ErrorReason::WrongGroup,
+        ErrorReason::RetiredGroup,
+        ErrorReason::AuthIdNotFound,
+        ErrorReason::ChallengeExpired,
+        ErrorReason::ContextMismatch,
+        ErrorReason::ChallengeMismatch,
+        ErrorReason::CommitmentMismatch,
+        ErrorReason::CommitmentReused,
+        ErrorReason::AnswerReplayed,
+        ErrorReason::ClientCertMismatch,
+        ErrorReason::MalformedProof,
+        ErrorReason::MalformedElement,
+        ErrorReason::InvalidProof,
+        ErrorReason::SessionNotFound,
+        ErrorReason::SessionExpired,
+        ErrorReason::AdminRequired,
+        ErrorReason::StoreUnavailable,
+        ErrorReason::StoreError,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorReason::UserNotFound => "USER_NOT_FOUND",
+            ErrorReason::KeyHandleNotFound => "KEY_HANDLE_NOT_FOUND",
+            ErrorReason::KeyNotRegistered => "KEY_NOT_REGISTERED",
+            ErrorReason::UsernameNotAllowed => "USERNAME_NOT_ALLOWED",
+            ErrorReason::InvalidPossessionProof => "INVALID_POSSESSION_PROOF",
+            ErrorReason::InvalidGroup => "INVALID_GROUP",
+            ErrorReason::WrongGroup => "WRONG_GROUP",
+            ErrorReason::RetiredGroup => "RETIRED_GROUP",
+            ErrorReason::AuthIdNotFound => "AUTH_ID_NOT_FOUND",
+            ErrorReason::ChallengeExpired => "CHALLENGE_EXPIRED",
+            ErrorReason::ContextMismatch => "CONTEXT_MISMATCH",
+            ErrorReason::ChallengeMismatch => "CHALLENGE_MISMATCH",
+            ErrorReason::CommitmentMismatch => "COMMITMENT_MISMATCH",
+            ErrorReason::CommitmentReused => "COMMITMENT_REUSED",
+            ErrorReason::AnswerReplayed => "ANSWER_REPLAYED",
+            ErrorReason::ClientCertMismatch => "CLIENT_CERT_MISMATCH",
+            ErrorReason::MalformedProof => "MALFORMED_PROOF",
+            ErrorReason::MalformedElement => "MALFORMED_ELEMENT",
+            ErrorReason::InvalidProof => "INVALID_PROOF",
+            ErrorReason::SessionNotFound => "SESSION_NOT_FOUND",
+            ErrorReason::SessionExpired => "SESSION_EXPIRED",
+            ErrorReason::AdminRequired => "ADMIN_REQUIRED",
+            ErrorReason::StoreUnavailable => "STORE_UNAVAILABLE",
+            ErrorReason::StoreError => "STORE_ERROR",
+        }
+    }
+
+    pub fn from_reason(reason: &str) -> Option<ErrorReason> {
+        ErrorReason::ALL
+            .into_iter()
+            .find(|known| known.as_str() == reason)
+    }
+}
+
+/// Status whose details hold an `ErrorInfo` with the given reason
+pub fn error_status(code: Code, reason: ErrorReason, message: impl Into<String>) -> Status {
+    let message = message.into();
+    let info = ErrorInfo {
+        reason: reason.as_str().to_string(),
+        domain: ERROR_DOMAIN.to_string(),
+        metadata: HashMap::new(),
+    };
+    let details = RpcStatus {
+        code: code as i32,
+        message: message.clone(),
+        details: vec![Any {
+            type_url: ERROR_INFO_TYPE_URL.to_string(),
+            value: info.encode_to_vec(),
+        }],
+    };
+    Status::with_details(code, message, Bytes::from(details.encode_to_vec()))
+}
+
+/// The `ErrorInfo` in the status details, if the server sent one
+pub fn error_info(status: &Status) -> Option<ErrorInfo> {
+    let details = RpcStatus::decode(status.details()).ok()?;
+    details
+        .details
+        .iter()
+        .find(|any| any.type_url == ERROR_INFO_TYPE_URL)
+        .and_then(|any| ErrorInfo::decode(any.value.as_slice()).ok())
+}
+
+/// Shorthand for the reason of a failed RPC, `None` for unknown or missing reasons
+pub fn error_reason(status: &Status) -> Option<ErrorReason> {
+    error_info(status).and_then(|info| ErrorReason::from_reason(&info.reason))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_details_round_trip() {
+        for reason in ErrorReason::ALL {
+            let status = error_status(Code::NotFound, reason, "nope");
+            assert_eq!(status.code(), Code::NotFound);
+            assert_eq!(status.message(), "nope");
+            assert_eq!(error_reason(&status), Some(reason));
+
+            let info = error_info(&status).unwrap();
+            assert_eq!(info.domain, ERROR_DOMAIN);
+            assert_eq!(info.reason, reason.as_str());
+        }
+
+        assert_eq!(error_reason(&Status::new(Code::NotFound, "nope")), None);
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use num_bigint::BigUint;
+
+use crate::VerifyError;
+
+/// Validation gate that turned a proof away, to tell client bugs (empty or
+/// oversized fields) from probing (elements outside the subgroup, unreduced
+/// challenges) and from plain wrong answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gate {
+    /// A field was left empty
+    Empty,
+    /// A field has more bytes than its modulus
+    Oversize,
+    /// An element is 0 or not below p, s not below q, or a list has the wrong length
+    Range,
+    /// An element is outside the order q subgroup
+    Subgroup,
+    /// c is not below q
+    ChallengeRange,
+    /// Everything was well formed, but the proof doesn't hold
+    Equation,
+}
+
+impl Gate {
+    pub const ALL: [Gate; 6] = [
+        Gate::Empty,
+        Gate::Oversize,
+        Gate::Range,
+        Gate::Subgroup,
+        Gate::ChallengeRange,
+        Gate::Equation,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gate::Empty => "empty",
+            Gate::Oversize => "oversize",
+            Gate::Range => "range",
+            Gate::Subgroup => "subgroup",
+            Gate::ChallengeRange => "challenge_range",
+            Gate::Equation => "equation",
+        }
+    }
+
+    /// The gate that reports `err`
+    pub fn of(err: &VerifyError) -> Gate {
+        match err {
+            VerifyError::Empty(_) => Gate::Empty,
+            VerifyError::Oversize(_) => Gate::Oversize,
+            VerifyError::OutOfRange("c") => Gate::ChallengeRange,
+            VerifyError::OutOfRange(_) | VerifyError::LengthMismatch => Gate::Range,
+            VerifyError::NotInSubgroup(_) => Gate::Subgroup,
+            VerifyError::R1Mismatch
+            | VerifyError::R2Mismatch
+            | VerifyError::ChallengeMismatch
+            | VerifyError::BaseMismatch(_)
+            | VerifyError::AuxiliaryMismatch => Gate::Equation,
+        }
+    }
+}
+
+/// Rejections counted per `Gate`, safe to share between threads
+#[derive(Debug, Default)]
+pub struct GateCounters {
+    counts: [AtomicU64; 6],
+}
+
+impl GateCounters {
+    pub fn record(&self, gate: Gate) {
+        self.counts[gate as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts the gate of a failed verification, returning the result as is
+    pub fn count<T>(&self, result: Result<T, VerifyError>) -> Result<T, VerifyError> {
+        if let Err(err) = &result {
+            self.record(Gate::of(err));
+        }
+        result
+    }
+
+    pub fn get(&self, gate: Gate) -> u64 {
+        self.counts[gate as usize].load(Ordering::Relaxed)
+    }
+
+    /// Every gate by name with its count
+    pub fn snapshot(&self) -> Vec<(&'static str, u64)> {
+        Gate::ALL
+            .iter()
+            .map(|gate| (gate.name(), self.get(*gate)))
+            .collect()
+    }
+}
+
+/// A field as sent on the wire, big-endian: refused if empty or longer than
+/// `modulus`, otherwise left for the range checks
+pub fn decode_field(
+    name: &'static str,
+    bytes: &[u8],
+    modulus: &BigUint,
+) -> Result<BigUint, VerifyError> {
+    if bytes.is_empty() {
+        return Err(VerifyError::Empty(name));
+    }
+    if bytes.len() as u64 > modulus.bits().div_ceil(8) {
+        return Err(VerifyError::Oversize(name));
+    }
+    Ok(BigUint::from_bytes_be(bytes))
+}
@@ -0,0 +1,814 @@
+use std::{fmt, str::FromStr};
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{Num, One, Zero};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::{prime, Entropy, ZKP};
+
+// https://www.rfc-editor.org/rfc/rfc5114.html#section-2.1
+const RFC5114_1024_160_P: &str = "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B616073E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BFACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371";
+const RFC5114_1024_160_Q: &str = "F518AA8781A8DF278ABA4E7D64B7CB9D49462353";
+const RFC5114_1024_160_ALPHA: &str = "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28AD662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5";
+
+// https://www.rfc-editor.org/rfc/rfc5114.html#section-2.2
+const RFC5114_2048_224_P: &str = "\
+    AD107E1E9123A9D0D660FAA79559C51FA20D64E5683B9FD1\
+    B54B1597B61D0A75E6FA141DF95A56DBAF9A3C407BA1DF15\
+    EB3D688A309C180E1DE6B85A1274A0A66D3F8152AD6AC212\
+    9037C9EDEFDA4DF8D91E8FEF55B7394B7AD5B7D0B6C12207\
+    C9F98D11ED34DBF6C6BA0B2C8BBC27BE6A00E0A0B9C49708\
+    B3BF8A317091883681286130BC8985DB1602E714415D9330\
+    278273C7DE31EFDC7310F7121FD5A07415987D9ADC0A486D\
+    CDF93ACC44328387315D75E198C641A480CD86A1B9E587E8\
+    BE60E69CC928B2B9C52172E413042E9B23F10B0E16E79763\
+    C9B53DCF4BA80A29E3FB73C16B8E75B97EF363E2FFA31F71\
+    CF9DE5384E71B81C0AC4DFFE0C10E64F";
+const RFC5114_2048_224_Q: &str = "801C0D34C58D93FE997177101F80535A4738CEBCBF389A99B36371EB";
+const RFC5114_2048_224_ALPHA: &str = "\
+    AC4032EF4F2D9AE39DF30B5C8FFDAC506CDEBE7B89998CAF\
+    74866A08CFE4FFE3A6824A4E10B9A6F0DD921F01A70C4AFA\
+    AB739D7700C29F52C57DB17C620A8652BE5E9001A8D66AD7\
+    C17669101999024AF4D027275AC1348BB8A762D0521BC98A\
+    E247150422EA1ED409939D54DA7460CDB5F6C6B250717CBE\
+    F180EB34118E98D119529A45D6F834566E3025E316A330EF\
+    BB77A86F0C1AB15B051AE3D428C8F8ACB70A8137150B8EEB\
+    10E183EDD19963DDD9E263E4770589EF6AA21E7F5F2FF381\
+    B539CCE3409D13CD566AFBB48D6C019181E1BCFE94B30269\
+    EDFE72FE9B6AA4BD7B5A0F1C71CFFF4C19C418E1F6EC0179\
+    81BC087F2A7065B384B890D3191F2BFA";
+
+// https://www.rfc-editor.org/rfc/rfc5114.html#section-2.3
+const RFC5114_2048_256_P: &str = "\
+    87A8E61DB4B6663CFFBBD19C651959998CEEF608660DD0F2\
+    5D2CEED4435E3B00E00DF8F1D61957D4FAF7DF4561B2AA30\
+    16C3D91134096FAA3BF4296D830E9A7C209E0C6497517ABD\
+    5A8A9D306BCF67ED91F9E6725B4758C022E0B1EF4275BF7B\
+    6C5BFC11D45F9088B941F54EB1E59BB8BC39A0BF12307F5C\
+    4FDB70C581B23F76B63ACAE1CAA6B7902D52526735488A0E\
+    F13C6D9A51BFA4AB3AD8347796524D8EF6A167B5A41825D9\
+    67E144E5140564251CCACB83E6B486F6B3CA3F7971506026\
+    C0B857F689962856DED4010ABD0BE621C3A3960A54E710C3\
+    75F26375D7014103A4B54330C198AF126116D2276E11715F\
+    693877FAD7EF09CADB094AE91E1A1597";
+const RFC5114_2048_256_Q: &str = "8CF83642A709A097B447997640129DA299B1A47D1EB3750BA308B0FE64F5FBD3";
+const RFC5114_2048_256_ALPHA: &str = "\
+    3FB32C9B73134D0B2E77506660EDBD484CA7B18F21EF2054\
+    07F4793A1A0BA12510DBC15077BE463FFF4FED4AAC0BB555\
+    BE3A6C1B0C6B47B1BC3773BF7E8C6F62901228F8C28CBB18\
+    A55AE31341000A650196F931C77A57F2DDF463E5E9EC144B\
+    777DE62AAAB8A8628AC376D282D6ED3864E67982428EBC83\
+    1D14348F6F2F9193B5045AF2767164E1DFC967C1FB3F2E55\
+    A4BD1BFFE83B9C80D052B985D182EA0ADB2A3B7313D3FE14\
+    C8484B1E052588B9B7D2BBD2DF016199ECD06E1557CD0915\
+    B3353BBB64E0EC377FD028370DF92B52C7891428CDC67EB6\
+    184B523D1DB246C32F63078490F00EF8D647D148D4795451\
+    5E2327CFEF98C582664B4C0F6CC41659";
+
+// alpha^i is also a generator, this is the i every named group uses for beta
+const BETA_EXPONENT: &str = "266D31266FEA1E5C41564B777E69";
+
+// hashed together with p, q and alpha to pick a beta nobody knows the log of
+const BETA_SEED: &[u8] = b"zkp-chaum-pedersen beta";
+
+/// Groups rated below this many bits of security are refused by strict builders
+pub const MIN_SECURITY_BITS: u64 = 80;
+
+/// `GroupBuilder::build` warns about beta = alpha^i for i up to this, in
+/// either sign
+pub const SMALL_POWER_LIMIT: u32 = 1024;
+
+/// `generate_safe_group` reports progress once per this many candidates
+pub const SAFE_GROUP_BATCH: u64 = 64;
+
+/// Named prime order subgroups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    /// 1024-bit MODP Group with 160-bit Prime Order Subgroup
+    Rfc5114_1024_160,
+    /// 2048-bit MODP Group with 224-bit Prime Order Subgroup
+    Rfc5114_2048_224,
+    /// 2048-bit MODP Group with 256-bit Prime Order Subgroup
+    Rfc5114_2048_256,
+}
+
+impl Group {
+    pub const ALL: [Group; 3] = [
+        Group::Rfc5114_1024_160,
+        Group::Rfc5114_2048_224,
+        Group::Rfc5114_2048_256,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Group::Rfc5114_1024_160 => "rfc5114-1024-160",
+            Group::Rfc5114_2048_224 => "rfc5114-2048-224",
+            Group::Rfc5114_2048_256 => "rfc5114-2048-256",
+        }
+    }
+
+    fn hex_constants(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Group::Rfc5114_1024_160 => (
+                RFC5114_1024_160_P,
+                RFC5114_1024_160_Q,
+                RFC5114_1024_160_ALPHA,
+            ),
+            Group::Rfc5114_2048_224 => (
+                RFC5114_2048_224_P,
+                RFC5114_2048_224_Q,
+                RFC5114_2048_224_ALPHA,
+            ),
+            Group::Rfc5114_2048_256 => (
+                RFC5114_2048_256_P,
+                RFC5114_2048_256_Q,
+                RFC5114_2048_256_ALPHA,
+            ),
+        }
+    }
+
+    /// Named group whose p, q and alpha match the given instance
+    pub fn identify(zkp: &ZKP) -> Option<Group> {
+        Group::ALL.into_iter().find(|group| {
+            let named = group.zkp();
+            named.p == zkp.p && named.q == zkp.q && named.alpha == zkp.alpha
+        })
+    }
+
+    pub fn zkp(&self) -> ZKP {
+        let (p_hex, q_hex, alpha_hex) = self.hex_constants();
+
+        let p = BigUint::from_str_radix(p_hex, 16).expect("Invalid hex for p");
+        let q = BigUint::from_str_radix(q_hex, 16).expect("Invalid hex for q");
+        let alpha = BigUint::from_str_radix(alpha_hex, 16).expect("Invalid hex for alpha");
+        let beta = derive_beta(&alpha, &p);
+
+        ZKP { p, q, alpha, beta }
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Group {
+    type Err = ParamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Group::ALL
+            .into_iter()
+            .find(|group| group.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParamError::UnknownGroup(s.to_string()))
+    }
+}
+
+/// Human-readable metadata about the group a `ZKP` works in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub p_bits: u64,
+    pub q_bits: u64,
+    pub name: Option<String>,
+}
+
+impl ZKP {
+    pub fn info(&self) -> GroupInfo {
+        GroupInfo {
+            p_bits: self.p.bits(),
+            q_bits: self.q.bits(),
+            name: Group::identify(self).map(|group| group.name().to_string()),
+        }
+    }
+}
+
+impl ZKP {
+    /// Rough strength of the group in bits: half the size of q, capped by the
+    /// NIST SP 800-57 rating of p. 0 for p under 1024 bits.
+    pub fn security_bits(&self) -> u64 {
+        let p_rating = match self.p.bits() {
+            bits if bits >= 15360 => 256,
+            bits if bits >= 7680 => 192,
+            bits if bits >= 3072 => 128,
+            bits if bits >= 2048 => 112,
+            bits if bits >= 1024 => 80,
+            _ => 0,
+        };
+        p_rating.min(self.q.bits() / 2)
+    }
+}
+
+impl ZKP {
+    /// Canonical string for this group that parses back into it: the name of a
+    /// named group, or a custom descriptor otherwise
+    pub fn descriptor(&self) -> String {
+        match Group::identify(self) {
+            Some(group) if group.zkp().beta == self.beta => group.name().to_string(),
+            _ => format!(
+                "p={:x};q={:x};alpha={:x};beta={:x}",
+                self.p, self.q, self.alpha, self.beta
+            ),
+        }
+    }
+}
+
+fn derive_beta(alpha: &BigUint, p: &BigUint) -> BigUint {
+    let exp = BigUint::from_str_radix(BETA_EXPONENT, 16).expect("could not create exp");
+    ZKP::exponentiate(alpha, &exp, p)
+}
+
+impl ZKP {
+    /// Replaces beta with a generator hashed from a fixed seed, p, q and alpha.
+    /// Unlike alpha^i, nobody knows log_alpha(beta) for it.
+    pub fn with_nothing_up_my_sleeve_beta(self) -> ZKP {
+        let beta = hash_to_subgroup(&self.p, &self.q, &self.alpha);
+        ZKP { beta, ..self }
+    }
+}
+
+/// h^((p - 1) / q) for h = SHA-256(seed, p, q, alpha, counter) expanded to
+/// 16 bytes past the size of p, bumping the counter until the result isn't 1
+pub(crate) fn hash_to_subgroup(p: &BigUint, q: &BigUint, alpha: &BigUint) -> BigUint {
+    let cofactor = (p - 1u32) / q;
+    let len = p.to_bytes_be().len() + 16;
+    for counter in 0u32.. {
+        let mut bytes = Vec::with_capacity(len + 32);
+        for block in 0u32.. {
+            if bytes.len() >= len {
+                break;
+            }
+            let mut hasher = Sha256::new();
+            hasher.update(BETA_SEED);
+            for n in [p, q, alpha] {
+                let n = n.to_bytes_be();
+                hasher.update((n.len() as u32).to_be_bytes());
+                hasher.update(n);
+            }
+            hasher.update(counter.to_be_bytes());
+            hasher.update(block.to_be_bytes());
+            bytes.extend_from_slice(&hasher.finalize());
+        }
+        let h = BigUint::from_bytes_be(&bytes[..len]) % p;
+        let beta = ZKP::exponentiate(&h, &cofactor, p);
+        if !beta.is_zero() && !beta.is_one() {
+            return beta;
+        }
+    }
+    unreachable!("ran out of counters")
+}
+
+impl ZKP {
+    /// Fresh group over a `bits`-bit safe prime p = 2q + 1, with alpha a random
+    /// quadratic residue and beta from `with_nothing_up_my_sleeve_beta`.
+    ///
+    /// This takes minutes for real sizes, so `progress` gets the number of q
+    /// candidates tested after every `SAFE_GROUP_BATCH` of them and once more
+    /// when a group is found.
+    pub fn generate_safe_group(bits: u64, progress: impl FnMut(u64)) -> ZKP {
+        ZKP::safe_group_from(bits, &mut rand::thread_rng(), progress)
+    }
+
+    /// `generate_safe_group` with the candidates and alpha drawn from `entropy`.
+    /// The Miller-Rabin bases still come from the thread's CSPRNG, they are
+    /// public and only need to be unpredictable to whoever picked the candidate.
+    pub fn generate_safe_group_with(
+        bits: u64,
+        entropy: &Entropy,
+        progress: impl FnMut(u64),
+    ) -> ZKP {
+        entropy.with_rng(|rng| ZKP::safe_group_from(bits, rng, progress))
+    }
+
+    fn safe_group_from<R: Rng + ?Sized>(
+        bits: u64,
+        rng: &mut R,
+        mut progress: impl FnMut(u64),
+    ) -> ZKP {
+        assert!(bits >= 4, "a safe prime needs at least 4 bits");
+        let mut tested = 0u64;
+        let (p, q) = loop {
+            // top bit set so p has exactly `bits` bits, odd to skip the obvious composites
+            let q = rng.gen_biguint(bits - 1) | (BigUint::one() << (bits - 2)) | BigUint::one();
+            let p = (&q << 1u32) + 1u32;
+            tested += 1;
+            // one cheap round to weed out candidates, the full check only on survivors
+            if prime::is_probable_prime(&q, 1)
+                && prime::is_probable_prime(&p, 1)
+                && prime::is_probable_prime(&q, 20)
+                && prime::is_probable_prime(&p, 20)
+            {
+                break (p, q);
+            }
+            if tested.is_multiple_of(SAFE_GROUP_BATCH) {
+                progress(tested);
+            }
+        };
+        progress(tested);
+
+        // squares generate the order q subgroup, only 1 and p - 1 square to 1
+        let alpha = loop {
+            let h = rng.gen_biguint_range(&BigUint::from(2u32), &(&p - 1u32));
+            let alpha = ZKP::exponentiate(&h, &BigUint::from(2u32), &p);
+            if !alpha.is_one() {
+                break alpha;
+            }
+        };
+        let beta = hash_to_subgroup(&p, &q, &alpha);
+        ZKP { p, q, alpha, beta }
+    }
+
+    /// i with beta = alpha^i mod p, for 1 < |i| <= `SMALL_POWER_LIMIT`. Anyone
+    /// finds such an i by trying, and every honest key then has y2 = y1^i, so
+    /// the two equations collapse into one. Toy groups always have one.
+    pub fn small_power_relation(&self) -> Option<i64> {
+        let mut power = self.alpha.clone();
+        for i in 1..=i64::from(SMALL_POWER_LIMIT) {
+            if i > 1 && power == self.beta {
+                return Some(i);
+            }
+            if (&power * &self.beta % &self.p).is_one() {
+                return Some(-i);
+            }
+            power = power * &self.alpha % &self.p;
+        }
+        None
+    }
+
+    /// Builder for custom groups, for the checks `from_params` doesn't run by default
+    pub fn builder(p: BigUint, q: BigUint, alpha: BigUint, beta: BigUint) -> GroupBuilder {
+        GroupBuilder {
+            zkp: ZKP { p, q, alpha, beta },
+            rounds: 0,
+            min_security_bits: if cfg!(feature = "strict") {
+                MIN_SECURITY_BITS
+            } else {
+                0
+            },
+        }
+    }
+}
+
+/// Validates custom group parameters, see `ZKP::builder`.
+pub struct GroupBuilder {
+    zkp: ZKP,
+    rounds: usize,
+    min_security_bits: u64,
+}
+
+impl GroupBuilder {
+    /// Runs `rounds` Miller-Rabin rounds on p and q, 0 (the default) skips them
+    pub fn prime_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Refuses groups below `MIN_SECURITY_BITS`, the default with the `strict` feature
+    pub fn strict(mut self) -> Self {
+        self.min_security_bits = MIN_SECURITY_BITS;
+        self
+    }
+
+    /// Accepts groups of any size, e.g. the toy groups of tests and examples
+    pub fn allow_insecure(mut self) -> Self {
+        self.min_security_bits = 0;
+        self
+    }
+
+    pub fn build(self) -> Result<ZKP, ParamError> {
+        let ZKP { p, q, alpha, beta } = &self.zkp;
+        let one = BigUint::one();
+        if *q <= one || p <= q {
+            return Err(ParamError::InvalidGroup("expected 1 < q < p"));
+        }
+        if !((p - &one) % q).is_zero() {
+            return Err(ParamError::InvalidGroup("q does not divide p - 1"));
+        }
+        if self.rounds > 0 {
+            if !prime::is_probable_prime(p, self.rounds) {
+                return Err(ParamError::InvalidGroup("p is not prime"));
+            }
+            if !prime::is_probable_prime(q, self.rounds) {
+                return Err(ParamError::InvalidGroup("q is not prime"));
+            }
+        }
+        for generator in [alpha, beta] {
+            if *generator <= one || generator >= p || ZKP::exponentiate(generator, q, p) != one {
+                return Err(ParamError::InvalidGroup(
+                    "alpha and beta must generate the order q subgroup",
+                ));
+            }
+        }
+        if alpha == beta {
+            return Err(ParamError::InvalidGroup("alpha and beta must differ"));
+        }
+        if let Some(i) = self.zkp.small_power_relation() {
+            tracing::warn!(
+                "beta = alpha^{} mod p, the second equation checks nothing the first doesn't",
+                i
+            );
+        }
+        let security_bits = self.zkp.security_bits();
+        if security_bits < self.min_security_bits {
+            return Err(ParamError::InsecureGroup(security_bits));
+        }
+
+        Ok(self.zkp)
+    }
+}
+
+/// Custom group descriptor of the form `p=<hex>;q=<hex>;alpha=<hex>[;beta=<hex>]`.
+///
+/// When `beta` is omitted it is derived from `alpha` the same way the named groups do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamSpec {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub alpha: BigUint,
+    pub beta: Option<BigUint>,
+}
+
+impl ParamSpec {
+    pub fn zkp(self) -> Result<ZKP, ParamError> {
+        self.builder().build()
+    }
+
+    /// `ZKP::builder` for these parameters, with beta derived if omitted
+    pub fn builder(self) -> GroupBuilder {
+        let beta = match self.beta {
+            Some(beta) => beta,
+            None => derive_beta(&self.alpha, &self.p),
+        };
+        ZKP::builder(self.p, self.q, self.alpha, beta)
+    }
+}
+
+impl FromStr for ParamSpec {
+    type Err = ParamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut p, mut q, mut alpha, mut beta) = (None, None, None, None);
+
+        for entry in s
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| ParamError::Malformed(entry.to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let slot = match key {
+                "p" => &mut p,
+                "q" => &mut q,
+                "alpha" => &mut alpha,
+                "beta" => &mut beta,
+                _ => return Err(ParamError::UnknownField(key.to_string())),
+            };
+            let hex = value.strip_prefix("0x").unwrap_or(value);
+            let parsed = BigUint::from_str_radix(hex, 16)
+                .map_err(|_| ParamError::InvalidHex(key.to_string()))?;
+            if slot.replace(parsed).is_some() {
+                return Err(ParamError::DuplicateField(key.to_string()));
+            }
+        }
+
+        Ok(ParamSpec {
+            p: p.ok_or(ParamError::MissingField("p"))?,
+            q: q.ok_or(ParamError::MissingField("q"))?,
+            alpha: alpha.ok_or(ParamError::MissingField("alpha"))?,
+            beta,
+        })
+    }
+}
+
+impl FromStr for ZKP {
+    type Err = ParamError;
+
+    /// Accepts either a named group (`"rfc5114-2048-256"`) or a custom [`ParamSpec`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('=') {
+            s.parse::<ParamSpec>()?.zkp()
+        } else {
+            Ok(s.parse::<Group>()?.zkp())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamError {
+    UnknownGroup(String),
+    Malformed(String),
+    UnknownField(String),
+    DuplicateField(String),
+    MissingField(&'static str),
+    InvalidHex(String),
+    InvalidGroup(&'static str),
+    /// The group is sound but rated at this many bits, below `MIN_SECURITY_BITS`
+    InsecureGroup(u64),
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamError::UnknownGroup(name) => write!(f, "unknown group {:?}", name),
+            ParamError::Malformed(entry) => write!(f, "expected key=value, got {:?}", entry),
+            ParamError::UnknownField(key) => write!(f, "unknown parameter {:?}", key),
+            ParamError::DuplicateField(key) => write!(f, "parameter {:?} given twice", key),
+            ParamError::MissingField(key) => write!(f, "missing parameter {:?}", key),
+            ParamError::InvalidHex(key) => write!(f, "invalid hex for {:?}", key),
+            ParamError::InvalidGroup(reason) => write!(f, "invalid group: {}", reason),
+            ParamError::InsecureGroup(bits) => write!(
+                f,
+                "group offers about {} bits of security, at least {} are required",
+                bits, MIN_SECURITY_BITS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_named_groups() {
+        for group in Group::ALL {
+            assert_eq!(group.name().parse::<Group>(), Ok(group));
+        }
+        assert_eq!(
+            "RFC5114-2048-256".parse::<Group>(),
+            Ok(Group::Rfc5114_2048_256)
+        );
+
+        let zkp: ZKP = "rfc5114-1024-160".parse().unwrap();
+        let default = ZKP::new();
+        assert_eq!(zkp.p, default.p);
+        assert_eq!(zkp.beta, default.beta);
+
+        let zkp: ZKP = "rfc5114-2048-256".parse().unwrap();
+        assert_eq!(zkp.p.bits(), 2048);
+        assert_eq!(zkp.q.bits(), 256);
+        assert_eq!(
+            ZKP::exponentiate(&zkp.alpha, &zkp.q, &zkp.p),
+            BigUint::from(1u32)
+        );
+        assert_eq!(
+            ZKP::exponentiate(&zkp.beta, &zkp.q, &zkp.p),
+            BigUint::from(1u32)
+        );
+    }
+
+    /// A custom group below `MIN_SECURITY_BITS`, opted into so the test also
+    /// runs with the strict feature
+    fn insecure(spec: &str) -> ZKP {
+        let spec: ParamSpec = spec.parse().unwrap();
+        spec.builder().allow_insecure().build().unwrap()
+    }
+
+    #[test]
+    fn test_group_info() {
+        let expected = [
+            (Group::Rfc5114_1024_160, 1024, 160),
+            (Group::Rfc5114_2048_224, 2048, 224),
+            (Group::Rfc5114_2048_256, 2048, 256),
+        ];
+        for (group, p_bits, q_bits) in expected {
+            assert_eq!(
+                group.zkp().info(),
+                GroupInfo {
+                    p_bits,
+                    q_bits,
+                    name: Some(group.name().to_string()),
+                }
+            );
+        }
+        assert_eq!(ZKP::new().info().name.as_deref(), Some("rfc5114-1024-160"));
+
+        let zkp = insecure("p=17;q=b;alpha=4;beta=9");
+        assert_eq!(
+            zkp.info(),
+            GroupInfo {
+                p_bits: 5,
+                q_bits: 4,
+                name: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_descriptor_round_trip() {
+        for group in Group::ALL {
+            assert_eq!(group.zkp().descriptor(), group.name());
+        }
+
+        let zkp = insecure("p=0x17; q=B; alpha=4; beta=9");
+        assert_eq!(zkp.descriptor(), "p=17;q=b;alpha=4;beta=9");
+        let parsed = insecure(&zkp.descriptor());
+        assert_eq!((parsed.p, parsed.beta), (zkp.p, zkp.beta));
+
+        // a named group with a different beta is not that named group anymore
+        let mut zkp = ZKP::new();
+        zkp.beta = zkp.alpha.clone();
+        assert!(zkp.descriptor().starts_with("p=b10b8f96"));
+    }
+
+    #[test]
+    fn test_nothing_up_my_sleeve_beta() {
+        let toy = insecure("p=17;q=b;alpha=4;beta=9");
+        for zkp in [toy, ZKP::new(), Group::Rfc5114_2048_256.zkp()] {
+            let default_beta = zkp.beta.clone();
+            let zkp = zkp.with_nothing_up_my_sleeve_beta();
+            // the toy subgroup is small enough for the two to collide
+            if zkp.q.bits() > 8 {
+                assert_ne!(zkp.beta, default_beta);
+            }
+            assert_ne!(zkp.beta, BigUint::one());
+            assert!(zkp.beta < zkp.p);
+            assert_eq!(ZKP::exponentiate(&zkp.beta, &zkp.q, &zkp.p), BigUint::one());
+
+            // deterministic, and accepted as a custom group
+            let again = ZKP::builder(
+                zkp.p.clone(),
+                zkp.q.clone(),
+                zkp.alpha.clone(),
+                zkp.beta.clone(),
+            )
+            .allow_insecure()
+            .build()
+            .unwrap()
+            .with_nothing_up_my_sleeve_beta();
+            assert_eq!(again.beta, zkp.beta);
+            assert_eq!(insecure(&zkp.descriptor()).beta, zkp.beta);
+        }
+    }
+
+    #[test]
+    fn test_security_bits() {
+        let expected = [
+            (Group::Rfc5114_1024_160, 80),
+            (Group::Rfc5114_2048_224, 112),
+            (Group::Rfc5114_2048_256, 112),
+        ];
+        for (group, bits) in expected {
+            assert_eq!(group.zkp().security_bits(), bits);
+        }
+        let toy = insecure("p=17;q=b;alpha=4;beta=9");
+        assert_eq!(toy.security_bits(), 0);
+    }
+
+    fn toy_builder() -> GroupBuilder {
+        ZKP::builder(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(9u32),
+        )
+    }
+
+    #[test]
+    fn test_strict_builder_rejects_toy_params() {
+        assert_eq!(
+            toy_builder().strict().build().err(),
+            Some(ParamError::InsecureGroup(0))
+        );
+        assert!(toy_builder().strict().allow_insecure().build().is_ok());
+
+        let ZKP { p, q, alpha, beta } = ZKP::new();
+        assert!(ZKP::builder(p, q, alpha, beta).strict().build().is_ok());
+    }
+
+    #[cfg(feature = "strict")]
+    #[test]
+    fn test_strict_feature_rejects_toy_params() {
+        assert_eq!(
+            toy_builder().build().err(),
+            Some(ParamError::InsecureGroup(0))
+        );
+        assert!(matches!(
+            "p=17;q=b;alpha=4;beta=9".parse::<ZKP>(),
+            Err(ParamError::InsecureGroup(0))
+        ));
+        assert!(toy_builder().allow_insecure().build().is_ok());
+    }
+
+    #[test]
+    fn test_generate_safe_group_reports_progress() {
+        let mut reports = vec![];
+        let zkp = ZKP::generate_safe_group(64, |tested| reports.push(tested));
+
+        assert!(!reports.is_empty());
+        assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+        let (batches, last) = reports.split_at(reports.len() - 1);
+        assert!(batches
+            .iter()
+            .enumerate()
+            .all(|(i, &tested)| tested == (i as u64 + 1) * SAFE_GROUP_BATCH));
+        assert!(last[0] > batches.len() as u64 * SAFE_GROUP_BATCH);
+
+        assert_eq!(zkp.p.bits(), 64);
+        assert_eq!(zkp.p, (&zkp.q << 1u32) + 1u32);
+        let ZKP { p, q, alpha, beta } = zkp;
+        assert!(ZKP::builder(p, q, alpha, beta)
+            .prime_rounds(20)
+            .allow_insecure()
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_parse_unknown_group() {
+        assert_eq!(
+            "rfc5114-4096-512".parse::<Group>(),
+            Err(ParamError::UnknownGroup("rfc5114-4096-512".to_string()))
+        );
+        assert!(matches!(
+            "modp-1024".parse::<ZKP>(),
+            Err(ParamError::UnknownGroup(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_custom_spec() {
+        let spec: ParamSpec = "p=17; q=b; alpha=4; beta=9".parse().unwrap();
+        assert_eq!(
+            spec,
+            ParamSpec {
+                p: BigUint::from(23u32),
+                q: BigUint::from(11u32),
+                alpha: BigUint::from(4u32),
+                beta: Some(BigUint::from(9u32)),
+            }
+        );
+
+        let zkp = insecure("p=0x17;q=0xb;alpha=0x4;beta=0x9");
+        assert_eq!(zkp.p, BigUint::from(23u32));
+        assert_eq!(zkp.beta, BigUint::from(9u32));
+
+        // beta is derived when omitted
+        let zkp = insecure("p=17;q=b;alpha=4");
+        assert_eq!(
+            ZKP::exponentiate(&zkp.beta, &zkp.q, &zkp.p),
+            BigUint::from(1u32)
+        );
+    }
+
+    #[test]
+    fn test_parse_custom_spec_errors() {
+        assert_eq!(
+            "p=17;q=b".parse::<ParamSpec>(),
+            Err(ParamError::MissingField("alpha"))
+        );
+        assert_eq!(
+            "p=17;q=b;alpha=4;gamma=2".parse::<ParamSpec>(),
+            Err(ParamError::UnknownField("gamma".to_string()))
+        );
+        assert_eq!(
+            "p=17;q=b;alpha=zz".parse::<ParamSpec>(),
+            Err(ParamError::InvalidHex("alpha".to_string()))
+        );
+        assert_eq!(
+            "p=17;p=17;q=b;alpha=4".parse::<ParamSpec>(),
+            Err(ParamError::DuplicateField("p".to_string()))
+        );
+        assert_eq!(
+            "p=17;q;alpha=4".parse::<ParamSpec>(),
+            Err(ParamError::Malformed("q".to_string()))
+        );
+        // 5 has order 22 mod 23, so it does not generate the order 11 subgroup
+        assert!(matches!(
+            "p=17;q=b;alpha=5;beta=9".parse::<ZKP>(),
+            Err(ParamError::InvalidGroup(_))
+        ));
+    }
+
+    #[test]
+    fn test_beta_independent_of_alpha() {
+        let zkp = ZKP::new();
+        let with_beta =
+            |beta: BigUint| ZKP::from_params(zkp.p.clone(), zkp.q.clone(), zkp.alpha.clone(), beta);
+        assert_eq!(
+            with_beta(zkp.alpha.clone()).err(),
+            Some(ParamError::InvalidGroup("alpha and beta must differ"))
+        );
+        assert_eq!(zkp.small_power_relation(), None);
+
+        // built, but flagged: log_alpha(beta) is found by trying
+        let cube = with_beta(ZKP::exponentiate(&zkp.alpha, &BigUint::from(3u32), &zkp.p)).unwrap();
+        assert_eq!(cube.small_power_relation(), Some(3));
+        let inverse_square = ZKP::exponentiate(&zkp.alpha, &(&zkp.q - 2u32), &zkp.p);
+        assert_eq!(
+            with_beta(inverse_square).unwrap().small_power_relation(),
+            Some(-2)
+        );
+        let far = BigUint::from(SMALL_POWER_LIMIT + 1);
+        let far = with_beta(ZKP::exponentiate(&zkp.alpha, &far, &zkp.p)).unwrap();
+        assert_eq!(far.small_power_relation(), None);
+
+        // beta = alpha^8 = alpha^-3 in the toy group
+        let toy = insecure("p=17;q=b;alpha=4;beta=9");
+        assert_eq!(toy.small_power_relation(), Some(-3));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::{decode_field, ElementEncoding, VerifyError, ZKP};
+
+/// The server echoed the handshake element back as something else than the
+/// client sent, so the two sides don't write elements the same way and every
+/// proof between them would fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingMismatch {
+    pub sent: Vec<u8>,
+    pub echoed: Vec<u8>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl fmt::Display for EncodingMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the server encodes elements differently: sent {}, it echoed {}",
+            hex(&self.sent),
+            hex(&self.echoed)
+        )
+    }
+}
+
+impl std::error::Error for EncodingMismatch {}
+
+impl ZKP {
+    /// p / 2^16 + 1, the element a client sends in a handshake. It is two
+    /// bytes shorter than p, so its padded encoding, its minimal bytes and its
+    /// length-prefixed bytes all have different lengths, and are told apart
+    /// even when the server reads them as the same value.
+    pub fn handshake_element(&self) -> BigUint {
+        (&self.p >> 16u32) + 1u32
+    }
+
+    /// What the server sends back for `bytes`: read as every element on the
+    /// wire, big-endian with any leading zeros, and written back padded to the
+    /// byte length of p
+    pub fn echo_element(&self, bytes: &[u8]) -> Result<Vec<u8>, VerifyError> {
+        let element = decode_field("element", bytes, &self.p)?;
+        if element >= self.p {
+            return Err(VerifyError::OutOfRange("element"));
+        }
+        Ok(ZKP::to_padded_bytes(&element, &self.p))
+    }
+
+    /// Checks the server's echo of the handshake element, sent in `encoding`
+    pub fn check_handshake(
+        &self,
+        encoding: ElementEncoding,
+        echoed: &[u8],
+    ) -> Result<(), EncodingMismatch> {
+        let sent = encoding.encode(&self.handshake_element(), &self.p);
+        if echoed != sent {
+            return Err(EncodingMismatch {
+                sent,
+                echoed: echoed.to_vec(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_handshake_detects_other_encodings() {
+        let zkp = ZKP::new();
+        let element = zkp.handshake_element();
+        let padded = ElementEncoding::Padded.encode(&element, &zkp.p);
+        assert_eq!(padded[..2], [0, 0]);
+        let echoed = zkp.echo_element(&padded).unwrap();
+        assert_eq!(
+            zkp.check_handshake(ElementEncoding::Padded, &echoed),
+            Ok(())
+        );
+        // minimal big-endian reads the same
+        assert_eq!(zkp.echo_element(&element.to_bytes_be()).unwrap(), echoed);
+
+        // a client writing varints, or little-endian
+        for sent in [
+            ElementEncoding::Varint.encode(&element, &zkp.p),
+            element.to_bytes_le(),
+        ] {
+            let echoed = zkp.echo_element(&sent).unwrap();
+            assert!(zkp
+                .check_handshake(ElementEncoding::Padded, &echoed)
+                .is_err());
+        }
+        let varint = ElementEncoding::Varint.encode(&element, &zkp.p);
+        let echoed = zkp.echo_element(&varint).unwrap();
+        assert_eq!(
+            zkp.check_handshake(ElementEncoding::Varint, &echoed),
+            Err(EncodingMismatch {
+                sent: varint,
+                echoed
+            })
+        );
+
+        assert_eq!(zkp.echo_element(&[]), Err(VerifyError::Empty("element")));
+    }
+}
@@ -0,0 +1,214 @@
+use digest::Digest;
+use num_bigint::BigUint;
+use sha2::Sha256;
+
+use crate::{group::hash_to_subgroup, OkamotoProof, ZKP};
+
+/// Keeps these challenges apart from every other hash in the crate
+const HIDDEN_KEY_DOMAIN: &[u8] = b"zkp-chaum-pedersen hidden key v1";
+
+/// Fiat-Shamir proof of knowing an opening (x, r) of a key commitment
+/// C = alpha^x * h^r, so of the secret behind the key (y1, y2) = (alpha^x,
+/// beta^x) it commits to, with neither y1 nor y2 in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HiddenKeyProof {
+    pub c: BigUint,
+    pub answers: OkamotoProof,
+}
+
+/// Fiat-Shamir proof that two key commitments hide the same x: their quotient
+/// is h^(r_a - r_b), shown by a Schnorr proof to h
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SameKeyProof {
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+impl ZKP {
+    /// Pedersen commitment C = alpha^x * h^r mod p to the secret x, for a
+    /// random r below q. It tells nothing about x, so nothing about y1 and
+    /// y2 either.
+    ///
+    /// h comes from `with_nothing_up_my_sleeve_beta` rather than being beta:
+    /// log_alpha(beta) is public for the named groups, and knowing it would
+    /// let the prover open C to any x.
+    pub fn commit_key(&self, x: &BigUint, r: &BigUint) -> BigUint {
+        self.pedersen().okamoto_commit(x, r)
+    }
+
+    /// Proves knowledge of the opening (x, r) of `commit_key(x, r)`, bound to
+    /// `context`
+    pub fn prove_hidden_key(&self, x: &BigUint, r: &BigUint, context: &[u8]) -> HiddenKeyProof {
+        let pedersen = self.pedersen();
+        let commitment = pedersen.okamoto_commit(x, r);
+        let k1 = ZKP::generate_random_below(&self.q);
+        let k2 = ZKP::generate_random_below(&self.q);
+        let t = pedersen.okamoto_commit(&k1, &k2);
+        let c = pedersen.hidden_key_challenge(&[&commitment], &t, context);
+        HiddenKeyProof {
+            answers: pedersen.prove_okamoto(&k1, &k2, &c, x, r),
+            c,
+        }
+    }
+
+    /// Checks `proof` for `commitment` under `context`, rebuilding the
+    /// Okamoto commitment t = alpha^s1 * h^s2 * C^c and its hash
+    pub fn verify_hidden_key(
+        &self,
+        commitment: &BigUint,
+        context: &[u8],
+        proof: &HiddenKeyProof,
+    ) -> bool {
+        let HiddenKeyProof { c, answers } = proof;
+        if !self.is_in_subgroup(commitment) {
+            return false;
+        }
+        if self.validate_challenge(c).is_err() || answers.s1 >= self.q || answers.s2 >= self.q {
+            return false;
+        }
+        let pedersen = self.pedersen();
+        let t = pedersen.okamoto_commit(&answers.s1, &answers.s2)
+            * ZKP::exponentiate(commitment, c, &self.p)
+            % &self.p;
+        *c == pedersen.hidden_key_challenge(&[commitment], &t, context)
+    }
+
+    /// Proves `commit_key(x, r_a)` and `commit_key(x, r_b)` hide the same x,
+    /// without x, so a key committed once can be shown under a fresh
+    /// commitment
+    pub fn prove_same_key(
+        &self,
+        [a, b]: [&BigUint; 2],
+        [r_a, r_b]: [&BigUint; 2],
+        context: &[u8],
+    ) -> SameKeyProof {
+        let pedersen = self.pedersen();
+        let d = (r_a + &self.q - r_b) % &self.q;
+        let k = ZKP::generate_random_below(&self.q);
+        let t = ZKP::exponentiate(&pedersen.beta, &k, &self.p);
+        let c = pedersen.hidden_key_challenge(&[a, b], &t, context);
+        SameKeyProof {
+            s: self.solve(&k, &c, &d),
+            c,
+        }
+    }
+
+    /// Checks h^s * (a / b)^c hashes back to c
+    pub fn verify_same_key(
+        &self,
+        [a, b]: [&BigUint; 2],
+        context: &[u8],
+        proof: &SameKeyProof,
+    ) -> bool {
+        let SameKeyProof { c, s } = proof;
+        if !self.is_in_subgroup(a) || !self.is_in_subgroup(b) {
+            return false;
+        }
+        if self.validate_challenge(c).is_err() || *s >= self.q {
+            return false;
+        }
+        let pedersen = self.pedersen();
+        // b^-1 = b^(q - 1) in the subgroup
+        let quotient = a * ZKP::exponentiate(b, &(&self.q - 1u32), &self.p) % &self.p;
+        let t = ZKP::exponentiate(&pedersen.beta, s, &self.p)
+            * ZKP::exponentiate(&quotient, c, &self.p)
+            % &self.p;
+        *c == pedersen.hidden_key_challenge(&[a, b], &t, context)
+    }
+
+    /// This group with h in place of beta
+    fn pedersen(&self) -> ZKP {
+        ZKP {
+            p: self.p.clone(),
+            q: self.q.clone(),
+            alpha: self.alpha.clone(),
+            beta: hash_to_subgroup(&self.p, &self.q, &self.alpha),
+        }
+    }
+
+    /// SHA-256 of the domain, p, q, alpha, h, the commitments, t and the
+    /// context, each length-prefixed, mod q
+    fn hidden_key_challenge(
+        &self,
+        commitments: &[&BigUint],
+        t: &BigUint,
+        context: &[u8],
+    ) -> BigUint {
+        let mut hasher = Sha256::new();
+        let mut update = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(bytes);
+        };
+        update(HIDDEN_KEY_DOMAIN);
+        for n in [&self.p, &self.q, &self.alpha, &self.beta] {
+            update(&n.to_bytes_be());
+        }
+        for n in commitments.iter().copied().chain([t]) {
+            update(&n.to_bytes_be());
+        }
+        update(context);
+        BigUint::from_bytes_be(&hasher.finalize()) % &self.q
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Whether `needle` shows up anywhere in `haystack`
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_hidden_key_verifies_without_the_key() {
+        // a toy group would let a bad proof through one time in q
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let r = ZKP::generate_random_below(&zkp.q);
+
+        // everything the verifier gets
+        let commitment = zkp.commit_key(&x, &r);
+        let proof = zkp.prove_hidden_key(&x, &r, b"login");
+        assert!(zkp.verify_hidden_key(&commitment, b"login", &proof));
+
+        let mut sent = commitment.to_bytes_be();
+        for n in [&proof.c, &proof.answers.s1, &proof.answers.s2] {
+            sent.extend(n.to_bytes_be());
+        }
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        assert!(!contains(&sent, &y1.to_bytes_be()));
+        assert!(!contains(&sent, &y2.to_bytes_be()));
+
+        // another context, another commitment, or a changed answer
+        assert!(!zkp.verify_hidden_key(&commitment, b"other", &proof));
+        let other = zkp.commit_key(&x, &((&r + 1u32) % &zkp.q));
+        assert!(!zkp.verify_hidden_key(&other, b"login", &proof));
+        let mut tampered = proof.clone();
+        tampered.answers.s1 = (&tampered.answers.s1 + 1u32) % &zkp.q;
+        assert!(!zkp.verify_hidden_key(&commitment, b"login", &tampered));
+        // h isn't beta, whose log to alpha is public
+        assert_ne!(zkp.commit_key(&x, &r), zkp.okamoto_commit(&x, &r));
+    }
+
+    #[test]
+    fn test_same_key_under_fresh_commitment() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let [r_a, r_b] = [(); 2].map(|_| ZKP::generate_random_below(&zkp.q));
+        let a = zkp.commit_key(&x, &r_a);
+        let b = zkp.commit_key(&x, &r_b);
+
+        let proof = zkp.prove_same_key([&a, &b], [&r_a, &r_b], b"");
+        assert!(zkp.verify_same_key([&a, &b], b"", &proof));
+        assert!(!zkp.verify_same_key([&b, &a], b"", &proof));
+
+        // a commitment to another secret
+        let other = zkp.commit_key(&((&x + 1u32) % &zkp.q), &r_b);
+        let proof = zkp.prove_same_key([&a, &other], [&r_a, &r_b], b"");
+        assert!(!zkp.verify_same_key([&a, &other], b"", &proof));
+    }
+}
@@ -0,0 +1,230 @@
+use std::{fmt, str::FromStr, sync::Mutex};
+
+use subtle::{Choice, ConstantTimeEq};
+
+use crate::ZKP;
+
+/// Length of the ids the server hands out
+pub const ID_LEN: usize = 12;
+
+/// Why a string isn't a valid id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdError {
+    /// Holds the length found, ids are exactly `ID_LEN` characters
+    Length(usize),
+    /// Holds the first character that isn't ASCII alphanumeric
+    Charset(char),
+}
+
+impl fmt::Display for IdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdError::Length(len) => write!(f, "id has {} characters, expected {}", len, ID_LEN),
+            IdError::Charset(c) => write!(f, "id contains {:?}, expected ASCII alphanumerics", c),
+        }
+    }
+}
+
+impl std::error::Error for IdError {}
+
+fn parse_id(s: &str) -> Result<[u8; ID_LEN], IdError> {
+    if let Some(c) = s.chars().find(|c| !c.is_ascii_alphanumeric()) {
+        return Err(IdError::Charset(c));
+    }
+    s.as_bytes()
+        .try_into()
+        .map_err(|_| IdError::Length(s.len()))
+}
+
+/// Where the server gets the ids and key handles it hands out.
+pub trait IdGenerator: Send + Sync {
+    /// output = `len` ASCII alphanumerics
+    fn generate(&self, len: usize) -> String;
+}
+
+/// Random ids from the thread's CSPRNG, what a real server must use.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomIds;
+
+impl IdGenerator for RandomIds {
+    fn generate(&self, len: usize) -> String {
+        ZKP::generate_random_string(len)
+    }
+}
+
+/// Replays a fixed sequence of ids, starting over once exhausted. Each one must
+/// have the length it is asked for. Only meant for reproducible tests.
+#[derive(Debug)]
+pub struct FixedIds {
+    values: Vec<String>,
+    next: Mutex<usize>,
+}
+
+impl FixedIds {
+    pub fn new(values: Vec<String>) -> Self {
+        assert!(!values.is_empty(), "at least one id is needed");
+        assert!(
+            values
+                .iter()
+                .all(|id| id.chars().all(|c| c.is_ascii_alphanumeric())),
+            "ids are ASCII alphanumerics"
+        );
+        FixedIds {
+            values,
+            next: Mutex::new(0),
+        }
+    }
+}
+
+impl IdGenerator for FixedIds {
+    fn generate(&self, len: usize) -> String {
+        let mut next = self.next.lock().unwrap();
+        let id = self.values[*next].clone();
+        *next = (*next + 1) % self.values.len();
+        assert_eq!(
+            id.len(),
+            len,
+            "fixed id {:?} doesn't have length {}",
+            id,
+            len
+        );
+        id
+    }
+}
+
+/// Random id of `ID_LEN` ASCII alphanumerics, stored inline so it's `Copy`.
+/// Separate types keep auth ids, session ids and user names from being mixed up.
+macro_rules! random_id {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name([u8; ID_LEN]);
+
+        impl $name {
+            pub fn generate() -> Self {
+                $name::generate_with(&RandomIds)
+            }
+
+            pub fn generate_with(ids: &dyn IdGenerator) -> Self {
+                let id = ids.generate(ID_LEN);
+                $name(parse_id(&id).expect("generated ids are ID_LEN alphanumerics"))
+            }
+
+            pub fn as_str(&self) -> &str {
+                std::str::from_utf8(&self.0).expect("ids are ASCII")
+            }
+
+            pub fn to_bytes(self) -> [u8; ID_LEN] {
+                self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = IdError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse_id(s).map($name)
+            }
+        }
+
+        impl TryFrom<[u8; ID_LEN]> for $name {
+            type Error = IdError;
+
+            fn try_from(bytes: [u8; ID_LEN]) -> Result<Self, Self::Error> {
+                match bytes.iter().find(|b| !b.is_ascii_alphanumeric()) {
+                    Some(&b) => Err(IdError::Charset(char::from(b))),
+                    None => Ok($name(bytes)),
+                }
+            }
+        }
+
+        /// Session ids are bearer tokens, compare them without stopping at the
+        /// first differing byte
+        impl ConstantTimeEq for $name {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                self.0.ct_eq(&other.0)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                self.as_str()
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> String {
+                id.as_str().to_string()
+            }
+        }
+    };
+}
+
+random_id!(
+    /// Names a pending challenge between `create_auth_challenge` and `verify_auth`
+    AuthId
+);
+random_id!(
+    /// Names a logged in session
+    SessionId
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_ids() {
+        let auth_id: AuthId = "aB3dE5gH7jK9".parse().unwrap();
+        assert_eq!(auth_id.as_str(), "aB3dE5gH7jK9");
+        assert_eq!(AsRef::<[u8]>::as_ref(&auth_id), b"aB3dE5gH7jK9");
+        assert_eq!(String::from(auth_id), "aB3dE5gH7jK9");
+        assert_eq!(AuthId::try_from(auth_id.to_bytes()), Ok(auth_id));
+        assert!(bool::from(auth_id.ct_eq(&auth_id)));
+        assert!(!bool::from(auth_id.ct_eq(&"aB3dE5gH7jK8".parse().unwrap())));
+
+        assert_eq!("".parse::<AuthId>(), Err(IdError::Length(0)));
+        assert_eq!(
+            "aB3dE5gH7jK9x".parse::<SessionId>(),
+            Err(IdError::Length(13))
+        );
+        assert_eq!(
+            "aB3dE5gH7jK!".parse::<SessionId>(),
+            Err(IdError::Charset('!'))
+        );
+        assert_eq!("aB3dE5gH7jé".parse::<AuthId>(), Err(IdError::Charset('é')));
+        assert_eq!(
+            SessionId::try_from(*b"aB3dE5gH7jK\n"),
+            Err(IdError::Charset('\n'))
+        );
+    }
+
+    #[test]
+    fn test_generated_ids_parse() {
+        for _ in 0..100 {
+            let session_id = SessionId::generate();
+            assert_eq!(session_id.to_string().parse(), Ok(session_id));
+            assert_eq!(session_id.to_string().len(), ID_LEN);
+        }
+    }
+
+    #[test]
+    fn test_fixed_ids_repeat() {
+        let ids = FixedIds::new(vec!["aB3dE5gH7jK9".to_string(), "000000000001".to_string()]);
+        assert_eq!(AuthId::generate_with(&ids).as_str(), "aB3dE5gH7jK9");
+        assert_eq!(SessionId::generate_with(&ids).as_str(), "000000000001");
+        assert_eq!(ids.generate(ID_LEN), "aB3dE5gH7jK9");
+    }
+}
@@ -1,7 +1,115 @@
+pub mod and;
+pub mod audit;
+pub mod auxiliary;
+pub mod batch;
+pub mod blinded;
+pub mod cache;
+pub mod capabilities;
+pub mod cbor;
+pub mod challenge;
+pub mod clock;
+pub mod commit;
+pub mod compact;
+#[cfg(feature = "console")]
+pub mod console;
+#[cfg(feature = "credential")]
+pub mod credential;
+pub mod cross_group;
+pub mod ct;
+pub mod encoding;
+pub mod encrypted;
+pub mod entropy;
+#[cfg(feature = "binaries")]
+pub mod error_details;
+pub mod gates;
+pub mod group;
+pub mod handshake;
+pub mod hidden;
+pub mod id;
+pub mod multi;
+pub mod okamoto;
+pub mod or;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod params_cache;
+pub mod policy;
+pub mod prime;
+pub mod prover;
+pub mod range;
+pub mod recover;
+pub mod reduce;
+pub mod replay;
+pub mod report;
+pub mod rounds;
+pub mod secret;
+pub mod sigma;
+pub mod signature;
+pub mod store;
+pub mod timestamped;
+pub mod timing;
+pub mod transcript;
+pub mod vector;
+pub mod verifier;
+pub mod verify;
+pub mod window;
+
 use num_bigint::{BigUint, RandBigInt};
-use num_traits::Num;
+use num_traits::{One, Zero};
 use rand::{self, Rng};
 
+pub use and::AndProof;
+pub use audit::{AuditRecord, AuditSink, MemoryAudit, NoAudit, StderrAudit};
+pub use auxiliary::Auxiliary;
+pub use batch::{BatchItem, BatchVerifier, CheckpointError};
+pub use blinded::{BlindedKey, BlindedProof};
+pub use cache::{PowerCache, DEFAULT_POWER_CACHE_SIZE};
+pub use cbor::CborError;
+pub use challenge::{
+    ChallengeSource, FixedChallenges, RandomChallenges, ShortChallenges, SmallChallengeSpace,
+    MIN_CHALLENGE_BITS,
+};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use commit::HashCommitment;
+pub use compact::CompactProof;
+pub use cross_group::{CrossGroupBounds, CrossGroupProof, CROSS_GROUP_SLACK_BITS};
+pub use digest::Digest;
+pub use encoding::{DecodeError, ElementEncoding};
+pub use encrypted::{EncryptedStore, InvalidStoreKey, StoreKey};
+pub use entropy::{BoxedRng, DeviceRng, Entropy, SecureRng};
+pub use gates::{decode_field, Gate, GateCounters};
+pub use group::{
+    Group, GroupBuilder, GroupInfo, ParamError, ParamSpec, MIN_SECURITY_BITS, SMALL_POWER_LIMIT,
+};
+pub use handshake::EncodingMismatch;
+pub use hidden::{HiddenKeyProof, SameKeyProof};
+pub use id::{AuthId, FixedIds, IdError, IdGenerator, RandomIds, SessionId, ID_LEN};
+pub use multi::{MultiBaseProof, MAX_BASES};
+pub use okamoto::OkamotoProof;
+pub use or::OrProof;
+pub use params_cache::{ParamsCache, ParamsSource};
+pub use policy::{NamePattern, UsernamePolicy};
+pub use prover::{Commitment, Nonce, Prover, Response};
+pub use range::{RangeError, RangeProof, MAX_RANGE_BITS};
+pub use recover::TooManyCandidates;
+pub use reduce::{Reducer, Reduction};
+pub use replay::SeenAnswers;
+pub use rounds::{ChallengeStream, RoundError};
+pub use secret::{MemorySecret, SecretBackend};
+pub use sigma::{
+    run_round, ChaumPedersen, Okamoto, OkamotoProver, Schnorr, SchnorrProver, SigmaProver,
+    SigmaVerifier,
+};
+pub use signature::Signature;
+pub use store::{FileStore, MemoryStore, StoreError, UserAuthInfo, UserStore};
+pub use subtle::{Choice, ConstantTimeEq};
+pub use timestamped::{TimestampError, TimestampedProof};
+pub use timing::Timings;
+pub use transcript::Transcript;
+pub use vector::TestVector;
+pub use verifier::{RotatingVerifier, Verifier};
+pub use verify::{VerifyDetails, VerifyError};
+pub use window::CommitmentWindow;
+
 pub struct ZKP {
     pub p: BigUint,
     pub q: BigUint,
@@ -17,20 +125,30 @@ impl Default for ZKP {
 
 impl ZKP {
     pub fn new() -> Self {
-        let p_hex = "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B616073E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BFACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371";
-        let q_hex = "F518AA8781A8DF278ABA4E7D64B7CB9D49462353";
-        let alpha_hex = "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28AD662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5";
-
-        let p = BigUint::from_str_radix(p_hex, 16).expect("Invalid hex for alpha");
-        let q = BigUint::from_str_radix(q_hex, 16).expect("Invalid hex for q");
-        let alpha = BigUint::from_str_radix(alpha_hex, 16).expect("Invalid hex for beta");
+        Group::Rfc5114_1024_160.zkp()
+    }
 
-        // alpha^i is also a generator
-        let exp = BigUint::from_str_radix("266D31266FEA1E5C41564B777E69", 16)
-            .expect("could not create exp");
-        let beta = ZKP::exponentiate(&alpha, &exp, &p);
+    /// Builds a custom group, checking that alpha and beta generate the order q subgroup of p.
+    /// With the `strict` feature, groups below `MIN_SECURITY_BITS` are rejected too.
+    pub fn from_params(
+        p: BigUint,
+        q: BigUint,
+        alpha: BigUint,
+        beta: BigUint,
+    ) -> Result<Self, ParamError> {
+        ZKP::from_params_with_rounds(p, q, alpha, beta, 0)
+    }
 
-        ZKP { alpha, beta, p, q }
+    /// Like `from_params`, but also runs `rounds` Miller-Rabin rounds on p and q.
+    /// 0 skips the primality check, which is costly for large groups.
+    pub fn from_params_with_rounds(
+        p: BigUint,
+        q: BigUint,
+        alpha: BigUint,
+        beta: BigUint,
+        rounds: usize,
+    ) -> Result<Self, ParamError> {
+        ZKP::builder(p, q, alpha, beta).prime_rounds(rounds).build()
     }
 
     /// output = n^exp mod p
@@ -38,12 +156,74 @@ impl ZKP {
         n.modpow(exponent, modulus)
     }
 
-    /// output = s = k - c * x mod q
+    /// output = [base^exp mod p for exp in exponents]
+    ///
+    /// Lim-Lee comb with 4 rows: each exponent is split into 4 slices of
+    /// a = bits / 4 bits, and the 16 products of base^(2^(i * a)) are computed once
+    /// and shared. Every exponent then costs a squarings and at most a
+    /// multiplications instead of a full modpow.
+    pub fn exponentiate_many(base: &BigUint, exponents: &[BigUint], p: &BigUint) -> Vec<BigUint> {
+        const ROWS: u64 = 4;
+        let bits = exponents
+            .iter()
+            .map(BigUint::bits)
+            .max()
+            .unwrap_or_default();
+        let a = bits.div_ceil(ROWS).max(1);
+
+        // table[mask] = product of base^(2^(i * a)) over the rows i set in mask
+        let mut row_bases = vec![base % p];
+        for _ in 1..ROWS {
+            let mut next = row_bases.last().unwrap().clone();
+            for _ in 0..a {
+                next = &next * &next % p;
+            }
+            row_bases.push(next);
+        }
+        let mut table = vec![BigUint::one() % p; 1 << ROWS];
+        for mask in 1..table.len() {
+            let low = mask.trailing_zeros() as usize;
+            table[mask] = &table[mask & (mask - 1)] * &row_bases[low] % p;
+        }
+
+        exponents
+            .iter()
+            .map(|exp| {
+                let mut result = BigUint::one() % p;
+                for j in (0..a).rev() {
+                    result = &result * &result % p;
+                    let mask = (0..ROWS)
+                        .filter(|&i| exp.bit(i * a + j))
+                        .fold(0, |mask, i| mask | 1 << i);
+                    if mask != 0 {
+                        result = result * &table[mask] % p;
+                    }
+                }
+                result
+            })
+            .collect()
+    }
+
+    /// output = s = k - c * x mod q, always in [0, q)
     pub fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
-        if *k >= c * x {
-            return (k - c * x).modpow(&BigUint::from(1u32), &self.q);
+        let cx = c * x;
+        if *k >= cx {
+            return (k - cx) % &self.q;
+        }
+        // when c * x - k is a multiple of q the answer is 0, not q
+        (&self.q - (cx - k) % &self.q) % &self.q
+    }
+
+    /// `solve` with the reductions mod q done by `reducer`, which must be built for q
+    pub fn solve_with(&self, reducer: &Reducer, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
+        debug_assert_eq!(reducer.modulus(), &self.q);
+        let cx = reducer.mul(c, x);
+        let k = reducer.reduce(k.clone());
+        if k >= cx {
+            k - cx
+        } else {
+            &self.q - cx + k
         }
-        &self.q - (c * x - k).modpow(&BigUint::from(1u32), &self.q)
     }
 
     /// r1 = alpha^s * y1^c
@@ -57,19 +237,53 @@ impl ZKP {
         c: &BigUint,
         s: &BigUint,
     ) -> bool {
-        let sol1 = ZKP::exponentiate(&self.alpha, s, &self.p) * ZKP::exponentiate(y1, c, &self.p);
-        let ver1 = *r1 == ZKP::exponentiate(&sol1, &BigUint::from(1u32), &self.p);
-
-        let sol2 = ZKP::exponentiate(&self.beta, s, &self.p) * ZKP::exponentiate(y2, c, &self.p);
-        let ver2 = *r2 == ZKP::exponentiate(&sol2, &BigUint::from(1u32), &self.p);
+        self.verify_detailed(r1, r2, y1, y2, c, s).is_valid()
+    }
 
-        ver1 && ver2
+    /// Big-endian bytes of n, left padded with zeros to the byte length of modulus
+    pub fn to_padded_bytes(n: &BigUint, modulus: &BigUint) -> Vec<u8> {
+        let width = modulus.bits().div_ceil(8) as usize;
+        let bytes = n.to_bytes_be();
+        let mut padded = vec![0u8; width.saturating_sub(bytes.len())];
+        padded.extend_from_slice(&bytes);
+        padded
     }
 
     pub fn generate_random_below(bound: &BigUint) -> BigUint {
         rand::thread_rng().gen_biguint_below(bound)
     }
 
+    /// `generate_random_below` drawing from `entropy`
+    pub fn generate_random_below_with(bound: &BigUint, entropy: &Entropy) -> BigUint {
+        entropy.below(bound)
+    }
+
+    /// Uniform c in [0, min(q, 2^bits)) for verifiers using short challenges.
+    ///
+    /// Draws `bits` random bits and retries when the value lands at or above q.
+    /// Masking and reducing mod q instead would make the values below
+    /// 2^bits - q twice as likely whenever q isn't a power of two.
+    pub fn generate_challenge(q: &BigUint, bits: u64) -> BigUint {
+        ZKP::challenge_from(q, bits, &mut rand::thread_rng())
+    }
+
+    /// `generate_challenge` drawing from `entropy`
+    pub fn generate_challenge_with(q: &BigUint, bits: u64, entropy: &Entropy) -> BigUint {
+        entropy.with_rng(|rng| ZKP::challenge_from(q, bits, rng))
+    }
+
+    fn challenge_from<R: Rng + ?Sized>(q: &BigUint, bits: u64, rng: &mut R) -> BigUint {
+        assert!(!q.is_zero(), "q must be positive");
+        // below q.bits() every draw is already < q, so at most one in two is rejected
+        let bits = bits.min(q.bits());
+        loop {
+            let c = rng.gen_biguint(bits);
+            if c < *q {
+                return c;
+            }
+        }
+    }
+
     pub fn generate_random_string(size: usize) -> String {
         rand::thread_rng()
             .sample_iter(rand::distributions::Alphanumeric)
@@ -82,6 +296,7 @@ impl ZKP {
 #[cfg(test)]
 mod test {
     use super::*;
+    use num_traits::Num;
 
     #[test]
     fn test_toy_example() {
@@ -157,6 +372,110 @@ mod test {
         assert!(result);
     }
 
+    #[test]
+    fn test_from_params_rejects_composite_p() {
+        // p = 23^2 is composite but its unit group still has an order 11 subgroup
+        let p = BigUint::from(529u32);
+        let q = BigUint::from(11u32);
+        let alpha = ZKP::exponentiate(&BigUint::from(2u32), &BigUint::from(46u32), &p);
+        let beta = ZKP::exponentiate(&alpha, &BigUint::from(2u32), &p);
+        assert_ne!(alpha, BigUint::one());
+
+        let builder = || ZKP::builder(p.clone(), q.clone(), alpha.clone(), beta.clone());
+        assert!(builder().allow_insecure().build().is_ok());
+        assert_eq!(
+            builder().prime_rounds(20).allow_insecure().build().err(),
+            Some(ParamError::InvalidGroup("p is not prime"))
+        );
+    }
+
+    #[test]
+    fn test_from_params_accepts_primes() {
+        let toy = ZKP::builder(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(9u32),
+        )
+        .prime_rounds(20)
+        .allow_insecure()
+        .build();
+        assert!(toy.is_ok());
+
+        let ZKP { p, q, alpha, beta } = ZKP::new();
+        assert!(ZKP::from_params_with_rounds(p, q, alpha, beta, 20).is_ok());
+    }
+
+    #[test]
+    fn test_exponentiate_many_matches_modpow() {
+        for zkp in [ZKP::new(), Group::Rfc5114_2048_256.zkp()] {
+            let mut exponents: Vec<BigUint> = (0..16)
+                .map(|_| ZKP::generate_random_below(&zkp.q))
+                .collect();
+            exponents.extend([0u32, 1, 2, 255].map(BigUint::from));
+            exponents.push(&zkp.q - 1u32);
+
+            let expected: Vec<BigUint> = exponents
+                .iter()
+                .map(|exp| ZKP::exponentiate(&zkp.alpha, exp, &zkp.p))
+                .collect();
+            assert_eq!(
+                ZKP::exponentiate_many(&zkp.alpha, &exponents, &zkp.p),
+                expected
+            );
+        }
+
+        let p = BigUint::from(23u32);
+        assert!(ZKP::exponentiate_many(&BigUint::from(4u32), &[], &p).is_empty());
+        assert_eq!(
+            ZKP::exponentiate_many(&BigUint::from(27u32), &[BigUint::from(3u32)], &p),
+            [BigUint::from(18u32)]
+        );
+    }
+
+    // chi-square over the 11 values of the toy q, 10 degrees of freedom
+    #[test]
+    fn test_generate_challenge_is_uniform() {
+        let q = BigUint::from(11u32);
+        let samples = 22_000;
+        let mut counts = [0u32; 11];
+        for _ in 0..samples {
+            let c = ZKP::generate_challenge(&q, 4);
+            counts[usize::try_from(c).unwrap()] += 1;
+        }
+
+        let expected = samples as f64 / 11.0;
+        let chi_square: f64 = counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        // p < 0.00001; reducing 4 random bits mod 11 instead lands near 2600
+        assert!(chi_square < 45.0, "counts {:?}", counts);
+
+        // fewer bits than q narrows the range instead
+        for _ in 0..100 {
+            assert!(ZKP::generate_challenge(&q, 2) < BigUint::from(4u32));
+        }
+    }
+
+    #[test]
+    fn test_to_padded_bytes() {
+        let q = BigUint::from(0x1_0001u32);
+        assert_eq!(ZKP::to_padded_bytes(&BigUint::from(4u32), &q), [0, 0, 4]);
+        assert_eq!(ZKP::to_padded_bytes(&BigUint::zero(), &q), [0, 0, 0]);
+        assert_eq!(
+            ZKP::to_padded_bytes(&BigUint::from(0x1_0000u32), &q),
+            [1, 0, 0]
+        );
+
+        let q = ZKP::new().q;
+        let c = &q >> 8;
+        let bytes = ZKP::to_padded_bytes(&c, &q);
+        assert_eq!(bytes.len(), 20);
+        assert_eq!(bytes[0], 0);
+        assert_eq!(BigUint::from_bytes_be(&bytes), c);
+    }
+
     #[test]
     fn test_1024_bits_constants() {
         // https://www.rfc-editor.org/rfc/rfc5114.html#section-2.1
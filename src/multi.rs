@@ -0,0 +1,166 @@
+use digest::Digest;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use sha2::Sha256;
+
+use crate::{VerifyError, ZKP};
+
+/// Bases a multi-base proof may have, bounding the work of one verification
+pub const MAX_BASES: usize = 64;
+
+/// Non-interactive proof that one x is the discrete log of every y_i to its
+/// base g_i, Chaum-Pedersen with any number of bases in place of (alpha, beta).
+/// rs[i] = g_i^k, c is the SHA-256 challenge of the group, bases, keys and rs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiBaseProof {
+    pub rs: Vec<BigUint>,
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+impl ZKP {
+    /// y_i = g_i^x mod p for every base
+    pub fn multi_base_keys(&self, bases: &[BigUint], x: &BigUint) -> Vec<BigUint> {
+        bases
+            .iter()
+            .map(|base| ZKP::exponentiate(base, x, &self.p))
+            .collect()
+    }
+
+    /// r_i = g_i^k mod p
+    /// c = H(..., g_i, y_i, r_i)
+    /// s = k - c * x mod q
+    pub fn prove_multi_base(&self, bases: &[BigUint], k: &BigUint, x: &BigUint) -> MultiBaseProof {
+        let ys = self.multi_base_keys(bases, x);
+        let rs = self.multi_base_keys(bases, k);
+        let c = self.multi_base_challenge(bases, &ys, &rs);
+        MultiBaseProof {
+            s: self.solve(k, &c, x),
+            rs,
+            c,
+        }
+    }
+
+    /// SHA-256 of p, q, the number of bases, then each (g_i, y_i, r_i), every
+    /// number length-prefixed, mod q
+    pub fn multi_base_challenge(
+        &self,
+        bases: &[BigUint],
+        ys: &[BigUint],
+        rs: &[BigUint],
+    ) -> BigUint {
+        let mut hasher = Sha256::new();
+        let mut update = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(bytes);
+        };
+        update(&self.p.to_bytes_be());
+        update(&self.q.to_bytes_be());
+        update(&(bases.len() as u32).to_be_bytes());
+        for ((base, y), r) in bases.iter().zip(ys).zip(rs) {
+            for n in [base, y, r] {
+                update(&n.to_bytes_be());
+            }
+        }
+        BigUint::from_bytes_be(&hasher.finalize()) % &self.q
+    }
+
+    /// r_i = g_i^s * y_i^c for every i, and c the challenge of the transcript.
+    ///
+    /// The slices are the wire format, element i of each belonging together.
+    /// They must be non-empty, of one length and at most `MAX_BASES` long; the
+    /// bases must be in the order q subgroup and not 1, which would say nothing
+    /// about x.
+    pub fn verify_multi_base(
+        &self,
+        bases: &[BigUint],
+        ys: &[BigUint],
+        rs: &[BigUint],
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        if bases.is_empty()
+            || bases.len() > MAX_BASES
+            || ys.len() != bases.len()
+            || rs.len() != bases.len()
+        {
+            return Err(VerifyError::LengthMismatch);
+        }
+        for base in bases {
+            if base.is_one() {
+                return Err(VerifyError::OutOfRange("bases"));
+            }
+            if !self.is_in_subgroup(base) {
+                return Err(VerifyError::NotInSubgroup("bases"));
+            }
+        }
+        for (name, elements) in [("ys", ys), ("rs", rs)] {
+            if elements.iter().any(|n| n.is_zero() || *n >= self.p) {
+                return Err(VerifyError::OutOfRange(name));
+            }
+        }
+        self.validate_challenge(c)?;
+        if *s >= self.q {
+            return Err(VerifyError::OutOfRange("s"));
+        }
+        if *c != self.multi_base_challenge(bases, ys, rs) {
+            return Err(VerifyError::ChallengeMismatch);
+        }
+        for (i, ((base, y), r)) in bases.iter().zip(ys).zip(rs).enumerate() {
+            let expected =
+                ZKP::exponentiate(base, s, &self.p) * ZKP::exponentiate(y, c, &self.p) % &self.p;
+            if *r != expected {
+                return Err(VerifyError::BaseMismatch(i));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_multi_base_proof() {
+        // a toy group would let a bad proof through one time in q
+        let zkp = ZKP::new();
+        let bases: Vec<BigUint> = [2u32, 3, 5]
+            .iter()
+            .map(|e| ZKP::exponentiate(&zkp.alpha, &BigUint::from(*e), &zkp.p))
+            .collect();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let ys = zkp.multi_base_keys(&bases, &x);
+        let MultiBaseProof { rs, c, s } = zkp.prove_multi_base(&bases, &k, &x);
+        assert_eq!(zkp.verify_multi_base(&bases, &ys, &rs, &c, &s), Ok(()));
+
+        // one key for another x
+        let mut other = ys.clone();
+        other[2] = ZKP::exponentiate(&bases[2], &(&x + 1u32), &zkp.p);
+        assert_eq!(
+            zkp.verify_multi_base(&bases, &other, &rs, &c, &s),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        let c_other = zkp.multi_base_challenge(&bases, &other, &rs);
+        assert_eq!(
+            zkp.verify_multi_base(&bases, &other, &rs, &c_other, &s),
+            Err(VerifyError::BaseMismatch(0))
+        );
+
+        assert_eq!(
+            zkp.verify_multi_base(&bases, &ys[..2], &rs, &c, &s),
+            Err(VerifyError::LengthMismatch)
+        );
+        assert_eq!(
+            zkp.verify_multi_base(&[], &[], &[], &c, &s),
+            Err(VerifyError::LengthMismatch)
+        );
+        let mut trivial = bases.clone();
+        trivial[1] = BigUint::one();
+        assert_eq!(
+            zkp.verify_multi_base(&trivial, &ys, &rs, &c, &s),
+            Err(VerifyError::OutOfRange("bases"))
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// Answers for both exponents of a representation y = alpha^x * beta^r
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OkamotoProof {
+    pub s1: BigUint,
+    pub s2: BigUint,
+}
+
+impl ZKP {
+    /// Commitment for the Okamoto proof, t = alpha^k1 * beta^k2 mod p
+    pub fn okamoto_commit(&self, k1: &BigUint, k2: &BigUint) -> BigUint {
+        ZKP::exponentiate(&self.alpha, k1, &self.p) * ZKP::exponentiate(&self.beta, k2, &self.p)
+            % &self.p
+    }
+
+    /// Knowledge of (x, r) with y = alpha^x * beta^r. Any representation of y
+    /// gives proofs with the same distribution, so the proof doesn't tell which
+    /// one the prover holds.
+    /// s1 = k1 - c * x mod q
+    /// s2 = k2 - c * r mod q
+    pub fn prove_okamoto(
+        &self,
+        k1: &BigUint,
+        k2: &BigUint,
+        c: &BigUint,
+        x: &BigUint,
+        r: &BigUint,
+    ) -> OkamotoProof {
+        OkamotoProof {
+            s1: self.solve(k1, c, x),
+            s2: self.solve(k2, c, r),
+        }
+    }
+
+    /// t = alpha^s1 * beta^s2 * y^c
+    pub fn verify_okamoto(
+        &self,
+        t: &BigUint,
+        y: &BigUint,
+        c: &BigUint,
+        proof: &OkamotoProof,
+    ) -> bool {
+        let OkamotoProof { s1, s2 } = proof;
+        if self.validate_challenge(c).is_err() || *s1 >= self.q || *s2 >= self.q {
+            return false;
+        }
+
+        let solution = self.okamoto_commit(s1, s2) * ZKP::exponentiate(y, c, &self.p) % &self.p;
+        *t == solution
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_okamoto_toy_group() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        let [x, r, k1, k2, c] = [6u32, 3, 7, 2, 4].map(BigUint::from);
+
+        // y = 2 * 16 mod 23
+        let y = zkp.okamoto_commit(&x, &r);
+        assert_eq!(y, BigUint::from(9u32));
+        let t = zkp.okamoto_commit(&k1, &k2);
+        assert_eq!(t, BigUint::from(4u32));
+
+        let proof = zkp.prove_okamoto(&k1, &k2, &c, &x, &r);
+        assert_eq!(
+            proof,
+            OkamotoProof {
+                s1: BigUint::from(5u32),
+                s2: BigUint::from(1u32),
+            }
+        );
+        assert!(zkp.verify_okamoto(&t, &y, &c, &proof));
+
+        // beta = alpha^8, so (8, 0) is another representation of the same y
+        // and proves just as well
+        let [x_other, r_other] = [8u32, 0].map(BigUint::from);
+        assert_eq!(zkp.okamoto_commit(&x_other, &r_other), y);
+        let other = zkp.prove_okamoto(&k1, &k2, &c, &x_other, &r_other);
+        assert!(zkp.verify_okamoto(&t, &y, &c, &other));
+
+        let wrong = zkp.prove_okamoto(&k1, &k2, &c, &x, &BigUint::from(4u32));
+        assert!(!zkp.verify_okamoto(&t, &y, &c, &wrong));
+        let other_c = BigUint::from(5u32);
+        assert!(!zkp.verify_okamoto(&t, &y, &other_c, &proof));
+        let wrapped = OkamotoProof {
+            s2: &proof.s2 + &zkp.q,
+            ..proof.clone()
+        };
+        assert!(!zkp.verify_okamoto(&t, &y, &c, &wrapped));
+    }
+}
@@ -0,0 +1,142 @@
+use digest::Digest;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use sha2::Sha256;
+
+use crate::{Entropy, ZKP};
+
+/// Proof of knowing w with h[0] = g^w or h[1] = g^w, without saying which
+/// (Cramer-Damgard-Schoenmakers). Each statement gets a challenge and an
+/// answer, the challenges summing to the Fiat-Shamir hash of both commitments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrProof {
+    pub c: [BigUint; 2],
+    pub s: [BigUint; 2],
+}
+
+impl ZKP {
+    /// `w` is the discrete log of `h[known]` to `g`, the other statement is
+    /// simulated. `context` is hashed in to bind the proof to where it is used.
+    pub fn prove_or(
+        &self,
+        g: &BigUint,
+        h: [&BigUint; 2],
+        known: usize,
+        w: &BigUint,
+        context: &[u8],
+    ) -> OrProof {
+        self.prove_or_from(g, h, known, w, context, ZKP::generate_random_below)
+    }
+
+    /// `prove_or` with k and the simulated challenge and answer drawn from
+    /// `entropy`
+    pub fn prove_or_with(
+        &self,
+        g: &BigUint,
+        h: [&BigUint; 2],
+        known: usize,
+        w: &BigUint,
+        context: &[u8],
+        entropy: &Entropy,
+    ) -> OrProof {
+        self.prove_or_from(g, h, known, w, context, |bound| entropy.below(bound))
+    }
+
+    fn prove_or_from(
+        &self,
+        g: &BigUint,
+        h: [&BigUint; 2],
+        known: usize,
+        w: &BigUint,
+        context: &[u8],
+        below: impl Fn(&BigUint) -> BigUint,
+    ) -> OrProof {
+        assert!(known < 2, "one of the two statements must be known");
+        let other = 1 - known;
+        let k = below(&self.q);
+        let mut c: [BigUint; 2] = Default::default();
+        let mut s: [BigUint; 2] = Default::default();
+        c[other] = below(&self.q);
+        s[other] = below(&self.q);
+
+        let mut a: [BigUint; 2] = Default::default();
+        a[known] = ZKP::exponentiate(g, &k, &self.p);
+        a[other] = self.or_commitment(g, h[other], &c[other], &s[other]);
+
+        let total = self.or_challenge(g, h, &a, context);
+        c[known] = (&total + &self.q - &c[other]) % &self.q;
+        s[known] = self.solve(&k, &c[known], w);
+        OrProof { c, s }
+    }
+
+    /// g^s_i * h_i^c_i rebuilt for both statements, and c_0 + c_1 their hash mod q
+    pub fn verify_or(
+        &self,
+        g: &BigUint,
+        h: [&BigUint; 2],
+        proof: &OrProof,
+        context: &[u8],
+    ) -> bool {
+        let elements = [g, h[0], h[1]];
+        if elements.iter().any(|n| n.is_zero() || **n >= self.p) {
+            return false;
+        }
+        if proof.c.iter().any(|c| self.validate_challenge(c).is_err())
+            || proof.s.iter().any(|s| *s >= self.q)
+        {
+            return false;
+        }
+        let a = [0, 1].map(|i| self.or_commitment(g, h[i], &proof.c[i], &proof.s[i]));
+        (&proof.c[0] + &proof.c[1]) % &self.q == self.or_challenge(g, h, &a, context)
+    }
+
+    fn or_commitment(&self, g: &BigUint, h: &BigUint, c: &BigUint, s: &BigUint) -> BigUint {
+        ZKP::exponentiate(g, s, &self.p) * ZKP::exponentiate(h, c, &self.p) % &self.p
+    }
+
+    /// SHA-256 of p, q, g, both h, both commitments and the context, each
+    /// length-prefixed, mod q
+    fn or_challenge(
+        &self,
+        g: &BigUint,
+        h: [&BigUint; 2],
+        a: &[BigUint; 2],
+        context: &[u8],
+    ) -> BigUint {
+        let mut hasher = Sha256::new();
+        let mut update = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(bytes);
+        };
+        for n in [&self.p, &self.q, g, h[0], h[1], &a[0], &a[1]] {
+            update(&n.to_bytes_be());
+        }
+        update(context);
+        BigUint::from_bytes_be(&hasher.finalize()) % &self.q
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_or_proof_for_either_statement() {
+        // a toy group would let a bad proof through one time in q
+        let zkp = ZKP::new();
+        let w = ZKP::generate_random_below(&zkp.q);
+        let known = ZKP::exponentiate(&zkp.alpha, &w, &zkp.p);
+        let unknown = ZKP::exponentiate(&zkp.beta, &w, &zkp.p);
+
+        for (h, index) in [([&known, &unknown], 0), ([&unknown, &known], 1)] {
+            let proof = zkp.prove_or(&zkp.alpha, h, index, &w, b"ctx");
+            assert!(zkp.verify_or(&zkp.alpha, h, &proof, b"ctx"));
+            assert!(!zkp.verify_or(&zkp.alpha, h, &proof, b"other"));
+            assert!(!zkp.verify_or(&zkp.alpha, [h[1], h[0]], &proof, b"ctx"));
+        }
+
+        // knowing neither
+        let proof = zkp.prove_or(&zkp.alpha, [&unknown, &unknown], 0, &w, b"");
+        assert!(!zkp.verify_or(&zkp.alpha, [&unknown, &unknown], &proof, b""));
+    }
+}
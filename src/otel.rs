@@ -0,0 +1,200 @@
+//! OpenTelemetry traces of the auth flow, with the `otel` feature.
+//!
+//! Each step the server handles, `Register`, `CreateAuthChallenge` and
+//! `VerifyAuth`, runs in a `tracing` span from `rpc_span`, named and
+//! attributed after the OpenTelemetry RPC conventions. A client that sends a
+//! W3C `traceparent` with each call gets all three under its own trace, so
+//! the flow shows up as one distributed trace; `x-request-id` is kept as the
+//! `zkp.request_id` attribute.
+//!
+//! `tracing-opentelemetry` turns the spans into OpenTelemetry ones, which
+//! `init` has exported over OTLP/gRPC in batches by `opentelemetry-otlp`.
+
+use std::{collections::HashMap, fmt};
+
+use opentelemetry::{
+    global,
+    propagation::TextMapPropagator,
+    trace::{TraceError, TracerProvider as _},
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self, TracerProvider},
+    Resource,
+};
+use tracing::Metadata;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{filter::filter_fn, prelude::*, util::TryInitError, Layer, Registry};
+
+/// Target of the spans `rpc_span` opens, the only ones exported
+pub const TARGET: &str = "zkp_auth";
+/// gRPC service the spans belong to
+pub const SERVICE: &str = "zkp_auth.Auth";
+/// `service.name` of the exported spans
+pub const SERVICE_NAME: &str = "zkp-server";
+
+/// Span of one server step, `method` as in the proto, e.g. `Register`. Its
+/// `rpc.grpc.status_code` is recorded once the step is answered. A
+/// `traceparent` that isn't valid W3C trace context starts a trace of its own.
+pub fn rpc_span(
+    method: &str,
+    traceparent: Option<&str>,
+    request_id: Option<&str>,
+) -> tracing::Span {
+    let span = tracing::info_span!(
+        target: TARGET,
+        "rpc",
+        otel.name = format!("{}/{}", SERVICE, method),
+        otel.kind = "server",
+        rpc.system = "grpc",
+        rpc.service = SERVICE,
+        rpc.method = method,
+        zkp.request_id = tracing::field::Empty,
+        rpc.grpc.status_code = tracing::field::Empty,
+    );
+    if let Some(request_id) = request_id {
+        span.record("zkp.request_id", request_id);
+    }
+    if let Some(traceparent) = traceparent {
+        let carrier = HashMap::from([("traceparent".to_string(), traceparent.to_string())]);
+        span.set_parent(TraceContextPropagator::new().extract(&carrier));
+    }
+    span
+}
+
+/// Layer handing the `TARGET` spans to `tracer`, and nothing else
+pub fn layer<S, T>(tracer: T) -> impl Layer<S>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    T: opentelemetry::trace::Tracer + tracing_opentelemetry::PreSampledTracer + 'static,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(filter_fn(|metadata: &Metadata<'_>| {
+            metadata.target() == TARGET
+        }))
+}
+
+#[derive(Debug)]
+pub enum InitError {
+    /// The OTLP exporter could not be built
+    Exporter(TraceError),
+    /// Another global subscriber is already installed
+    Subscriber(TryInitError),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Exporter(err) => write!(f, "could not build the OTLP exporter: {}", err),
+            InitError::Subscriber(err) => write!(f, "could not install the subscriber: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
+/// Installs the global subscriber exporting spans over OTLP/gRPC to
+/// `endpoint`, e.g. `http://localhost:4317`. Spans are sent in batches from
+/// the tokio runtime, call `shutdown` before it stops to flush the last ones.
+pub fn init(endpoint: &str) -> Result<(), InitError> {
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint)
+        .build_span_exporter()
+        .map_err(InitError::Exporter)?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_config(
+            trace::config()
+                .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)])),
+        )
+        .build();
+    let tracer = provider.tracer(TARGET);
+    global::set_tracer_provider(provider);
+    Registry::default()
+        .with(layer(tracer))
+        .try_init()
+        .map_err(InitError::Subscriber)
+}
+
+/// Exports the spans still waiting in the batch
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use opentelemetry::{
+        trace::{SpanId, SpanKind, TraceId},
+        Value,
+    };
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+
+    #[test]
+    fn test_auth_flow_spans() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = Registry::default().with(layer(provider.tracer(TARGET)));
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        tracing::subscriber::with_default(subscriber, || {
+            for (method, code) in [
+                ("Register", 0),
+                ("CreateAuthChallenge", 0),
+                ("VerifyAuth", 7),
+            ] {
+                let span = rpc_span(method, Some(traceparent), Some("req-1"));
+                span.record("rpc.grpc.status_code", code);
+            }
+            // no trace context, a trace of its own
+            drop(rpc_span("Register", Some("garbage"), None));
+            // spans of other targets aren't exported
+            drop(tracing::info_span!(target: "h2", "connection"));
+        });
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let names: Vec<_> = spans.iter().map(|span| span.name.as_ref()).collect();
+        assert_eq!(
+            names,
+            [
+                "zkp_auth.Auth/Register",
+                "zkp_auth.Auth/CreateAuthChallenge",
+                "zkp_auth.Auth/VerifyAuth",
+                "zkp_auth.Auth/Register"
+            ]
+        );
+        let attribute = |i: usize, key: &str| {
+            spans[i]
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        // the three steps share the client's trace
+        let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+        let parent = SpanId::from_hex("00f067aa0ba902b7").unwrap();
+        for (i, span) in spans[..3].iter().enumerate() {
+            assert_eq!(span.span_context.trace_id(), trace_id);
+            assert_eq!(span.parent_span_id, parent);
+            assert_eq!(span.span_kind, SpanKind::Server);
+            assert_eq!(attribute(i, "rpc.system"), Some(Value::from("grpc")));
+            assert_eq!(attribute(i, "zkp.request_id"), Some(Value::from("req-1")));
+            assert!(span.end_time >= span.start_time);
+        }
+        assert_eq!(
+            attribute(1, "rpc.method"),
+            Some(Value::from("CreateAuthChallenge"))
+        );
+        assert_eq!(attribute(2, "rpc.grpc.status_code"), Some(Value::I64(7)));
+        assert_ne!(spans[3].span_context.trace_id(), trace_id);
+        assert_eq!(spans[3].parent_span_id, SpanId::INVALID);
+        assert_eq!(attribute(3, "zkp.request_id"), None);
+        assert_eq!(attribute(3, "rpc.grpc.status_code"), None);
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    fs,
+    future::Future,
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use digest::Digest;
+use sha2::Sha256;
+
+use crate::capabilities::Capabilities;
+
+const CACHE_HEADER: &str = "zkp-params-cache v1";
+
+/// Where `ParamsCache::get_or_fetch` got the parameters from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamsSource {
+    Cache,
+    Server,
+}
+
+/// What servers answered to `GetParams`, one file per server URL in `dir`,
+/// trusted for `ttl` after it was fetched.
+///
+/// Every entry carries the `Capabilities::fingerprint` of what it holds, an
+/// entry that doesn't match its fingerprint is ignored. A client that finds
+/// the cached settings no longer work with the server, e.g. after the server
+/// moved to another group, calls `invalidate` and fetches again.
+pub struct ParamsCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ParamsCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        ParamsCache {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// The cached parameters of `url` if fetched less than `ttl` before `now`,
+    /// otherwise those `fetch` returns, cached for next time. `fetch` is only
+    /// awaited on a miss. The cache is best effort, an entry that can't be
+    /// written is not an error.
+    pub async fn get_or_fetch<E>(
+        &self,
+        url: &str,
+        now: SystemTime,
+        fetch: impl Future<Output = Result<Capabilities, E>>,
+    ) -> Result<(Capabilities, ParamsSource), E> {
+        if let Some(capabilities) = self.load(url, now) {
+            return Ok((capabilities, ParamsSource::Cache));
+        }
+        let capabilities = fetch.await?;
+        let _ = self.store(url, &capabilities, now);
+        Ok((capabilities, ParamsSource::Server))
+    }
+
+    /// The entry for `url`, if there is one still fresh at `now`
+    pub fn load(&self, url: &str, now: SystemTime) -> Option<Capabilities> {
+        let text = fs::read_to_string(self.path(url)).ok()?;
+        let mut lines = text.lines();
+        if lines.next() != Some(CACHE_HEADER) {
+            return None;
+        }
+        let mut field = |name: &str| {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(name))
+                .and_then(|line| line.strip_prefix(' '))
+        };
+        if field("url")? != url {
+            return None;
+        }
+        let fetched_at = UNIX_EPOCH + Duration::from_secs(field("fetched_at")?.parse().ok()?);
+        let fingerprint = field("fingerprint")?.to_string();
+        let list = |line: &str| line.split_whitespace().map(str::to_string).collect();
+        let capabilities = Capabilities {
+            protocol_version: field("protocol_version")?.parse().ok()?,
+            groups: list(field("groups")?),
+            encodings: list(field("encodings")?),
+            hashes: list(field("hashes")?),
+        };
+        let age = now.duration_since(fetched_at).ok()?;
+        if age >= self.ttl || capabilities.fingerprint() != fingerprint {
+            return None;
+        }
+        Some(capabilities)
+    }
+
+    /// Caches what `url` answered at `now`
+    pub fn store(&self, url: &str, capabilities: &Capabilities, now: SystemTime) -> io::Result<()> {
+        let fetched_at = now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            self.path(url),
+            format!(
+                "{}\nurl {}\nfetched_at {}\nfingerprint {}\nprotocol_version {}\ngroups {}\nencodings {}\nhashes {}\n",
+                CACHE_HEADER,
+                url,
+                fetched_at,
+                capabilities.fingerprint(),
+                capabilities.protocol_version,
+                capabilities.groups.join(" "),
+                capabilities.encodings.join(" "),
+                capabilities.hashes.join(" "),
+            ),
+        )
+    }
+
+    /// Drops the entry for `url`, returning whether there was one
+    pub fn invalidate(&self, url: &str) -> bool {
+        fs::remove_file(self.path(url)).is_ok()
+    }
+
+    /// Named after the SHA-256 of the URL, which may hold anything
+    fn path(&self, url: &str) -> PathBuf {
+        let name: String = Sha256::digest(url)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.dir.join(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::Cell,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use super::*;
+    use crate::Group;
+
+    /// The fetches here never wait, so one poll finishes them
+    fn now_or_never<T>(future: impl Future<Output = T>) -> T {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future is not ready"),
+        }
+    }
+
+    #[test]
+    fn test_cached_params_within_ttl_skip_the_server() {
+        let dir = std::env::temp_dir().join(format!("zkp-params-cache-{}", std::process::id()));
+        let cache = ParamsCache::new(&dir, Duration::from_secs(60));
+        let url = "http://127.0.0.1:50051";
+        let t0 = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let server = Capabilities::new(vec![Group::Rfc5114_1024_160.name().to_string()]);
+
+        // a mock GetParams counting how often it is called
+        let calls = Cell::new(0);
+        let get_params = |capabilities: &Capabilities| {
+            let capabilities = capabilities.clone();
+            let calls = &calls;
+            async move {
+                calls.set(calls.get() + 1);
+                Ok::<_, ()>(capabilities)
+            }
+        };
+        let get = |now| now_or_never(cache.get_or_fetch(url, now, get_params(&server))).unwrap();
+
+        assert_eq!(get(t0), (server.clone(), ParamsSource::Server));
+        assert_eq!(
+            get(t0 + Duration::from_secs(59)),
+            (server.clone(), ParamsSource::Cache)
+        );
+        assert_eq!(calls.get(), 1);
+
+        // other servers have their own entry
+        let other = now_or_never(cache.get_or_fetch("http://other:50051", t0, get_params(&server)));
+        assert_eq!(other.unwrap().1, ParamsSource::Server);
+        assert_eq!(calls.get(), 2);
+
+        // expired, and cached again from then on
+        assert_eq!(get(t0 + Duration::from_secs(60)).1, ParamsSource::Server);
+        assert_eq!(get(t0 + Duration::from_secs(61)).1, ParamsSource::Cache);
+        assert_eq!(calls.get(), 3);
+
+        // a server that moved to another group
+        assert!(cache.invalidate(url));
+        assert!(!cache.invalidate(url));
+        let moved = Capabilities::new(vec![Group::Rfc5114_2048_256.name().to_string()]);
+        let fetched = now_or_never(cache.get_or_fetch(url, t0, get_params(&moved))).unwrap();
+        assert_eq!(fetched, (moved.clone(), ParamsSource::Server));
+        assert_eq!(cache.load(url, t0), Some(moved));
+
+        // an entry edited by hand no longer matches its fingerprint
+        let path = cache.path(url);
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(&path, text.replace("2048-256", "1024-160")).unwrap();
+        assert_eq!(cache.load(url, t0), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
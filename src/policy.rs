@@ -0,0 +1,108 @@
+/// Glob over user names, `*` matches any run of characters and `?` exactly one,
+/// so `admin*` is a prefix pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePattern(String);
+
+impl NamePattern {
+    pub fn new(pattern: &str) -> Self {
+        NamePattern(pattern.to_string())
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+
+        // matched[j] is true when the pattern read so far matches name[..j]
+        let mut matched = vec![false; name.len() + 1];
+        matched[0] = true;
+        for token in pattern {
+            let previous = matched.clone();
+            matched[0] = token == '*' && previous[0];
+            for j in 1..=name.len() {
+                matched[j] = match token {
+                    '*' => previous[j] || matched[j - 1],
+                    '?' => previous[j - 1],
+                    literal => previous[j - 1] && name[j - 1] == literal,
+                };
+            }
+        }
+        matched[name.len()]
+    }
+}
+
+/// Which user names may register. A name matching any deny pattern is rejected,
+/// and when there are allow patterns the name must match one of them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UsernamePolicy {
+    pub allow: Vec<NamePattern>,
+    pub deny: Vec<NamePattern>,
+}
+
+impl UsernamePolicy {
+    pub fn is_allowed(&self, name: &str) -> bool {
+        if self.deny.iter().any(|pattern| pattern.matches(name)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|pattern| pattern.matches(name))
+    }
+}
+
+/// Parses a comma separated list of patterns, e.g. `"admin*, root"`
+pub fn parse_patterns(s: &str) -> Vec<NamePattern> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(NamePattern::new)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_name_pattern() {
+        let cases = [
+            ("admin*", "admin", true),
+            ("admin*", "administrator", true),
+            ("admin*", "sysadmin", false),
+            ("*bot", "helpbot", true),
+            ("*bot", "bottle", false),
+            ("user?", "user1", true),
+            ("user?", "user", false),
+            ("user?", "user12", false),
+            ("a*b*c", "aXbYc", true),
+            ("a*b*c", "acb", false),
+            ("root", "root", true),
+            ("root", "Root", false),
+            ("*", "", true),
+            ("", "", true),
+            ("", "x", false),
+        ];
+        for (pattern, name, expected) in cases {
+            assert_eq!(
+                NamePattern::new(pattern).matches(name),
+                expected,
+                "{:?} on {:?}",
+                pattern,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_username_policy() {
+        assert!(UsernamePolicy::default().is_allowed("anyone"));
+
+        let policy = UsernamePolicy {
+            allow: parse_patterns("team-*, guest"),
+            deny: parse_patterns(" team-admin* ,"),
+        };
+        assert_eq!(policy.deny, [NamePattern::new("team-admin*")]);
+        assert!(policy.is_allowed("team-peggy"));
+        assert!(policy.is_allowed("guest"));
+        assert!(!policy.is_allowed("victor"));
+        assert!(!policy.is_allowed("team-admin"));
+        assert!(!policy.is_allowed("team-administrator"));
+    }
+}
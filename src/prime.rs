@@ -0,0 +1,66 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+
+/// Miller-Rabin with `rounds` random bases. A composite passes with probability at most 4^-rounds.
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    let one = BigUint::one();
+    let two = BigUint::from(2u32);
+    let three = BigUint::from(3u32);
+    if *n < two {
+        return false;
+    }
+    if *n <= three {
+        return true;
+    }
+    if (n % &two).is_zero() {
+        return false;
+    }
+
+    // n - 1 = d * 2^r with d odd
+    let n_minus_one = n - &one;
+    let r = n_minus_one.trailing_zeros().unwrap_or_default();
+    let d = &n_minus_one >> r;
+
+    let mut rng = rand::thread_rng();
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&two, &n_minus_one);
+        let mut x = a.modpow(&d, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..r {
+            x = x.modpow(&two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_small_numbers() {
+        let primes: Vec<u32> = (0..100u32)
+            .filter(|&n| is_probable_prime(&BigUint::from(n), 20))
+            .collect();
+        assert_eq!(
+            primes,
+            [
+                2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79,
+                83, 89, 97
+            ]
+        );
+    }
+
+    #[test]
+    fn test_carmichael_numbers_are_composite() {
+        for n in [561u32, 1105, 1729, 2465, 2821, 6601, 8911] {
+            assert!(!is_probable_prime(&BigUint::from(n), 20), "{}", n);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use num_bigint::BigUint;
+
+use sha2::Sha256;
+
+use crate::{CompactProof, Entropy, MemorySecret, Reducer, Reduction, SecretBackend, ZKP};
+
+/// r1 = alpha^k mod p
+/// r2 = beta^k mod p
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Commitment {
+    pub r1: BigUint,
+    pub r2: BigUint,
+}
+
+/// The k behind a `Commitment`. It can't be cloned and `Prover::respond` takes it
+/// by value, so every nonce answers exactly one challenge:
+///
+/// ```compile_fail
+/// use num_bigint::BigUint;
+/// use zkp_chaum_pedersen::{Prover, ZKP};
+///
+/// let prover = Prover::new(ZKP::new(), BigUint::from(6u32));
+/// let (_, nonce) = prover.commit();
+/// prover.respond(nonce, &BigUint::from(4u32));
+/// prover.respond(nonce, &BigUint::from(5u32));
+/// ```
+#[derive(Debug)]
+pub struct Nonce(pub(crate) BigUint);
+
+/// s = k - c * x mod q
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub s: BigUint,
+}
+
+/// Prover side of the protocol, answers for the secret x in its `SecretBackend`
+/// and hands out fresh nonces.
+pub struct Prover {
+    zkp: ZKP,
+    secret: Box<dyn SecretBackend>,
+    /// Reduces mod q
+    reducer: Reducer,
+    /// Source of the nonces, the thread's CSPRNG if unset
+    entropy: Option<Entropy>,
+}
+
+impl Prover {
+    /// Prover with x in memory
+    pub fn new(zkp: ZKP, x: BigUint) -> Self {
+        Prover::with_backend(zkp, MemorySecret::new(x))
+    }
+
+    /// Prover leaving every operation on x to `secret`, e.g. an HSM
+    pub fn with_backend(zkp: ZKP, secret: impl SecretBackend + 'static) -> Self {
+        Prover {
+            reducer: Reducer::new(&zkp.q, Reduction::Plain),
+            entropy: None,
+            zkp,
+            secret: Box::new(secret),
+        }
+    }
+
+    /// Computes responses with `reduction` mod q, see `Reducer`
+    pub fn with_reduction(mut self, reduction: Reduction) -> Self {
+        self.reducer = Reducer::new(&self.zkp.q, reduction);
+        self
+    }
+
+    /// Draws its nonces from `entropy`
+    pub fn with_entropy(mut self, entropy: Entropy) -> Self {
+        self.entropy = Some(entropy);
+        self
+    }
+
+    fn random_nonce(&self) -> BigUint {
+        match &self.entropy {
+            Some(entropy) => ZKP::generate_random_below_with(&self.zkp.q, entropy),
+            None => ZKP::generate_random_below(&self.zkp.q),
+        }
+    }
+
+    /// y1 = alpha^x mod p
+    /// y2 = beta^x mod p
+    pub fn public_key(&self) -> (BigUint, BigUint) {
+        self.secret.public_key(&self.zkp)
+    }
+
+    /// Draws a random k and commits to it
+    pub fn commit(&self) -> (Commitment, Nonce) {
+        let k = self.random_nonce();
+        (self.commit_with(&k), Nonce(k))
+    }
+
+    fn commit_with(&self, k: &BigUint) -> Commitment {
+        Commitment {
+            r1: ZKP::exponentiate(&self.zkp.alpha, k, &self.zkp.p),
+            r2: ZKP::exponentiate(&self.zkp.beta, k, &self.zkp.p),
+        }
+    }
+
+    /// Non-interactive proof that this prover holds the x behind `public_key`,
+    /// sent along with it when registering as `user_name`
+    pub fn prove_possession(&self, user_name: &str) -> CompactProof {
+        let k = self.random_nonce();
+        let (y1, y2) = self.public_key();
+        let Commitment { r1, r2 } = self.commit_with(&k);
+        let c =
+            self.zkp
+                .labelled_challenge::<Sha256>(&y1, &y2, &r1, &r2, Some(user_name.as_bytes()));
+        CompactProof {
+            s: self.secret.respond(&self.zkp, &self.reducer, &k, &c),
+            c,
+        }
+    }
+
+    /// Consumes the nonce to answer the verifier's challenge
+    pub fn respond(&self, nonce: Nonce, c: &BigUint) -> Response {
+        Response {
+            s: self.secret.respond(&self.zkp, &self.reducer, &nonce.0, c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FixedChallenges, Verifier, VerifyError};
+    use std::sync::{Arc, Mutex};
+
+    fn toy_zkp() -> ZKP {
+        ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        }
+    }
+
+    #[test]
+    fn test_prover_against_verifier() {
+        let prover = Prover::new(toy_zkp(), BigUint::from(6u32));
+        let verifier = Verifier::new(toy_zkp());
+
+        let (y1, y2) = prover.public_key();
+        assert_eq!(y1, BigUint::from(2u32));
+        assert_eq!(y2, BigUint::from(3u32));
+
+        for _ in 0..20 {
+            let (commitment, nonce) = prover.commit();
+            let c = verifier.challenge();
+            let Response { s } = prover.respond(nonce, &c);
+            assert_eq!(
+                verifier.verify(&commitment.r1, &commitment.r2, &y1, &y2, &c, &s),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_prover_toy_transcript() {
+        let prover = Prover::new(toy_zkp(), BigUint::from(6u32));
+        let verifier =
+            Verifier::with_challenge_source(toy_zkp(), FixedChallenges::new(vec![4u32.into()]));
+
+        let k = BigUint::from(7u32);
+        let commitment = prover.commit_with(&k);
+        assert_eq!(
+            commitment,
+            Commitment {
+                r1: BigUint::from(8u32),
+                r2: BigUint::from(4u32),
+            }
+        );
+
+        let c = verifier.challenge();
+        let response = prover.respond(Nonce(k), &c);
+        assert_eq!(response.s, BigUint::from(5u32));
+
+        // a different prover can't answer for this one's commitment
+        let (y1, y2) = prover.public_key();
+        let impostor = Prover::new(toy_zkp(), BigUint::from(7u32));
+        let forged = impostor.respond(Nonce(BigUint::from(7u32)), &c);
+        assert_eq!(
+            verifier.verify(&commitment.r1, &commitment.r2, &y1, &y2, &c, &forged.s),
+            Err(VerifyError::R1Mismatch)
+        );
+        assert_eq!(
+            verifier.verify(&commitment.r1, &commitment.r2, &y1, &y2, &c, &response.s),
+            Ok(())
+        );
+    }
+
+    /// Stands in for an HSM: x stays inside and every value handed out is
+    /// logged
+    struct MockHsm {
+        x: BigUint,
+        outputs: Arc<Mutex<Vec<BigUint>>>,
+    }
+
+    impl SecretBackend for MockHsm {
+        fn public_key(&self, zkp: &ZKP) -> (BigUint, BigUint) {
+            let y1 = ZKP::exponentiate(&zkp.alpha, &self.x, &zkp.p);
+            let y2 = ZKP::exponentiate(&zkp.beta, &self.x, &zkp.p);
+            self.outputs
+                .lock()
+                .unwrap()
+                .extend([y1.clone(), y2.clone()]);
+            (y1, y2)
+        }
+
+        fn respond(&self, zkp: &ZKP, _: &Reducer, k: &BigUint, c: &BigUint) -> BigUint {
+            let s = zkp.solve(k, c, &self.x);
+            self.outputs.lock().unwrap().push(s.clone());
+            s
+        }
+    }
+
+    #[test]
+    fn test_prover_with_a_secret_backend() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let outputs = Arc::default();
+        let prover = Prover::with_backend(
+            ZKP::new(),
+            MockHsm {
+                x: x.clone(),
+                outputs: Arc::clone(&outputs),
+            },
+        );
+        let verifier = Verifier::new(ZKP::new());
+
+        let (y1, y2) = prover.public_key();
+        assert!(verifier.verify_possession(&y1, &y2, &prover.prove_possession("peggy"), "peggy"));
+        let (commitment, nonce) = prover.commit();
+        let c = verifier.challenge();
+        let Response { s } = prover.respond(nonce, &c);
+        assert_eq!(
+            verifier.verify(&commitment.r1, &commitment.r2, &y1, &y2, &c, &s),
+            Ok(())
+        );
+
+        // public keys and answers went out, x never did
+        let outputs = outputs.lock().unwrap();
+        assert_eq!(outputs.len(), 6);
+        assert!(!outputs.contains(&x));
+    }
+}
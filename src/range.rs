@@ -0,0 +1,223 @@
+use std::fmt;
+
+use digest::Digest;
+use num_bigint::BigUint;
+use sha2::Sha256;
+
+use crate::{OrProof, ZKP};
+
+/// Most bits a range proof may cover, it grows by one commitment and one
+/// `OrProof` per bit
+pub const MAX_RANGE_BITS: usize = 64;
+
+/// Proof that the x behind y1 = alpha^x lies in [0, 2^n), from a commitment
+/// to each bit of x.
+///
+/// C_i = alpha^b_i * beta^r_i commits to bit i, an `OrProof` shows C_i or
+/// C_i / alpha is a power of beta, so b_i is 0 or 1. The bits add up to x as
+/// prod C_i^(2^i) / y1 = beta^R, with R = sum r_i * 2^i, shown by a
+/// Fiat-Shamir proof of knowing R in (c, s).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeProof {
+    /// C_i for each bit, the lowest first
+    pub commitments: Vec<BigUint>,
+    pub bits: Vec<OrProof>,
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+/// Why `prove_range` made no proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// n must be between 1 and `MAX_RANGE_BITS`, and 2^n no more than q so the
+    /// bits can't wrap around mod q
+    UnsupportedBits(usize),
+    /// x is not below 2^n
+    OutOfRange,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::UnsupportedBits(n) => write!(f, "a range of {} bits is not supported", n),
+            RangeError::OutOfRange => f.write_str("x is not in the range"),
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+impl ZKP {
+    /// Proves the x behind y1 = alpha^x is below 2^n
+    pub fn prove_range(&self, x: &BigUint, n: usize) -> Result<RangeProof, RangeError> {
+        self.check_range_bits(n)?;
+        if x.bits() > n as u64 {
+            return Err(RangeError::OutOfRange);
+        }
+        let y1 = ZKP::exponentiate(&self.alpha, x, &self.p);
+        let alpha_inverse = self.inverse(&self.alpha);
+
+        let mut commitments = Vec::with_capacity(n);
+        let mut blindings = Vec::with_capacity(n);
+        for i in 0..n {
+            let r = ZKP::generate_random_below(&self.q);
+            let mut commitment = ZKP::exponentiate(&self.beta, &r, &self.p);
+            if x.bit(i as u64) {
+                commitment = commitment * &self.alpha % &self.p;
+            }
+            commitments.push(commitment);
+            blindings.push(r);
+        }
+        let bits = commitments
+            .iter()
+            .zip(&blindings)
+            .enumerate()
+            .map(|(i, (commitment, r))| {
+                let shifted = commitment * &alpha_inverse % &self.p;
+                let known = x.bit(i as u64) as usize;
+                let context = bit_context(&y1, n, i);
+                self.prove_or(&self.beta, [commitment, &shifted], known, r, &context)
+            })
+            .collect();
+
+        let sum = blindings
+            .iter()
+            .rev()
+            .fold(BigUint::default(), |sum, r| (sum * 2u32 + r) % &self.q);
+        let k = ZKP::generate_random_below(&self.q);
+        let a = ZKP::exponentiate(&self.beta, &k, &self.p);
+        let c = self.range_challenge(&y1, n, &commitments, &a);
+        Ok(RangeProof {
+            s: self.solve(&k, &c, &sum),
+            commitments,
+            bits,
+            c,
+        })
+    }
+
+    /// Checks y1 = alpha^x for some x in [0, 2^n)
+    pub fn verify_range(&self, y1: &BigUint, n: usize, proof: &RangeProof) -> bool {
+        if self.check_range_bits(n).is_err()
+            || proof.commitments.len() != n
+            || proof.bits.len() != n
+            || self.validate_challenge(&proof.c).is_err()
+            || proof.s >= self.q
+        {
+            return false;
+        }
+        if !self.is_in_subgroup(y1) || !proof.commitments.iter().all(|c| self.is_in_subgroup(c)) {
+            return false;
+        }
+        let alpha_inverse = self.inverse(&self.alpha);
+        for (i, (commitment, bit)) in proof.commitments.iter().zip(&proof.bits).enumerate() {
+            let shifted = commitment * &alpha_inverse % &self.p;
+            let context = bit_context(y1, n, i);
+            if !self.verify_or(&self.beta, [commitment, &shifted], bit, &context) {
+                return false;
+            }
+        }
+
+        // prod C_i^(2^i) / y1, by Horner from the highest bit
+        let sum = proof
+            .commitments
+            .iter()
+            .rev()
+            .fold(BigUint::from(1u32), |sum, c| {
+                &sum * &sum % &self.p * c % &self.p
+            });
+        let blinding = sum * self.inverse(y1) % &self.p;
+        let a = ZKP::exponentiate(&self.beta, &proof.s, &self.p)
+            * ZKP::exponentiate(&blinding, &proof.c, &self.p)
+            % &self.p;
+        self.range_challenge(y1, n, &proof.commitments, &a) == proof.c
+    }
+
+    fn check_range_bits(&self, n: usize) -> Result<(), RangeError> {
+        if n == 0 || n > MAX_RANGE_BITS || n as u64 >= self.q.bits() {
+            return Err(RangeError::UnsupportedBits(n));
+        }
+        Ok(())
+    }
+
+    /// n^-1 = n^(q - 1) for n in the order q subgroup
+    fn inverse(&self, n: &BigUint) -> BigUint {
+        ZKP::exponentiate(n, &(&self.q - 1u32), &self.p)
+    }
+
+    /// SHA-256 of the group, y1, n, every C_i and the commitment, each
+    /// length-prefixed, mod q
+    fn range_challenge(
+        &self,
+        y1: &BigUint,
+        n: usize,
+        commitments: &[BigUint],
+        a: &BigUint,
+    ) -> BigUint {
+        let mut hasher = Sha256::new();
+        let mut update = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(bytes);
+        };
+        for element in [&self.p, &self.q, &self.alpha, &self.beta, y1] {
+            update(&element.to_bytes_be());
+        }
+        update(&(n as u32).to_be_bytes());
+        for element in commitments.iter().chain([a]) {
+            update(&element.to_bytes_be());
+        }
+        BigUint::from_bytes_be(&hasher.finalize()) % &self.q
+    }
+}
+
+/// Binds the proof for bit i to the statement it is part of
+fn bit_context(y1: &BigUint, n: usize, i: usize) -> Vec<u8> {
+    let mut context = y1.to_bytes_be();
+    context.extend((n as u32).to_be_bytes());
+    context.extend((i as u32).to_be_bytes());
+    context
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_range_proof() {
+        // a toy group would let a bad proof through one time in q
+        let zkp = ZKP::new();
+        let n = 8;
+        for x in [0u32, 1, 200, 255] {
+            let x = BigUint::from(x);
+            let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+            let proof = zkp.prove_range(&x, n).unwrap();
+            assert!(zkp.verify_range(&y1, n, &proof));
+            assert!(!zkp.verify_range(&y1, n - 1, &proof));
+        }
+
+        let x = BigUint::from(256u32);
+        assert_eq!(zkp.prove_range(&x, n), Err(RangeError::OutOfRange));
+        assert_eq!(zkp.prove_range(&x, 0), Err(RangeError::UnsupportedBits(0)));
+
+        // the proof for 200 doesn't hold for 200 + 2^8, which its bits can't reach
+        let proof = zkp.prove_range(&BigUint::from(200u32), n).unwrap();
+        let y1 = ZKP::exponentiate(&zkp.alpha, &BigUint::from(456u32), &zkp.p);
+        assert!(!zkp.verify_range(&y1, n, &proof));
+    }
+
+    #[test]
+    fn test_range_proof_needs_bits() {
+        let zkp = ZKP::new();
+        let x = BigUint::from(5u32);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let proof = zkp.prove_range(&x, 4).unwrap();
+
+        // a commitment to 2 in place of bit 0 breaks its OR proof
+        let mut forged = proof.clone();
+        forged.commitments[0] = forged.commitments[0].clone() * &zkp.alpha % &zkp.p;
+        assert!(!zkp.verify_range(&y1, 4, &forged));
+
+        let mut swapped = proof;
+        swapped.bits.swap(0, 2);
+        assert!(!zkp.verify_range(&y1, 4, &swapped));
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// Most candidates `find_secret` tries, each one costs four exponentiations
+pub const MAX_CANDIDATES: usize = 64;
+
+/// More candidates than `MAX_CANDIDATES` were given, holds how many.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyCandidates(pub usize);
+
+impl fmt::Display for TooManyCandidates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} candidates given, at most {} are tried",
+            self.0, MAX_CANDIDATES
+        )
+    }
+}
+
+impl std::error::Error for TooManyCandidates {}
+
+impl ZKP {
+    /// Index of the first candidate x that the transcript (r1, r2, c, s) verifies
+    /// against, i.e. r1 = alpha^s * (alpha^x)^c and r2 = beta^s * (beta^x)^c.
+    /// Meant for recovery tools, when a user forgot which of a few passwords
+    /// they registered.
+    pub fn find_secret(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+        candidates: &[BigUint],
+    ) -> Result<Option<usize>, TooManyCandidates> {
+        if candidates.len() > MAX_CANDIDATES {
+            return Err(TooManyCandidates(candidates.len()));
+        }
+        Ok(candidates.iter().position(|x| {
+            let y1 = ZKP::exponentiate(&self.alpha, x, &self.p);
+            let y2 = ZKP::exponentiate(&self.beta, x, &self.p);
+            self.verify(r1, r2, &y1, &y2, c, s)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_secret() {
+        let zkp = ZKP::new();
+        let candidates: Vec<BigUint> = ["hunter2", "correct horse", "letmein"]
+            .iter()
+            .map(|password| BigUint::from_bytes_be(password.as_bytes()))
+            .collect();
+        let x = &candidates[1];
+        let k = ZKP::generate_random_below(&zkp.q);
+        let c = ZKP::generate_random_below(&zkp.q);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+        let s = zkp.solve(&k, &c, x);
+
+        assert_eq!(zkp.find_secret(&r1, &r2, &c, &s, &candidates), Ok(Some(1)));
+        assert_eq!(
+            zkp.find_secret(&r1, &r2, &c, &s, &candidates[2..]),
+            Ok(None)
+        );
+
+        let too_many = vec![x.clone(); MAX_CANDIDATES + 1];
+        assert_eq!(
+            zkp.find_secret(&r1, &r2, &c, &s, &too_many),
+            Err(TooManyCandidates(MAX_CANDIDATES + 1))
+        );
+    }
+}
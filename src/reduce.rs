@@ -0,0 +1,116 @@
+use num_bigint::BigUint;
+
+/// How products are reduced modulo a fixed modulus.
+///
+/// Only the products outside of `exponentiate` go through a `Reducer`, modpow
+/// already does its own Montgomery multiplication for odd moduli.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reduction {
+    /// A division for every reduction
+    #[default]
+    Plain,
+    /// Barrett reduction, two multiplications and shifts with a constant
+    /// precomputed from the modulus
+    Barrett,
+}
+
+/// Reduces modulo one modulus, with whatever `Reduction` needs precomputed.
+#[derive(Debug, Clone)]
+pub struct Reducer {
+    modulus: BigUint,
+    /// Bits of the modulus
+    k: u64,
+    /// floor(4^k / modulus), only set for Barrett
+    mu: Option<BigUint>,
+}
+
+impl Reducer {
+    pub fn new(modulus: &BigUint, reduction: Reduction) -> Self {
+        assert!(modulus.bits() > 0, "the modulus must be positive");
+        let k = modulus.bits();
+        let mu = match reduction {
+            Reduction::Plain => None,
+            Reduction::Barrett => Some((BigUint::from(1u32) << (2 * k)) / modulus),
+        };
+        Reducer {
+            modulus: modulus.clone(),
+            k,
+            mu,
+        }
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    pub fn reduction(&self) -> Reduction {
+        match self.mu {
+            Some(_) => Reduction::Barrett,
+            None => Reduction::Plain,
+        }
+    }
+
+    /// x mod modulus. Barrett covers x below 4^k, which includes every product of
+    /// two reduced values, larger x fall back to a division.
+    pub fn reduce(&self, x: BigUint) -> BigUint {
+        let Some(mu) = &self.mu else {
+            return x % &self.modulus;
+        };
+        if x.bits() > 2 * self.k {
+            return x % &self.modulus;
+        }
+        // the estimate of x / modulus is at most 2 too small
+        let estimate = ((&x >> (self.k - 1)) * mu) >> (self.k + 1);
+        let mut r = x - estimate * &self.modulus;
+        while r >= self.modulus {
+            r -= &self.modulus;
+        }
+        r
+    }
+
+    /// a * b mod modulus
+    pub fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        self.reduce(a * b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Group, ZKP};
+
+    #[test]
+    fn test_barrett_matches_plain() {
+        let moduli = [
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            Group::Rfc5114_2048_256.zkp().p,
+            Group::Rfc5114_2048_256.zkp().q,
+        ];
+        for modulus in moduli {
+            let plain = Reducer::new(&modulus, Reduction::Plain);
+            let barrett = Reducer::new(&modulus, Reduction::Barrett);
+            assert_eq!(barrett.reduction(), Reduction::Barrett);
+
+            let largest = &modulus - 1u32;
+            let mut cases = vec![
+                BigUint::default(),
+                largest.clone(),
+                modulus.clone(),
+                &largest * &largest,
+                // past 4^k, where Barrett falls back to a division
+                BigUint::from(1u32) << (2 * modulus.bits() + 3),
+            ];
+            for _ in 0..200 {
+                let a = ZKP::generate_random_below(&modulus);
+                let b = ZKP::generate_random_below(&modulus);
+                cases.push(a * b);
+            }
+            for x in cases {
+                let expected = &x % &modulus;
+                assert_eq!(plain.reduce(x.clone()), expected);
+                assert_eq!(barrett.reduce(x), expected);
+            }
+        }
+    }
+}
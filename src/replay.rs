@@ -0,0 +1,79 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+use num_bigint::BigUint;
+
+use crate::AuthId;
+
+/// Answers `(auth_id, s)` taken up lately, so a copy of one arriving while the
+/// first is still being checked, or after it, is turned away instead of being
+/// checked again and handing out a second session. Each answer is remembered
+/// for `ttl`, which only needs to outlast the challenge it answers.
+#[derive(Debug)]
+pub struct SeenAnswers {
+    ttl: Duration,
+    /// Insertion order with the time of each, for expiry
+    order: VecDeque<(SystemTime, (AuthId, BigUint))>,
+    seen: HashSet<(AuthId, BigUint)>,
+}
+
+impl SeenAnswers {
+    pub fn new(ttl: Duration) -> Self {
+        SeenAnswers {
+            ttl,
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Records the answer at `now`, false if it was already seen within the
+    /// last `ttl`. s is compared by value, leading zero bytes don't make it new.
+    pub fn insert(&mut self, auth_id: AuthId, s: &BigUint, now: SystemTime) -> bool {
+        while let Some((at, _)) = self.order.front() {
+            if now.duration_since(*at).is_ok_and(|age| age < self.ttl) {
+                break;
+            }
+            let (_, answer) = self.order.pop_front().expect("the front exists");
+            self.seen.remove(&answer);
+        }
+        let answer = (auth_id, s.clone());
+        if !self.seen.insert(answer.clone()) {
+            return false;
+        }
+        self.order.push_back((now, answer));
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_answers_are_seen_until_they_expire() {
+        let mut answers = SeenAnswers::new(Duration::from_secs(60));
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let auth_id: AuthId = "aB3dE5gH7jK9".parse().unwrap();
+        let s = BigUint::from(5u32);
+
+        assert!(answers.insert(auth_id, &s, start));
+        assert!(!answers.insert(auth_id, &s, start + Duration::from_secs(59)));
+        // another s, or the same s for another challenge
+        assert!(answers.insert(auth_id, &BigUint::from(6u32), start));
+        assert!(answers.insert("000000000001".parse().unwrap(), &s, start));
+        assert_eq!(answers.len(), 3);
+
+        assert!(answers.insert(auth_id, &s, start + Duration::from_secs(60)));
+        assert_eq!(answers.len(), 1);
+    }
+}
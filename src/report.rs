@@ -0,0 +1,113 @@
+use std::{fmt::Write, time::Instant};
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+use crate::ZKP;
+
+impl ZKP {
+    /// `verify_checked` with its diagnostics as one JSON object, for callers
+    /// that want them machine-readable:
+    ///
+    /// - `valid`, and `error`, the reason it was rejected or null
+    /// - `in_range`, for each input whether it is in [1, p) for r1, r2, y1
+    ///   and y2, or in [0, q) for c and s
+    /// - `equations`, whether r1 and r2 each matched, null when the inputs
+    ///   were turned away before the equations
+    /// - `group`, the `descriptor`, and `elapsed_us`, the time the check took
+    ///
+    /// None of the inputs are echoed, nor is anything derived from a secret.
+    pub fn verify_report_json(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> String {
+        let start = Instant::now();
+        let result = self.verify_checked(r1, r2, y1, y2, c, s);
+        let equations = self
+            .check_ranges(r1, r2, y1, y2, c, s)
+            .is_ok()
+            .then(|| self.verify_detailed(r1, r2, y1, y2, c, s));
+        let elapsed = start.elapsed();
+
+        let in_range = [("r1", r1), ("r2", r2), ("y1", y1), ("y2", y2)]
+            .map(|(name, n)| (name, !n.is_zero() && *n < self.p))
+            .into_iter()
+            .chain([("c", *c < self.q), ("s", *s < self.q)])
+            .map(|(name, ok)| format!("\"{}\": {}", name, ok))
+            .collect::<Vec<_>>()
+            .join(", ");
+        // error messages and descriptors hold no quotes or backslashes
+        let error = match &result {
+            Ok(()) => "null".to_string(),
+            Err(err) => format!("\"{}\"", err),
+        };
+        let equations = match equations {
+            Some(details) => format!(
+                "{{\"r1\": {}, \"r2\": {}}}",
+                details.r1_matches, details.r2_matches
+            ),
+            None => "null".to_string(),
+        };
+        let mut json = String::from("{\n");
+        writeln!(json, "  \"group\": \"{}\",", self.descriptor()).unwrap();
+        writeln!(json, "  \"valid\": {},", result.is_ok()).unwrap();
+        writeln!(json, "  \"error\": {},", error).unwrap();
+        writeln!(json, "  \"in_range\": {{{}}},", in_range).unwrap();
+        writeln!(json, "  \"equations\": {},", equations).unwrap();
+        writeln!(json, "  \"elapsed_us\": {}", elapsed.as_micros()).unwrap();
+        json.push('}');
+        json
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_report_of_a_failed_verification() {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        // x = 6, k = 7, c = 4 gives s = 5, a wrong r2 in the subgroup breaks
+        // only its equation
+        let [r1, r2, y1, y2, c, s] = [8u32, 6, 2, 3, 4, 5].map(BigUint::from);
+        let report: Value =
+            serde_json::from_str(&zkp.verify_report_json(&r1, &r2, &y1, &y2, &c, &s)).unwrap();
+        assert_eq!(report["valid"], false);
+        assert_eq!(report["error"], "r2 does not match beta^s * y2^c");
+        assert_eq!(report["equations"], json!({"r1": true, "r2": false}));
+        assert_eq!(
+            report["in_range"],
+            json!({"r1": true, "r2": true, "y1": true, "y2": true, "c": true, "s": true})
+        );
+        assert_eq!(report["group"], zkp.descriptor());
+        assert!(report["elapsed_us"].is_u64());
+        // nothing but the fields above, so no room for x or k
+        assert_eq!(report.as_object().unwrap().len(), 6);
+
+        // out of range never gets to the equations
+        let c = BigUint::from(11u32);
+        let report: Value =
+            serde_json::from_str(&zkp.verify_report_json(&r1, &r2, &y1, &y2, &c, &s)).unwrap();
+        assert_eq!(report["error"], "c is out of range");
+        assert_eq!(report["in_range"]["c"], false);
+        assert_eq!(report["equations"], Value::Null);
+
+        let r2 = BigUint::from(4u32);
+        let c = BigUint::from(4u32);
+        let report: Value =
+            serde_json::from_str(&zkp.verify_report_json(&r1, &r2, &y1, &y2, &c, &s)).unwrap();
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["error"], Value::Null);
+    }
+}
@@ -0,0 +1,566 @@
+//! JSON gateway over the same handlers as the gRPC service.
+//!
+//! Field names match the proto messages and elements are big-endian numbers
+//! written with an [`ElementCodec`], hex strings by default.
+
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use num_bigint::BigUint;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tonic::{metadata::MetadataKey, transport::server::TcpConnectInfo, Code, Request, Status};
+
+use crate::{
+    zkp_auth::{auth_server::Auth, AuthAnswerRequest, AuthChallengeRequest, RegisterRequest},
+    AuthImpl, ClientCerts,
+};
+
+#[derive(Deserialize)]
+struct RegisterBody {
+    user: String,
+    y1: String,
+    y2: String,
+    #[serde(default)]
+    group: String,
+    pop_c: String,
+    pop_s: String,
+}
+
+#[derive(Serialize)]
+struct RegisterReply {
+    key_handle: String,
+}
+
+#[derive(Deserialize)]
+struct ChallengeBody {
+    #[serde(default)]
+    user: String,
+    /// r1 and r2 are left out when committing by commitment_hash
+    r1: Option<String>,
+    r2: Option<String>,
+    #[serde(default)]
+    context: String,
+    #[serde(default)]
+    key_handle: String,
+    commitment_hash: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ChallengeReply {
+    auth_id: String,
+    c: String,
+    expires_at: u64,
+}
+
+#[derive(Deserialize)]
+struct VerifyBody {
+    auth_id: String,
+    s: String,
+    #[serde(default)]
+    context: String,
+    r1: Option<String>,
+    r2: Option<String>,
+}
+
+#[derive(Serialize)]
+struct VerifyReply {
+    session_id: String,
+    expires_at: u64,
+}
+
+/// Request header choosing the element encoding of a single request and its reply
+const ELEMENT_ENCODING_HEADER: &str = "x-element-encoding";
+
+/// How elements are written in JSON bodies.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ElementCodec {
+    /// Big-endian bytes as lowercase hex, an optional `0x` prefix is accepted
+    #[default]
+    Hex,
+    /// Big-endian bytes as standard padded base64
+    Base64,
+    /// The number in base 10
+    Decimal,
+}
+
+impl ElementCodec {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ElementCodec::Hex => "hex",
+            ElementCodec::Base64 => "base64",
+            ElementCodec::Decimal => "decimal",
+        }
+    }
+
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            ElementCodec::Hex => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            ElementCodec::Base64 => BASE64.encode(bytes),
+            ElementCodec::Decimal => BigUint::from_bytes_be(bytes).to_str_radix(10),
+        }
+    }
+
+    pub fn decode(&self, value: &str) -> Option<Vec<u8>> {
+        match self {
+            ElementCodec::Hex => {
+                let value = value.strip_prefix("0x").unwrap_or(value);
+                if !value.is_ascii() {
+                    return None;
+                }
+                let padded = if value.len() % 2 == 1 {
+                    format!("0{}", value)
+                } else {
+                    value.to_string()
+                };
+                (0..padded.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&padded[i..i + 2], 16).ok())
+                    .collect()
+            }
+            ElementCodec::Base64 => BASE64.decode(value).ok(),
+            ElementCodec::Decimal => {
+                BigUint::parse_bytes(value.as_bytes(), 10).map(|n| n.to_bytes_be())
+            }
+        }
+    }
+}
+
+impl FromStr for ElementCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            ElementCodec::Hex,
+            ElementCodec::Base64,
+            ElementCodec::Decimal,
+        ]
+        .into_iter()
+        .find(|codec| codec.name().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| format!("unknown element encoding {:?}", s))
+    }
+}
+
+#[derive(Clone)]
+struct Gateway {
+    auth_impl: Arc<AuthImpl>,
+    codec: ElementCodec,
+}
+
+impl Gateway {
+    /// The codec asked for in the request headers, or the gateway's default
+    fn codec(&self, headers: &HeaderMap) -> Result<ElementCodec, RestError> {
+        match headers.get(ELEMENT_ENCODING_HEADER) {
+            Some(value) => value
+                .to_str()
+                .map_err(|err| err.to_string())
+                .and_then(str::parse)
+                .map_err(|err| RestError(StatusCode::BAD_REQUEST, err)),
+            None => Ok(self.codec),
+        }
+    }
+
+    /// gRPC request for `message` from `peer`, with the client certificate
+    /// subject from the proxy's header copied over when the server takes it
+    /// from one. `AuthImpl::cert_subject` only believes it if `peer` is that proxy.
+    fn request<T>(
+        &self,
+        headers: &HeaderMap,
+        peer: Option<ConnectInfo<SocketAddr>>,
+        message: T,
+    ) -> Request<T> {
+        let mut request = Request::new(message);
+        request.extensions_mut().insert(TcpConnectInfo {
+            local_addr: None,
+            remote_addr: peer.map(|ConnectInfo(addr)| addr),
+        });
+        if let Some(ClientCerts::Proxy { header, .. }) = &self.auth_impl.client_certs {
+            let key = MetadataKey::from_bytes(header.as_bytes());
+            let value = headers.get(header).and_then(|value| value.to_str().ok());
+            if let (Ok(key), Some(Ok(value))) = (key, value.map(str::parse)) {
+                request.metadata_mut().insert(key, value);
+            }
+        }
+        request
+    }
+}
+
+/// Routes of the gateway, `codec` is used for requests without an encoding header
+pub fn router(auth_impl: Arc<AuthImpl>, codec: ElementCodec) -> Router {
+    Router::new()
+        .route("/register", post(register))
+        .route("/challenge", post(challenge))
+        .route("/verify", post(verify))
+        .with_state(Gateway { auth_impl, codec })
+}
+
+struct RestError(StatusCode, String);
+
+impl From<Status> for RestError {
+    fn from(status: Status) -> Self {
+        let code = match status.code() {
+            Code::InvalidArgument | Code::FailedPrecondition => StatusCode::BAD_REQUEST,
+            Code::NotFound => StatusCode::NOT_FOUND,
+            Code::PermissionDenied => StatusCode::FORBIDDEN,
+            Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Code::AlreadyExists => StatusCode::CONFLICT,
+            Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+            Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        RestError(code, status.message().to_string())
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, RestError> {
+    serde_json::from_slice(body).map_err(|err| RestError(StatusCode::BAD_REQUEST, err.to_string()))
+}
+
+fn json<T: Serialize>(value: &T) -> Response {
+    let body = serde_json::to_string(value).expect("replies always serialize");
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+fn decode(codec: ElementCodec, name: &str, value: &str) -> Result<Vec<u8>, RestError> {
+    codec.decode(value).ok_or_else(|| {
+        RestError(
+            StatusCode::BAD_REQUEST,
+            format!("invalid {} for {}", codec.name(), name),
+        )
+    })
+}
+
+/// Missing optional fields stand for the proto's empty bytes
+fn decode_optional(
+    codec: ElementCodec,
+    name: &str,
+    value: &Option<String>,
+) -> Result<Vec<u8>, RestError> {
+    match value {
+        Some(value) => decode(codec, name, value),
+        None => Ok(Vec::new()),
+    }
+}
+
+async fn register(
+    State(gateway): State<Gateway>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, RestError> {
+    let codec = gateway.codec(&headers)?;
+    let RegisterBody {
+        user,
+        y1,
+        y2,
+        group,
+        pop_c,
+        pop_s,
+    } = parse_body(&body)?;
+    let request = RegisterRequest {
+        user,
+        y1: decode(codec, "y1", &y1)?,
+        y2: decode(codec, "y2", &y2)?,
+        group,
+        pop_c: decode(codec, "pop_c", &pop_c)?,
+        pop_s: decode(codec, "pop_s", &pop_s)?,
+    };
+    let response = gateway
+        .auth_impl
+        .register(gateway.request(&headers, peer, request))
+        .await?
+        .into_inner();
+    Ok(json(&RegisterReply {
+        key_handle: response.key_handle,
+    }))
+}
+
+async fn challenge(
+    State(gateway): State<Gateway>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, RestError> {
+    let codec = gateway.codec(&headers)?;
+    let ChallengeBody {
+        user,
+        r1,
+        r2,
+        context,
+        key_handle,
+        commitment_hash,
+    } = parse_body(&body)?;
+    if commitment_hash.is_none() && (r1.is_none() || r2.is_none()) {
+        return Err(RestError(
+            StatusCode::BAD_REQUEST,
+            "r1 and r2 are required without a commitment_hash".to_string(),
+        ));
+    }
+    let request = AuthChallengeRequest {
+        user,
+        r1: decode_optional(codec, "r1", &r1)?,
+        r2: decode_optional(codec, "r2", &r2)?,
+        context,
+        key_handle,
+        commitment_hash: decode_optional(codec, "commitment_hash", &commitment_hash)?,
+    };
+    let response = gateway
+        .auth_impl
+        .create_auth_challenge(gateway.request(&headers, peer, request))
+        .await?
+        .into_inner();
+    Ok(json(&ChallengeReply {
+        auth_id: response.auth_id,
+        c: codec.encode(&response.c),
+        expires_at: response.expires_at,
+    }))
+}
+
+async fn verify(
+    State(gateway): State<Gateway>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, RestError> {
+    let codec = gateway.codec(&headers)?;
+    let VerifyBody {
+        auth_id,
+        s,
+        context,
+        r1,
+        r2,
+    } = parse_body(&body)?;
+    let request = AuthAnswerRequest {
+        auth_id,
+        s: decode(codec, "s", &s)?,
+        context,
+        r1: decode_optional(codec, "r1", &r1)?,
+        r2: decode_optional(codec, "r2", &r2)?,
+    };
+    let response = gateway
+        .auth_impl
+        .verify_auth(gateway.request(&headers, peer, request))
+        .await?
+        .into_inner();
+    Ok(json(&VerifyReply {
+        session_id: response.session_id,
+        expires_at: response.expires_at,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{body::Body, http};
+    use num_bigint::BigUint;
+    use num_traits::Num;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+    use zkp_chaum_pedersen::ZKP;
+
+    async fn post(router: &Router, path: &str, body: Value) -> (StatusCode, Value) {
+        post_encoded(router, path, None, body).await
+    }
+
+    async fn post_encoded(
+        router: &Router,
+        path: &str,
+        encoding: Option<&str>,
+        body: Value,
+    ) -> (StatusCode, Value) {
+        let mut request =
+            http::Request::post(path).header(header::CONTENT_TYPE, "application/json");
+        if let Some(encoding) = encoding {
+            request = request.header(ELEMENT_ENCODING_HEADER, encoding);
+        }
+        let request = request.body(Body::from(body.to_string())).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn hex(n: &BigUint) -> String {
+        n.to_str_radix(16)
+    }
+
+    #[tokio::test]
+    async fn test_rest_auth_flow() {
+        let router = router(Arc::new(AuthImpl::default()), ElementCodec::Hex);
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let possession = zkp.prove_possession(&ZKP::generate_random_below(&zkp.q), &x, "peggy");
+
+        let (status, _) = post(
+            &router,
+            "/register",
+            json!({
+                "user": "peggy",
+                "y1": hex(&ZKP::exponentiate(&zkp.alpha, &x, &zkp.p)),
+                "y2": hex(&ZKP::exponentiate(&zkp.beta, &x, &zkp.p)),
+                "pop_c": hex(&possession.c),
+                "pop_s": hex(&possession.s),
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, challenge) = post(
+            &router,
+            "/challenge",
+            json!({
+                "user": "peggy",
+                "r1": hex(&ZKP::exponentiate(&zkp.alpha, &k, &zkp.p)),
+                "r2": hex(&ZKP::exponentiate(&zkp.beta, &k, &zkp.p)),
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let c = BigUint::from_str_radix(challenge["c"].as_str().unwrap(), 16).unwrap();
+        let auth_id = challenge["auth_id"].as_str().unwrap();
+
+        // a wrong answer is forbidden, the right one logs in
+        let s = zkp.solve(&k, &c, &(&x + 1u32));
+        let (status, _) = post(
+            &router,
+            "/verify",
+            json!({ "auth_id": auth_id, "s": hex(&s) }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let s = zkp.solve(&k, &c, &x);
+        let (status, session) = post(
+            &router,
+            "/verify",
+            json!({ "auth_id": auth_id, "s": hex(&s) }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!session["session_id"].as_str().unwrap().is_empty());
+        assert!(session["expires_at"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_rest_rejects_bad_input() {
+        let router = router(Arc::new(AuthImpl::default()), ElementCodec::Hex);
+
+        for (y1, y2) in [("zz", "03"), ("02", "é3")] {
+            let (status, _) = post(
+                &router,
+                "/register",
+                json!({ "user": "peggy", "y1": y1, "y2": y2 }),
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+
+        let (status, _) = post(&router, "/challenge", json!({ "user": "peggy" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = post(
+            &router,
+            "/challenge",
+            json!({ "user": "nobody", "r1": "08", "r2": "04" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_element_codec_round_trip() {
+        let bytes = [1u8, 0, 255];
+        let expected = [
+            (ElementCodec::Hex, "0100ff"),
+            (ElementCodec::Base64, "AQD/"),
+            (ElementCodec::Decimal, "65791"),
+        ];
+        for (codec, encoded) in expected {
+            assert_eq!(codec.encode(&bytes), encoded);
+            assert_eq!(codec.decode(encoded), Some(bytes.to_vec()));
+            assert_eq!(codec.name().parse(), Ok(codec));
+
+            let q = ZKP::new().q.to_bytes_be();
+            assert_eq!(codec.decode(&codec.encode(&q)), Some(q));
+        }
+
+        assert_eq!(ElementCodec::Hex.decode("0x100ff"), Some(bytes.to_vec()));
+        assert_eq!(ElementCodec::Hex.decode("0g"), None);
+        assert_eq!(ElementCodec::Base64.decode("AQD"), None);
+        assert_eq!(ElementCodec::Decimal.decode("-1"), None);
+        assert_eq!("BASE64".parse(), Ok(ElementCodec::Base64));
+        assert!("base32".parse::<ElementCodec>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rest_encoding_header() {
+        let router = router(Arc::new(AuthImpl::default()), ElementCodec::Decimal);
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let base64 = |n: &BigUint| ElementCodec::Base64.encode(&n.to_bytes_be());
+        let possession = zkp.prove_possession(&ZKP::generate_random_below(&zkp.q), &x, "peggy");
+
+        let (status, _) = post_encoded(
+            &router,
+            "/register",
+            Some("base64"),
+            json!({
+                "user": "peggy",
+                "y1": base64(&ZKP::exponentiate(&zkp.alpha, &x, &zkp.p)),
+                "y2": base64(&ZKP::exponentiate(&zkp.beta, &x, &zkp.p)),
+                "pop_c": base64(&possession.c),
+                "pop_s": base64(&possession.s),
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // no header, the gateway default is decimal
+        let (status, challenge) = post(
+            &router,
+            "/challenge",
+            json!({
+                "user": "peggy",
+                "r1": ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_string(),
+                "r2": ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_string(),
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let c: BigUint = challenge["c"].as_str().unwrap().parse().unwrap();
+
+        let s = zkp.solve(&k, &c, &x);
+        let (status, _) = post_encoded(
+            &router,
+            "/verify",
+            Some("hex"),
+            json!({ "auth_id": challenge["auth_id"], "s": hex(&s) }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = post_encoded(
+            &router,
+            "/verify",
+            Some("base32"),
+            json!({ "auth_id": challenge["auth_id"], "s": hex(&s) }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
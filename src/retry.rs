@@ -0,0 +1,152 @@
+//! Per-RPC retries for the client, with exponential backoff and full jitter.
+//!
+//! `Register` and `CreateAuthChallenge` can be sent again safely: a lost
+//! registration is redone with a new key handle, a lost challenge expires
+//! unanswered. `VerifyAuth` is not retried by default, an answer that got
+//! through but whose reply was lost would log in twice, or be refused as a
+//! replay and hide the first login.
+
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use tonic::{Code, Status};
+
+/// How often and how far apart an RPC is retried after a transient failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in all, the first one included, 1 never retries
+    pub max_attempts: u32,
+    /// Upper bound of the first wait, doubled after every attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// `CLIENT_RETRY_ATTEMPTS`, `CLIENT_RETRY_BACKOFF_MS` and
+    /// `CLIENT_RETRY_MAX_BACKOFF_MS` over the defaults
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name).ok().map(|value| {
+                value
+                    .parse::<u64>()
+                    .unwrap_or_else(|_| panic!("invalid {}", name))
+            })
+        };
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            max_attempts: var("CLIENT_RETRY_ATTEMPTS").map_or(default.max_attempts, |n| {
+                u32::try_from(n.max(1)).expect("invalid CLIENT_RETRY_ATTEMPTS")
+            }),
+            initial_backoff: var("CLIENT_RETRY_BACKOFF_MS")
+                .map_or(default.initial_backoff, Duration::from_millis),
+            max_backoff: var("CLIENT_RETRY_MAX_BACKOFF_MS")
+                .map_or(default.max_backoff, Duration::from_millis),
+        }
+    }
+
+    /// Wait before attempt `attempt + 1`: uniform in [0, min(max_backoff,
+    /// initial_backoff * 2^(attempt - 1))], so clients that failed together
+    /// don't retry together
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_backoff);
+        ceiling.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// Failures that say nothing about the request, only about the way there
+pub fn is_transient(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted
+    )
+}
+
+/// Runs `call` until it succeeds, fails for good, or `policy` runs out of
+/// attempts, returning the last status
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, mut call: F) -> Result<T, Status>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Err(status) if is_transient(&status) && attempt < policy.max_attempts => {
+                tokio::time::sleep(policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with `code` the first `failures` calls, then answers the attempt number
+    async fn flaky(calls: &AtomicU32, failures: u32, code: Code) -> Result<u32, Status> {
+        let attempt = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt <= failures {
+            return Err(Status::new(code, "dropped"));
+        }
+        Ok(attempt)
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        };
+        let calls = AtomicU32::new(0);
+        let result = retry(&policy, || flaky(&calls, 3, Code::Unavailable)).await;
+        assert_eq!(result.unwrap(), 4);
+
+        // one failure too many
+        let calls = AtomicU32::new(0);
+        let result = retry(&policy, || flaky(&calls, 4, Code::Unavailable)).await;
+        assert_eq!(result.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // a refusal is final, and `none` never retries
+        let calls = AtomicU32::new(0);
+        let result = retry(&policy, || flaky(&calls, 1, Code::InvalidArgument)).await;
+        assert_eq!(result.unwrap_err().code(), Code::InvalidArgument);
+        let result = retry(&RetryPolicy::none(), || flaky(&calls, 2, Code::Unavailable)).await;
+        assert_eq!(result.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_backoff_grows_up_to_its_bound() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..10 {
+            let ceiling = (policy.initial_backoff * 2u32.pow(attempt - 1)).min(policy.max_backoff);
+            assert!(policy.backoff(attempt) <= ceiling);
+        }
+    }
+}
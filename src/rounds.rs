@@ -0,0 +1,151 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::{Commitment, Response, Verifier, VerifyError};
+
+/// Why `ChallengeStream::verify` rejected a multi-round proof, rounds counted from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundError {
+    /// The answer to this round doesn't verify
+    Invalid(usize, VerifyError),
+    /// This round was challenged but never answered
+    Missing(usize),
+    /// This answer came without a challenge
+    Unchallenged(usize),
+}
+
+impl fmt::Display for RoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundError::Invalid(round, err) => write!(f, "round {}: {}", round, err),
+            RoundError::Missing(round) => write!(f, "round {} was not answered", round),
+            RoundError::Unchallenged(round) => write!(f, "round {} was never challenged", round),
+        }
+    }
+}
+
+impl std::error::Error for RoundError {}
+
+/// Endless stream of independent challenges from a `Verifier`, remembering each
+/// one it hands out so `verify` can check the answers in the same order.
+pub struct ChallengeStream<'a> {
+    verifier: &'a Verifier,
+    issued: Vec<BigUint>,
+}
+
+impl Verifier {
+    /// Challenges for a proof of several rounds, take as many as there are rounds
+    pub fn challenge_stream(&self) -> ChallengeStream<'_> {
+        ChallengeStream {
+            verifier: self,
+            issued: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for ChallengeStream<'_> {
+    type Item = BigUint;
+
+    fn next(&mut self) -> Option<BigUint> {
+        let c = self.verifier.challenge();
+        self.issued.push(c.clone());
+        Some(c)
+    }
+}
+
+impl ChallengeStream<'_> {
+    /// Challenges handed out so far, in order
+    pub fn issued(&self) -> &[BigUint] {
+        &self.issued
+    }
+
+    /// Checks one (commitment, response) per challenge issued, in order. Stops
+    /// at the first answer that fails, later ones aren't consumed.
+    pub fn verify(
+        self,
+        y1: &BigUint,
+        y2: &BigUint,
+        answers: impl IntoIterator<Item = (Commitment, Response)>,
+    ) -> Result<(), RoundError> {
+        let mut answers = answers.into_iter();
+        for (round, c) in self.issued.iter().enumerate() {
+            let (commitment, response) = answers.next().ok_or(RoundError::Missing(round))?;
+            self.verifier
+                .verify(&commitment.r1, &commitment.r2, y1, y2, c, &response.s)
+                .map_err(|err| RoundError::Invalid(round, err))?;
+        }
+        match answers.next() {
+            Some(_) => Err(RoundError::Unchallenged(self.issued.len())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Prover, ZKP};
+
+    fn toy_zkp() -> ZKP {
+        ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        }
+    }
+
+    #[test]
+    fn test_ten_rounds() {
+        let verifier = Verifier::new(toy_zkp());
+        let prover = Prover::new(toy_zkp(), BigUint::from(6u32));
+        let (y1, y2) = prover.public_key();
+
+        let answer = |rounds: usize| {
+            let (commitments, nonces): (Vec<_>, Vec<_>) =
+                (0..rounds).map(|_| prover.commit()).unzip();
+            let mut challenges = verifier.challenge_stream();
+            let responses: Vec<_> = nonces
+                .into_iter()
+                .zip(challenges.by_ref())
+                .map(|(nonce, c)| prover.respond(nonce, &c))
+                .collect();
+            assert_eq!(challenges.issued().len(), rounds);
+            (challenges, commitments.into_iter().zip(responses))
+        };
+
+        let (challenges, answers) = answer(10);
+        assert_eq!(challenges.verify(&y1, &y2, answers), Ok(()));
+
+        // a wrong answer in round 3 ends the check there
+        let (challenges, answers) = answer(10);
+        let mut consumed = 0;
+        let answers = answers.enumerate().map(|(round, (commitment, response))| {
+            consumed += 1;
+            let s = if round == 3 {
+                (&response.s + 1u32) % 11u32
+            } else {
+                response.s
+            };
+            (commitment, Response { s })
+        });
+        assert!(matches!(
+            challenges.verify(&y1, &y2, answers),
+            Err(RoundError::Invalid(3, _))
+        ));
+        assert_eq!(consumed, 4);
+
+        let (challenges, answers) = answer(10);
+        assert_eq!(
+            challenges.verify(&y1, &y2, answers.take(9)),
+            Err(RoundError::Missing(9))
+        );
+        let (mut challenges, answers) = answer(10);
+        challenges.issued.pop();
+        assert_eq!(
+            challenges.verify(&y1, &y2, answers),
+            Err(RoundError::Unchallenged(9))
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::{Reducer, ZKP};
+
+/// Where a `Prover` keeps its secret x. Only these two operations ever touch
+/// x, so an implementation can run them inside an HSM, e.g. over PKCS#11, and
+/// never hand x out.
+pub trait SecretBackend: Send + Sync {
+    /// y1 = alpha^x mod p
+    /// y2 = beta^x mod p
+    fn public_key(&self, zkp: &ZKP) -> (BigUint, BigUint);
+
+    /// s = k - c * x mod q, `reducer` reduces mod q for backends computing in
+    /// software
+    fn respond(&self, zkp: &ZKP, reducer: &Reducer, k: &BigUint, c: &BigUint) -> BigUint;
+}
+
+/// x held in memory, the default backend of `Prover::new`
+pub struct MemorySecret {
+    x: BigUint,
+}
+
+impl MemorySecret {
+    pub fn new(x: BigUint) -> Self {
+        MemorySecret { x }
+    }
+}
+
+/// Leaves x out
+impl fmt::Debug for MemorySecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MemorySecret")
+    }
+}
+
+impl SecretBackend for MemorySecret {
+    fn public_key(&self, zkp: &ZKP) -> (BigUint, BigUint) {
+        (
+            ZKP::exponentiate(&zkp.alpha, &self.x, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &self.x, &zkp.p),
+        )
+    }
+
+    fn respond(&self, zkp: &ZKP, reducer: &Reducer, k: &BigUint, c: &BigUint) -> BigUint {
+        zkp.solve_with(reducer, k, c, &self.x)
+    }
+}
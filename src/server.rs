@@ -1,37 +1,741 @@
 pub mod zkp_auth {
     include!("./zkp_auth.rs");
 }
+#[cfg(feature = "rest")]
+mod rest;
 
-use std::{collections::HashMap, env, sync::Mutex};
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    future::Future,
+    io,
+    net::IpAddr,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock, Weak},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use num_bigint::BigUint;
-use tonic::{transport::Server, Code, Request, Response, Status};
+use num_traits::Zero;
+use tokio::{
+    net::TcpListener,
+    signal,
+    sync::{mpsc, Semaphore},
+};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{
+    metadata::MetadataMap,
+    transport::{
+        server::{TcpConnectInfo, TlsConnectInfo},
+        Certificate, Identity, Server, ServerTlsConfig,
+    },
+    Code, Request, Response, Status, Streaming,
+};
+use x509_parser::{certificate::X509Certificate, prelude::FromDer};
 use zkp_auth::{
     auth_server::{Auth, AuthServer},
-    AuthAnswerRensponse, AuthAnswerRequest, AuthChallengeRequest, AuthChallengeResponse,
-    RegisterRequest, RegisterResponse,
+    login_stream_request, login_stream_response, AuthAnswerRensponse, AuthAnswerRequest,
+    AuthChallengeRequest, AuthChallengeResponse, ExportUsersRequest, ExportUsersResponse,
+    GetParamsRequest, GetParamsResponse, HandshakeRequest, HandshakeResponse, ImportUsersRequest,
+    ImportUsersResponse, LoginStreamRequest, LoginStreamResponse, RefreshSessionRequest,
+    RefreshSessionResponse, RegisterRequest, RegisterResponse, RotateGroupRequest,
+    RotateGroupResponse, StatsRequest, StatsResponse, UserRecord, VerifyMultiProofRequest,
+    VerifyProofCborRequest, VerifyProofRequest, VerifyProofResponse,
+};
+#[cfg(feature = "otel")]
+use zkp_chaum_pedersen::otel;
+use zkp_chaum_pedersen::{
+    capabilities::Capabilities,
+    commit::hash_elements,
+    error_details::{error_info, error_status, ErrorReason},
+    policy, AuditRecord, AuditSink, AuthId, ChallengeSource, Clock, Commitment, CommitmentWindow,
+    CompactProof, ConstantTimeEq, DeviceRng, ElementEncoding, EncryptedStore, Entropy, FileStore,
+    IdGenerator, MemoryStore, NoAudit, ParamError, RandomChallenges, RandomIds,
+    Response as ProofResponse, RotatingVerifier, SeenAnswers, SessionId, SigmaVerifier,
+    StderrAudit, StoreError, StoreKey, SystemClock, UserAuthInfo, UserStore, UsernamePolicy,
+    Verifier, VerifyError, DEFAULT_POWER_CACHE_SIZE, MAX_BASES, ZKP,
 };
-use zkp_chaum_pedersen::ZKP;
 
-#[derive(Default)]
-pub struct UserAuthInfo {
-    // registration
+/// Characters in a key handle
+const KEY_HANDLE_LEN: usize = 16;
+
+/// Proofs timed by `--bench`
+const BENCH_ROUNDS: u32 = 20;
+
+/// Request metadata carrying the admin token
+const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+/// Commitments remembered per user to turn away a reused nonce
+const COMMITMENT_WINDOW: usize = 64;
+/// Where the gRPC service listens by default
+const DEFAULT_ADDRESS: &str = "127.0.0.1:50051";
+/// How long the prover has to answer a challenge by default
+const CHALLENGE_TTL: Duration = Duration::from_secs(60);
+/// How long a session lives after login or after its last refresh by default
+const SESSION_TTL: Duration = Duration::from_secs(15 * 60);
+/// Hard cap on a session's lifetime counted from login, regardless of refreshes
+const SESSION_MAX_LIFETIME: Duration = Duration::from_secs(8 * 60 * 60);
+
+/// What a successful verification vouches for, the session it opens is built from it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionClaims {
     pub user_name: String,
-    pub y1: BigUint,
-    pub y2: BigUint,
-    // authorization
-    pub r1: BigUint,
-    pub r2: BigUint,
-    // verification
-    pub c: BigUint,
-    pub s: BigUint,
-    pub session_id: String,
-}
-
-#[derive(Default)]
+    /// Descriptor of the group the proof was checked in
+    pub group: String,
+    pub authenticated_at: SystemTime,
+}
+
+pub struct Session {
+    pub claims: SessionClaims,
+    pub expires_at: SystemTime,
+    /// Added by each refresh
+    ttl: Duration,
+}
+
+impl Session {
+    pub fn new(claims: SessionClaims) -> Self {
+        Session::with_ttl(claims, SESSION_TTL)
+    }
+
+    pub fn with_ttl(claims: SessionClaims, ttl: Duration) -> Self {
+        Session {
+            expires_at: claims.authenticated_at + ttl,
+            claims,
+            ttl,
+        }
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Extends the session by another TTL, never past its maximum lifetime
+    pub fn refresh(&mut self, now: SystemTime) -> SystemTime {
+        let max_expiry = self.claims.authenticated_at + SESSION_MAX_LIFETIME;
+        self.expires_at = (now + self.ttl).min(max_expiry);
+        self.expires_at
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Checks s against the pending challenge of `user_info` with the verifier
+/// half of `protocol`, a success yields the claims of a session in `group` the user
+/// is now entitled to. The proof is checked against the y1 and y2 stored at
+/// registration and nothing else, a proof that holds for some other key is
+/// rejected like any wrong answer.
+fn verify_answer<P>(
+    protocol: &P,
+    group: &str,
+    user_info: &UserAuthInfo,
+    commitment: Commitment,
+    response: ProofResponse,
+    now: SystemTime,
+) -> Result<SessionClaims, VerifyError>
+where
+    P: SigmaVerifier<
+        Statement = (BigUint, BigUint),
+        Commitment = Commitment,
+        Challenge = BigUint,
+        Response = ProofResponse,
+        Error = VerifyError,
+    >,
+{
+    let statement = (user_info.y1.clone(), user_info.y2.clone());
+    protocol.verify(&statement, &commitment, &user_info.c, &response)?;
+    Ok(SessionClaims {
+        user_name: user_info.user_name.clone(),
+        group: group.to_string(),
+        authenticated_at: now,
+    })
+}
+
+/// A record with no y1 or y2 has nothing a proof could be checked against
+fn has_registered_key(user_info: &UserAuthInfo) -> bool {
+    !user_info.y1.is_zero() && !user_info.y2.is_zero()
+}
+
+fn key_not_registered_status(user_name: &str) -> Status {
+    error_status(
+        Code::FailedPrecondition,
+        ErrorReason::KeyNotRegistered,
+        format!("User {:?} has no registered key, register first", user_name),
+    )
+}
+
+fn retired_group_status(user_name: &str) -> Status {
+    error_status(
+        Code::FailedPrecondition,
+        ErrorReason::RetiredGroup,
+        format!(
+            "User {:?} is registered under a group no longer accepted, register again",
+            user_name
+        ),
+    )
+}
+
+/// Connection a `LoginStream` was opened over, plain or TLS
+#[derive(Clone, Default)]
+struct StreamPeer {
+    tcp: Option<TcpConnectInfo>,
+    tls: Option<TlsConnectInfo<TcpConnectInfo>>,
+}
+
+impl StreamPeer {
+    fn of<T>(request: &Request<T>) -> Self {
+        StreamPeer {
+            tcp: request.extensions().get().cloned(),
+            tls: request.extensions().get().cloned(),
+        }
+    }
+}
+
+/// A step of a `LoginStream`, with the metadata and peer of the stream so it
+/// is checked like the unary call, client certificate included
+fn stream_step<T>(metadata: &MetadataMap, peer: &StreamPeer, message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    *request.metadata_mut() = metadata.clone();
+    if let Some(tcp) = &peer.tcp {
+        request.extensions_mut().insert(tcp.clone());
+    }
+    if let Some(tls) = &peer.tls {
+        request.extensions_mut().insert(tls.clone());
+    }
+    request
+}
+
+fn invalid_group_status(err: ParamError) -> Status {
+    error_status(
+        Code::InvalidArgument,
+        ErrorReason::InvalidGroup,
+        err.to_string(),
+    )
+}
+
+fn session_not_found(session_id: &str) -> Status {
+    error_status(
+        Code::NotFound,
+        ErrorReason::SessionNotFound,
+        format!("Session: {} not found", session_id),
+    )
+}
+
+fn store_status(err: StoreError) -> Status {
+    match err {
+        StoreError::Unavailable(_) => error_status(
+            Code::Unavailable,
+            ErrorReason::StoreUnavailable,
+            err.to_string(),
+        ),
+        StoreError::Internal(_) => {
+            error_status(Code::Internal, ErrorReason::StoreError, err.to_string())
+        }
+    }
+}
+
+/// Server settings that don't depend on where state lives or where time comes from
+struct AuthConfig {
+    /// Where the gRPC service listens
+    pub address: String,
+    /// Group new users register under
+    pub group: ZKP,
+    /// Group being migrated away from, see `AuthImpl::retiring_verifier`
+    pub retiring_group: Option<ZKP>,
+    pub username_policy: UsernamePolicy,
+    /// Largest request the service decodes, tonic's 4 MiB when unset
+    pub max_decoding_message_size: Option<usize>,
+    /// Largest response the service encodes, unbounded when unset
+    pub max_encoding_message_size: Option<usize>,
+    /// Token admin RPCs must send as `x-admin-token`, they are disabled when unset
+    pub admin_token: Option<String>,
+    /// Proofs checked at once on tokio's blocking threads, one per CPU when unset
+    pub verify_workers: Option<usize>,
+    /// Certificate and key the gRPC service terminates TLS with, plain text when unset
+    pub tls: Option<TlsFiles>,
+    /// Where the verified client certificate subject comes from. When set,
+    /// keys are bound to the subject they are registered over.
+    pub client_certs: Option<ClientCerts>,
+    /// How long the prover has to answer a challenge
+    pub challenge_ttl: Duration,
+    /// How long a session lives after login or its last refresh, up to
+    /// `SESSION_MAX_LIFETIME`
+    pub session_ttl: Duration,
+    /// Deadline for each gRPC request, unbounded when unset
+    pub request_timeout: Option<Duration>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            address: DEFAULT_ADDRESS.to_string(),
+            group: ZKP::default(),
+            retiring_group: None,
+            username_policy: UsernamePolicy::default(),
+            max_decoding_message_size: None,
+            max_encoding_message_size: None,
+            admin_token: None,
+            verify_workers: None,
+            tls: None,
+            client_certs: None,
+            challenge_ttl: CHALLENGE_TTL,
+            session_ttl: SESSION_TTL,
+            request_timeout: None,
+        }
+    }
+}
+
+/// An environment variable `AuthConfig::from_env` couldn't use
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ConfigError {
+    pub variable: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}: {}", self.variable, self.reason)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// PEM files the gRPC service terminates TLS with
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// CA that signs client certificates. When set every client must present
+    /// one, and its subject is what keys get bound to.
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsFiles {
+    fn load(&self) -> io::Result<ServerTlsConfig> {
+        let identity = Identity::from_pem(fs::read(&self.cert)?, fs::read(&self.key)?);
+        let mut config = ServerTlsConfig::new().identity(identity);
+        if let Some(client_ca) = &self.client_ca {
+            config = config.client_ca_root(Certificate::from_pem(fs::read(client_ca)?));
+        }
+        Ok(config)
+    }
+}
+
+/// Where `AuthImpl::cert_subject` takes the client certificate subject from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClientCerts {
+    /// The certificate the client presented in the TLS handshake with this server
+    Tls,
+    /// Metadata in which a TLS terminating proxy passes the subject. It is
+    /// only read on connections from `proxies`, any other client could put
+    /// whatever subject it likes in it.
+    Proxy {
+        header: String,
+        proxies: Vec<IpAddr>,
+    },
+}
+
+impl AuthConfig {
+    /// Reads every setting from the environment, unset variables keep their default:
+    ///
+    /// - `SERVER_ADDRESS`: gRPC listen address, `127.0.0.1:50051`
+    /// - `SERVER_GROUP`: group descriptor new users register under, the 1024-bit group
+    /// - `SERVER_RETIRING_GROUP`: group descriptor being migrated away from, none
+    /// - `SERVER_ALLOWED_USERS`, `SERVER_DENIED_USERS`: comma separated name patterns, none
+    /// - `SERVER_MAX_DECODING_MESSAGE_SIZE`, `SERVER_MAX_ENCODING_MESSAGE_SIZE`: bytes,
+    ///   tonic's defaults
+    /// - `SERVER_ADMIN_TOKEN`: admin RPCs are disabled when unset
+    /// - `SERVER_VERIFY_WORKERS`: proofs checked at once, one per CPU
+    /// - `SERVER_TLS_CERT`, `SERVER_TLS_KEY`: PEM certificate and key to serve gRPC over
+    ///   TLS with, plain text
+    /// - `SERVER_TLS_CLIENT_CA`: PEM CA client certificates must be signed by, none
+    /// - `SERVER_CLIENT_CERT_HEADER`: metadata with the client certificate subject, none,
+    ///   only with `SERVER_TRUSTED_PROXIES`, the comma separated addresses of the proxies
+    /// - `SERVER_CHALLENGE_TTL_SECS`: 60
+    /// - `SERVER_SESSION_TTL_SECS`: 900, at most 8 hours
+    /// - `SERVER_REQUEST_TIMEOUT_MS`: unbounded
+    pub fn from_env() -> Result<AuthConfig, ConfigError> {
+        let var = |variable: &'static str| env::var(variable).ok();
+        let defaults = AuthConfig::default();
+        let tls = parse_tls()?;
+        Ok(AuthConfig {
+            address: var("SERVER_ADDRESS").unwrap_or(defaults.address),
+            group: match var("SERVER_GROUP") {
+                Some(group) => parse_group("SERVER_GROUP", &group)?,
+                None => defaults.group,
+            },
+            retiring_group: var("SERVER_RETIRING_GROUP")
+                .map(|group| parse_group("SERVER_RETIRING_GROUP", &group))
+                .transpose()?,
+            username_policy: UsernamePolicy {
+                allow: policy::parse_patterns(&var("SERVER_ALLOWED_USERS").unwrap_or_default()),
+                deny: policy::parse_patterns(&var("SERVER_DENIED_USERS").unwrap_or_default()),
+            },
+            max_decoding_message_size: parse_positive("SERVER_MAX_DECODING_MESSAGE_SIZE")?,
+            max_encoding_message_size: parse_positive("SERVER_MAX_ENCODING_MESSAGE_SIZE")?,
+            admin_token: var("SERVER_ADMIN_TOKEN"),
+            verify_workers: parse_positive("SERVER_VERIFY_WORKERS")?,
+            tls: tls.clone(),
+            client_certs: parse_client_certs(tls.as_ref())?,
+            challenge_ttl: parse_positive("SERVER_CHALLENGE_TTL_SECS")?
+                .map_or(defaults.challenge_ttl, Duration::from_secs),
+            session_ttl: match parse_positive("SERVER_SESSION_TTL_SECS")? {
+                Some(secs) if Duration::from_secs(secs) > SESSION_MAX_LIFETIME => {
+                    return Err(ConfigError {
+                        variable: "SERVER_SESSION_TTL_SECS",
+                        reason: format!("{} seconds is longer than a session may live", secs),
+                    })
+                }
+                Some(secs) => Duration::from_secs(secs),
+                None => defaults.session_ttl,
+            },
+            request_timeout: parse_positive("SERVER_REQUEST_TIMEOUT_MS")?
+                .map(Duration::from_millis),
+        })
+    }
+}
+
+fn parse_group(variable: &'static str, descriptor: &str) -> Result<ZKP, ConfigError> {
+    descriptor.parse().map_err(|err: ParamError| ConfigError {
+        variable,
+        reason: err.to_string(),
+    })
+}
+
+/// `SERVER_TLS_CERT` and `SERVER_TLS_KEY`, with `SERVER_TLS_CLIENT_CA`
+fn parse_tls() -> Result<Option<TlsFiles>, ConfigError> {
+    let var = |variable: &'static str| env::var_os(variable).map(PathBuf::from);
+    let client_ca = var("SERVER_TLS_CLIENT_CA");
+    match (var("SERVER_TLS_CERT"), var("SERVER_TLS_KEY")) {
+        (Some(cert), Some(key)) => Ok(Some(TlsFiles {
+            cert,
+            key,
+            client_ca,
+        })),
+        (None, None) if client_ca.is_none() => Ok(None),
+        (cert, _) => Err(ConfigError {
+            variable: match cert {
+                Some(_) => "SERVER_TLS_KEY",
+                None => "SERVER_TLS_CERT",
+            },
+            reason: "SERVER_TLS_CERT, SERVER_TLS_KEY and SERVER_TLS_CLIENT_CA need a \
+                certificate and its key"
+                .to_string(),
+        }),
+    }
+}
+
+/// The subject from TLS with a client CA, or from `SERVER_CLIENT_CERT_HEADER`
+/// sent by one of `SERVER_TRUSTED_PROXIES`
+fn parse_client_certs(tls: Option<&TlsFiles>) -> Result<Option<ClientCerts>, ConfigError> {
+    let over_tls = tls.is_some_and(|tls| tls.client_ca.is_some());
+    let Ok(header) = env::var("SERVER_CLIENT_CERT_HEADER") else {
+        return Ok(over_tls.then_some(ClientCerts::Tls));
+    };
+    if over_tls {
+        return Err(ConfigError {
+            variable: "SERVER_CLIENT_CERT_HEADER",
+            reason: "client certificates are already checked with SERVER_TLS_CLIENT_CA".to_string(),
+        });
+    }
+    let proxies = env::var("SERVER_TRUSTED_PROXIES").unwrap_or_default();
+    let proxies = proxies
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| {
+            proxy
+                .parse::<IpAddr>()
+                .map(|proxy| proxy.to_canonical())
+                .map_err(|err| ConfigError {
+                    variable: "SERVER_TRUSTED_PROXIES",
+                    reason: format!("{:?}: {}", proxy, err),
+                })
+        })
+        .collect::<Result<Vec<IpAddr>, _>>()?;
+    if proxies.is_empty() {
+        return Err(ConfigError {
+            variable: "SERVER_TRUSTED_PROXIES",
+            reason: "SERVER_CLIENT_CERT_HEADER is only read from a proxy, name at least one"
+                .to_string(),
+        });
+    }
+    Ok(Some(ClientCerts::Proxy {
+        header: header.to_ascii_lowercase(),
+        proxies,
+    }))
+}
+
+/// The variable as a number above zero, None when unset
+fn parse_positive<T>(variable: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T: std::str::FromStr + Default + PartialEq,
+    T::Err: fmt::Display,
+{
+    let Ok(value) = env::var(variable) else {
+        return Ok(None);
+    };
+    match value.parse::<T>() {
+        Ok(n) if n == T::default() => Err(ConfigError {
+            variable,
+            reason: "must be above zero".to_string(),
+        }),
+        Ok(n) => Ok(Some(n)),
+        Err(err) => Err(ConfigError {
+            variable,
+            reason: format!("{:?}: {}", value, err),
+        }),
+    }
+}
+
 struct AuthImpl {
-    pub user_info: Mutex<HashMap<String, UserAuthInfo>>,
-    pub auth_user: Mutex<HashMap<String, String>>,
+    pub store: Box<dyn UserStore>,
+    pub sessions: Mutex<HashMap<SessionId, Session>>,
+    /// Recent commitments of each user by H(r1 || r2), see `CommitmentWindow`.
+    /// A commitment sent in the clear and the same one sent as its hash are
+    /// one entry.
+    pub seen_commitments: Mutex<CommitmentWindow<[u8; 32]>>,
+    /// Answers taken up while their challenge could still be live, see `SeenAnswers`
+    pub seen_answers: Mutex<SeenAnswers>,
+    /// Verifier of the current group, swapped by the `RotateGroup` RPC
+    pub verifier: RotatingVerifier,
+    /// Group being migrated away from, its users can still log in but not register
+    pub retiring_verifier: Option<Arc<Verifier>>,
+    pub username_policy: UsernamePolicy,
+    pub clock: Box<dyn Clock>,
+    /// Source of auth ids, session ids and key handles
+    pub ids: Box<dyn IdGenerator>,
+    /// Receives an `AuthFailure` for every rejected answer
+    pub audit: Box<dyn AuditSink>,
+    /// Applied by `AuthImpl::service`, see `AuthConfig`
+    pub max_decoding_message_size: Option<usize>,
+    pub max_encoding_message_size: Option<usize>,
+    pub admin_token: Option<String>,
+    /// Bounds the proofs being checked at once, see `AuthImpl::offload`
+    pub verify_permits: Semaphore,
+    pub client_certs: Option<ClientCerts>,
+    pub challenge_ttl: Duration,
+    pub session_ttl: Duration,
+    pub request_timeout: Option<Duration>,
+    /// Set by `AuthImpl::service`, so the steps of a `LoginStream` can run
+    /// after the call that opened it has returned
+    pub shared: OnceLock<Weak<AuthImpl>>,
+}
+
+/// In-memory store, default config, wall clock and random challenges
+impl Default for AuthImpl {
+    fn default() -> Self {
+        AuthImpl::with_challenge_source(RandomChallenges)
+    }
+}
+
+impl AuthImpl {
+    /// `challenge_source` serves the current group, the retiring one always
+    /// draws random challenges
+    pub fn new(
+        store: Box<dyn UserStore>,
+        config: AuthConfig,
+        clock: Box<dyn Clock>,
+        challenge_source: impl ChallengeSource + 'static,
+    ) -> Self {
+        AuthImpl::with_sources(store, config, clock, challenge_source, RandomChallenges)
+    }
+
+    /// Draws every challenge, id and key handle from `entropy`, e.g. a hardware
+    /// RNG, the retiring group's challenges included
+    pub fn from_entropy(
+        store: Box<dyn UserStore>,
+        config: AuthConfig,
+        clock: Box<dyn Clock>,
+        entropy: Entropy,
+    ) -> Self {
+        let mut auth_impl =
+            AuthImpl::with_sources(store, config, clock, entropy.clone(), entropy.clone());
+        auth_impl.ids = Box::new(entropy);
+        auth_impl
+    }
+
+    fn with_sources(
+        store: Box<dyn UserStore>,
+        config: AuthConfig,
+        clock: Box<dyn Clock>,
+        challenge_source: impl ChallengeSource + 'static,
+        retiring_source: impl ChallengeSource + 'static,
+    ) -> Self {
+        AuthImpl {
+            store,
+            sessions: Default::default(),
+            seen_commitments: Mutex::new(CommitmentWindow::new(COMMITMENT_WINDOW)),
+            seen_answers: Mutex::new(SeenAnswers::new(config.challenge_ttl)),
+            // a retried answer reuses its alpha^s and beta^s
+            verifier: RotatingVerifier::new(
+                Verifier::with_challenge_source(config.group, challenge_source)
+                    .with_power_cache(DEFAULT_POWER_CACHE_SIZE),
+            ),
+            retiring_verifier: config
+                .retiring_group
+                .map(|group| Arc::new(Verifier::with_challenge_source(group, retiring_source))),
+            username_policy: config.username_policy,
+            clock,
+            ids: Box::new(RandomIds),
+            audit: Box::new(NoAudit),
+            max_decoding_message_size: config.max_decoding_message_size,
+            max_encoding_message_size: config.max_encoding_message_size,
+            admin_token: config.admin_token,
+            verify_permits: Semaphore::new(
+                config.verify_workers.unwrap_or_else(|| {
+                    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+                }),
+            ),
+            client_certs: config.client_certs,
+            challenge_ttl: config.challenge_ttl,
+            session_ttl: config.session_ttl,
+            request_timeout: config.request_timeout,
+            shared: OnceLock::new(),
+        }
+    }
+
+    /// gRPC service over `auth_impl` with its message size limits applied
+    pub fn service(auth_impl: Arc<AuthImpl>) -> AuthServer<AuthImpl> {
+        let _ = auth_impl.shared.set(Arc::downgrade(&auth_impl));
+        let mut service = AuthServer::from_arc(auth_impl.clone());
+        if let Some(limit) = auth_impl.max_decoding_message_size {
+            service = service.max_decoding_message_size(limit);
+        }
+        if let Some(limit) = auth_impl.max_encoding_message_size {
+            service = service.max_encoding_message_size(limit);
+        }
+        service
+    }
+
+    pub fn with_audit_sink(mut self, audit: impl AuditSink + 'static) -> Self {
+        self.audit = Box::new(audit);
+        self
+    }
+
+    pub fn with_challenge_source(challenge_source: impl ChallengeSource + 'static) -> Self {
+        AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig::default(),
+            Box::new(SystemClock),
+            challenge_source,
+        )
+    }
+
+    /// User whose current registration was issued `key_handle`
+    fn user_for_key_handle(&self, key_handle: &str) -> Result<Option<String>, StoreError> {
+        let Some(user_name) = self.store.get_key_handle(key_handle)? else {
+            return Ok(None);
+        };
+        // registering again issues a new handle and retires the old one
+        Ok(self
+            .store
+            .get_user(&user_name)?
+            .filter(|user_info| user_info.key_handle == key_handle)
+            .map(|_| user_name))
+    }
+
+    /// Status refusing an admin RPC, None if the request carries the admin token
+    fn admin_denied<T>(&self, request: &Request<T>) -> Option<Status> {
+        let Some(admin_token) = &self.admin_token else {
+            return Some(error_status(
+                Code::PermissionDenied,
+                ErrorReason::AdminRequired,
+                "Admin RPCs are disabled, set SERVER_ADMIN_TOKEN to enable them",
+            ));
+        };
+        let sent = request
+            .metadata()
+            .get(ADMIN_TOKEN_HEADER)
+            .map(|token| token.as_bytes())
+            .unwrap_or_default();
+        if bool::from(sent.ct_eq(admin_token.as_bytes())) {
+            None
+        } else {
+            Some(error_status(
+                Code::Unauthenticated,
+                ErrorReason::AdminRequired,
+                format!("Missing or wrong {}", ADMIN_TOKEN_HEADER),
+            ))
+        }
+    }
+
+    /// Subject of the client certificate the request came over, if `client_certs` is set
+    pub fn cert_subject<T>(&self, request: &Request<T>) -> Option<String> {
+        match self.client_certs.as_ref()? {
+            ClientCerts::Tls => {
+                let certs = request.peer_certs()?;
+                // tonic hands over the DER of each certificate, the client's own first
+                let (_, cert) = X509Certificate::from_der(certs.first()?.get_ref()).ok()?;
+                Some(cert.subject().to_string())
+            }
+            ClientCerts::Proxy { header, proxies } => {
+                let peer = request.remote_addr()?.ip().to_canonical();
+                if !proxies.contains(&peer) {
+                    return None;
+                }
+                let subject = request.metadata().get(header)?.to_str().ok()?;
+                Some(subject.to_string())
+            }
+        }
+    }
+
+    /// Runs `work` on tokio's blocking threads, at most `verify_permits` at a
+    /// time. Modpows on large groups would otherwise hold up an async worker
+    /// and every request queued on it.
+    async fn offload<T: Send + 'static>(&self, work: impl FnOnce() -> T + Send + 'static) -> T {
+        let _permit = self
+            .verify_permits
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+        tokio::task::spawn_blocking(work)
+            .await
+            .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+
+    /// Descriptor of the group a request names, the current group when it names none
+    fn requested_group(&self, group: &str) -> Result<String, ParamError> {
+        if group.is_empty() {
+            return Ok(self.verifier.load().group().to_string());
+        }
+        group.parse::<ZKP>().map(|zkp| zkp.descriptor())
+    }
+
+    fn verifier_for(&self, group: &str) -> Option<Arc<Verifier>> {
+        std::iter::once(self.verifier.load())
+            .chain(self.retiring_verifier.clone())
+            .find(|verifier| verifier.group() == group)
+    }
+
+    /// A stateless proof was sent for a group with no verifier
+    fn unchecked_group_status(&self) -> Status {
+        error_status(
+            Code::FailedPrecondition,
+            ErrorReason::WrongGroup,
+            format!(
+                "Proofs are only checked under {}",
+                self.verifier.load().group()
+            ),
+        )
+    }
+
+    /// What `GetParams` announces: the current group then the retiring one, and
+    /// elements as big-endian bytes, which covers the padded encoding
+    pub fn capabilities(&self) -> Capabilities {
+        let groups = std::iter::once(self.verifier.load())
+            .chain(self.retiring_verifier.clone())
+            .map(|verifier| verifier.group().to_string())
+            .collect();
+        Capabilities {
+            encodings: vec![ElementEncoding::Padded.name().to_string()],
+            ..Capabilities::new(groups)
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -40,106 +744,3674 @@ impl Auth for AuthImpl {
         &self,
         request: Request<RegisterRequest>,
     ) -> Result<Response<RegisterResponse>, Status> {
-        println!("[register] ...");
+        let span = step_span("Register", request.metadata());
+        traced(span, async move {
+            println!("[register] ...");
 
-        let RegisterRequest { user, y1, y2 } = request.into_inner();
-        let y1 = BigUint::from_bytes_be(&y1);
-        let y2 = BigUint::from_bytes_be(&y2);
+            let cert_subject = self.cert_subject(&request);
+            if self.client_certs.is_some() && cert_subject.is_none() {
+                return Err(error_status(
+                    Code::Unauthenticated,
+                    ErrorReason::ClientCertMismatch,
+                    "Registering requires a client certificate",
+                ));
+            }
+            let RegisterRequest {
+                user,
+                y1,
+                y2,
+                group,
+                pop_c,
+                pop_s,
+            } = request.into_inner();
+            if !self.username_policy.is_allowed(&user) {
+                return Err(error_status(
+                    Code::PermissionDenied,
+                    ErrorReason::UsernameNotAllowed,
+                    format!("User {:?} is not allowed to register", user),
+                ));
+            }
+            let group = self.requested_group(&group).map_err(invalid_group_status)?;
+            let verifier = self.verifier.load();
+            if group != verifier.group() {
+                return Err(error_status(
+                    Code::FailedPrecondition,
+                    ErrorReason::WrongGroup,
+                    format!("New users must register under {}", verifier.group()),
+                ));
+            }
+            let y1 = BigUint::from_bytes_be(&y1);
+            let y2 = BigUint::from_bytes_be(&y2);
+            let possession = CompactProof {
+                c: BigUint::from_bytes_be(&pop_c),
+                s: BigUint::from_bytes_be(&pop_s),
+            };
+            let proven = {
+                let (y1, y2, user) = (y1.clone(), y2.clone(), user.clone());
+                self.offload(move || verifier.verify_possession(&y1, &y2, &possession, &user))
+                    .await
+            };
+            if !proven {
+                return Err(error_status(
+                    Code::PermissionDenied,
+                    ErrorReason::InvalidPossessionProof,
+                    format!("User {:?} did not prove knowledge of the key", user),
+                ));
+            }
 
-        let user_auth_info = UserAuthInfo {
-            y1,
-            y2,
-            user_name: user.clone(),
-            ..Default::default()
-        };
+            let key_handle = self.ids.generate(KEY_HANDLE_LEN);
+            let user_auth_info = UserAuthInfo {
+                y1,
+                y2,
+                user_name: user.clone(),
+                group,
+                key_handle: key_handle.clone(),
+                cert_subject: cert_subject.unwrap_or_default(),
+                ..Default::default()
+            };
 
-        let user_info = &mut self.user_info.lock().unwrap();
-        user_info.insert(user, user_auth_info);
+            self.store
+                .put_registration(user_auth_info)
+                .map_err(store_status)?;
 
-        Ok(Response::new(RegisterResponse {}))
+            Ok(Response::new(RegisterResponse { key_handle }))
+        })
+        .await
     }
 
     async fn create_auth_challenge(
         &self,
         request: Request<AuthChallengeRequest>,
     ) -> Result<Response<AuthChallengeResponse>, Status> {
-        println!("[create_auth_challenge] ...");
-
-        let AuthChallengeRequest { user, r1, r2 } = request.into_inner();
-
-        let user_info = &mut self.user_info.lock().unwrap();
-        if let Some(user_info) = user_info.get_mut(&user) {
-            let zkp = ZKP::new();
-            let c = ZKP::generate_random_below(&zkp.q);
-            user_info.c.clone_from(&c);
-            user_info.r1 = BigUint::from_bytes_be(&r1);
-            user_info.r2 = BigUint::from_bytes_be(&r2);
-
-            let auth_user = &mut self.auth_user.lock().unwrap();
-            let auth_id = ZKP::generate_random_string(12);
-            auth_user.insert(auth_id.clone(), user);
-            Ok(Response::new(AuthChallengeResponse {
-                auth_id,
-                c: c.to_bytes_be(),
-            }))
-        } else {
-            Err(Status::new(
-                Code::NotFound,
-                format!("User {:?} not found", user),
-            ))
-        }
+        let span = step_span("CreateAuthChallenge", request.metadata());
+        traced(span, async move {
+            println!("[create_auth_challenge] ...");
+
+            let AuthChallengeRequest {
+                user,
+                r1,
+                r2,
+                context,
+                key_handle,
+                commitment_hash,
+            } = request.into_inner();
+            if !commitment_hash.is_empty() && commitment_hash.len() != 32 {
+                return Err(error_status(
+                    Code::InvalidArgument,
+                    ErrorReason::MalformedProof,
+                    format!(
+                        "commitment_hash has {} bytes, a SHA-256 hash has 32",
+                        commitment_hash.len()
+                    ),
+                ));
+            }
+            let user = if user.is_empty() && !key_handle.is_empty() {
+                self.user_for_key_handle(&key_handle)
+                    .map_err(store_status)?
+                    .ok_or_else(|| {
+                        error_status(
+                            Code::NotFound,
+                            ErrorReason::KeyHandleNotFound,
+                            format!("KeyHandle: {} not found", key_handle),
+                        )
+                    })?
+            } else {
+                user
+            };
+
+            if let Some(mut user_info) = self.store.get_user(&user).map_err(store_status)? {
+                if !has_registered_key(&user_info) {
+                    return Err(key_not_registered_status(&user));
+                }
+                let verifier = self
+                    .verifier_for(&user_info.group)
+                    .ok_or_else(|| retired_group_status(&user))?;
+                // equal (r1, r2) hash alike, so a hidden commitment is checked too
+                let hash = match <[u8; 32]>::try_from(commitment_hash.as_slice()) {
+                    Ok(hash) => hash,
+                    Err(_) => {
+                        hash_elements(&BigUint::from_bytes_be(&r1), &BigUint::from_bytes_be(&r2))
+                    }
+                };
+                if !self.seen_commitments.lock().unwrap().insert(&user, hash) {
+                    return Err(error_status(
+                        Code::InvalidArgument,
+                        ErrorReason::CommitmentReused,
+                        format!(
+                            "User {:?} already sent this commitment, commit to a fresh nonce",
+                            user
+                        ),
+                    ));
+                }
+                let c = SigmaVerifier::challenge(verifier.as_ref());
+                let expires_at = self.clock.now() + self.challenge_ttl;
+                let auth_id = AuthId::generate_with(self.ids.as_ref());
+                user_info.c.clone_from(&c);
+                user_info.auth_id = Some(auth_id);
+                user_info.r1 = BigUint::from_bytes_be(&r1);
+                user_info.r2 = BigUint::from_bytes_be(&r2);
+                user_info.commitment_hash = commitment_hash;
+                user_info.challenge_expires_at = Some(expires_at);
+                user_info.context = context;
+                self.store
+                    .put_challenge(user_info, auth_id)
+                    .map_err(store_status)?;
+                Ok(Response::new(AuthChallengeResponse {
+                    auth_id: auth_id.into(),
+                    c: ZKP::to_padded_bytes(&c, verifier.q()),
+                    expires_at: unix_seconds(expires_at),
+                }))
+            } else {
+                Err(error_status(
+                    Code::NotFound,
+                    ErrorReason::UserNotFound,
+                    format!("User {:?} not found", user),
+                ))
+            }
+        })
+        .await
     }
 
     async fn verify_auth(
         &self,
         request: Request<AuthAnswerRequest>,
     ) -> std::result::Result<Response<AuthAnswerRensponse>, Status> {
-        println!("[verify_auth]");
-        let AuthAnswerRequest { auth_id, s } = request.into_inner();
-
-        let auth_user = &mut self.auth_user.lock().unwrap();
-        if let Some(user_name) = auth_user.get(&auth_id) {
-            let user_info = &mut self.user_info.lock().unwrap();
-            let user_info = user_info.get_mut(user_name).expect("auth_id not found");
-
-            let zkp = ZKP::new();
-            let verification = zkp.verify(
-                &user_info.r1,
-                &user_info.r2,
-                &user_info.y1,
-                &user_info.y2,
-                &user_info.c,
-                &BigUint::from_bytes_be(&s),
-            );
+        let span = step_span("VerifyAuth", request.metadata());
+        traced(span, async move {
+            println!("[verify_auth]");
+            let peer = request.remote_addr().map(|addr| addr.ip());
+            let cert_subject = self.cert_subject(&request);
+            let answer = request.into_inner();
+            let auth_id = answer.auth_id.clone();
 
-            if verification {
-                let session_id = ZKP::generate_random_string(12);
-                Ok(Response::new(AuthAnswerRensponse { session_id }))
-            } else {
-                Err(Status::new(
-                    Code::PermissionDenied,
-                    format!("AuthId: {} send an invalid challenge answer", auth_id),
+            // every early return inside lands here, so each rejection is audited once
+            let result = async {
+                let AuthAnswerRequest {
+                    auth_id,
+                    s,
+                    context,
+                    r1,
+                    r2,
+                } = answer;
+
+                // a malformed id was never issued, so it's reported like an unknown one
+                let parsed_id = auth_id.parse::<AuthId>().ok();
+
+                // taken up before any lookup, so of two copies sent at once only one
+                // goes on to be checked
+                if let Some(id) = parsed_id {
+                    let seen = &mut self.seen_answers.lock().unwrap();
+                    if !seen.insert(id, &BigUint::from_bytes_be(&s), self.clock.now()) {
+                        return Err(error_status(
+                            Code::AlreadyExists,
+                            ErrorReason::AnswerReplayed,
+                            format!("AuthId: {} already sent this answer", auth_id),
+                        ));
+                    }
+                }
+
+                let user_name = match parsed_id {
+                    Some(id) => self.store.get_auth_id(&id).map_err(store_status)?,
+                    None => None,
+                };
+                if let Some(user_name) = user_name {
+                    let user_info = self
+                        .store
+                        .get_user(&user_name)
+                        .map_err(store_status)?
+                        .ok_or_else(|| {
+                            error_status(
+                                Code::Internal,
+                                ErrorReason::StoreError,
+                                format!("AuthId: {} points to a missing user", auth_id),
+                            )
+                        })?;
+                    if !has_registered_key(&user_info) {
+                        return Err(key_not_registered_status(&user_name));
+                    }
+
+                    // c is never taken from the client, and the stored one is only
+                    // used if it was issued under this auth_id. A newer challenge for
+                    // the same user replaces it, the older auth_id is then stale.
+                    if user_info.auth_id != parsed_id {
+                        return Err(error_status(
+                            Code::FailedPrecondition,
+                            ErrorReason::ChallengeMismatch,
+                            format!(
+                                "AuthId: {} answered a challenge replaced by a newer one",
+                                auth_id
+                            ),
+                        ));
+                    }
+
+                    let now = self.clock.now();
+                    if user_info
+                        .challenge_expires_at
+                        .is_some_and(|expires_at| now >= expires_at)
+                    {
+                        return Err(error_status(
+                            Code::DeadlineExceeded,
+                            ErrorReason::ChallengeExpired,
+                            format!("AuthId: {} answered an expired challenge", auth_id),
+                        ));
+                    }
+
+                    if context != user_info.context {
+                        return Err(error_status(
+                            Code::PermissionDenied,
+                            ErrorReason::ContextMismatch,
+                            format!(
+                                "AuthId: {} answered under context {:?}, the challenge was for {:?}",
+                                auth_id, context, user_info.context
+                            ),
+                        ));
+                    }
+
+                    // keys registered before binding was turned on stay unbound
+                    if !user_info.cert_subject.is_empty()
+                        && cert_subject.as_deref() != Some(user_info.cert_subject.as_str())
+                    {
+                        return Err(error_status(
+                            Code::PermissionDenied,
+                            ErrorReason::ClientCertMismatch,
+                            format!(
+                                "AuthId: {} answered over a client certificate other than {:?}'s",
+                                auth_id, user_info.user_name
+                            ),
+                        ));
+                    }
+
+                    let verifier = self
+                        .verifier_for(&user_info.group)
+                        .ok_or_else(|| retired_group_status(&user_name))?;
+                    // an unreduced s would still fail the algebra, but only as an opaque mismatch
+                    let s = BigUint::from_bytes_be(&s);
+                    if s >= *verifier.q() {
+                        return Err(error_status(
+                            Code::InvalidArgument,
+                            ErrorReason::MalformedProof,
+                            format!(
+                                "AuthId: {} sent an s that is not below q, it must be reduced mod q",
+                                auth_id
+                            ),
+                        ));
+                    }
+                    // with a commitment hash the server only learns r1 and r2 now
+                    let (r1, r2) = if user_info.commitment_hash.is_empty() {
+                        (user_info.r1.clone(), user_info.r2.clone())
+                    } else {
+                        let (r1, r2) = (BigUint::from_bytes_be(&r1), BigUint::from_bytes_be(&r2));
+                        if hash_elements(&r1, &r2).as_slice() != user_info.commitment_hash {
+                            return Err(error_status(
+                                Code::PermissionDenied,
+                                ErrorReason::CommitmentMismatch,
+                                format!(
+                                    "AuthId: {} revealed an r1, r2 that don't open the commitment hash",
+                                    auth_id
+                                ),
+                            ));
+                        }
+                        (r1, r2)
+                    };
+                    let verification = self
+                        .offload(move || {
+                            verify_answer(
+                                verifier.as_ref(),
+                                verifier.group(),
+                                &user_info,
+                                Commitment { r1, r2 },
+                                ProofResponse { s },
+                                now,
+                            )
+                        })
+                        .await;
+                    match verification {
+                        Ok(claims) => {
+                            let session_id = SessionId::generate_with(self.ids.as_ref());
+                            let session = Session::with_ttl(claims, self.session_ttl);
+                            let expires_at = unix_seconds(session.expires_at);
+
+                            let sessions = &mut self.sessions.lock().unwrap();
+                            sessions.insert(session_id, session);
+                            Ok(Response::new(AuthAnswerRensponse {
+                                session_id: session_id.into(),
+                                expires_at,
+                            }))
+                        }
+                        Err(err @ (VerifyError::OutOfRange(_) | VerifyError::NotInSubgroup(_))) => {
+                            Err(error_status(
+                                Code::InvalidArgument,
+                                ErrorReason::MalformedProof,
+                                format!("AuthId: {} sent a malformed proof: {}", auth_id, err),
+                            ))
+                        }
+                        Err(err) => Err(error_status(
+                            Code::PermissionDenied,
+                            ErrorReason::InvalidProof,
+                            format!(
+                                "AuthId: {} send an invalid challenge answer: {}",
+                                auth_id, err
+                            ),
+                        )),
+                    }
+                } else {
+                    Err(error_status(
+                        Code::NotFound,
+                        ErrorReason::AuthIdNotFound,
+                        format!("AuthId: {} not found", auth_id),
+                    ))
+                }
+            }
+            .await;
+
+            if let Err(status) = &result {
+                let user = auth_id
+                    .parse()
+                    .ok()
+                    .and_then(|id| self.store.get_auth_id(&id).ok().flatten())
+                    .unwrap_or_default();
+                self.audit.record(AuditRecord::AuthFailure {
+                    at: self.clock.now(),
+                    peer,
+                    user,
+                    reason: error_info(status)
+                        .map_or_else(|| format!("{:?}", status.code()), |info| info.reason),
+                });
+            }
+            result
+        })
+        .await
+    }
+
+    async fn refresh_session(
+        &self,
+        request: Request<RefreshSessionRequest>,
+    ) -> Result<Response<RefreshSessionResponse>, Status> {
+        println!("[refresh_session] ...");
+        let RefreshSessionRequest { session_id } = request.into_inner();
+        // a malformed id was never issued, so it's reported like an unknown one
+        let Ok(id) = session_id.parse::<SessionId>() else {
+            return Err(session_not_found(&session_id));
+        };
+
+        let sessions = &mut self.sessions.lock().unwrap();
+        let now = self.clock.now();
+        match sessions.get_mut(&id) {
+            Some(session) if session.is_expired(now) => {
+                sessions.remove(&id);
+                Err(error_status(
+                    Code::Unauthenticated,
+                    ErrorReason::SessionExpired,
+                    format!("Session: {} expired", session_id),
                 ))
             }
-        } else {
-            Err(Status::new(
-                Code::NotFound,
-                format!("AuthId: {} not found", auth_id),
-            ))
+            Some(session) => Ok(Response::new(RefreshSessionResponse {
+                expires_at: unix_seconds(session.refresh(now)),
+            })),
+            None => Err(session_not_found(&session_id)),
+        }
+    }
+
+    async fn export_users(
+        &self,
+        request: Request<ExportUsersRequest>,
+    ) -> Result<Response<ExportUsersResponse>, Status> {
+        println!("[export_users] ...");
+        if let Some(status) = self.admin_denied(&request) {
+            return Err(status);
+        }
+
+        let users = self
+            .store
+            .users()
+            .map_err(store_status)?
+            .into_iter()
+            .map(|user_info| UserRecord {
+                user: user_info.user_name,
+                y1: user_info.y1.to_bytes_be(),
+                y2: user_info.y2.to_bytes_be(),
+                group: user_info.group,
+                cert_subject: user_info.cert_subject,
+            })
+            .collect();
+        Ok(Response::new(ExportUsersResponse { users }))
+    }
+
+    async fn import_users(
+        &self,
+        request: Request<ImportUsersRequest>,
+    ) -> Result<Response<ImportUsersResponse>, Status> {
+        println!("[import_users] ...");
+        if let Some(status) = self.admin_denied(&request) {
+            return Err(status);
+        }
+
+        let ImportUsersRequest { users } = request.into_inner();
+        // check every record before writing any, so a bad backup imports nothing
+        if let Some(record) = users
+            .iter()
+            .find(|record| self.verifier_for(&record.group).is_none())
+        {
+            return Err(error_status(
+                Code::FailedPrecondition,
+                ErrorReason::WrongGroup,
+                format!(
+                    "User {:?} is under {}, which this server doesn't accept",
+                    record.user, record.group
+                ),
+            ));
+        }
+
+        let imported = users.len() as u32;
+        for UserRecord {
+            user,
+            y1,
+            y2,
+            group,
+            cert_subject,
+        } in users
+        {
+            let key_handle = self.ids.generate(KEY_HANDLE_LEN);
+            self.store
+                .put_registration(UserAuthInfo {
+                    user_name: user,
+                    group,
+                    key_handle,
+                    y1: BigUint::from_bytes_be(&y1),
+                    y2: BigUint::from_bytes_be(&y2),
+                    cert_subject,
+                    ..Default::default()
+                })
+                .map_err(store_status)?;
+        }
+        Ok(Response::new(ImportUsersResponse { imported }))
+    }
+
+    async fn verify_proof(
+        &self,
+        request: Request<VerifyProofRequest>,
+    ) -> Result<Response<VerifyProofResponse>, Status> {
+        println!("[verify_proof] ...");
+        let VerifyProofRequest {
+            group,
+            y1,
+            y2,
+            r1,
+            r2,
+            c,
+            s,
+        } = request.into_inner();
+        let group = self.requested_group(&group).map_err(invalid_group_status)?;
+        let verifier = self
+            .verifier_for(&group)
+            .ok_or_else(|| self.unchecked_group_status())?;
+        if r1.is_empty() != r2.is_empty() {
+            return Err(error_status(
+                Code::InvalidArgument,
+                ErrorReason::MalformedProof,
+                "r1 and r2 are sent together or not at all",
+            ));
+        }
+
+        let verification = self
+            .offload(move || {
+                if r1.is_empty() {
+                    let [y1, y2, c, s] = [y1, y2, c, s].map(|n| BigUint::from_bytes_be(&n));
+                    let proof = CompactProof { c, s };
+                    match verifier.verify_compact(&y1, &y2, &proof) {
+                        true => Ok(()),
+                        false => Err("the proof does not verify".to_string()),
+                    }
+                } else {
+                    verifier
+                        .decode([&r1, &r2, &y1, &y2, &c, &s])
+                        .and_then(|[r1, r2, y1, y2, c, s]| {
+                            verifier.verify_fiat_shamir(&r1, &r2, &y1, &y2, &c, &s)
+                        })
+                        .map_err(|err| err.to_string())
+                }
+            })
+            .await;
+        Ok(Response::new(match verification {
+            Ok(()) => VerifyProofResponse {
+                valid: true,
+                reason: String::new(),
+            },
+            Err(reason) => VerifyProofResponse {
+                valid: false,
+                reason,
+            },
+        }))
+    }
+
+    async fn verify_multi_proof(
+        &self,
+        request: Request<VerifyMultiProofRequest>,
+    ) -> Result<Response<VerifyProofResponse>, Status> {
+        println!("[verify_multi_proof] ...");
+        let VerifyMultiProofRequest {
+            group,
+            bases,
+            ys,
+            rs,
+            c,
+            s,
+        } = request.into_inner();
+        let group = self.requested_group(&group).map_err(invalid_group_status)?;
+        let verifier = self
+            .verifier_for(&group)
+            .ok_or_else(|| self.unchecked_group_status())?;
+        if bases.is_empty()
+            || bases.len() > MAX_BASES
+            || ys.len() != bases.len()
+            || rs.len() != bases.len()
+        {
+            return Err(error_status(
+                Code::InvalidArgument,
+                ErrorReason::MalformedProof,
+                format!(
+                    "bases, ys and rs must have the same length, between 1 and {}",
+                    MAX_BASES
+                ),
+            ));
+        }
+
+        let elements = |list: Vec<Vec<u8>>| -> Vec<BigUint> {
+            list.iter().map(|n| BigUint::from_bytes_be(n)).collect()
+        };
+        let (bases, ys, rs) = (elements(bases), elements(ys), elements(rs));
+        let [c, s] = [c, s].map(|n| BigUint::from_bytes_be(&n));
+        let verification = self
+            .offload(move || verifier.verify_multi_base(&bases, &ys, &rs, &c, &s))
+            .await;
+        Ok(Response::new(match verification {
+            Ok(()) => VerifyProofResponse {
+                valid: true,
+                reason: String::new(),
+            },
+            Err(err) => VerifyProofResponse {
+                valid: false,
+                reason: err.to_string(),
+            },
+        }))
+    }
+
+    type LoginStreamStream = ReceiverStream<Result<LoginStreamResponse, Status>>;
+
+    async fn login_stream(
+        &self,
+        request: Request<Streaming<LoginStreamRequest>>,
+    ) -> Result<Response<Self::LoginStreamStream>, Status> {
+        use login_stream_request::Step;
+        use login_stream_response::Step as Reply;
+
+        println!("[login_stream] ...");
+        let auth_impl = self.shared.get().and_then(Weak::upgrade).ok_or_else(|| {
+            Status::unimplemented("LoginStream is only served by AuthImpl::service")
+        })?;
+        let metadata = request.metadata().clone();
+        let peer = StreamPeer::of(&request);
+        let mut steps = request.into_inner();
+        let (replies, receiver) = mpsc::channel(1);
+        tokio::spawn(async move {
+            // the challenge of this stream, for an answer sent without its auth_id
+            let mut auth_id = String::new();
+            loop {
+                let reply = match steps.message().await {
+                    Ok(None) => break,
+                    Err(status) => Err(status),
+                    Ok(Some(LoginStreamRequest { step: None })) => Err(error_status(
+                        Code::InvalidArgument,
+                        ErrorReason::MalformedProof,
+                        "every LoginStream message carries a step",
+                    )),
+                    Ok(Some(LoginStreamRequest {
+                        step: Some(Step::Register(request)),
+                    })) => auth_impl
+                        .register(stream_step(&metadata, &peer, request))
+                        .await
+                        .map(|response| Reply::Registered(response.into_inner())),
+                    Ok(Some(LoginStreamRequest {
+                        step: Some(Step::Commit(request)),
+                    })) => auth_impl
+                        .create_auth_challenge(stream_step(&metadata, &peer, request))
+                        .await
+                        .map(|response| {
+                            let challenge = response.into_inner();
+                            auth_id = challenge.auth_id.clone();
+                            Reply::Challenge(challenge)
+                        }),
+                    Ok(Some(LoginStreamRequest {
+                        step: Some(Step::Answer(mut request)),
+                    })) => {
+                        if request.auth_id.is_empty() {
+                            request.auth_id = auth_id.clone();
+                        }
+                        auth_impl
+                            .verify_auth(stream_step(&metadata, &peer, request))
+                            .await
+                            .map(|response| Reply::Session(response.into_inner()))
+                    }
+                };
+                let done = !matches!(reply, Ok(Reply::Registered(_) | Reply::Challenge(_)));
+                let reply = reply.map(|step| LoginStreamResponse { step: Some(step) });
+                if replies.send(reply).await.is_err() || done {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn stats(
+        &self,
+        request: Request<StatsRequest>,
+    ) -> Result<Response<StatsResponse>, Status> {
+        println!("[stats] ...");
+        if let Some(status) = self.admin_denied(&request) {
+            return Err(status);
+        }
+
+        let now = self.clock.now();
+        let users = self.store.users().map_err(store_status)?;
+        let pending_challenges = users
+            .iter()
+            .filter(|user_info| {
+                user_info.auth_id.is_some()
+                    && user_info
+                        .challenge_expires_at
+                        .is_some_and(|expires_at| now < expires_at)
+            })
+            .count();
+        let active_sessions = self
+            .sessions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|session| !session.is_expired(now))
+            .count();
+        let mut rejections = HashMap::new();
+        for verifier in std::iter::once(self.verifier.load()).chain(self.retiring_verifier.clone())
+        {
+            for (gate, count) in verifier.rejections().snapshot() {
+                *rejections.entry(gate.to_string()).or_default() += count;
+            }
+        }
+        Ok(Response::new(StatsResponse {
+            users: users.len() as u64,
+            pending_challenges: pending_challenges as u64,
+            active_sessions: active_sessions as u64,
+            rejections,
+        }))
+    }
+
+    async fn get_params(
+        &self,
+        _request: Request<GetParamsRequest>,
+    ) -> Result<Response<GetParamsResponse>, Status> {
+        let Capabilities {
+            protocol_version,
+            groups,
+            encodings,
+            hashes,
+        } = self.capabilities();
+        Ok(Response::new(GetParamsResponse {
+            protocol_version,
+            groups,
+            encodings,
+            hashes,
+        }))
+    }
+
+    async fn handshake(
+        &self,
+        request: Request<HandshakeRequest>,
+    ) -> Result<Response<HandshakeResponse>, Status> {
+        let HandshakeRequest { element, group } = request.into_inner();
+        let group = self.requested_group(&group).map_err(invalid_group_status)?;
+        let verifier = self
+            .verifier_for(&group)
+            .ok_or_else(|| self.unchecked_group_status())?;
+        let element = verifier.echo_element(&element).map_err(|err| {
+            error_status(
+                Code::InvalidArgument,
+                ErrorReason::MalformedElement,
+                format!("Could not read the handshake element: {}", err),
+            )
+        })?;
+        Ok(Response::new(HandshakeResponse { element }))
+    }
+
+    async fn verify_proof_cbor(
+        &self,
+        request: Request<VerifyProofCborRequest>,
+    ) -> Result<Response<VerifyProofResponse>, Status> {
+        println!("[verify_proof_cbor] ...");
+        let VerifyProofCborRequest { group, proof } = request.into_inner();
+        let group = self.requested_group(&group).map_err(invalid_group_status)?;
+        let verifier = self
+            .verifier_for(&group)
+            .ok_or_else(|| self.unchecked_group_status())?;
+        let [r1, r2, y1, y2, c, s] = verifier.decode_cbor(&proof).map_err(|err| {
+            error_status(
+                Code::InvalidArgument,
+                ErrorReason::MalformedProof,
+                format!("Could not read the CBOR proof: {}", err),
+            )
+        })?;
+
+        let verification = self
+            .offload(move || verifier.verify_fiat_shamir(&r1, &r2, &y1, &y2, &c, &s))
+            .await;
+        Ok(Response::new(match verification {
+            Ok(()) => VerifyProofResponse {
+                valid: true,
+                reason: String::new(),
+            },
+            Err(err) => VerifyProofResponse {
+                valid: false,
+                reason: err.to_string(),
+            },
+        }))
+    }
+
+    /// Users registered under the replaced group are turned away like those of
+    /// a retired group until they register again; verifications already
+    /// running finish under it
+    async fn rotate_group(
+        &self,
+        request: Request<RotateGroupRequest>,
+    ) -> Result<Response<RotateGroupResponse>, Status> {
+        println!("[rotate_group] ...");
+        if let Some(status) = self.admin_denied(&request) {
+            return Err(status);
         }
+        let RotateGroupRequest { group } = request.into_inner();
+        let zkp: ZKP = group.parse().map_err(invalid_group_status)?;
+        let previous = self.verifier.rotate(zkp);
+        Ok(Response::new(RotateGroupResponse {
+            previous_group: previous.descriptor(),
+        }))
+    }
+}
+
+/// Span of one step of the auth flow, exported with the `otel` feature, see
+/// `zkp_chaum_pedersen::otel`
+#[cfg(feature = "otel")]
+fn step_span(method: &str, metadata: &MetadataMap) -> tracing::Span {
+    let header = |name| metadata.get(name).and_then(|value| value.to_str().ok());
+    otel::rpc_span(method, header("traceparent"), header("x-request-id"))
+}
+
+#[cfg(not(feature = "otel"))]
+fn step_span(_: &str, _: &MetadataMap) {}
+
+/// Runs `step` in `span`, recording the status it answers with
+#[cfg(feature = "otel")]
+async fn traced<T>(
+    span: tracing::Span,
+    step: impl Future<Output = Result<T, Status>>,
+) -> Result<T, Status> {
+    let result = step.await;
+    let code = result
+        .as_ref()
+        .map_or_else(|status| status.code() as i64, |_| 0);
+    span.record("rpc.grpc.status_code", code);
+    result
+}
+
+#[cfg(not(feature = "otel"))]
+async fn traced<T>(_: (), step: impl Future<Output = Result<T, Status>>) -> Result<T, Status> {
+    step.await
+}
+
+/// Wraps the store in an `EncryptedStore` when a key is configured
+fn seal_store<S: UserStore + 'static>(store: S, key: Option<StoreKey>) -> Box<dyn UserStore> {
+    match key {
+        Some(key) => Box::new(EncryptedStore::new(store, key)),
+        None => Box::new(store),
     }
 }
 
 #[tokio::main]
 async fn main() {
-    let addr = env::var("SERVER_ADDRESS").unwrap_or("127.0.0.1:50051".to_string());
-    println!("Running the server in {:?}", addr);
+    #[cfg(feature = "console")]
+    zkp_chaum_pedersen::console::init().expect("could not install the tokio-console layer");
+    #[cfg(feature = "otel")]
+    if let Ok(endpoint) = env::var("SERVER_OTEL_ENDPOINT") {
+        otel::init(&endpoint).expect("could not install the trace exporter");
+    }
 
-    let auth_impl = AuthImpl::default();
+    let config = match AuthConfig::from_env() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    if env::args().skip(1).any(|arg| arg == "--bench") {
+        println!("{}", config.group.measure(BENCH_ROUNDS));
+        return;
+    }
+    let store_key = env::var("SERVER_STORE_KEY")
+        .ok()
+        .map(|hex| hex.parse::<StoreKey>().expect("invalid SERVER_STORE_KEY"));
+    let store = match env::var("SERVER_STORE_PATH") {
+        Ok(path) => seal_store(
+            FileStore::open(path).expect("could not open SERVER_STORE_PATH"),
+            store_key,
+        ),
+        Err(_) => seal_store(MemoryStore::default(), store_key),
+    };
+    let addr = config.address.clone();
+    let tls = config
+        .tls
+        .as_ref()
+        .map(|tls| tls.load().expect("could not read the SERVER_TLS_* files"));
+    let mut auth_impl = match env::var("SERVER_ENTROPY_DEVICE") {
+        Ok(path) => AuthImpl::from_entropy(
+            store,
+            config,
+            Box::new(SystemClock),
+            Entropy::new(DeviceRng::open(path).expect("could not open SERVER_ENTROPY_DEVICE")),
+        ),
+        Err(_) => AuthImpl::new(store, config, Box::new(SystemClock), RandomChallenges),
+    };
+    if env::var("SERVER_LOG_AUTH_FAILURES").is_ok() {
+        auth_impl = auth_impl.with_audit_sink(StderrAudit);
+    }
+    let auth_impl = Arc::new(auth_impl);
 
-    Server::builder()
-        .add_service(AuthServer::new(auth_impl))
-        .serve(addr.parse().expect("could not convert address"))
+    println!("Running the server in {:?}", addr);
+    let grpc = TcpListener::bind(&addr)
         .await
-        .unwrap();
+        .expect("could not bind SERVER_ADDRESS");
+    #[cfg(feature = "rest")]
+    let rest = {
+        let rest_addr = env::var("REST_ADDRESS").unwrap_or("127.0.0.1:8080".to_string());
+        println!("Running the REST gateway in {:?}", rest_addr);
+        let codec = env::var("REST_ELEMENT_ENCODING")
+            .map(|codec| codec.parse().expect("invalid REST_ELEMENT_ENCODING"))
+            .unwrap_or_default();
+        let listener = TcpListener::bind(&rest_addr)
+            .await
+            .expect("could not bind REST_ADDRESS");
+        Some((listener, codec))
+    };
+
+    run_server(
+        auth_impl.clone(),
+        grpc,
+        tls,
+        #[cfg(feature = "rest")]
+        rest,
+        shutdown_requested(),
+    )
+    .await
+    .unwrap();
+
+    // flushed explicitly, the services may still hold a reference to the store
+    println!("Shutting down, saving pending challenges");
+    if let Err(err) = auth_impl.store.flush() {
+        eprintln!("could not save the store: {}", err);
+    }
+    // blocks until the batch is sent, which takes a runtime worker of its own
+    #[cfg(feature = "otel")]
+    tokio::task::spawn_blocking(otel::shutdown).await.unwrap();
+}
+
+/// Serves gRPC on `grpc`, over TLS with `tls`, and with the rest feature the
+/// JSON gateway on `rest`, both on the current runtime and over the same
+/// `auth_impl`. Returns once `shutdown` resolves or either of them fails.
+async fn run_server(
+    auth_impl: Arc<AuthImpl>,
+    grpc: TcpListener,
+    tls: Option<ServerTlsConfig>,
+    #[cfg(feature = "rest")] rest: Option<(TcpListener, rest::ElementCodec)>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut builder = Server::builder();
+    if let Some(tls) = tls {
+        builder = builder.tls_config(tls)?;
+    }
+    if let Some(timeout) = auth_impl.request_timeout {
+        builder = builder.timeout(timeout);
+    }
+    let grpc = builder
+        .add_service(AuthImpl::service(auth_impl.clone()))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(grpc), shutdown);
+
+    #[cfg(feature = "rest")]
+    if let Some((listener, codec)) = rest {
+        let router = rest::router(auth_impl, codec);
+        let rest = axum::Server::from_tcp(listener.into_std()?)?
+            .serve(router.into_make_service_with_connect_info::<std::net::SocketAddr>());
+        // the gateway has no shutdown of its own, it stops with the gRPC service
+        return tokio::select! {
+            result = grpc => result.map_err(Into::into),
+            result = rest => result.map_err(Into::into),
+        };
+    }
+
+    grpc.await.map_err(Into::into)
+}
+
+/// Resolves once SIGINT or SIGTERM arrives
+async fn shutdown_requested() {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("could not listen for SIGTERM");
+        tokio::select! {
+            result = signal::ctrl_c() => result.expect("could not listen for SIGINT"),
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    signal::ctrl_c().await.expect("could not listen for Ctrl-C");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{net::SocketAddr, time::Instant};
+    use tonic::transport::server::TcpConnectInfo;
+    use zkp_auth::auth_client::AuthClient;
+    use zkp_chaum_pedersen::{
+        error_details, FixedChallenges, FixedIds, Group, MemoryAudit, MockClock,
+    };
+
+    /// Registration of y = (alpha^x, beta^x) with its proof of possession
+    fn register_request(zkp: &ZKP, user: &str, x: &BigUint) -> RegisterRequest {
+        let k = ZKP::generate_random_below(&zkp.q);
+        let possession = zkp.prove_possession(&k, x, user);
+        RegisterRequest {
+            user: user.to_string(),
+            y1: ZKP::exponentiate(&zkp.alpha, x, &zkp.p).to_bytes_be(),
+            y2: ZKP::exponentiate(&zkp.beta, x, &zkp.p).to_bytes_be(),
+            pop_c: possession.c.to_bytes_be(),
+            pop_s: possession.s.to_bytes_be(),
+            ..Default::default()
+        }
+    }
+
+    async fn login(auth_impl: &AuthImpl, zkp: &ZKP, user: &str) -> AuthAnswerRensponse {
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(zkp, user, &x)))
+            .await
+            .unwrap();
+
+        authenticate(auth_impl, zkp, user, &x).await.unwrap()
+    }
+
+    async fn authenticate(
+        auth_impl: &AuthImpl,
+        zkp: &ZKP,
+        user: &str,
+        x: &BigUint,
+    ) -> Result<AuthAnswerRensponse, Status> {
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: user.to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await?
+            .into_inner();
+
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), x);
+        auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .map(Response::into_inner)
+    }
+
+    #[tokio::test]
+    async fn test_group_migration() {
+        let old_zkp = ZKP::new();
+        let new_zkp = Group::Rfc5114_2048_256.zkp();
+
+        // alice registered before the rotation started
+        let before = AuthImpl::default();
+        let alice_x = ZKP::generate_random_below(&old_zkp.q);
+        before
+            .register(Request::new(RegisterRequest {
+                group: "rfc5114-1024-160".to_string(),
+                ..register_request(&old_zkp, "alice", &alice_x)
+            }))
+            .await
+            .unwrap();
+
+        let migrating = AuthImpl::new(
+            before.store,
+            AuthConfig {
+                group: Group::Rfc5114_2048_256.zkp(),
+                retiring_group: Some(ZKP::new()),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        );
+        authenticate(&migrating, &old_zkp, "alice", &alice_x)
+            .await
+            .unwrap();
+
+        // bob registers under the new group while alice still works
+        login(&migrating, &new_zkp, "bob").await;
+        authenticate(&migrating, &old_zkp, "alice", &alice_x)
+            .await
+            .unwrap();
+
+        // nobody new may join the retiring group
+        let status = migrating
+            .register(Request::new(RegisterRequest {
+                user: "carol".to_string(),
+                y1: vec![2],
+                y2: vec![3],
+                group: "rfc5114-1024-160".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+
+        // alice re-registers under the new group
+        let alice_x = ZKP::generate_random_below(&new_zkp.q);
+        migrating
+            .register(Request::new(RegisterRequest {
+                group: "rfc5114-2048-256".to_string(),
+                ..register_request(&new_zkp, "alice", &alice_x)
+            }))
+            .await
+            .unwrap();
+        authenticate(&migrating, &new_zkp, "alice", &alice_x)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retired_group_is_rejected() {
+        let before = AuthImpl::default();
+        login(&before, &ZKP::new(), "alice").await;
+
+        // once the migration is over the old group is gone
+        let after = AuthImpl::new(
+            before.store,
+            AuthConfig {
+                group: Group::Rfc5114_2048_256.zkp(),
+                retiring_group: None,
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        );
+        let status = after
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "alice".to_string(),
+                r1: vec![8],
+                r2: vec![4],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_fixed_challenges_reproduce_transcript() {
+        let auth_impl =
+            AuthImpl::with_challenge_source(FixedChallenges::new(vec![BigUint::from(4u32)]));
+        let zkp = ZKP::new();
+        let x = BigUint::from(6u32);
+
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+
+        // each round commits to a fresh nonce, a repeated one would be turned away
+        for k in [7u32, 8] {
+            let k = BigUint::from(k);
+            let challenge = auth_impl
+                .create_auth_challenge(Request::new(AuthChallengeRequest {
+                    user: "peggy".to_string(),
+                    r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                    r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(challenge.c.len(), 20);
+            assert_eq!(BigUint::from_bytes_be(&challenge.c), BigUint::from(4u32));
+
+            // s = k - c * x = k - 24 mod q
+            let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+            assert_eq!(s, &zkp.q - (BigUint::from(24u32) - &k));
+
+            auth_impl
+                .verify_auth(Request::new(AuthAnswerRequest {
+                    auth_id: challenge.auth_id,
+                    s: s.to_bytes_be(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fixed_ids_reproduce_known_ids() {
+        let ids = ["h4ndle0000000001", "auth00000001", "sess00000001"];
+        let mut auth_impl =
+            AuthImpl::with_challenge_source(FixedChallenges::new(vec![BigUint::from(4u32)]));
+        auth_impl.ids = Box::new(FixedIds::new(ids.map(String::from).to_vec()));
+        let zkp = ZKP::new();
+        let x = BigUint::from(6u32);
+
+        let registered = auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(registered.key_handle, ids[0]);
+
+        let k = BigUint::from(7u32);
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                key_handle: registered.key_handle,
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(challenge.auth_id, ids[1]);
+
+        let session = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: zkp.solve(&k, &BigUint::from(4u32), &x).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(session.session_id, ids[2]);
+    }
+
+    /// Key handle, auth id, challenge and session id of one login against a
+    /// server drawing from a seeded generator
+    async fn seeded_login(seed: u64) -> [Vec<u8>; 4] {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let auth_impl = AuthImpl::from_entropy(
+            Box::new(MemoryStore::default()),
+            AuthConfig::default(),
+            Box::new(SystemClock),
+            Entropy::new(StdRng::seed_from_u64(seed)),
+        );
+        let zkp = ZKP::new();
+        let x = BigUint::from(6u32);
+        let registered = auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap()
+            .into_inner();
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                key_handle: registered.key_handle.clone(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let session = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id.clone(),
+                s: zkp
+                    .solve(&k, &BigUint::from_bytes_be(&challenge.c), &x)
+                    .to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        [
+            registered.key_handle.into_bytes(),
+            challenge.auth_id.into_bytes(),
+            challenge.c,
+            session.session_id.into_bytes(),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_entropy_source_feeds_ids_and_challenges() {
+        // the same seed gives the same ids and challenge, whatever the client sent
+        let first = seeded_login(7).await;
+        assert_eq!(seeded_login(7).await, first);
+        let other = seeded_login(8).await;
+        for (a, b) in first.iter().zip(&other) {
+            assert_ne!(a, b);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_rotate_group_during_logins() {
+        let auth_impl = Arc::new(AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                admin_token: Some("s3cret".to_string()),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        ));
+        let rotate = |group: &str| {
+            let mut request = Request::new(RotateGroupRequest {
+                group: group.to_string(),
+            });
+            request
+                .metadata_mut()
+                .insert(ADMIN_TOKEN_HEADER, "s3cret".parse().unwrap());
+            request
+        };
+        let old = ZKP::new();
+        let users: Vec<_> = (0..16)
+            .map(|i| (format!("user-{}", i), ZKP::generate_random_below(&old.q)))
+            .collect();
+        for (user, x) in &users {
+            auth_impl
+                .register(Request::new(register_request(&old, user, x)))
+                .await
+                .unwrap();
+        }
+
+        let logins: Vec<_> = users
+            .into_iter()
+            .map(|(user, x)| {
+                let auth_impl = auth_impl.clone();
+                tokio::spawn(async move {
+                    let mut outcomes = Vec::new();
+                    for _ in 0..4 {
+                        outcomes.push(authenticate(&auth_impl, &ZKP::new(), &user, &x).await);
+                    }
+                    outcomes
+                })
+            })
+            .collect();
+        let new = Group::Rfc5114_2048_256.zkp();
+        let replaced = auth_impl
+            .rotate_group(rotate(&new.descriptor()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(replaced.previous_group, old.descriptor());
+
+        // each login either got through under the old group or was turned away
+        // as belonging to a retired one, none failed any other way
+        for login in logins {
+            for outcome in login.await.unwrap() {
+                if let Err(status) = outcome {
+                    assert_eq!(
+                        error_details::error_reason(&status),
+                        Some(ErrorReason::RetiredGroup),
+                        "{:?}",
+                        status
+                    );
+                }
+            }
+        }
+        let status = authenticate(&auth_impl, &old, "user-0", &BigUint::from(6u32))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(auth_impl.capabilities().groups, vec![new.descriptor()]);
+
+        // new users register and log in under the new group
+        let x = ZKP::generate_random_below(&new.q);
+        auth_impl
+            .register(Request::new(register_request(&old, "victor", &x)))
+            .await
+            .unwrap_err();
+        auth_impl
+            .register(Request::new(RegisterRequest {
+                group: new.descriptor(),
+                ..register_request(&new, "victor", &x)
+            }))
+            .await
+            .unwrap();
+        authenticate(&auth_impl, &new, "victor", &x).await.unwrap();
+
+        let status = auth_impl
+            .rotate_group(Request::new(RotateGroupRequest {
+                group: old.descriptor(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::AdminRequired)
+        );
+        let status = auth_impl
+            .rotate_group(rotate("rfc5114-512-64"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_replays_log_in_once() {
+        let auth_impl = Arc::new(AuthImpl::with_challenge_source(FixedChallenges::new(vec![
+            BigUint::from(4u32),
+        ])));
+        let zkp = ZKP::new();
+        let x = BigUint::from(6u32);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+
+        let k = BigUint::from(7u32);
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let answer = AuthAnswerRequest {
+            auth_id: challenge.auth_id,
+            s: zkp.solve(&k, &BigUint::from(4u32), &x).to_bytes_be(),
+            ..Default::default()
+        };
+
+        let attempts: Vec<_> = (0..2)
+            .map(|_| {
+                let auth_impl = auth_impl.clone();
+                let answer = answer.clone();
+                tokio::spawn(async move { auth_impl.verify_auth(Request::new(answer)).await })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for attempt in attempts {
+            results.push(attempt.await.unwrap());
+        }
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        let replay = results.into_iter().find_map(Result::err).unwrap();
+        assert_eq!(replay.code(), Code::AlreadyExists);
+        assert_eq!(
+            error_details::error_reason(&replay),
+            Some(ErrorReason::AnswerReplayed)
+        );
+        assert_eq!(auth_impl.sessions.lock().unwrap().len(), 1);
+
+        // a leading zero byte doesn't make it another answer
+        let mut padded = answer;
+        padded.s.insert(0, 0);
+        let replay = auth_impl
+            .verify_auth(Request::new(padded))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&replay),
+            Some(ErrorReason::AnswerReplayed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verification_leaves_the_runtime_responsive() {
+        let zkp = Group::Rfc5114_2048_256.zkp();
+        let auth_impl = AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                group: Group::Rfc5114_2048_256.zkp(),
+                verify_workers: Some(1),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        );
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+
+        // the test runtime has a single thread, other tasks only get to run
+        // while the modpows happen elsewhere
+        let verifying = auth_impl.verify_auth(Request::new(AuthAnswerRequest {
+            auth_id: challenge.auth_id,
+            s: s.to_bytes_be(),
+            ..Default::default()
+        }));
+        tokio::pin!(verifying);
+        let mut other_polls = 0;
+        let result = loop {
+            tokio::select! {
+                biased;
+                result = &mut verifying => break result,
+                _ = tokio::task::yield_now() => other_polls += 1,
+            }
+        };
+        assert!(result.is_ok());
+        assert!(other_polls > 0);
+    }
+
+    #[tokio::test]
+    async fn test_challenge_keeps_leading_zero_byte() {
+        let zkp = ZKP::new();
+        // c < q / 256, so its big-endian form is one byte shorter than q's
+        let c = &zkp.q >> 8u32;
+        let auth_impl = AuthImpl::with_challenge_source(FixedChallenges::new(vec![c.clone()]));
+        let response = login(&auth_impl, &zkp, "peggy").await;
+        assert!(!response.session_id.is_empty());
+
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: vec![8],
+                r2: vec![4],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(c.to_bytes_be().len(), 19);
+        assert_eq!(challenge.c.len(), 20);
+        assert_eq!(challenge.c[0], 0);
+        assert_eq!(BigUint::from_bytes_be(&challenge.c), c);
+    }
+
+    #[tokio::test]
+    async fn test_context_binds_answer() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+
+        // a valid proof requested for app A is only accepted for app A
+        for (context, expected) in [("app-b", Some(Code::PermissionDenied)), ("app-a", None)] {
+            let k = ZKP::generate_random_below(&zkp.q);
+            let challenge = auth_impl
+                .create_auth_challenge(Request::new(AuthChallengeRequest {
+                    user: "peggy".to_string(),
+                    r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                    r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                    context: "app-a".to_string(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+
+            let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+            let result = auth_impl
+                .verify_auth(Request::new(AuthAnswerRequest {
+                    auth_id: challenge.auth_id,
+                    s: s.to_bytes_be(),
+                    context: context.to_string(),
+                    ..Default::default()
+                }))
+                .await;
+            assert_eq!(result.err().map(|status| status.code()), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_cannot_choose_challenge() {
+        let auth_impl = AuthImpl::with_challenge_source(FixedChallenges::new(vec![
+            BigUint::from(4u32),
+            BigUint::from(5u32),
+        ]));
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let challenge_for = |k: &BigUint| AuthChallengeRequest {
+            user: "peggy".to_string(),
+            r1: ZKP::exponentiate(&zkp.alpha, k, &zkp.p).to_bytes_be(),
+            r2: ZKP::exponentiate(&zkp.beta, k, &zkp.p).to_bytes_be(),
+            ..Default::default()
+        };
+
+        // without x, a transcript only verifies for the c it was simulated for
+        let s = ZKP::generate_random_below(&zkp.q);
+        let own_c = BigUint::from(5u32);
+        let simulated = |base: &BigUint, y: &BigUint| {
+            (ZKP::exponentiate(base, &s, &zkp.p) * ZKP::exponentiate(y, &own_c, &zkp.p) % &zkp.p)
+                .to_bytes_be()
+        };
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: simulated(&zkp.alpha, &y1),
+                r2: simulated(&zkp.beta, &y2),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(BigUint::from_bytes_be(&challenge.c), BigUint::from(4u32));
+        let status = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::InvalidProof)
+        );
+
+        // a newer challenge replaces the stored c, so an older auth_id can't be
+        // answered with it
+        let k = ZKP::generate_random_below(&zkp.q);
+        let stale = auth_impl
+            .create_auth_challenge(Request::new(challenge_for(&ZKP::generate_random_below(
+                &zkp.q,
+            ))))
+            .await
+            .unwrap()
+            .into_inner();
+        let current = auth_impl
+            .create_auth_challenge(Request::new(challenge_for(&k)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_ne!(stale.c, current.c);
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&current.c), &x);
+        let answer = |auth_id: String| {
+            Request::new(AuthAnswerRequest {
+                auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            })
+        };
+        let status = auth_impl
+            .verify_auth(answer(stale.auth_id))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::ChallengeMismatch)
+        );
+        auth_impl
+            .verify_auth(answer(current.auth_id))
+            .await
+            .unwrap();
+    }
+
+    const PROXY: &str = "10.0.0.2:443";
+
+    /// Server binding keys to the subject a proxy at `PROXY` passes on
+    fn behind_proxy() -> AuthImpl {
+        AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                client_certs: Some(ClientCerts::Proxy {
+                    header: "x-client-cert-subject".to_string(),
+                    proxies: vec![PROXY.parse::<SocketAddr>().unwrap().ip()],
+                }),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        )
+    }
+
+    /// `message` from `peer` with `subject` in the proxy's header
+    fn from_peer<T>(peer: &str, subject: Option<&str>, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        request.extensions_mut().insert(TcpConnectInfo {
+            local_addr: None,
+            remote_addr: Some(peer.parse().unwrap()),
+        });
+        if let Some(subject) = subject {
+            request
+                .metadata_mut()
+                .insert("x-client-cert-subject", subject.parse().unwrap());
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn test_client_cert_binding() {
+        fn over_cert<T>(subject: Option<&str>, message: T) -> Request<T> {
+            from_peer(PROXY, subject, message)
+        }
+
+        let auth_impl = behind_proxy();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let status = auth_impl
+            .register(over_cert(None, register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        auth_impl
+            .register(over_cert(
+                Some("CN=peggy"),
+                register_request(&zkp, "peggy", &x),
+            ))
+            .await
+            .unwrap();
+
+        // the right proof, only accepted over the certificate peggy registered with
+        for (subject, expected) in [
+            (Some("CN=mallory"), Some(Code::PermissionDenied)),
+            (None, Some(Code::PermissionDenied)),
+            (Some("CN=peggy"), None),
+        ] {
+            let k = ZKP::generate_random_below(&zkp.q);
+            let challenge = auth_impl
+                .create_auth_challenge(over_cert(
+                    subject,
+                    AuthChallengeRequest {
+                        user: "peggy".to_string(),
+                        r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                        r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                        ..Default::default()
+                    },
+                ))
+                .await
+                .unwrap()
+                .into_inner();
+            let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+            let result = auth_impl
+                .verify_auth(over_cert(
+                    subject,
+                    AuthAnswerRequest {
+                        auth_id: challenge.auth_id,
+                        s: s.to_bytes_be(),
+                        ..Default::default()
+                    },
+                ))
+                .await;
+            if let Err(status) = &result {
+                assert_eq!(
+                    error_details::error_reason(status),
+                    Some(ErrorReason::ClientCertMismatch)
+                );
+            }
+            assert_eq!(result.err().map(|status| status.code()), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forged_cert_header_is_rejected() {
+        let auth_impl = behind_proxy();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+
+        // straight to the port, past the proxy, claiming peggy's certificate
+        let forged = from_peer(
+            "203.0.113.7:40000",
+            Some("CN=peggy"),
+            register_request(&zkp, "peggy", &x),
+        );
+        assert_eq!(auth_impl.cert_subject(&forged), None);
+        let status = auth_impl.register(forged).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::ClientCertMismatch)
+        );
+        // nor from a request without a peer at all
+        let mut unknown = Request::new(register_request(&zkp, "peggy", &x));
+        unknown
+            .metadata_mut()
+            .insert("x-client-cert-subject", "CN=peggy".parse().unwrap());
+        auth_impl.register(unknown).await.unwrap_err();
+
+        auth_impl
+            .register(from_peer(
+                PROXY,
+                Some("CN=peggy"),
+                register_request(&zkp, "peggy", &x),
+            ))
+            .await
+            .unwrap();
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+        let status = auth_impl
+            .verify_auth(from_peer(
+                "203.0.113.7:40000",
+                Some("CN=peggy"),
+                AuthAnswerRequest {
+                    auth_id: challenge.auth_id,
+                    s: s.to_bytes_be(),
+                    ..Default::default()
+                },
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::ClientCertMismatch)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commitment_hash() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+        let nonce = || {
+            let k = ZKP::generate_random_below(&zkp.q);
+            let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+            let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+            (k, r1, r2)
+        };
+
+        let status = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                commitment_hash: vec![0; 16],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        // opening a different nonce than the committed one fails the hash check,
+        // even though its algebra holds
+        for open_another in [true, false] {
+            let committed = nonce();
+            let (opened, expected) = match open_another {
+                true => (nonce(), Some(ErrorReason::CommitmentMismatch)),
+                false => (committed.clone(), None),
+            };
+            let challenge = auth_impl
+                .create_auth_challenge(Request::new(AuthChallengeRequest {
+                    user: "peggy".to_string(),
+                    commitment_hash: hash_elements(&committed.1, &committed.2).to_vec(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+
+            let (k, r1, r2) = opened;
+            let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+            let result = auth_impl
+                .verify_auth(Request::new(AuthAnswerRequest {
+                    auth_id: challenge.auth_id,
+                    s: s.to_bytes_be(),
+                    r1: r1.to_bytes_be(),
+                    r2: r2.to_bytes_be(),
+                    ..Default::default()
+                }))
+                .await;
+            assert_eq!(
+                result
+                    .err()
+                    .and_then(|status| error_details::error_reason(&status)),
+                expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reused_commitment_is_rejected() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = || {
+            Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            })
+        };
+
+        auth_impl.create_auth_challenge(challenge()).await.unwrap();
+        // a second c for the same nonce would let the two answers reveal x
+        let status = auth_impl
+            .create_auth_challenge(challenge())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::CommitmentReused)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reused_commitment_hash_is_rejected() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+        let k = ZKP::generate_random_below(&zkp.q);
+        let (r1, r2) = (
+            ZKP::exponentiate(&zkp.alpha, &k, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &k, &zkp.p),
+        );
+        let hashed = || {
+            Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                commitment_hash: hash_elements(&r1, &r2).to_vec(),
+                ..Default::default()
+            })
+        };
+
+        auth_impl.create_auth_challenge(hashed()).await.unwrap();
+        let status = auth_impl.create_auth_challenge(hashed()).await.unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::CommitmentReused)
+        );
+        // nor does sending the same nonce in the clear get around it
+        let status = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: r1.to_bytes_be(),
+                r2: r2.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::CommitmentReused)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_auth_is_audited() {
+        let audit = MemoryAudit::default();
+        let auth_impl = AuthImpl::default().with_audit_sink(audit.clone());
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+
+        authenticate(&auth_impl, &zkp, "peggy", &x).await.unwrap();
+        assert!(audit.records().is_empty());
+
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let wrong_x = &x + 1u32;
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &wrong_x);
+        let mut request = Request::new(AuthAnswerRequest {
+            auth_id: challenge.auth_id,
+            s: s.to_bytes_be(),
+            ..Default::default()
+        });
+        let peer: SocketAddr = "203.0.113.7:40000".parse().unwrap();
+        request.extensions_mut().insert(TcpConnectInfo {
+            local_addr: None,
+            remote_addr: Some(peer),
+        });
+        auth_impl.verify_auth(request).await.unwrap_err();
+
+        let records = audit.records();
+        assert_eq!(records.len(), 1);
+        let AuditRecord::AuthFailure {
+            peer: recorded_peer,
+            user,
+            reason,
+            ..
+        } = &records[0];
+        assert_eq!(*recorded_peer, Some(peer.ip()));
+        assert_eq!(user, "peggy");
+        assert_eq!(reason, "INVALID_PROOF");
+    }
+
+    #[tokio::test]
+    async fn test_verify_auth_error_codes() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+
+        let challenge = |k: &BigUint| {
+            Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            })
+        };
+        let k = ZKP::generate_random_below(&zkp.q);
+
+        // wrong secret
+        let response = auth_impl
+            .create_auth_challenge(challenge(&k))
+            .await
+            .unwrap()
+            .into_inner();
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&response.c), &(&x + 1u32));
+        let status = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: response.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+
+        // s that is not reduced mod q
+        let k = ZKP::generate_random_below(&zkp.q);
+        let response = auth_impl
+            .create_auth_challenge(challenge(&k))
+            .await
+            .unwrap()
+            .into_inner();
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&response.c), &x);
+        for unreduced in [&s + &zkp.q, zkp.q.clone()] {
+            let status = auth_impl
+                .verify_auth(Request::new(AuthAnswerRequest {
+                    auth_id: response.auth_id.clone(),
+                    s: unreduced.to_bytes_be(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+            assert_eq!(
+                error_details::error_reason(&status),
+                Some(ErrorReason::MalformedProof)
+            );
+            assert!(status.message().contains("not below q"));
+        }
+
+        // rejecting the unreduced s didn't burn the challenge
+        auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: response.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_proof_is_checked_against_the_registered_key() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+
+        // an honest proof for a key of the prover's own choosing
+        let other_x = ZKP::generate_random_below(&zkp.q);
+        let other_y1 = ZKP::exponentiate(&zkp.alpha, &other_x, &zkp.p);
+        let other_y2 = ZKP::exponentiate(&zkp.beta, &other_x, &zkp.p);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let (r1, r2) = (
+            ZKP::exponentiate(&zkp.alpha, &k, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &k, &zkp.p),
+        );
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: r1.to_bytes_be(),
+                r2: r2.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let c = BigUint::from_bytes_be(&challenge.c);
+        let s = zkp.solve(&k, &c, &other_x);
+        assert!(zkp.verify(&r1, &r2, &other_y1, &other_y2, &c, &s));
+
+        let status = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::InvalidProof)
+        );
+
+        // a record without a key, e.g. written by another tool
+        let auth_id = AuthId::generate();
+        let keyless = UserAuthInfo {
+            user_name: "victor".to_string(),
+            group: auth_impl.verifier.load().group().to_string(),
+            r1: r1.clone(),
+            r2: r2.clone(),
+            c,
+            auth_id: Some(auth_id),
+            ..Default::default()
+        };
+        auth_impl.store.put_challenge(keyless, auth_id).unwrap();
+        let status = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "victor".to_string(),
+                r1: r1.to_bytes_be(),
+                r2: r2.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::KeyNotRegistered)
+        );
+        let status = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: auth_id.to_string(),
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::KeyNotRegistered)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_with_fewer_capabilities_negotiates() {
+        let auth_impl = AuthImpl::new(
+            Box::<MemoryStore>::default(),
+            AuthConfig {
+                group: Group::Rfc5114_2048_256.zkp(),
+                retiring_group: Some(ZKP::new()),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        );
+        let response = auth_impl
+            .get_params(Request::new(GetParamsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response.groups,
+            [
+                "rfc5114-2048-256".to_string(),
+                "rfc5114-1024-160".to_string()
+            ]
+        );
+        let server = Capabilities {
+            protocol_version: response.protocol_version,
+            groups: response.groups,
+            encodings: response.encodings,
+            hashes: response.hashes,
+        };
+
+        // only the retiring group and one of each setting
+        let client = Capabilities {
+            groups: vec![ZKP::new().descriptor()],
+            encodings: vec![ElementEncoding::Padded.name().to_string()],
+            ..Capabilities::new(Vec::new())
+        };
+        let negotiated = client.negotiate(&server).unwrap();
+        assert_eq!(negotiated.group, "rfc5114-1024-160");
+        assert_eq!(negotiated.encoding, "padded");
+        assert_eq!(negotiated.hash, "sha256");
+
+        // the server doesn't put varint elements on the wire
+        let varint_only = Capabilities {
+            encodings: vec![ElementEncoding::Varint.name().to_string()],
+            ..client
+        };
+        assert!(varint_only.negotiate(&server).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_catches_an_encoding_mismatch() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let handshake = |element: Vec<u8>| {
+            auth_impl.handshake(Request::new(HandshakeRequest {
+                element,
+                group: zkp.descriptor(),
+            }))
+        };
+
+        let padded = ElementEncoding::Padded.encode(&zkp.handshake_element(), &zkp.p);
+        let echoed = handshake(padded).await.unwrap().into_inner().element;
+        assert_eq!(
+            zkp.check_handshake(ElementEncoding::Padded, &echoed),
+            Ok(())
+        );
+
+        // a client writing varints is told before it sends a proof
+        let varint = ElementEncoding::Varint.encode(&zkp.handshake_element(), &zkp.p);
+        let echoed = handshake(varint).await.unwrap().into_inner().element;
+        assert!(zkp
+            .check_handshake(ElementEncoding::Varint, &echoed)
+            .is_err());
+
+        let status = handshake(vec![0xff; 200]).await.unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::MalformedElement)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_challenge_expiry() {
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let auth_impl = AuthImpl {
+            clock: Box::new(clock.clone()),
+            ..Default::default()
+        };
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+
+        let answer_after = |elapsed: Duration| {
+            let auth_impl = &auth_impl;
+            let (zkp, x, clock) = (&zkp, &x, &clock);
+            async move {
+                let k = ZKP::generate_random_below(&zkp.q);
+                let challenge = auth_impl
+                    .create_auth_challenge(Request::new(AuthChallengeRequest {
+                        user: "peggy".to_string(),
+                        r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                        r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                assert_eq!(
+                    challenge.expires_at,
+                    unix_seconds(clock.now()) + CHALLENGE_TTL.as_secs()
+                );
+
+                clock.advance(elapsed);
+                let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), x);
+                auth_impl
+                    .verify_auth(Request::new(AuthAnswerRequest {
+                        auth_id: challenge.auth_id,
+                        s: s.to_bytes_be(),
+                        ..Default::default()
+                    }))
+                    .await
+            }
+        };
+
+        assert!(answer_after(CHALLENGE_TTL - Duration::from_secs(1))
+            .await
+            .is_ok());
+
+        let status = answer_after(CHALLENGE_TTL).await.unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::ChallengeExpired)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_key_handle() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let register = || Request::new(register_request(&zkp, "peggy", &x));
+        let key_handle = auth_impl
+            .register(register())
+            .await
+            .unwrap()
+            .into_inner()
+            .key_handle;
+        assert!(!key_handle.is_empty());
+
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge_with = |key_handle: &str, k: &BigUint| {
+            Request::new(AuthChallengeRequest {
+                r1: ZKP::exponentiate(&zkp.alpha, k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, k, &zkp.p).to_bytes_be(),
+                key_handle: key_handle.to_string(),
+                ..Default::default()
+            })
+        };
+        // every other challenge commits to a fresh nonce
+        let challenge_for =
+            |key_handle: &str| challenge_with(key_handle, &ZKP::generate_random_below(&zkp.q));
+        let challenge = auth_impl
+            .create_auth_challenge(challenge_with(&key_handle, &k))
+            .await
+            .unwrap()
+            .into_inner();
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+        auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let status = auth_impl
+            .create_auth_challenge(challenge_for("unknown"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::KeyHandleNotFound)
+        );
+
+        // registering again retires the previous handle
+        let new_handle = auth_impl
+            .register(register())
+            .await
+            .unwrap()
+            .into_inner()
+            .key_handle;
+        assert_ne!(new_handle, key_handle);
+        let status = auth_impl
+            .create_auth_challenge(challenge_for(&key_handle))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert!(auth_impl
+            .create_auth_challenge(challenge_for(&new_handle))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_register_requires_proof_of_possession() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let peggy = register_request(&zkp, "peggy", &x);
+
+        let without_proof = RegisterRequest {
+            pop_c: vec![],
+            pop_s: vec![],
+            ..peggy.clone()
+        };
+        // peggy's key and proof, claimed by someone who doesn't know x
+        let substituted = RegisterRequest {
+            user: "mallory".to_string(),
+            ..peggy.clone()
+        };
+        // y1 and y2 swapped, with peggy's proof or one made for swapped generators
+        let swapped = RegisterRequest {
+            y1: peggy.y2.clone(),
+            y2: peggy.y1.clone(),
+            ..peggy.clone()
+        };
+        let swapped_zkp = ZKP {
+            alpha: zkp.beta.clone(),
+            beta: zkp.alpha.clone(),
+            ..ZKP::new()
+        };
+        let swapped_proof = RegisterRequest {
+            user: "peggy".to_string(),
+            ..register_request(&swapped_zkp, "peggy", &x)
+        };
+        assert_eq!(swapped_proof.y1, peggy.y2);
+        for request in [without_proof, substituted, swapped, swapped_proof] {
+            let status = auth_impl.register(Request::new(request)).await.unwrap_err();
+            assert_eq!(status.code(), Code::PermissionDenied);
+            assert_eq!(
+                error_details::error_reason(&status),
+                Some(ErrorReason::InvalidPossessionProof)
+            );
+        }
+        assert!(auth_impl.store.get_user("peggy").unwrap().is_none());
+        assert!(auth_impl.store.get_user("mallory").unwrap().is_none());
+
+        auth_impl.register(Request::new(peggy)).await.unwrap();
+        authenticate(&auth_impl, &zkp, "peggy", &x).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_proof_is_stateless() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+        let proof = zkp.prove_compact(&k, &x);
+
+        let full = VerifyProofRequest {
+            group: String::new(),
+            y1: y1.to_bytes_be(),
+            y2: y2.to_bytes_be(),
+            r1: r1.to_bytes_be(),
+            r2: r2.to_bytes_be(),
+            c: proof.c.to_bytes_be(),
+            s: proof.s.to_bytes_be(),
+        };
+        let compact = VerifyProofRequest {
+            group: zkp.descriptor(),
+            r1: vec![],
+            r2: vec![],
+            ..full.clone()
+        };
+        for request in [full.clone(), compact.clone()] {
+            let response = auth_impl.verify_proof(Request::new(request)).await.unwrap();
+            assert_eq!(
+                response.into_inner(),
+                VerifyProofResponse {
+                    valid: true,
+                    reason: String::new(),
+                }
+            );
+        }
+        // nothing was registered along the way
+        assert!(auth_impl.store.users().unwrap().is_empty());
+
+        let s = ZKP::to_padded_bytes(&((&proof.s + 1u32) % &zkp.q), &zkp.q);
+        let other_r1 = ZKP::exponentiate(&zkp.alpha, &(&k + 1u32), &zkp.p).to_bytes_be();
+        let tampered = [
+            VerifyProofRequest {
+                s: s.clone(),
+                ..full.clone()
+            },
+            VerifyProofRequest { s, ..compact },
+            // r1 that isn't the one hashed into c
+            VerifyProofRequest {
+                r1: other_r1,
+                ..full.clone()
+            },
+        ];
+        for request in tampered {
+            let response = auth_impl.verify_proof(Request::new(request)).await.unwrap();
+            let response = response.into_inner();
+            assert!(!response.valid);
+            assert!(!response.reason.is_empty());
+        }
+
+        let status = auth_impl
+            .verify_proof(Request::new(VerifyProofRequest {
+                r2: vec![],
+                ..full.clone()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let status = auth_impl
+            .verify_proof(Request::new(VerifyProofRequest {
+                group: "rfc5114-2048-256".to_string(),
+                ..full
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::WrongGroup)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_proof_cbor() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+        let proof = zkp.prove_compact(&k, &x);
+        let verify = |proof: Vec<u8>| {
+            auth_impl.verify_proof_cbor(Request::new(VerifyProofCborRequest {
+                group: String::new(),
+                proof,
+            }))
+        };
+
+        let bytes = zkp.encode_proof_cbor([&r1, &r2, &y1, &y2, &proof.c, &proof.s]);
+        let response = verify(bytes.clone()).await.unwrap().into_inner();
+        assert_eq!(
+            response,
+            VerifyProofResponse {
+                valid: true,
+                reason: String::new(),
+            }
+        );
+
+        let s = (&proof.s + 1u32) % &zkp.q;
+        let tampered = zkp.encode_proof_cbor([&r1, &r2, &y1, &y2, &proof.c, &s]);
+        let response = verify(tampered).await.unwrap().into_inner();
+        assert!(!response.valid);
+
+        // cut short, or a JSON body sent by mistake
+        for malformed in [bytes[..bytes.len() - 1].to_vec(), b"{}".to_vec()] {
+            let status = verify(malformed).await.unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+            assert_eq!(
+                error_details::error_reason(&status),
+                Some(ErrorReason::MalformedProof)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_rejected_proofs_per_gate() {
+        let auth_impl = AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                admin_token: Some("s3cret".to_string()),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        );
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let proof = zkp.prove_compact(&k, &x);
+        let full = VerifyProofRequest {
+            group: String::new(),
+            y1: ZKP::exponentiate(&zkp.alpha, &x, &zkp.p).to_bytes_be(),
+            y2: ZKP::exponentiate(&zkp.beta, &x, &zkp.p).to_bytes_be(),
+            r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+            r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+            c: proof.c.to_bytes_be(),
+            s: proof.s.to_bytes_be(),
+        };
+
+        let mut oversize = ZKP::to_padded_bytes(&proof.s, &zkp.q);
+        oversize.insert(0, 0);
+        let bad = [
+            VerifyProofRequest {
+                y1: vec![],
+                ..full.clone()
+            },
+            VerifyProofRequest {
+                s: oversize,
+                ..full.clone()
+            },
+            VerifyProofRequest {
+                c: zkp.q.to_bytes_be(),
+                ..full.clone()
+            },
+            VerifyProofRequest {
+                s: ((&proof.s + 1u32) % &zkp.q).to_bytes_be(),
+                ..full.clone()
+            },
+        ];
+        for request in bad.into_iter().chain([full]) {
+            auth_impl.verify_proof(Request::new(request)).await.unwrap();
+        }
+
+        let mut request = Request::new(StatsRequest {});
+        request
+            .metadata_mut()
+            .insert(ADMIN_TOKEN_HEADER, "s3cret".parse().unwrap());
+        let rejections = auth_impl
+            .stats(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .rejections;
+        let expected = [
+            ("empty", 1),
+            ("oversize", 1),
+            ("range", 0),
+            ("subgroup", 0),
+            ("challenge_range", 1),
+            ("equation", 1),
+        ];
+        assert_eq!(
+            rejections,
+            expected
+                .iter()
+                .map(|(gate, count)| (gate.to_string(), *count))
+                .collect()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_with_mocks() {
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let toy = || {
+            ZKP::builder(
+                BigUint::from(23u32),
+                BigUint::from(11u32),
+                BigUint::from(4u32),
+                BigUint::from(9u32),
+            )
+            .allow_insecure()
+            .build()
+            .unwrap()
+        };
+        let auth_impl = AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                group: toy(),
+                username_policy: UsernamePolicy {
+                    allow: vec![],
+                    deny: policy::parse_patterns("root"),
+                },
+                ..Default::default()
+            },
+            Box::new(clock.clone()),
+            FixedChallenges::new(vec![BigUint::from(4u32)]),
+        );
+
+        let status = auth_impl
+            .register(Request::new(register_request(
+                &toy(),
+                "root",
+                &BigUint::from(6u32),
+            )))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+
+        // the toy transcript, x = 6, k = 7, c = 4 gives s = 5
+        auth_impl
+            .register(Request::new(register_request(
+                &toy(),
+                "peggy",
+                &BigUint::from(6u32),
+            )))
+            .await
+            .unwrap();
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: vec![8],
+                r2: vec![4],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(challenge.c, vec![4]);
+        assert_eq!(
+            challenge.expires_at,
+            1_700_000_000 + CHALLENGE_TTL.as_secs()
+        );
+
+        let login = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: vec![5],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(login.expires_at, 1_700_000_000 + SESSION_TTL.as_secs());
+    }
+
+    #[tokio::test]
+    async fn test_username_policy() {
+        let auth_impl = AuthImpl {
+            username_policy: UsernamePolicy {
+                allow: vec![],
+                deny: policy::parse_patterns("admin*, root"),
+            },
+            ..Default::default()
+        };
+
+        for user in ["admin", "administrator", "root"] {
+            let status = auth_impl
+                .register(Request::new(RegisterRequest {
+                    user: user.to_string(),
+                    y1: vec![2],
+                    y2: vec![3],
+                    ..Default::default()
+                }))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::PermissionDenied);
+        }
+        assert!(auth_impl.store.get_user("admin").unwrap().is_none());
+
+        login(&auth_impl, &ZKP::new(), "peggy").await;
+    }
+
+    #[tokio::test]
+    async fn test_error_reasons() {
+        let auth_impl = AuthImpl::default();
+
+        let status = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "nobody".to_string(),
+                r1: vec![8],
+                r2: vec![4],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        let info = error_details::error_info(&status).unwrap();
+        assert_eq!(info.reason, "USER_NOT_FOUND");
+        assert_eq!(info.domain, error_details::ERROR_DOMAIN);
+
+        let status = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: "unknown".to_string(),
+                s: vec![5],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::AuthIdNotFound)
+        );
+
+        let status = auth_impl
+            .refresh_session(Request::new(RefreshSessionRequest {
+                session_id: "unknown".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_challenge_survives_restart() {
+        let path = env::temp_dir().join(format!("zkp-server-{}", ZKP::generate_random_string(12)));
+        let with_file_store = || AuthImpl {
+            store: Box::new(FileStore::open(&path).unwrap()),
+            ..Default::default()
+        };
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+
+        let auth_impl = with_file_store();
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+        let challenge = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        // what main does once the server has stopped
+        auth_impl.store.flush().unwrap();
+        drop(auth_impl);
+
+        let restarted = with_file_store();
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+        let answer = restarted
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            }))
+            .await;
+        assert!(answer.is_ok());
+
+        drop(restarted);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    struct UnavailableStore;
+
+    impl UserStore for UnavailableStore {
+        fn put_user(&self, _: UserAuthInfo) -> Result<(), StoreError> {
+            Err(StoreError::Unavailable("connection refused".to_string()))
+        }
+
+        fn get_user(&self, _: &str) -> Result<Option<UserAuthInfo>, StoreError> {
+            Err(StoreError::Unavailable("connection refused".to_string()))
+        }
+
+        fn users(&self) -> Result<Vec<UserAuthInfo>, StoreError> {
+            Err(StoreError::Unavailable("connection refused".to_string()))
+        }
+
+        fn put_auth_id(&self, _: AuthId, _: String) -> Result<(), StoreError> {
+            Err(StoreError::Unavailable("connection refused".to_string()))
+        }
+
+        fn get_auth_id(&self, _: &AuthId) -> Result<Option<String>, StoreError> {
+            Err(StoreError::Unavailable("connection refused".to_string()))
+        }
+
+        fn put_key_handle(&self, _: String, _: String) -> Result<(), StoreError> {
+            Err(StoreError::Unavailable("connection refused".to_string()))
+        }
+
+        fn get_key_handle(&self, _: &str) -> Result<Option<String>, StoreError> {
+            Err(StoreError::Unavailable("connection refused".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_message_size_limit() {
+        let auth_impl = AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                max_decoding_message_size: Some(1024),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthImpl::service(Arc::new(auth_impl)))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = AuthClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        client
+            .register(register_request(&zkp, "peggy", &x))
+            .await
+            .unwrap();
+
+        let oversized = RegisterRequest {
+            y1: vec![1; 2048],
+            ..register_request(&zkp, "victor", &x)
+        };
+        let status = client.register(oversized).await.unwrap_err();
+        assert_eq!(status.code(), Code::OutOfRange);
+    }
+
+    #[tokio::test]
+    async fn test_three_base_proof_over_grpc() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthImpl::service(Arc::new(AuthImpl::default())))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = AuthClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        let zkp = ZKP::new();
+        let bases = [
+            zkp.alpha.clone(),
+            zkp.beta.clone(),
+            zkp.alpha.pow(3u32) % &zkp.p,
+        ];
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let ys = zkp.multi_base_keys(&bases, &x);
+        let proof = zkp.prove_multi_base(&bases, &k, &x);
+        let bytes = |list: &[BigUint]| list.iter().map(BigUint::to_bytes_be).collect();
+        let request = VerifyMultiProofRequest {
+            group: String::new(),
+            bases: bytes(&bases),
+            ys: bytes(&ys),
+            rs: bytes(&proof.rs),
+            c: proof.c.to_bytes_be(),
+            s: proof.s.to_bytes_be(),
+        };
+
+        let response = client
+            .verify_multi_proof(request.clone())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response,
+            VerifyProofResponse {
+                valid: true,
+                reason: String::new(),
+            }
+        );
+
+        let mut wrong_key = request.clone();
+        wrong_key.ys[1] = zkp.multi_base_keys(&bases[1..2], &(&x + 1u32))[0].to_bytes_be();
+        let response = client
+            .verify_multi_proof(wrong_key)
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.valid);
+        assert_eq!(response.reason, VerifyError::ChallengeMismatch.to_string());
+
+        let mut short = request;
+        short.rs.pop();
+        let status = client.verify_multi_proof(short).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::MalformedProof)
+        );
+    }
+
+    #[cfg(feature = "rest")]
+    #[tokio::test]
+    async fn test_grpc_and_rest_share_state() {
+        use serde_json::{json, Value};
+
+        let grpc = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rest = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rest_addr = rest.local_addr().unwrap();
+        let mut client = AuthClient::connect(format!("http://{}", grpc.local_addr().unwrap()))
+            .await
+            .unwrap();
+        tokio::spawn(run_server(
+            Arc::new(AuthImpl::default()),
+            grpc,
+            None,
+            Some((rest, rest::ElementCodec::Hex)),
+            std::future::pending(),
+        ));
+        let post = |path: &str, body: Value| {
+            let request = hyper::Request::post(format!("http://{}{}", rest_addr, path))
+                .header("content-type", "application/json")
+                .body(hyper::Body::from(body.to_string()))
+                .unwrap();
+            async move {
+                let response = hyper::Client::new().request(request).await.unwrap();
+                assert!(response.status().is_success(), "{}", response.status());
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                serde_json::from_slice::<Value>(&body).unwrap()
+            }
+        };
+
+        // registered over gRPC, logged in over REST
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        client
+            .register(register_request(&zkp, "peggy", &x))
+            .await
+            .unwrap();
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = post(
+            "/challenge",
+            json!({
+                "user": "peggy",
+                "r1": ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_str_radix(16),
+                "r2": ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_str_radix(16),
+            }),
+        )
+        .await;
+        let c = BigUint::parse_bytes(challenge["c"].as_str().unwrap().as_bytes(), 16).unwrap();
+        let login = post(
+            "/verify",
+            json!({
+                "auth_id": challenge["auth_id"],
+                "s": zkp.solve(&k, &c, &x).to_str_radix(16),
+            }),
+        )
+        .await;
+
+        // and the session it opened is known to gRPC
+        client
+            .refresh_session(RefreshSessionRequest {
+                session_id: login["session_id"].as_str().unwrap().to_string(),
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_login_stream() {
+        use login_stream_request::Step;
+        use login_stream_response::Step as Reply;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthImpl::service(Arc::new(AuthImpl::default())))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = AuthClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let (steps, receiver) = mpsc::channel(4);
+        let mut replies = client
+            .login_stream(ReceiverStream::new(receiver))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // register and commit go out together, without waiting in between
+        let send = |step| LoginStreamRequest { step: Some(step) };
+        steps
+            .send(send(Step::Register(register_request(&zkp, "peggy", &x))))
+            .await
+            .unwrap();
+        steps
+            .send(send(Step::Commit(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            })))
+            .await
+            .unwrap();
+        let reply = replies.message().await.unwrap().unwrap();
+        assert!(matches!(reply.step, Some(Reply::Registered(_))));
+        let Some(Reply::Challenge(challenge)) = replies.message().await.unwrap().unwrap().step
+        else {
+            panic!("expected a challenge");
+        };
+
+        // the answer leaves auth_id to the stream
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), &x);
+        steps
+            .send(send(Step::Answer(AuthAnswerRequest {
+                s: s.to_bytes_be(),
+                ..Default::default()
+            })))
+            .await
+            .unwrap();
+        let Some(Reply::Session(session)) = replies.message().await.unwrap().unwrap().step else {
+            panic!("expected a session");
+        };
+        assert!(!session.session_id.is_empty());
+        assert!(replies.message().await.unwrap().is_none());
+
+        // a wrong answer ends the stream with the status of VerifyAuth
+        let k = ZKP::generate_random_below(&zkp.q);
+        let (steps, receiver) = mpsc::channel(4);
+        let mut replies = client
+            .login_stream(ReceiverStream::new(receiver))
+            .await
+            .unwrap()
+            .into_inner();
+        steps
+            .send(send(Step::Commit(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            })))
+            .await
+            .unwrap();
+        replies.message().await.unwrap().unwrap();
+        steps
+            .send(send(Step::Answer(AuthAnswerRequest {
+                s: vec![1],
+                ..Default::default()
+            })))
+            .await
+            .unwrap();
+        let status = replies.message().await.unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::InvalidProof)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stats_follow_registrations_challenges_and_sessions() {
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let auth_impl = AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                admin_token: Some("s3cret".to_string()),
+                ..Default::default()
+            },
+            Box::new(clock.clone()),
+            RandomChallenges,
+        );
+        let stats = || async {
+            let mut request = Request::new(StatsRequest {});
+            request
+                .metadata_mut()
+                .insert(ADMIN_TOKEN_HEADER, "s3cret".parse().unwrap());
+            let StatsResponse {
+                users,
+                pending_challenges,
+                active_sessions,
+                ..
+            } = auth_impl.stats(request).await.unwrap().into_inner();
+            (users, pending_challenges, active_sessions)
+        };
+        assert_eq!(stats().await, (0, 0, 0));
+
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap();
+        auth_impl
+            .register(Request::new(register_request(&zkp, "victor", &x)))
+            .await
+            .unwrap();
+        assert_eq!(stats().await, (2, 0, 0));
+
+        let k = ZKP::generate_random_below(&zkp.q);
+        auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "victor".to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        assert_eq!(stats().await, (2, 1, 0));
+
+        authenticate(&auth_impl, &zkp, "peggy", &x).await.unwrap();
+        assert_eq!(stats().await, (2, 2, 1));
+
+        // challenges lapse after a minute, sessions after fifteen
+        clock.advance(CHALLENGE_TTL);
+        assert_eq!(stats().await, (2, 0, 1));
+        clock.advance(SESSION_TTL);
+        assert_eq!(stats().await, (2, 0, 0));
+
+        let status = auth_impl
+            .stats(Request::new(StatsRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::AdminRequired)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let with_admin = || {
+            AuthImpl::new(
+                Box::new(MemoryStore::default()),
+                AuthConfig {
+                    admin_token: Some("s3cret".to_string()),
+                    ..Default::default()
+                },
+                Box::new(SystemClock),
+                RandomChallenges,
+            )
+        };
+        let admin = |token: &str| {
+            let mut request = Request::new(ExportUsersRequest {});
+            request
+                .metadata_mut()
+                .insert(ADMIN_TOKEN_HEADER, token.parse().unwrap());
+            request
+        };
+
+        let zkp = ZKP::new();
+        let source = with_admin();
+        let secrets: Vec<(&str, BigUint)> = ["peggy", "victor"]
+            .into_iter()
+            .map(|user| (user, ZKP::generate_random_below(&zkp.q)))
+            .collect();
+        for (user, x) in &secrets {
+            source
+                .register(Request::new(register_request(&zkp, user, x)))
+                .await
+                .unwrap();
+        }
+
+        for (auth_impl, request, code) in [
+            (
+                &source,
+                Request::new(ExportUsersRequest {}),
+                Code::Unauthenticated,
+            ),
+            (&source, admin("guess"), Code::Unauthenticated),
+            (
+                &AuthImpl::default(),
+                admin("s3cret"),
+                Code::PermissionDenied,
+            ),
+        ] {
+            let status = auth_impl.export_users(request).await.unwrap_err();
+            assert_eq!(status.code(), code);
+            assert_eq!(
+                error_details::error_reason(&status),
+                Some(ErrorReason::AdminRequired)
+            );
+        }
+
+        let users = source
+            .export_users(admin("s3cret"))
+            .await
+            .unwrap()
+            .into_inner()
+            .users;
+        let names: Vec<&str> = users.iter().map(|record| record.user.as_str()).collect();
+        assert_eq!(names, ["peggy", "victor"]);
+
+        let restored = with_admin();
+        let mut import = Request::new(ImportUsersRequest {
+            users: users.clone(),
+        });
+        *import.metadata_mut() = admin("s3cret").metadata().clone();
+        let response = restored.import_users(import).await.unwrap().into_inner();
+        assert_eq!(response.imported, 2);
+
+        // the restored server knows the same keys, so the same secrets log in
+        let exported_again = restored
+            .export_users(admin("s3cret"))
+            .await
+            .unwrap()
+            .into_inner()
+            .users;
+        assert_eq!(exported_again, users);
+        for (user, x) in &secrets {
+            authenticate(&restored, &zkp, user, x).await.unwrap();
+        }
+
+        let mut foreign = Request::new(ImportUsersRequest {
+            users: vec![UserRecord {
+                group: "rfc5114-2048-256".to_string(),
+                ..users[0].clone()
+            }],
+        });
+        *foreign.metadata_mut() = admin("s3cret").metadata().clone();
+        let status = with_admin().import_users(foreign).await.unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_store() {
+        let auth_impl = AuthImpl {
+            store: Box::new(UnavailableStore),
+            ..Default::default()
+        };
+
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let status = auth_impl
+            .register(Request::new(register_request(&zkp, "peggy", &x)))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+
+        let status = auth_impl
+            .create_auth_challenge(Request::new(AuthChallengeRequest {
+                user: "peggy".to_string(),
+                r1: vec![8],
+                r2: vec![4],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+
+        let status = auth_impl
+            .verify_auth(Request::new(AuthAnswerRequest {
+                auth_id: AuthId::generate().into(),
+                s: vec![5],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+
+    #[test]
+    fn test_refresh_extends_expiry_up_to_hard_cap() {
+        let created_at = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut session = Session::new(SessionClaims {
+            user_name: "peggy".to_string(),
+            group: ZKP::new().descriptor(),
+            authenticated_at: created_at,
+        });
+        assert_eq!(session.expires_at, created_at + SESSION_TTL);
+
+        let now = created_at + SESSION_TTL / 2;
+        assert_eq!(session.refresh(now), now + SESSION_TTL);
+
+        // keep refreshing before every expiry, the cap still ends the session
+        let max_expiry = created_at + SESSION_MAX_LIFETIME;
+        let mut now = now;
+        while now < max_expiry {
+            assert!(!session.is_expired(now));
+            let expires_at = session.refresh(now);
+            assert_eq!(expires_at, (now + SESSION_TTL).min(max_expiry));
+            now += SESSION_TTL / 2;
+        }
+        assert_eq!(session.expires_at, max_expiry);
+        assert!(session.is_expired(max_expiry));
+    }
+
+    #[tokio::test]
+    async fn test_session_claims_name_the_authenticated_user() {
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let auth_impl = AuthImpl {
+            clock: Box::new(clock.clone()),
+            ..Default::default()
+        };
+        let zkp = ZKP::new();
+        login(&auth_impl, &zkp, "victor").await;
+        let response = login(&auth_impl, &zkp, "peggy").await;
+
+        let session_id = response.session_id.parse().unwrap();
+        let sessions = auth_impl.sessions.lock().unwrap();
+        let session = &sessions[&session_id];
+        assert_eq!(
+            session.claims,
+            SessionClaims {
+                user_name: "peggy".to_string(),
+                group: zkp.descriptor(),
+                authenticated_at: clock.now(),
+            }
+        );
+        assert_eq!(unix_seconds(session.expires_at), response.expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_session() {
+        let auth_impl = AuthImpl::default();
+        let zkp = ZKP::new();
+        let login = login(&auth_impl, &zkp, "peggy").await;
+        assert!(login.expires_at > unix_seconds(SystemTime::now()));
+
+        let response = auth_impl
+            .refresh_session(Request::new(RefreshSessionRequest {
+                session_id: login.session_id,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.expires_at >= login.expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rejects_unknown_and_expired_sessions() {
+        let auth_impl = AuthImpl::default();
+
+        let status = auth_impl
+            .refresh_session(Request::new(RefreshSessionRequest {
+                session_id: "unknown".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        let logged_in_at = SystemTime::now() - SESSION_MAX_LIFETIME;
+        let expired = SessionId::generate();
+        auth_impl.sessions.lock().unwrap().insert(
+            expired,
+            Session::new(SessionClaims {
+                user_name: "peggy".to_string(),
+                group: ZKP::new().descriptor(),
+                authenticated_at: logged_in_at,
+            }),
+        );
+        let status = auth_impl
+            .refresh_session(Request::new(RefreshSessionRequest {
+                session_id: expired.into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert!(auth_impl.sessions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_config_from_env() {
+        let vars = [
+            ("SERVER_ADDRESS", "0.0.0.0:50051"),
+            ("SERVER_GROUP", "rfc5114-2048-256"),
+            ("SERVER_ALLOWED_USERS", "peggy,team-*"),
+            ("SERVER_MAX_DECODING_MESSAGE_SIZE", "65536"),
+            ("SERVER_ADMIN_TOKEN", "secret"),
+            ("SERVER_VERIFY_WORKERS", "4"),
+            ("SERVER_CLIENT_CERT_HEADER", "X-Client-Cert-Subject"),
+            ("SERVER_TRUSTED_PROXIES", "10.0.0.2, ::ffff:10.0.0.3"),
+            ("SERVER_CHALLENGE_TTL_SECS", "30"),
+            ("SERVER_SESSION_TTL_SECS", "3600"),
+            ("SERVER_REQUEST_TIMEOUT_MS", "1500"),
+        ];
+        for (variable, value) in vars {
+            env::set_var(variable, value);
+        }
+
+        let config = AuthConfig::from_env().unwrap();
+        assert_eq!(config.address, "0.0.0.0:50051");
+        assert_eq!(config.group.descriptor(), "rfc5114-2048-256");
+        assert!(config.retiring_group.is_none());
+        assert!(config.username_policy.is_allowed("team-a"));
+        assert!(!config.username_policy.is_allowed("victor"));
+        assert_eq!(config.max_decoding_message_size, Some(65536));
+        assert_eq!(config.max_encoding_message_size, None);
+        assert_eq!(config.admin_token.as_deref(), Some("secret"));
+        assert_eq!(config.verify_workers, Some(4));
+        assert_eq!(config.tls, None);
+        assert_eq!(
+            config.client_certs,
+            Some(ClientCerts::Proxy {
+                header: "x-client-cert-subject".to_string(),
+                proxies: vec!["10.0.0.2".parse().unwrap(), "10.0.0.3".parse().unwrap()],
+            })
+        );
+        assert_eq!(config.challenge_ttl, Duration::from_secs(30));
+        assert_eq!(config.session_ttl, Duration::from_secs(3600));
+        assert_eq!(config.request_timeout, Some(Duration::from_millis(1500)));
+
+        for (variable, value) in [
+            ("SERVER_VERIFY_WORKERS", "0"),
+            ("SERVER_CHALLENGE_TTL_SECS", "a minute"),
+            ("SERVER_SESSION_TTL_SECS", "86400"),
+            ("SERVER_GROUP", "rfc5114-512"),
+            ("SERVER_TRUSTED_PROXIES", ""),
+            ("SERVER_TRUSTED_PROXIES", "proxy.internal"),
+        ] {
+            let previous = env::var(variable).unwrap();
+            env::set_var(variable, value);
+            let err = AuthConfig::from_env().err().unwrap();
+            assert_eq!(err.variable, variable);
+            assert!(err
+                .to_string()
+                .starts_with(&format!("invalid {}: ", variable)));
+            env::set_var(variable, previous);
+        }
+
+        // the header can't stand in for a handshake the server does itself
+        env::set_var("SERVER_TLS_CERT", "server.pem");
+        env::set_var("SERVER_TLS_KEY", "server.key");
+        env::set_var("SERVER_TLS_CLIENT_CA", "ca.pem");
+        let err = AuthConfig::from_env().err().unwrap();
+        assert_eq!(err.variable, "SERVER_CLIENT_CERT_HEADER");
+        for (variable, _) in vars {
+            env::remove_var(variable);
+        }
+        let config = AuthConfig::from_env().unwrap();
+        assert_eq!(
+            config.tls,
+            Some(TlsFiles {
+                cert: "server.pem".into(),
+                key: "server.key".into(),
+                client_ca: Some("ca.pem".into()),
+            })
+        );
+        assert_eq!(config.client_certs, Some(ClientCerts::Tls));
+        env::remove_var("SERVER_TLS_KEY");
+        assert_eq!(
+            AuthConfig::from_env().err().unwrap().variable,
+            "SERVER_TLS_KEY"
+        );
+        env::remove_var("SERVER_TLS_CERT");
+        assert_eq!(
+            AuthConfig::from_env().err().unwrap().variable,
+            "SERVER_TLS_CERT"
+        );
+        env::remove_var("SERVER_TLS_CLIENT_CA");
+
+        let defaults = AuthConfig::from_env().unwrap();
+        assert_eq!(defaults.address, DEFAULT_ADDRESS);
+        assert_eq!(defaults.challenge_ttl, CHALLENGE_TTL);
+        assert_eq!(defaults.session_ttl, SESSION_TTL);
+    }
+
+    /// Register and log in over gRPC, the way the client does
+    async fn grpc_login(
+        client: &mut AuthClient<tonic::transport::Channel>,
+        zkp: &ZKP,
+        user: &str,
+    ) -> Result<(), Status> {
+        let x = ZKP::generate_random_below(&zkp.q);
+        client.register(register_request(zkp, user, &x)).await?;
+        grpc_answer(client, zkp, user, &x).await
+    }
+
+    /// Log in over gRPC as `user`, registered with the secret `x`
+    async fn grpc_answer(
+        client: &mut AuthClient<tonic::transport::Channel>,
+        zkp: &ZKP,
+        user: &str,
+        x: &BigUint,
+    ) -> Result<(), Status> {
+        let k = ZKP::generate_random_below(&zkp.q);
+        let challenge = client
+            .create_auth_challenge(AuthChallengeRequest {
+                user: user.to_string(),
+                r1: ZKP::exponentiate(&zkp.alpha, &k, &zkp.p).to_bytes_be(),
+                r2: ZKP::exponentiate(&zkp.beta, &k, &zkp.p).to_bytes_be(),
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+        let s = zkp.solve(&k, &BigUint::from_bytes_be(&challenge.c), x);
+        client
+            .verify_auth(AuthAnswerRequest {
+                auth_id: challenge.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            })
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_cert_over_tls() {
+        use tonic::transport::{ClientTlsConfig, Endpoint};
+
+        let certs = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/certs");
+        let tls = TlsFiles {
+            cert: certs.join("server.pem"),
+            key: certs.join("server.key"),
+            client_ca: Some(certs.join("ca.pem")),
+        };
+        let auth_impl = AuthImpl::new(
+            Box::new(MemoryStore::default()),
+            AuthConfig {
+                tls: Some(tls.clone()),
+                client_certs: Some(ClientCerts::Tls),
+                ..Default::default()
+            },
+            Box::new(SystemClock),
+            RandomChallenges,
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("https://{}", listener.local_addr().unwrap());
+        tokio::spawn(run_server(
+            Arc::new(auth_impl),
+            listener,
+            Some(tls.load().unwrap()),
+            #[cfg(feature = "rest")]
+            None,
+            std::future::pending(),
+        ));
+        let connect = |name: &str| {
+            let read = |file: String| fs::read(certs.join(file)).unwrap();
+            let tls = ClientTlsConfig::new()
+                .domain_name("localhost")
+                .ca_certificate(Certificate::from_pem(read("ca.pem".to_string())))
+                .identity(Identity::from_pem(
+                    read(format!("{}.pem", name)),
+                    read(format!("{}.key", name)),
+                ));
+            let endpoint = Endpoint::from_shared(addr.clone())
+                .unwrap()
+                .tls_config(tls)
+                .unwrap();
+            async move { AuthClient::new(endpoint.connect().await.unwrap()) }
+        };
+
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let mut peggy = connect("peggy").await;
+        peggy
+            .register(register_request(&zkp, "peggy", &x))
+            .await
+            .unwrap();
+
+        // a certificate from the same CA, but not the one peggy's key is bound to
+        let mut mallory = connect("mallory").await;
+        let status = grpc_answer(&mut mallory, &zkp, "peggy", &x)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(
+            error_details::error_reason(&status),
+            Some(ErrorReason::ClientCertMismatch)
+        );
+
+        grpc_answer(&mut peggy, &zkp, "peggy", &x).await.unwrap();
+    }
+
+    /// The whole server in process, with `BENCH_CLIENTS` clients (default 16)
+    /// each registering and logging in `BENCH_LOGINS` users (default 25) over
+    /// their own connection. Reports logins per second and the p50 and p99
+    /// latency of one register and login, for sizing a deployment.
+    /// Run with `cargo test --release --bin server -- --ignored --nocapture bench_`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn bench_concurrent_logins() {
+        let setting = |name: &str, default: usize| {
+            env::var(name).map_or(default, |value| {
+                value.parse().expect("invalid bench setting")
+            })
+        };
+        let clients = setting("BENCH_CLIENTS", 16);
+        let logins = setting("BENCH_LOGINS", 25);
+
+        for group in [Group::Rfc5114_1024_160, Group::Rfc5114_2048_256] {
+            let auth_impl = AuthImpl::new(
+                Box::new(MemoryStore::default()),
+                AuthConfig {
+                    group: group.zkp(),
+                    ..Default::default()
+                },
+                Box::new(SystemClock),
+                RandomChallenges,
+            );
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = format!("http://{}", listener.local_addr().unwrap());
+            let server = tokio::spawn(
+                Server::builder()
+                    .add_service(AuthImpl::service(Arc::new(auth_impl)))
+                    .serve_with_incoming(TcpListenerStream::new(listener)),
+            );
+
+            let started = Instant::now();
+            let tasks: Vec<_> = (0..clients)
+                .map(|i| {
+                    let addr = addr.clone();
+                    tokio::spawn(async move {
+                        let zkp = group.zkp();
+                        let mut client = AuthClient::connect(addr).await.unwrap();
+                        let mut latencies = Vec::with_capacity(logins);
+                        for j in 0..logins {
+                            let start = Instant::now();
+                            grpc_login(&mut client, &zkp, &format!("user-{}-{}", i, j))
+                                .await
+                                .unwrap();
+                            latencies.push(start.elapsed());
+                        }
+                        latencies
+                    })
+                })
+                .collect();
+            let mut latencies = Vec::with_capacity(clients * logins);
+            for task in tasks {
+                latencies.extend(task.await.unwrap());
+            }
+            let elapsed = started.elapsed();
+            server.abort();
+
+            latencies.sort();
+            let percentile = |p: usize| latencies[(latencies.len() * p).div_ceil(100) - 1];
+            println!(
+                "[{}] {} clients x {} logins: {:.1} logins/s, p50 {:?}, p99 {:?}",
+                group.name(),
+                clients,
+                logins,
+                latencies.len() as f64 / elapsed.as_secs_f64(),
+                percentile(50),
+                percentile(99),
+            );
+        }
+    }
 }
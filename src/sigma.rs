@@ -0,0 +1,402 @@
+use num_bigint::BigUint;
+
+use crate::{Commitment, Entropy, Nonce, OkamotoProof, Prover, Response, VerifyError, ZKP};
+
+/// The verifier's half of a sigma protocol: draw a challenge once the
+/// commitment is in, then accept or reject the response. It only ever sees
+/// public values, so a `Verifier` can implement it and hold nothing secret.
+///
+/// With `SigmaProver`, the three moves every proof in this crate is built
+/// from. Code written against the two runs with any of `ChaumPedersen`,
+/// `Schnorr` or `Okamoto`, see `run_round`. The server's challenge and answer
+/// handlers run on a `Verifier` through this half, the client's login on a
+/// `Prover` through the other; the proof of possession sent at registration is
+/// non-interactive and has no challenge move to abstract.
+pub trait SigmaVerifier {
+    /// What is proven, public
+    type Statement;
+    type Commitment;
+    type Challenge;
+    type Response;
+    /// Why `verify` turned a proof away
+    type Error;
+
+    /// Drawn once the commitment is in
+    fn challenge(&self) -> Self::Challenge;
+
+    fn verify(
+        &self,
+        statement: &Self::Statement,
+        commitment: &Self::Commitment,
+        challenge: &Self::Challenge,
+        response: &Self::Response,
+    ) -> Result<(), Self::Error>;
+}
+
+/// The prover's half of a sigma protocol, answering for the witness it holds.
+/// See `SigmaVerifier`.
+pub trait SigmaProver {
+    type Statement;
+    type Commitment;
+    /// Kept between `commit` and `respond`, used up by `respond`
+    type Nonce;
+    type Challenge;
+    type Response;
+
+    /// The statement its witness proves
+    fn statement(&self) -> Self::Statement;
+
+    fn commit(&self) -> (Self::Commitment, Self::Nonce);
+
+    fn respond(&self, nonce: Self::Nonce, challenge: &Self::Challenge) -> Self::Response;
+}
+
+/// One interactive round of `prover` against `verifier`, checked against
+/// `statement`
+pub fn run_round<P, V>(prover: &P, verifier: &V, statement: &V::Statement) -> Result<(), V::Error>
+where
+    P: SigmaProver,
+    V: SigmaVerifier<
+        Statement = P::Statement,
+        Commitment = P::Commitment,
+        Challenge = P::Challenge,
+        Response = P::Response,
+    >,
+{
+    let (commitment, nonce) = prover.commit();
+    let challenge = verifier.challenge();
+    let response = prover.respond(nonce, &challenge);
+    verifier.verify(statement, &commitment, &challenge, &response)
+}
+
+/// The protocol of this crate: x is the discrete log of y1 to alpha and of
+/// y2 to beta. Its prover is `Prover`.
+pub struct ChaumPedersen(pub ZKP);
+
+/// x is the discrete log of y to alpha, proven by a `SchnorrProver`
+pub struct Schnorr(pub ZKP);
+
+/// The prover of `Schnorr`, holding x
+pub struct SchnorrProver {
+    zkp: ZKP,
+    x: BigUint,
+    entropy: Option<Entropy>,
+}
+
+impl SchnorrProver {
+    pub fn new(zkp: ZKP, x: BigUint) -> Self {
+        SchnorrProver {
+            zkp,
+            x,
+            entropy: None,
+        }
+    }
+
+    /// Draws its nonces from `entropy`, as `Prover::with_entropy`
+    pub fn with_entropy(mut self, entropy: Entropy) -> Self {
+        self.entropy = Some(entropy);
+        self
+    }
+}
+
+/// (x, r) is a representation y = alpha^x * beta^r, see `ZKP::prove_okamoto`,
+/// proven by an `OkamotoProver`
+pub struct Okamoto(pub ZKP);
+
+/// The prover of `Okamoto`, holding (x, r)
+pub struct OkamotoProver {
+    zkp: ZKP,
+    x: BigUint,
+    r: BigUint,
+    entropy: Option<Entropy>,
+}
+
+impl OkamotoProver {
+    pub fn new(zkp: ZKP, x: BigUint, r: BigUint) -> Self {
+        OkamotoProver {
+            zkp,
+            x,
+            r,
+            entropy: None,
+        }
+    }
+
+    /// Draws its nonces from `entropy`, as `Prover::with_entropy`
+    pub fn with_entropy(mut self, entropy: Entropy) -> Self {
+        self.entropy = Some(entropy);
+        self
+    }
+}
+
+/// Nonce below q, from `entropy` if the prover was given one
+fn random_nonce(zkp: &ZKP, entropy: &Option<Entropy>) -> BigUint {
+    match entropy {
+        Some(entropy) => ZKP::generate_random_below_with(&zkp.q, entropy),
+        None => ZKP::generate_random_below(&zkp.q),
+    }
+}
+
+impl SigmaVerifier for ChaumPedersen {
+    /// (y1, y2)
+    type Statement = (BigUint, BigUint);
+    type Commitment = Commitment;
+    type Challenge = BigUint;
+    type Response = Response;
+    type Error = VerifyError;
+
+    fn challenge(&self) -> BigUint {
+        ZKP::generate_random_below(&self.0.q)
+    }
+
+    fn verify(
+        &self,
+        (y1, y2): &(BigUint, BigUint),
+        commitment: &Commitment,
+        c: &BigUint,
+        response: &Response,
+    ) -> Result<(), VerifyError> {
+        let Commitment { r1, r2 } = commitment;
+        self.0.verify_checked(r1, r2, y1, y2, c, &response.s)
+    }
+}
+
+/// x stays in its `SecretBackend`
+impl SigmaProver for Prover {
+    /// (y1, y2)
+    type Statement = (BigUint, BigUint);
+    type Commitment = Commitment;
+    type Nonce = Nonce;
+    type Challenge = BigUint;
+    type Response = Response;
+
+    fn statement(&self) -> (BigUint, BigUint) {
+        self.public_key()
+    }
+
+    fn commit(&self) -> (Commitment, Nonce) {
+        Prover::commit(self)
+    }
+
+    fn respond(&self, nonce: Nonce, c: &BigUint) -> Response {
+        Prover::respond(self, nonce, c)
+    }
+}
+
+impl SigmaVerifier for Schnorr {
+    /// y
+    type Statement = BigUint;
+    /// t = alpha^k mod p
+    type Commitment = BigUint;
+    type Challenge = BigUint;
+    type Response = Response;
+    type Error = VerifyError;
+
+    fn challenge(&self) -> BigUint {
+        ZKP::generate_random_below(&self.0.q)
+    }
+
+    /// t = alpha^s * y^c
+    fn verify(
+        &self,
+        y: &BigUint,
+        t: &BigUint,
+        c: &BigUint,
+        response: &Response,
+    ) -> Result<(), VerifyError> {
+        let zkp = &self.0;
+        zkp.validate_challenge(c)?;
+        if response.s >= zkp.q {
+            return Err(VerifyError::OutOfRange("s"));
+        }
+        if !zkp.is_in_subgroup(y) {
+            return Err(VerifyError::NotInSubgroup("y"));
+        }
+        let solution = ZKP::exponentiate(&zkp.alpha, &response.s, &zkp.p)
+            * ZKP::exponentiate(y, c, &zkp.p)
+            % &zkp.p;
+        if *t != solution {
+            return Err(VerifyError::R1Mismatch);
+        }
+        Ok(())
+    }
+}
+
+impl SigmaProver for SchnorrProver {
+    type Statement = BigUint;
+    type Commitment = BigUint;
+    type Nonce = Nonce;
+    type Challenge = BigUint;
+    type Response = Response;
+
+    fn statement(&self) -> BigUint {
+        ZKP::exponentiate(&self.zkp.alpha, &self.x, &self.zkp.p)
+    }
+
+    fn commit(&self) -> (BigUint, Nonce) {
+        let k = random_nonce(&self.zkp, &self.entropy);
+        (
+            ZKP::exponentiate(&self.zkp.alpha, &k, &self.zkp.p),
+            Nonce(k),
+        )
+    }
+
+    fn respond(&self, nonce: Nonce, c: &BigUint) -> Response {
+        Response {
+            s: self.zkp.solve(&nonce.0, c, &self.x),
+        }
+    }
+}
+
+impl SigmaVerifier for Okamoto {
+    /// y
+    type Statement = BigUint;
+    /// t = alpha^k1 * beta^k2 mod p
+    type Commitment = BigUint;
+    type Challenge = BigUint;
+    type Response = OkamotoProof;
+    type Error = VerifyError;
+
+    fn challenge(&self) -> BigUint {
+        ZKP::generate_random_below(&self.0.q)
+    }
+
+    fn verify(
+        &self,
+        y: &BigUint,
+        t: &BigUint,
+        c: &BigUint,
+        proof: &OkamotoProof,
+    ) -> Result<(), VerifyError> {
+        let zkp = &self.0;
+        zkp.validate_challenge(c)?;
+        for (name, s) in [("s1", &proof.s1), ("s2", &proof.s2)] {
+            if *s >= zkp.q {
+                return Err(VerifyError::OutOfRange(name));
+            }
+        }
+        if !zkp.verify_okamoto(t, y, c, proof) {
+            return Err(VerifyError::R1Mismatch);
+        }
+        Ok(())
+    }
+}
+
+impl SigmaProver for OkamotoProver {
+    type Statement = BigUint;
+    type Commitment = BigUint;
+    /// (k1, k2)
+    type Nonce = (Nonce, Nonce);
+    type Challenge = BigUint;
+    type Response = OkamotoProof;
+
+    fn statement(&self) -> BigUint {
+        self.zkp.okamoto_commit(&self.x, &self.r)
+    }
+
+    fn commit(&self) -> (BigUint, (Nonce, Nonce)) {
+        let k1 = random_nonce(&self.zkp, &self.entropy);
+        let k2 = random_nonce(&self.zkp, &self.entropy);
+        (self.zkp.okamoto_commit(&k1, &k2), (Nonce(k1), Nonce(k2)))
+    }
+
+    fn respond(&self, (k1, k2): (Nonce, Nonce), c: &BigUint) -> OkamotoProof {
+        self.zkp.prove_okamoto(&k1.0, &k2.0, c, &self.x, &self.r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FixedChallenges, Verifier};
+
+    /// Runs rounds honestly, then with a prover for another statement
+    fn check<P, V>(verifier: &V, prover: &P, other: &P)
+    where
+        P: SigmaProver,
+        V: SigmaVerifier<
+            Statement = P::Statement,
+            Commitment = P::Commitment,
+            Challenge = P::Challenge,
+            Response = P::Response,
+        >,
+    {
+        let statement = prover.statement();
+        for _ in 0..5 {
+            assert!(run_round(prover, verifier, &statement).is_ok());
+            assert!(run_round(other, verifier, &statement).is_err());
+        }
+    }
+
+    #[test]
+    fn test_generic_round_for_each_protocol() {
+        // a toy group would let the wrong witness through one time in q
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let other = (&x + 1u32) % &zkp.q;
+
+        check(
+            &ChaumPedersen(ZKP::new()),
+            &Prover::new(ZKP::new(), x.clone()),
+            &Prover::new(ZKP::new(), other.clone()),
+        );
+        check(
+            &Schnorr(ZKP::new()),
+            &SchnorrProver::new(ZKP::new(), x.clone()),
+            &SchnorrProver::new(ZKP::new(), other.clone()),
+        );
+        let r = ZKP::generate_random_below(&zkp.q);
+        check(
+            &Okamoto(zkp),
+            &OkamotoProver::new(ZKP::new(), x, r.clone()),
+            &OkamotoProver::new(ZKP::new(), other, r),
+        );
+    }
+
+    #[test]
+    fn test_chaum_pedersen_matches_prover_and_verifier() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let protocol = ChaumPedersen(ZKP::new());
+        let prover = Prover::new(ZKP::new(), x);
+        let verifier = Verifier::new(zkp);
+        let statement = SigmaProver::statement(&prover);
+        assert_eq!(statement, prover.public_key());
+
+        // the trait's moves and the concrete ones are interchangeable
+        let (y1, y2) = prover.public_key();
+        let (commitment, nonce) = SigmaProver::commit(&prover);
+        let c = verifier.challenge();
+        let response = prover.respond(nonce, &c);
+        assert_eq!(
+            verifier.verify(&commitment.r1, &commitment.r2, &y1, &y2, &c, &response.s),
+            Ok(())
+        );
+        let (commitment, nonce) = prover.commit();
+        let c = SigmaVerifier::challenge(&protocol);
+        let response = SigmaProver::respond(&prover, nonce, &c);
+        assert_eq!(
+            SigmaVerifier::verify(&protocol, &statement, &commitment, &c, &response),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_verifier_runs_the_generic_round() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let other = (&x + 1u32) % &zkp.q;
+        let c = ZKP::generate_random_below(&zkp.q);
+        let verifier = Verifier::with_challenge_source(zkp, FixedChallenges::new(vec![c.clone()]));
+        let prover = Prover::new(ZKP::new(), x);
+        let statement = prover.statement();
+        assert!(run_round(&prover, &verifier, &statement).is_ok());
+        // the challenge is the verifier's own, from its source
+        assert_eq!(SigmaVerifier::challenge(&verifier), c);
+        let impostor = Prover::new(ZKP::new(), other);
+        assert_eq!(
+            run_round(&impostor, &verifier, &statement),
+            Err(VerifyError::R1Mismatch)
+        );
+        assert!(verifier.rejections().snapshot().contains(&("equation", 1)));
+    }
+}
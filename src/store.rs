@@ -0,0 +1,566 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use num_bigint::BigUint;
+
+use crate::AuthId;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UserAuthInfo {
+    // registration
+    pub user_name: String,
+    /// Descriptor of the group y1 and y2 live in
+    pub group: String,
+    /// Handle issued for this registration's y1 and y2
+    pub key_handle: String,
+    pub y1: BigUint,
+    pub y2: BigUint,
+    /// Client certificate subject the key was registered over, empty when unbound
+    pub cert_subject: String,
+    /// y1, y2 and cert_subject as sealed by an `EncryptedStore`, which leaves
+    /// those three empty. Empty otherwise
+    pub sealed: Vec<u8>,
+    // authorization
+    pub r1: BigUint,
+    pub r2: BigUint,
+    /// H(r1 || r2) if the prover only committed to r1 and r2, they are then
+    /// revealed with the answer. Empty otherwise
+    pub commitment_hash: Vec<u8>,
+    pub challenge_expires_at: Option<SystemTime>,
+    /// What the pending challenge was requested for, the answer must match it
+    pub context: String,
+    // verification
+    pub c: BigUint,
+    /// AuthId the pending c was issued under, answers for any other auth_id are stale
+    pub auth_id: Option<AuthId>,
+    pub s: BigUint,
+    pub session_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreError {
+    /// The backend can't be reached right now, the call may be retried
+    Unavailable(String),
+    /// The backend answered but something is wrong with it or its data
+    Internal(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Unavailable(reason) => write!(f, "store unavailable: {}", reason),
+            StoreError::Internal(reason) => write!(f, "store error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/// Where the verifier keeps registered users and their pending challenges.
+pub trait UserStore: Send + Sync {
+    /// Inserts the user or replaces it if the name is already taken
+    fn put_user(&self, user: UserAuthInfo) -> Result<(), StoreError>;
+
+    fn get_user(&self, user_name: &str) -> Result<Option<UserAuthInfo>, StoreError>;
+
+    /// Every registered user, sorted by name
+    fn users(&self) -> Result<Vec<UserAuthInfo>, StoreError>;
+
+    /// Links a freshly issued auth_id to the user who must answer it
+    fn put_auth_id(&self, auth_id: AuthId, user_name: String) -> Result<(), StoreError>;
+
+    fn get_auth_id(&self, auth_id: &AuthId) -> Result<Option<String>, StoreError>;
+
+    /// Links a key handle to the user it was issued to
+    fn put_key_handle(&self, key_handle: String, user_name: String) -> Result<(), StoreError>;
+
+    fn get_key_handle(&self, key_handle: &str) -> Result<Option<String>, StoreError>;
+
+    /// Stores a newly registered user and links their key handle to them. The
+    /// default makes two calls, stores that can should do both in one update so
+    /// a failure between them can't leave a handle without its user.
+    fn put_registration(&self, user: UserAuthInfo) -> Result<(), StoreError> {
+        let (key_handle, user_name) = (user.key_handle.clone(), user.user_name.clone());
+        self.put_user(user)?;
+        self.put_key_handle(key_handle, user_name)
+    }
+
+    /// Stores the user with their new challenge and links its auth_id to them,
+    /// like `put_registration` in one update where the store can
+    fn put_challenge(&self, user: UserAuthInfo, auth_id: AuthId) -> Result<(), StoreError> {
+        let user_name = user.user_name.clone();
+        self.put_user(user)?;
+        self.put_auth_id(auth_id, user_name)
+    }
+
+    /// Persists whatever is only held in memory, called on graceful shutdown
+    fn flush(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+/// In-memory store, everything is lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    state: Mutex<MemoryState>,
+}
+
+/// Everything behind the one lock, so paired updates happen together
+#[derive(Debug, Default)]
+struct MemoryState {
+    user_info: HashMap<String, UserAuthInfo>,
+    auth_user: HashMap<AuthId, String>,
+    key_handles: HashMap<String, String>,
+}
+
+impl MemoryState {
+    /// Drops the links a panic mid-update may have left half made: auth_ids and
+    /// key handles of users that aren't stored, and pending challenges whose
+    /// auth_id was never linked. The latter have to be requested again.
+    fn repair(&mut self) {
+        let MemoryState {
+            user_info,
+            auth_user,
+            key_handles,
+        } = self;
+        auth_user.retain(|_, user_name| user_info.contains_key(user_name));
+        key_handles.retain(|_, user_name| user_info.contains_key(user_name));
+        for user in user_info.values_mut() {
+            if user.auth_id.is_some_and(|id| !auth_user.contains_key(&id)) {
+                user.auth_id = None;
+            }
+            if !user.key_handle.is_empty() {
+                key_handles
+                    .entry(user.key_handle.clone())
+                    .or_insert_with(|| user.user_name.clone());
+            }
+        }
+    }
+}
+
+impl MemoryStore {
+    /// Locks the state, repairing it first if a thread panicked while holding it
+    fn state(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|poisoned| {
+            let mut state = poisoned.into_inner();
+            state.repair();
+            self.state.clear_poison();
+            state
+        })
+    }
+}
+
+impl UserStore for MemoryStore {
+    fn put_user(&self, user: UserAuthInfo) -> Result<(), StoreError> {
+        let state = &mut self.state();
+        state.user_info.insert(user.user_name.clone(), user);
+        Ok(())
+    }
+
+    fn get_user(&self, user_name: &str) -> Result<Option<UserAuthInfo>, StoreError> {
+        Ok(self.state().user_info.get(user_name).cloned())
+    }
+
+    fn users(&self) -> Result<Vec<UserAuthInfo>, StoreError> {
+        let mut users: Vec<UserAuthInfo> = self.state().user_info.values().cloned().collect();
+        users.sort_by(|a, b| a.user_name.cmp(&b.user_name));
+        Ok(users)
+    }
+
+    fn put_auth_id(&self, auth_id: AuthId, user_name: String) -> Result<(), StoreError> {
+        let state = &mut self.state();
+        state.auth_user.insert(auth_id, user_name);
+        Ok(())
+    }
+
+    fn get_auth_id(&self, auth_id: &AuthId) -> Result<Option<String>, StoreError> {
+        Ok(self.state().auth_user.get(auth_id).cloned())
+    }
+
+    fn put_key_handle(&self, key_handle: String, user_name: String) -> Result<(), StoreError> {
+        let state = &mut self.state();
+        state.key_handles.insert(key_handle, user_name);
+        Ok(())
+    }
+
+    fn get_key_handle(&self, key_handle: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.state().key_handles.get(key_handle).cloned())
+    }
+
+    fn put_registration(&self, user: UserAuthInfo) -> Result<(), StoreError> {
+        let state = &mut self.state();
+        state
+            .key_handles
+            .insert(user.key_handle.clone(), user.user_name.clone());
+        state.user_info.insert(user.user_name.clone(), user);
+        Ok(())
+    }
+
+    fn put_challenge(&self, user: UserAuthInfo, auth_id: AuthId) -> Result<(), StoreError> {
+        let state = &mut self.state();
+        state.auth_user.insert(auth_id, user.user_name.clone());
+        state.user_info.insert(user.user_name.clone(), user);
+        Ok(())
+    }
+}
+
+const SNAPSHOT_HEADER: &str = "zkp-store v1";
+
+impl MemoryStore {
+    /// One tab separated line per entry, strings hex encoded so they can't
+    /// break the format
+    fn snapshot(&self) -> String {
+        let state = self.state();
+        let mut lines = vec![SNAPSHOT_HEADER.to_string()];
+        for user in state.user_info.values() {
+            let expires_at = match user.challenge_expires_at {
+                Some(at) => at
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos()
+                    .to_string(),
+                None => "-".to_string(),
+            };
+            let fields = [
+                hex_string(&user.user_name),
+                hex_string(&user.group),
+                hex_string(&user.key_handle),
+                format!("{:x}", user.y1),
+                format!("{:x}", user.y2),
+                format!("{:x}", user.r1),
+                format!("{:x}", user.r2),
+                expires_at,
+                hex_string(&user.context),
+                format!("{:x}", user.c),
+                format!("{:x}", user.s),
+                hex_string(&user.session_id),
+                hex_bytes(&user.commitment_hash),
+                user.auth_id
+                    .map_or("-".to_string(), |id| hex_string(id.as_str())),
+                hex_string(&user.cert_subject),
+                hex_bytes(&user.sealed),
+            ];
+            lines.push(format!("user\t{}", fields.join("\t")));
+        }
+        let auth_ids = state
+            .auth_user
+            .iter()
+            .map(|(id, user)| ("auth", id.as_str(), user));
+        let handles = state
+            .key_handles
+            .iter()
+            .map(|(handle, user)| ("handle", handle.as_str(), user));
+        for (kind, key, user_name) in auth_ids.chain(handles) {
+            lines.push(format!(
+                "{}\t{}\t{}",
+                kind,
+                hex_string(key),
+                hex_string(user_name)
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn from_snapshot(snapshot: &str) -> Result<MemoryStore, StoreError> {
+        let corrupt = |line: usize| StoreError::Internal(format!("corrupt snapshot line {}", line));
+        let mut lines = snapshot.lines().enumerate();
+        if lines.next().map(|(_, header)| header) != Some(SNAPSHOT_HEADER) {
+            return Err(corrupt(1));
+        }
+
+        let store = MemoryStore::default();
+        for (index, line) in lines {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let string = |i: usize| unhex_string(fields[i]).ok_or_else(|| corrupt(index + 1));
+            let number = |i: usize| {
+                BigUint::parse_bytes(fields[i].as_bytes(), 16).ok_or_else(|| corrupt(index + 1))
+            };
+            match (fields[0], fields.len()) {
+                ("user", 17) => {
+                    let challenge_expires_at = match fields[8] {
+                        "-" => None,
+                        nanos => {
+                            let nanos: u64 = nanos.parse().map_err(|_| corrupt(index + 1))?;
+                            Some(UNIX_EPOCH + Duration::from_nanos(nanos))
+                        }
+                    };
+                    store.put_user(UserAuthInfo {
+                        user_name: string(1)?,
+                        group: string(2)?,
+                        key_handle: string(3)?,
+                        y1: number(4)?,
+                        y2: number(5)?,
+                        r1: number(6)?,
+                        r2: number(7)?,
+                        challenge_expires_at,
+                        context: string(9)?,
+                        c: number(10)?,
+                        s: number(11)?,
+                        session_id: string(12)?,
+                        commitment_hash: unhex_bytes(fields[13])
+                            .ok_or_else(|| corrupt(index + 1))?,
+                        auth_id: match fields[14] {
+                            "-" => None,
+                            _ => Some(string(14)?.parse().map_err(|_| corrupt(index + 1))?),
+                        },
+                        cert_subject: string(15)?,
+                        sealed: unhex_bytes(fields[16]).ok_or_else(|| corrupt(index + 1))?,
+                    })?;
+                }
+                ("auth", 3) => {
+                    let auth_id = string(1)?.parse().map_err(|_| corrupt(index + 1))?;
+                    store.put_auth_id(auth_id, string(2)?)?
+                }
+                ("handle", 3) => store.put_key_handle(string(1)?, string(2)?)?,
+                _ => return Err(corrupt(index + 1)),
+            }
+        }
+        Ok(store)
+    }
+}
+
+fn hex_string(s: &str) -> String {
+    hex_bytes(s.as_bytes())
+}
+
+fn unhex_string(hex: &str) -> Option<String> {
+    String::from_utf8(unhex_bytes(hex)?).ok()
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// `MemoryStore` backed by a snapshot file: loaded on open and written back on
+/// `flush`, which the server calls when it shuts down, so pending challenges
+/// outlive a restart.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    memory: MemoryStore,
+}
+
+impl FileStore {
+    /// Starts empty when there is no file at `path` yet
+    pub fn open(path: impl Into<PathBuf>) -> Result<FileStore, StoreError> {
+        let path = path.into();
+        let memory = match fs::read_to_string(&path) {
+            Ok(snapshot) => MemoryStore::from_snapshot(&snapshot)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => MemoryStore::default(),
+            Err(err) => return Err(StoreError::Unavailable(err.to_string())),
+        };
+        Ok(FileStore { path, memory })
+    }
+}
+
+impl UserStore for FileStore {
+    fn put_user(&self, user: UserAuthInfo) -> Result<(), StoreError> {
+        self.memory.put_user(user)
+    }
+
+    fn get_user(&self, user_name: &str) -> Result<Option<UserAuthInfo>, StoreError> {
+        self.memory.get_user(user_name)
+    }
+
+    fn users(&self) -> Result<Vec<UserAuthInfo>, StoreError> {
+        self.memory.users()
+    }
+
+    fn put_auth_id(&self, auth_id: AuthId, user_name: String) -> Result<(), StoreError> {
+        self.memory.put_auth_id(auth_id, user_name)
+    }
+
+    fn get_auth_id(&self, auth_id: &AuthId) -> Result<Option<String>, StoreError> {
+        self.memory.get_auth_id(auth_id)
+    }
+
+    fn put_key_handle(&self, key_handle: String, user_name: String) -> Result<(), StoreError> {
+        self.memory.put_key_handle(key_handle, user_name)
+    }
+
+    fn get_key_handle(&self, key_handle: &str) -> Result<Option<String>, StoreError> {
+        self.memory.get_key_handle(key_handle)
+    }
+
+    fn put_registration(&self, user: UserAuthInfo) -> Result<(), StoreError> {
+        self.memory.put_registration(user)
+    }
+
+    fn put_challenge(&self, user: UserAuthInfo, auth_id: AuthId) -> Result<(), StoreError> {
+        self.memory.put_challenge(user, auth_id)
+    }
+
+    /// Writes a temporary file and renames it over the snapshot, so a crash
+    /// mid-write leaves the previous snapshot intact
+    fn flush(&self) -> Result<(), StoreError> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, self.memory.snapshot())
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .map_err(|err| StoreError::Unavailable(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::default();
+        assert_eq!(store.get_user("peggy"), Ok(None));
+
+        let user = UserAuthInfo {
+            user_name: "peggy".to_string(),
+            y1: BigUint::from(2u32),
+            y2: BigUint::from(3u32),
+            ..Default::default()
+        };
+        store.put_user(user.clone()).unwrap();
+        assert_eq!(store.get_user("peggy"), Ok(Some(user.clone())));
+
+        let updated = UserAuthInfo {
+            c: BigUint::from(4u32),
+            ..user
+        };
+        store.put_user(updated.clone()).unwrap();
+        assert_eq!(store.get_user("peggy"), Ok(Some(updated)));
+
+        let auth_id = AuthId::generate();
+        store.put_auth_id(auth_id, "peggy".to_string()).unwrap();
+        assert_eq!(store.get_auth_id(&auth_id), Ok(Some("peggy".to_string())));
+        assert_eq!(store.get_auth_id(&AuthId::generate()), Ok(None));
+
+        store
+            .put_key_handle("handle".to_string(), "peggy".to_string())
+            .unwrap();
+        assert_eq!(
+            store.get_key_handle("handle"),
+            Ok(Some("peggy".to_string()))
+        );
+        assert_eq!(store.get_key_handle("other"), Ok(None));
+    }
+
+    #[test]
+    fn test_panic_mid_update_leaves_a_consistent_store() {
+        let store = MemoryStore::default();
+        let peggy = UserAuthInfo {
+            user_name: "peggy".to_string(),
+            key_handle: "handle".to_string(),
+            ..Default::default()
+        };
+        store.put_registration(peggy.clone()).unwrap();
+
+        // writers that die halfway through a paired update, while holding the lock
+        let orphan_id = AuthId::generate();
+        let unlinked_id = AuthId::generate();
+        let panicked = std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let state = &mut store.state.lock().unwrap();
+                    state.auth_user.insert(orphan_id, "victor".to_string());
+                    state
+                        .key_handles
+                        .insert("victor's handle".to_string(), "victor".to_string());
+                    let peggy = state.user_info.get_mut("peggy").unwrap();
+                    peggy.auth_id = Some(unlinked_id);
+                    peggy.c = BigUint::from(4u32);
+                    panic!("injected before the rest of the update");
+                })
+                .join()
+        });
+        assert!(panicked.is_err());
+        assert!(store.state.is_poisoned());
+
+        // the next call repairs the half made links instead of failing
+        assert_eq!(store.get_auth_id(&orphan_id), Ok(None));
+        assert!(!store.state.is_poisoned());
+        assert_eq!(store.get_key_handle("victor's handle"), Ok(None));
+        let repaired = store.get_user("peggy").unwrap().unwrap();
+        assert_eq!(repaired.auth_id, None);
+        assert_eq!(store.get_auth_id(&unlinked_id), Ok(None));
+        assert_eq!(
+            store.get_key_handle("handle"),
+            Ok(Some("peggy".to_string()))
+        );
+
+        // and keeps working
+        let auth_id = AuthId::generate();
+        store
+            .put_challenge(
+                UserAuthInfo {
+                    auth_id: Some(auth_id),
+                    ..peggy
+                },
+                auth_id,
+            )
+            .unwrap();
+        assert_eq!(store.get_auth_id(&auth_id), Ok(Some("peggy".to_string())));
+    }
+
+    #[test]
+    fn test_file_store_survives_reopen() {
+        let path = std::env::temp_dir().join(format!(
+            "zkp-store-{}",
+            crate::ZKP::generate_random_string(12)
+        ));
+        let auth_id = AuthId::generate();
+        let user = UserAuthInfo {
+            user_name: "peggy\tthe prover".to_string(),
+            group: "rfc5114-1024-160".to_string(),
+            key_handle: "handle".to_string(),
+            y1: BigUint::from(2u32),
+            y2: BigUint::from(3u32),
+            r1: BigUint::from(8u32),
+            r2: BigUint::from(4u32),
+            commitment_hash: vec![0x00, 0xff, 0x10],
+            challenge_expires_at: Some(SystemTime::now()),
+            context: "app-a".to_string(),
+            c: BigUint::from(4u32),
+            auth_id: Some(auth_id),
+            cert_subject: "CN=peggy,O=Example".to_string(),
+            sealed: vec![0x01, 0x00],
+            ..Default::default()
+        };
+        {
+            let store = FileStore::open(&path).unwrap();
+            assert_eq!(store.get_user(&user.user_name), Ok(None));
+            store.put_user(user.clone()).unwrap();
+            store.put_auth_id(auth_id, user.user_name.clone()).unwrap();
+            store
+                .put_key_handle("handle".to_string(), user.user_name.clone())
+                .unwrap();
+            store.flush().unwrap();
+        }
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get_user(&user.user_name), Ok(Some(user.clone())));
+        assert_eq!(
+            store.get_auth_id(&auth_id),
+            Ok(Some(user.user_name.clone()))
+        );
+        assert_eq!(store.get_key_handle("handle"), Ok(Some(user.user_name)));
+        drop(store);
+
+        fs::write(&path, "not a snapshot").unwrap();
+        assert!(matches!(
+            FileStore::open(&path),
+            Err(StoreError::Internal(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use num_bigint::BigUint;
+use sha2::Sha256;
+
+use crate::{Clock, CompactProof, ZKP};
+
+/// Compact proof with the time it was made hashed into its challenge, so it
+/// can't be passed off as made at another time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampedProof {
+    pub proof: CompactProof,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+/// Why `verify_timestamped` rejected a proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampError {
+    /// Made longer ago than the allowed skew
+    Stale,
+    /// Dated further ahead than the allowed skew
+    Future,
+    InvalidProof,
+}
+
+impl fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampError::Stale => f.write_str("the proof is too old"),
+            TimestampError::Future => f.write_str("the proof is dated in the future"),
+            TimestampError::InvalidProof => f.write_str("the proof does not verify"),
+        }
+    }
+}
+
+impl std::error::Error for TimestampError {}
+
+/// Label hashed after r2. It starts with a byte that can't appear in UTF-8, so
+/// it never equals the user name label of a proof of possession.
+fn timestamp_label(timestamp: u64) -> Vec<u8> {
+    let mut label = b"\xfftimestamp".to_vec();
+    label.extend_from_slice(&timestamp.to_be_bytes());
+    label
+}
+
+impl ZKP {
+    /// `prove_compact` for the time `at`, truncated to whole seconds
+    pub fn prove_timestamped(&self, k: &BigUint, x: &BigUint, at: SystemTime) -> TimestampedProof {
+        let timestamp = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        TimestampedProof {
+            proof: self.prove_labelled::<Sha256>(k, x, Some(&timestamp_label(timestamp))),
+            timestamp,
+        }
+    }
+
+    /// Checks the proof and that its timestamp is within `max_skew` of `clock`,
+    /// either way, so a proof stops verifying `max_skew` after it was made
+    pub fn verify_timestamped(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &TimestampedProof,
+        clock: &dyn Clock,
+        max_skew: Duration,
+    ) -> Result<(), TimestampError> {
+        let made_at = UNIX_EPOCH + Duration::from_secs(proof.timestamp);
+        let now = clock.now();
+        match now.duration_since(made_at) {
+            Ok(age) if age > max_skew => return Err(TimestampError::Stale),
+            Err(ahead) if ahead.duration() > max_skew => return Err(TimestampError::Future),
+            _ => {}
+        }
+        let label = timestamp_label(proof.timestamp);
+        if !self.verify_labelled::<Sha256>(y1, y2, &proof.proof, Some(&label)) {
+            return Err(TimestampError::InvalidProof);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MockClock, Verifier};
+
+    #[test]
+    fn test_timestamped_proof_expires() {
+        let zkp = ZKP::new();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let made_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let proof = zkp.prove_timestamped(&k, &x, made_at);
+        let max_skew = Duration::from_secs(30);
+
+        let clock = MockClock::new(made_at);
+        let verifier = Verifier::new(ZKP::new());
+        assert_eq!(
+            verifier.verify_timestamped(&y1, &y2, &proof, &clock, max_skew),
+            Ok(())
+        );
+        clock.advance(max_skew);
+        assert_eq!(
+            zkp.verify_timestamped(&y1, &y2, &proof, &clock, max_skew),
+            Ok(())
+        );
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            zkp.verify_timestamped(&y1, &y2, &proof, &clock, max_skew),
+            Err(TimestampError::Stale)
+        );
+
+        // redating the proof breaks its challenge
+        let redated = TimestampedProof {
+            timestamp: proof.timestamp + 31,
+            ..proof.clone()
+        };
+        assert_eq!(
+            zkp.verify_timestamped(&y1, &y2, &redated, &clock, max_skew),
+            Err(TimestampError::InvalidProof)
+        );
+        let early = MockClock::new(made_at - Duration::from_secs(31));
+        assert_eq!(
+            zkp.verify_timestamped(&y1, &y2, &proof, &early, max_skew),
+            Err(TimestampError::Future)
+        );
+        // and it isn't an untimed compact proof
+        assert!(!zkp.verify_compact(&y1, &y2, &proof.proof));
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    fmt,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use crate::ZKP;
+
+/// Mean wall time of each protocol operation, measured by `ZKP::measure`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    pub rounds: u32,
+    /// alpha^k mod p with k below q
+    pub exponentiate: Duration,
+    /// r1, r2 and s of one proof
+    pub prove: Duration,
+    /// `verify_checked` of one proof
+    pub verify: Duration,
+}
+
+impl fmt::Display for Timings {
+    /// One line of key=value pairs, durations in microseconds
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rounds={} exponentiate_us={} prove_us={} verify_us={}",
+            self.rounds,
+            self.exponentiate.as_micros(),
+            self.prove.as_micros(),
+            self.verify.as_micros()
+        )
+    }
+}
+
+impl ZKP {
+    /// Times `rounds` proofs in this group on the current machine, e.g. to check
+    /// at startup that the hardware keeps up with the group's size
+    pub fn measure(&self, rounds: u32) -> Timings {
+        assert!(rounds > 0, "at least one round is needed");
+        let x = ZKP::generate_random_below(&self.q);
+        let y1 = ZKP::exponentiate(&self.alpha, &x, &self.p);
+        let y2 = ZKP::exponentiate(&self.beta, &x, &self.p);
+
+        let (mut exponentiate, mut prove, mut verify) =
+            (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+        for _ in 0..rounds {
+            let k = ZKP::generate_random_below(&self.q);
+            let c = ZKP::generate_random_below(&self.q);
+
+            let start = Instant::now();
+            black_box(ZKP::exponentiate(&self.alpha, &k, &self.p));
+            exponentiate += start.elapsed();
+
+            let start = Instant::now();
+            let r1 = ZKP::exponentiate(&self.alpha, &k, &self.p);
+            let r2 = ZKP::exponentiate(&self.beta, &k, &self.p);
+            let s = self.solve(&k, &c, &x);
+            prove += start.elapsed();
+
+            let start = Instant::now();
+            let verification = black_box(self.verify_checked(&r1, &r2, &y1, &y2, &c, &s));
+            verify += start.elapsed();
+            assert_eq!(verification, Ok(()), "a fresh proof must verify");
+        }
+        Timings {
+            rounds,
+            exponentiate: exponentiate / rounds,
+            prove: prove / rounds,
+            verify: verify / rounds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_measure() {
+        let timings = ZKP::new().measure(3);
+        assert_eq!(timings.rounds, 3);
+        assert!(timings.exponentiate > Duration::ZERO);
+        assert!(timings.prove > Duration::ZERO);
+        assert!(timings.verify > Duration::ZERO);
+
+        let line = timings.to_string();
+        assert!(line.starts_with("rounds=3 exponentiate_us="));
+        assert!(line.contains(" prove_us=") && line.contains(" verify_us="));
+    }
+}
@@ -0,0 +1,220 @@
+//! JSON transcripts of the client's messages for `--trace-json`.
+//!
+//! One object per message with every proto field, bytes as lowercase hex, so
+//! transcripts of two implementations can be diffed line by line.
+
+use std::fmt::Write;
+
+use crate::zkp_auth::{
+    AuthAnswerRensponse, AuthAnswerRequest, AuthChallengeRequest, AuthChallengeResponse,
+    GetParamsRequest, GetParamsResponse, RegisterRequest, RegisterResponse,
+};
+
+pub enum TraceValue<'a> {
+    Str(&'a str),
+    Bytes(&'a [u8]),
+    Number(u64),
+    Strs(&'a [String]),
+}
+
+/// A proto message as its name and fields, in proto field order
+pub trait Traced {
+    const MESSAGE: &'static str;
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)>;
+
+    fn to_trace_json(&self) -> String {
+        let mut json = format!("{{\"message\":\"{}\"", Self::MESSAGE);
+        for (name, value) in self.fields() {
+            write!(json, ",\"{}\":", name).unwrap();
+            match value {
+                TraceValue::Str(s) => write_json_string(&mut json, s),
+                TraceValue::Bytes(bytes) => {
+                    json.push('"');
+                    for byte in bytes {
+                        write!(json, "{:02x}", byte).unwrap();
+                    }
+                    json.push('"');
+                }
+                TraceValue::Number(n) => write!(json, "{}", n).unwrap(),
+                TraceValue::Strs(strings) => {
+                    json.push('[');
+                    for (i, s) in strings.iter().enumerate() {
+                        if i > 0 {
+                            json.push(',');
+                        }
+                        write_json_string(&mut json, s);
+                    }
+                    json.push(']');
+                }
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+fn write_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+impl Traced for RegisterRequest {
+    const MESSAGE: &'static str = "RegisterRequest";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        vec![
+            ("user", TraceValue::Str(&self.user)),
+            ("y1", TraceValue::Bytes(&self.y1)),
+            ("y2", TraceValue::Bytes(&self.y2)),
+            ("group", TraceValue::Str(&self.group)),
+            ("pop_c", TraceValue::Bytes(&self.pop_c)),
+            ("pop_s", TraceValue::Bytes(&self.pop_s)),
+        ]
+    }
+}
+
+impl Traced for RegisterResponse {
+    const MESSAGE: &'static str = "RegisterResponse";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        vec![("key_handle", TraceValue::Str(&self.key_handle))]
+    }
+}
+
+impl Traced for AuthChallengeRequest {
+    const MESSAGE: &'static str = "AuthChallengeRequest";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        vec![
+            ("user", TraceValue::Str(&self.user)),
+            ("r1", TraceValue::Bytes(&self.r1)),
+            ("r2", TraceValue::Bytes(&self.r2)),
+            ("context", TraceValue::Str(&self.context)),
+            ("key_handle", TraceValue::Str(&self.key_handle)),
+            ("commitment_hash", TraceValue::Bytes(&self.commitment_hash)),
+        ]
+    }
+}
+
+impl Traced for AuthChallengeResponse {
+    const MESSAGE: &'static str = "AuthChallengeResponse";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        vec![
+            ("auth_id", TraceValue::Str(&self.auth_id)),
+            ("c", TraceValue::Bytes(&self.c)),
+            ("expires_at", TraceValue::Number(self.expires_at)),
+        ]
+    }
+}
+
+impl Traced for AuthAnswerRequest {
+    const MESSAGE: &'static str = "AuthAnswerRequest";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        vec![
+            ("auth_id", TraceValue::Str(&self.auth_id)),
+            ("s", TraceValue::Bytes(&self.s)),
+            ("context", TraceValue::Str(&self.context)),
+            ("r1", TraceValue::Bytes(&self.r1)),
+            ("r2", TraceValue::Bytes(&self.r2)),
+        ]
+    }
+}
+
+impl Traced for AuthAnswerRensponse {
+    const MESSAGE: &'static str = "AuthAnswerRensponse";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        vec![
+            ("session_id", TraceValue::Str(&self.session_id)),
+            ("expires_at", TraceValue::Number(self.expires_at)),
+        ]
+    }
+}
+
+impl Traced for GetParamsRequest {
+    const MESSAGE: &'static str = "GetParamsRequest";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        Vec::new()
+    }
+}
+
+impl Traced for GetParamsResponse {
+    const MESSAGE: &'static str = "GetParamsResponse";
+
+    fn fields(&self) -> Vec<(&'static str, TraceValue<'_>)> {
+        vec![
+            (
+                "protocol_version",
+                TraceValue::Number(self.protocol_version.into()),
+            ),
+            ("groups", TraceValue::Strs(&self.groups)),
+            ("encodings", TraceValue::Strs(&self.encodings)),
+            ("hashes", TraceValue::Strs(&self.hashes)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_trace_json_has_every_field() {
+        let request = AuthChallengeRequest {
+            user: "peggy \"p\"\n".to_string(),
+            r1: vec![0x08],
+            r2: vec![0x00, 0x04],
+            context: "app-a".to_string(),
+            key_handle: String::new(),
+            commitment_hash: Vec::new(),
+        };
+        assert_eq!(
+            request.to_trace_json(),
+            "{\"message\":\"AuthChallengeRequest\",\"user\":\"peggy \\\"p\\\"\\u000a\",\
+             \"r1\":\"08\",\"r2\":\"0004\",\"context\":\"app-a\",\"key_handle\":\"\",\
+             \"commitment_hash\":\"\"}"
+        );
+
+        let response = AuthChallengeResponse {
+            auth_id: "aB3dE5gH7jK9".to_string(),
+            c: vec![0xab, 0xcd],
+            expires_at: 1_700_000_060,
+        };
+        assert_eq!(
+            response.to_trace_json(),
+            "{\"message\":\"AuthChallengeResponse\",\"auth_id\":\"aB3dE5gH7jK9\",\
+             \"c\":\"abcd\",\"expires_at\":1700000060}"
+        );
+
+        let params = GetParamsResponse {
+            protocol_version: 1,
+            groups: vec!["rfc5114-1024-160".to_string()],
+            encodings: vec!["padded".to_string(), "varint".to_string()],
+            hashes: Vec::new(),
+        };
+        assert_eq!(
+            params.to_trace_json(),
+            "{\"message\":\"GetParamsResponse\",\"protocol_version\":1,\
+             \"groups\":[\"rfc5114-1024-160\"],\"encodings\":[\"padded\",\"varint\"],\
+             \"hashes\":[]}"
+        );
+
+        // every proto field shows up, even the ones left at their default
+        let answer = AuthAnswerRequest::default().to_trace_json();
+        for field in ["auth_id", "s", "context", "r1", "r2"] {
+            assert!(answer.contains(&format!("\"{}\":", field)), "{}", field);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// Everything public in one run of the protocol except the challenge, handed to
+/// a challenge derivation by `Verifier::verify_derived`.
+#[derive(Clone, Copy)]
+pub struct Transcript<'a> {
+    pub zkp: &'a ZKP,
+    pub y1: &'a BigUint,
+    pub y2: &'a BigUint,
+    pub r1: &'a BigUint,
+    pub r2: &'a BigUint,
+}
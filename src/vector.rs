@@ -0,0 +1,87 @@
+use std::fmt::Write;
+
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// One full transcript of the protocol, for checking another implementation
+/// against this one, in the format of `tests/vectors.json`.
+///
+/// Encoding: `group` is the group's `descriptor`, every number is a lowercase
+/// big-endian hex string without prefix or leading zeros ("0" for zero), x, k,
+/// c and s are below q and s = (k - c * x) mod q.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    pub group: String,
+    pub x: BigUint,
+    pub k: BigUint,
+    pub c: BigUint,
+    pub y1: BigUint,
+    pub y2: BigUint,
+    pub r1: BigUint,
+    pub r2: BigUint,
+    pub s: BigUint,
+}
+
+impl ZKP {
+    /// Transcript for the secret x, with a random nonce and challenge
+    pub fn test_vector(&self, x: &BigUint) -> TestVector {
+        let k = ZKP::generate_random_below(&self.q);
+        let c = ZKP::generate_random_below(&self.q);
+        self.test_vector_with(x, &k, &c)
+    }
+
+    /// Transcript for the given secret, nonce and challenge, all below q
+    pub fn test_vector_with(&self, x: &BigUint, k: &BigUint, c: &BigUint) -> TestVector {
+        assert!(
+            *x < self.q && *k < self.q && *c < self.q,
+            "x, k and c must be below q"
+        );
+        TestVector {
+            group: self.descriptor(),
+            x: x.clone(),
+            k: k.clone(),
+            c: c.clone(),
+            y1: ZKP::exponentiate(&self.alpha, x, &self.p),
+            y2: ZKP::exponentiate(&self.beta, x, &self.p),
+            r1: ZKP::exponentiate(&self.alpha, k, &self.p),
+            r2: ZKP::exponentiate(&self.beta, k, &self.p),
+            s: self.solve(k, c, x),
+        }
+    }
+}
+
+impl TestVector {
+    /// One JSON object, fields in the order of `tests/vectors.json`
+    pub fn to_json(&self) -> String {
+        let numbers = [
+            ("x", &self.x),
+            ("k", &self.k),
+            ("c", &self.c),
+            ("y1", &self.y1),
+            ("y2", &self.y2),
+            ("r1", &self.r1),
+            ("r2", &self.r2),
+            ("s", &self.s),
+        ];
+        // descriptors only hold names, hex digits and `=;`, nothing to escape
+        let mut json = format!("{{\n  \"group\": \"{}\"", self.group);
+        for (name, value) in numbers {
+            write!(json, ",\n  \"{}\": \"{:x}\"", name, value).unwrap();
+        }
+        json.push_str("\n}");
+        json
+    }
+}
+
+/// `{"vectors": [...]}`, the whole of a `tests/vectors.json`
+pub fn vectors_json(vectors: &[TestVector]) -> String {
+    let objects: Vec<String> = vectors
+        .iter()
+        .map(|vector| vector.to_json().replace('\n', "\n    "))
+        .collect();
+    format!(
+        "{{\n  \"vectors\": [\n    {}\n  ]\n}}\n",
+        objects.join(",\n    ")
+    )
+}
@@ -0,0 +1,692 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use num_bigint::BigUint;
+
+use crate::{
+    decode_field, Auxiliary, CborError, ChallengeSource, Clock, Commitment, CompactProof,
+    GateCounters, GroupInfo, PowerCache, RandomChallenges, Reducer, Reduction, Response,
+    SigmaVerifier, SmallChallengeSpace, TimestampError, TimestampedProof, Transcript, VerifyError,
+    ZKP,
+};
+
+/// Verifier side of the protocol. It only holds the public group parameters and
+/// where challenges come from, so it has nothing that could touch a secret.
+pub struct Verifier {
+    zkp: ZKP,
+    group: String,
+    challenge_source: Arc<dyn ChallengeSource>,
+    power_cache: Option<PowerCache>,
+    /// Reduces mod p
+    reducer: Reducer,
+    /// Set once a challenge below a too short q was warned about
+    warned: AtomicBool,
+    rejections: GateCounters,
+}
+
+impl Verifier {
+    /// Verifier drawing uniformly random challenges
+    pub fn new(zkp: ZKP) -> Self {
+        Verifier::with_challenge_source(zkp, RandomChallenges)
+    }
+
+    pub fn with_challenge_source(
+        zkp: ZKP,
+        challenge_source: impl ChallengeSource + 'static,
+    ) -> Self {
+        Verifier {
+            group: zkp.descriptor(),
+            reducer: Reducer::new(&zkp.p, Reduction::Plain),
+            zkp,
+            challenge_source: Arc::new(challenge_source),
+            power_cache: None,
+            warned: AtomicBool::new(false),
+            rejections: GateCounters::default(),
+        }
+    }
+
+    /// Remembers alpha^s and beta^s for the last `capacity` values of s, see `PowerCache`
+    pub fn with_power_cache(mut self, capacity: usize) -> Self {
+        self.power_cache = Some(PowerCache::new(capacity));
+        self
+    }
+
+    /// Reduces the products checked by `verify` with `reduction`, see `Reducer`
+    pub fn with_reduction(mut self, reduction: Reduction) -> Self {
+        self.reducer = Reducer::new(&self.zkp.p, reduction);
+        self
+    }
+
+    /// A verifier for `zkp` set up like this one: the same challenge source
+    /// and reduction, and an empty power cache of the same capacity. Nothing
+    /// derived from this group, the descriptor, the reducer, a cached power or
+    /// a rejection count, carries over. See `RotatingVerifier`.
+    pub fn rotated(&self, zkp: ZKP) -> Verifier {
+        Verifier {
+            group: zkp.descriptor(),
+            reducer: Reducer::new(&zkp.p, self.reducer.reduction()),
+            zkp,
+            challenge_source: self.challenge_source.clone(),
+            power_cache: self
+                .power_cache
+                .as_ref()
+                .map(|cache| PowerCache::new(cache.capacity())),
+            warned: AtomicBool::new(false),
+            rejections: GateCounters::default(),
+        }
+    }
+
+    /// Descriptor of the group this verifier works in, see `ZKP::descriptor`
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn info(&self) -> GroupInfo {
+        self.zkp.info()
+    }
+
+    pub fn q(&self) -> &BigUint {
+        &self.zkp.q
+    }
+
+    /// The server's side of a handshake, see `ZKP::echo_element`
+    pub fn echo_element(&self, bytes: &[u8]) -> Result<Vec<u8>, VerifyError> {
+        self.zkp.echo_element(bytes)
+    }
+
+    /// A proof sent as CBOR, see `ZKP::decode_proof_cbor`
+    pub fn decode_cbor(&self, bytes: &[u8]) -> Result<[BigUint; 6], CborError> {
+        self.zkp.decode_proof_cbor(bytes)
+    }
+
+    /// output = c in [0, q)
+    ///
+    /// If q is too short, see `ZKP::check_challenge_space`, the first call
+    /// logs a `tracing` warning. Use `try_challenge` to refuse instead.
+    pub fn challenge(&self) -> BigUint {
+        if let Err(err) = self.zkp.check_challenge_space() {
+            self.warn(err);
+        }
+        self.challenge_source.challenge(&self.zkp.q)
+    }
+
+    /// Like `challenge`, but with the `strict` feature a q too short for its
+    /// challenges is an error rather than a warning
+    pub fn try_challenge(&self) -> Result<BigUint, SmallChallengeSpace> {
+        if let Err(err) = self.zkp.check_challenge_space() {
+            if cfg!(feature = "strict") {
+                return Err(err);
+            }
+            self.warn(err);
+        }
+        Ok(self.challenge_source.challenge(&self.zkp.q))
+    }
+
+    fn warn(&self, err: SmallChallengeSpace) {
+        if !self.warned.swap(true, Ordering::Relaxed) {
+            tracing::warn!("{}", err);
+        }
+    }
+
+    /// Whether a too short q has been warned about
+    pub fn warned(&self) -> bool {
+        self.warned.load(Ordering::Relaxed)
+    }
+
+    /// Checks that whoever registers (y1, y2) as `user_name` knows their x
+    pub fn verify_possession(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &CompactProof,
+        user_name: &str,
+    ) -> bool {
+        self.zkp.verify_possession(y1, y2, proof, user_name)
+    }
+
+    /// Checks a compact proof, see `ZKP::verify_compact`
+    pub fn verify_compact(&self, y1: &BigUint, y2: &BigUint, proof: &CompactProof) -> bool {
+        self.zkp.verify_compact(y1, y2, proof)
+    }
+
+    /// Checks a multi-base proof, see `ZKP::verify_multi_base`
+    pub fn verify_multi_base(
+        &self,
+        bases: &[BigUint],
+        ys: &[BigUint],
+        rs: &[BigUint],
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        self.rejections
+            .count(self.zkp.verify_multi_base(bases, ys, rs, c, s))
+    }
+
+    /// See `ZKP::verify_timestamped`
+    pub fn verify_timestamped(
+        &self,
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &TimestampedProof,
+        clock: &dyn Clock,
+        max_skew: Duration,
+    ) -> Result<(), TimestampError> {
+        self.zkp.verify_timestamped(y1, y2, proof, clock, max_skew)
+    }
+
+    /// Checks a non-interactive proof sent with its commitments: the same as
+    /// `verify_compact`, but r1 and r2 are taken as sent rather than recomputed
+    pub fn verify_fiat_shamir(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        self.rejections
+            .count(self.zkp.check_ranges(r1, r2, y1, y2, c, s))?;
+        if *c != self.zkp.fiat_shamir_challenge(y1, y2, r1, r2) {
+            return self.rejections.count(Err(VerifyError::ChallengeMismatch));
+        }
+        self.verify(r1, r2, y1, y2, c, s)
+    }
+
+    /// r1 = alpha^s * y1^c
+    /// r2 = beta^s * y2^c
+    pub fn verify(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        let result = self.check(r1, r2, y1, y2, c, s);
+        self.rejections.count(result)
+    }
+
+    /// `verify` with the link to an auxiliary commitment, see `ZKP::verify_linked`
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_linked(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+        auxiliary: &Auxiliary,
+    ) -> Result<(), VerifyError> {
+        let linked = self
+            .zkp
+            .check_linked_challenge(r1, r2, y1, y2, c, auxiliary);
+        self.rejections.count(linked)?;
+        self.verify(r1, r2, y1, y2, c, s)?;
+        self.rejections.count(self.zkp.check_link(c, s, auxiliary))
+    }
+
+    fn check(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        self.zkp.check_ranges(r1, r2, y1, y2, c, s)?;
+        let powers = match &self.power_cache {
+            Some(cache) => cache.powers(&self.zkp, s),
+            None => (
+                ZKP::exponentiate(&self.zkp.alpha, s, &self.zkp.p),
+                ZKP::exponentiate(&self.zkp.beta, s, &self.zkp.p),
+            ),
+        };
+        self.zkp
+            .verify_with_powers(&self.reducer, r1, r2, y1, y2, c, powers)
+            .into_result()
+    }
+
+    /// (r1, r2, y1, y2, c, s) as sent on the wire, big-endian. Empty fields
+    /// and fields longer than their modulus are refused before anything else,
+    /// counted like every other rejection
+    pub fn decode(&self, fields: [&[u8]; 6]) -> Result<[BigUint; 6], VerifyError> {
+        const NAMES: [&str; 6] = ["r1", "r2", "y1", "y2", "c", "s"];
+        let mut decoded: [BigUint; 6] = Default::default();
+        for (i, bytes) in fields.into_iter().enumerate() {
+            let modulus = if i < 4 { &self.zkp.p } else { &self.zkp.q };
+            decoded[i] = self
+                .rejections
+                .count(decode_field(NAMES[i], bytes, modulus))?;
+        }
+        Ok(decoded)
+    }
+
+    /// `verify` on wire fields, see `decode`, with r1 and r2 also checked to
+    /// be in the subgroup. Every rejection is counted under the gate that
+    /// made it, see `rejections`.
+    pub fn verify_bytes(&self, fields: [&[u8]; 6]) -> Result<(), VerifyError> {
+        let [r1, r2, y1, y2, c, s] = self.decode(fields)?;
+        self.rejections
+            .count(self.zkp.check_ranges(&r1, &r2, &y1, &y2, &c, &s))?;
+        for (name, element) in [("r1", &r1), ("r2", &r2)] {
+            if !self.zkp.is_in_subgroup(element) {
+                return self.rejections.count(Err(VerifyError::NotInSubgroup(name)));
+            }
+        }
+        self.verify(&r1, &r2, &y1, &y2, &c, &s)
+    }
+
+    /// How many proofs each validation gate turned away so far
+    pub fn rejections(&self) -> &GateCounters {
+        &self.rejections
+    }
+
+    /// `verify` with c computed by `derive` from the transcript, for protocols
+    /// that derive the challenge from their own data instead of asking for it
+    /// interactively or hashing with `fiat_shamir_challenge`. A derived c outside
+    /// [0, q) is rejected, not reduced.
+    pub fn verify_derived(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        s: &BigUint,
+        derive: impl FnOnce(&Transcript) -> BigUint,
+    ) -> Result<(), VerifyError> {
+        let c = derive(&Transcript {
+            zkp: &self.zkp,
+            y1,
+            y2,
+            r1,
+            r2,
+        });
+        self.verify(r1, r2, y1, y2, &c, s)
+    }
+}
+
+/// Chaum-Pedersen's verifier half as `ChaumPedersen` runs it, but with the
+/// challenges of the verifier's source and its checks, reduction and
+/// rejection counts
+impl SigmaVerifier for Verifier {
+    /// (y1, y2)
+    type Statement = (BigUint, BigUint);
+    type Commitment = Commitment;
+    type Challenge = BigUint;
+    type Response = Response;
+    type Error = VerifyError;
+
+    fn challenge(&self) -> BigUint {
+        Verifier::challenge(self)
+    }
+
+    fn verify(
+        &self,
+        (y1, y2): &(BigUint, BigUint),
+        commitment: &Commitment,
+        c: &BigUint,
+        response: &Response,
+    ) -> Result<(), VerifyError> {
+        let Commitment { r1, r2 } = commitment;
+        Verifier::verify(self, r1, r2, y1, y2, c, &response.s)
+    }
+}
+
+/// The current `Verifier` of a service, swapped out whole when an operator
+/// rotates parameters, so it can be shared between request handlers.
+///
+/// A verification takes the verifier with `load` and finishes in the group it
+/// started in, however long it runs; a proof for the old group checked after
+/// the swap is rejected like any wrong proof.
+pub struct RotatingVerifier {
+    current: RwLock<Arc<Verifier>>,
+}
+
+impl RotatingVerifier {
+    pub fn new(verifier: Verifier) -> Self {
+        RotatingVerifier {
+            current: RwLock::new(Arc::new(verifier)),
+        }
+    }
+
+    pub fn load(&self) -> Arc<Verifier> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Swaps in `Verifier::rotated` for `zkp`, returning the old group for the
+    /// audit trail
+    pub fn rotate(&self, zkp: ZKP) -> ZKP {
+        let current = &mut *self.current.write().unwrap();
+        let old = std::mem::replace(current, Arc::new(current.rotated(zkp)));
+        let ZKP { p, q, alpha, beta } = &old.zkp;
+        ZKP {
+            p: p.clone(),
+            q: q.clone(),
+            alpha: alpha.clone(),
+            beta: beta.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FixedChallenges;
+
+    fn toy_zkp() -> ZKP {
+        ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        }
+    }
+
+    #[test]
+    fn test_verifier_accepts_prover_proof() {
+        let verifier = Verifier::with_challenge_source(
+            toy_zkp(),
+            FixedChallenges::new(vec![BigUint::from(4u32)]),
+        );
+
+        // prover side, using its own copy of the public parameters
+        let zkp = toy_zkp();
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+
+        let c = verifier.challenge();
+        assert_eq!(c, BigUint::from(4u32));
+
+        let s = zkp.solve(&k, &c, &x);
+        assert_eq!(verifier.verify(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+
+        let s_fake = zkp.solve(&k, &c, &BigUint::from(7u32));
+        assert_eq!(
+            verifier.verify(&r1, &r2, &y1, &y2, &c, &s_fake),
+            Err(VerifyError::R1Mismatch)
+        );
+    }
+
+    #[test]
+    fn test_barrett_reduction_agrees_with_plain() {
+        use crate::{Group, Prover};
+
+        let groups: [fn() -> ZKP; 2] = [toy_zkp, || Group::Rfc5114_2048_256.zkp()];
+        for group in groups {
+            let zkp = group();
+            let x = ZKP::generate_random_below(&zkp.q);
+            let prover = Prover::new(group(), x.clone()).with_reduction(Reduction::Barrett);
+            let verifier = Verifier::new(group()).with_reduction(Reduction::Barrett);
+            let q_reducer = Reducer::new(&zkp.q, Reduction::Barrett);
+            let (y1, y2) = prover.public_key();
+
+            for _ in 0..20 {
+                let k = ZKP::generate_random_below(&zkp.q);
+                let c = verifier.challenge();
+                let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+                let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+                let s = zkp.solve(&k, &c, &x);
+                assert_eq!(zkp.solve_with(&q_reducer, &k, &c, &x), s);
+                assert_eq!(verifier.verify(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+
+                let wrong = (&s + 1u32) % &zkp.q;
+                assert_eq!(
+                    verifier.verify(&r1, &r2, &y1, &y2, &c, &wrong),
+                    zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &wrong)
+                );
+            }
+            let (commitment, nonce) = prover.commit();
+            let c = verifier.challenge();
+            let response = prover.respond(nonce, &c);
+            assert_eq!(
+                verifier.verify(&commitment.r1, &commitment.r2, &y1, &y2, &c, &response.s),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_derived_challenge() {
+        use sha2::{Digest, Sha256};
+
+        // an application binding the challenge to an order number
+        let derive = |order: &'static str| {
+            move |t: &Transcript| {
+                let mut hasher = Sha256::new();
+                hasher.update(order);
+                for n in [t.y1, t.y2, t.r1, t.r2] {
+                    hasher.update(n.to_bytes_be());
+                }
+                BigUint::from_bytes_be(&hasher.finalize()) % &t.zkp.q
+            }
+        };
+
+        let zkp = toy_zkp();
+        let verifier = Verifier::new(toy_zkp());
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+
+        let c = derive("order-42")(&Transcript {
+            zkp: &zkp,
+            y1: &y1,
+            y2: &y2,
+            r1: &r1,
+            r2: &r2,
+        });
+        let s = zkp.solve(&k, &c, &x);
+        assert_eq!(
+            verifier.verify_derived(&r1, &r2, &y1, &y2, &s, derive("order-42")),
+            Ok(())
+        );
+
+        // another order derives another challenge, unless both happen to agree
+        let other = verifier.verify_derived(&r1, &r2, &y1, &y2, &s, derive("order-43"));
+        let c_other = derive("order-43")(&Transcript {
+            zkp: &zkp,
+            y1: &y1,
+            y2: &y2,
+            r1: &r1,
+            r2: &r2,
+        });
+        assert_eq!(other.is_ok(), c_other == c);
+
+        // a derivation that forgets to reduce mod q
+        assert_eq!(
+            verifier.verify_derived(&r1, &r2, &y1, &y2, &s, |t| &c + &t.zkp.q),
+            Err(VerifyError::OutOfRange("c"))
+        );
+    }
+
+    #[test]
+    fn test_tiny_q_warns_or_is_refused_under_strict() {
+        let verifier = Verifier::new(toy_zkp());
+        assert!(!verifier.warned());
+        let c = verifier.try_challenge();
+        if cfg!(feature = "strict") {
+            assert_eq!(c, Err(SmallChallengeSpace { q_bits: 4 }));
+            assert!(!verifier.warned());
+        } else {
+            assert!(c.unwrap() < BigUint::from(11u32));
+            assert!(verifier.warned());
+        }
+        verifier.challenge();
+        assert!(verifier.warned());
+
+        let verifier = Verifier::new(crate::Group::Rfc5114_1024_160.zkp());
+        assert!(verifier.try_challenge().is_ok());
+        verifier.challenge();
+        assert!(!verifier.warned());
+    }
+
+    #[test]
+    fn test_rotation_mid_stream() {
+        use crate::Group;
+
+        let prove = |zkp: &ZKP, x: &BigUint, k: &BigUint, c: &BigUint| {
+            let y = |n: &BigUint| {
+                (
+                    ZKP::exponentiate(&zkp.alpha, n, &zkp.p),
+                    ZKP::exponentiate(&zkp.beta, n, &zkp.p),
+                )
+            };
+            let ((y1, y2), (r1, r2)) = (y(x), y(k));
+            [r1, r2, y1, y2, c.clone(), zkp.solve(k, c, x)]
+        };
+
+        let old_zkp = Group::Rfc5114_1024_160.zkp();
+        let rotating = RotatingVerifier::new(
+            Verifier::new(Group::Rfc5114_1024_160.zkp())
+                .with_power_cache(8)
+                .with_reduction(Reduction::Barrett),
+        );
+        let in_flight = rotating.load();
+        let (x, k, c) = (
+            BigUint::from(6u32),
+            BigUint::from(7u32),
+            BigUint::from(4u32),
+        );
+        let [r1, r2, y1, y2, c, s] = prove(&old_zkp, &x, &k, &c);
+        assert_eq!(in_flight.verify(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+
+        let old = rotating.rotate(Group::Rfc5114_2048_256.zkp());
+        let verifier = rotating.load();
+        assert_eq!(old.descriptor(), old_zkp.descriptor());
+        assert_eq!(verifier.group(), Group::Rfc5114_2048_256.name());
+        assert_eq!(verifier.reducer.reduction(), Reduction::Barrett);
+        assert_eq!(verifier.power_cache.as_ref().map(PowerCache::len), Some(0));
+
+        // a verification that started before the swap still runs in the old
+        // group, one that starts after it turns the old proof away, already
+        // by the subgroup check with the strict feature
+        assert_eq!(in_flight.group(), old_zkp.descriptor());
+        assert_eq!(in_flight.verify(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+        assert!(matches!(
+            verifier.verify(&r1, &r2, &y1, &y2, &c, &s),
+            Err(VerifyError::R1Mismatch | VerifyError::NotInSubgroup("r1"))
+        ));
+
+        // one for the new group with the same s, which a stale cache would fail
+        let new_zkp = Group::Rfc5114_2048_256.zkp();
+        let k = (&s + &c * &x) % &new_zkp.q;
+        let [r1, r2, y1, y2, c, s_new] = prove(&new_zkp, &x, &k, &c);
+        assert_eq!(s_new, s);
+        assert_eq!(verifier.verify(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+    }
+
+    #[test]
+    fn test_rotation_while_verifying() {
+        use crate::Group;
+
+        let zkp = Group::Rfc5114_1024_160.zkp();
+        let rotating = RotatingVerifier::new(Verifier::new(Group::Rfc5114_1024_160.zkp()));
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let c = BigUint::from(4u32);
+        let y = |n: &BigUint| {
+            (
+                ZKP::exponentiate(&zkp.alpha, n, &zkp.p),
+                ZKP::exponentiate(&zkp.beta, n, &zkp.p),
+            )
+        };
+        let ((y1, y2), (r1, r2)) = (y(&x), y(&k));
+        let s = zkp.solve(&k, &c, &x);
+
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut outcomes = (0, 0);
+                        for _ in 0..50 {
+                            // every check runs in one group from start to end
+                            let verifier = rotating.load();
+                            let result = verifier.verify(&r1, &r2, &y1, &y2, &c, &s);
+                            if verifier.group() == zkp.descriptor() {
+                                assert_eq!(result, Ok(()));
+                                outcomes.0 += 1;
+                            } else {
+                                assert!(result.is_err());
+                                outcomes.1 += 1;
+                            }
+                        }
+                        outcomes
+                    })
+                })
+                .collect();
+            std::thread::sleep(Duration::from_millis(5));
+            let old = rotating.rotate(Group::Rfc5114_2048_256.zkp());
+            assert_eq!(old.descriptor(), zkp.descriptor());
+            let total = workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+            assert_eq!(total.0 + total.1, 200);
+        });
+        assert_eq!(rotating.load().group(), Group::Rfc5114_2048_256.name());
+    }
+
+    #[test]
+    fn test_each_gate_counts_its_rejections() {
+        use crate::Gate;
+
+        let zkp = toy_zkp();
+        let verifier = Verifier::new(toy_zkp());
+        // x = 6, k = 7, c = 4
+        let [r1, r2, y1, y2, c, s] = [8u32, 4, 2, 3, 4, 5].map(|n| vec![n as u8]);
+        let valid = [&r1[..], &r2, &y1, &y2, &c, &s];
+        assert_eq!(verifier.verify_bytes(valid), Ok(()));
+
+        let minus_one = vec![zkp.p.to_bytes_be()[0] - 1];
+        let cases: [(Gate, [&[u8]; 6]); 6] = [
+            (Gate::Empty, [&r1, &r2, &[], &y2, &c, &s]),
+            (Gate::Oversize, [&r1, &r2, &y1, &y2, &c, &[0, 5]]),
+            (Gate::Range, [&[0], &r2, &y1, &y2, &c, &s]),
+            (Gate::Subgroup, [&minus_one, &r2, &y1, &y2, &c, &s]),
+            (Gate::ChallengeRange, [&r1, &r2, &y1, &y2, &[11], &s]),
+            (Gate::Equation, [&r1, &r2, &y1, &y2, &c, &[6]]),
+        ];
+        for (gate, fields) in cases {
+            let before = verifier.rejections().get(gate);
+            assert!(verifier.verify_bytes(fields).is_err(), "{:?}", gate);
+            assert_eq!(verifier.rejections().get(gate), before + 1, "{:?}", gate);
+        }
+        assert!(verifier
+            .rejections()
+            .snapshot()
+            .iter()
+            .all(|(_, count)| *count == 1));
+
+        // the other paths count too
+        let [r1, r2, y1, y2, c, s] = [8u32, 4, 2, 3, 4, 6].map(BigUint::from);
+        assert!(verifier.verify(&r1, &r2, &y1, &y2, &c, &s).is_err());
+        assert!(verifier
+            .verify_fiat_shamir(&r1, &r2, &y1, &y2, &c, &s)
+            .is_err());
+        assert_eq!(verifier.rejections().get(Gate::Equation), 3);
+    }
+
+    #[test]
+    fn test_verifier_random_challenges_below_q() {
+        let verifier = Verifier::new(toy_zkp());
+        assert_eq!(verifier.info().q_bits, 4);
+        for _ in 0..100 {
+            assert!(verifier.challenge() < BigUint::from(11u32));
+        }
+    }
+}
@@ -0,0 +1,491 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
+
+use crate::{Reducer, Reduction, ZKP};
+
+/// Outcome of each of the two equations checked by `verify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyDetails {
+    /// r1 == alpha^s * y1^c mod p
+    pub r1_matches: bool,
+    /// r2 == beta^s * y2^c mod p
+    pub r2_matches: bool,
+}
+
+impl VerifyDetails {
+    pub fn is_valid(&self) -> bool {
+        self.r1_matches && self.r2_matches
+    }
+
+    pub(crate) fn into_result(self) -> Result<(), VerifyError> {
+        if !self.r1_matches {
+            return Err(VerifyError::R1Mismatch);
+        }
+        if !self.r2_matches {
+            return Err(VerifyError::R2Mismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Why `verify_checked` rejected a proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The named input is outside [1, p) for elements or [0, q) for c and s
+    OutOfRange(&'static str),
+    /// The named element is in [1, p) but outside the order q subgroup
+    NotInSubgroup(&'static str),
+    R1Mismatch,
+    R2Mismatch,
+    /// c is not the Fiat-Shamir challenge of the transcript
+    ChallengeMismatch,
+    /// The named field arrived empty
+    Empty(&'static str),
+    /// The named field has more bytes than its modulus
+    Oversize(&'static str),
+    /// The bases, keys and commitments of a multi-base proof are empty, too
+    /// many or of different lengths
+    LengthMismatch,
+    /// r_i does not match g_i^s * y_i^c for this base of a multi-base proof
+    BaseMismatch(usize),
+    /// r3 does not match alpha^s * h^u * C^c for the auxiliary commitment C
+    AuxiliaryMismatch,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::OutOfRange(name) => write!(f, "{} is out of range", name),
+            VerifyError::NotInSubgroup(name) => {
+                write!(f, "{} is not in the order q subgroup", name)
+            }
+            VerifyError::R1Mismatch => f.write_str("r1 does not match alpha^s * y1^c"),
+            VerifyError::R2Mismatch => f.write_str("r2 does not match beta^s * y2^c"),
+            VerifyError::ChallengeMismatch => {
+                f.write_str("c is not the Fiat-Shamir challenge of y1, y2, r1 and r2")
+            }
+            VerifyError::Empty(name) => write!(f, "{} is empty", name),
+            VerifyError::Oversize(name) => write!(f, "{} has more bytes than its modulus", name),
+            VerifyError::LengthMismatch => {
+                f.write_str("bases, ys and rs must be non-empty and of the same length")
+            }
+            VerifyError::BaseMismatch(i) => {
+                write!(f, "rs[{}] does not match bases[{}]^s * ys[{}]^c", i, i, i)
+            }
+            VerifyError::AuxiliaryMismatch => f.write_str("r3 does not match alpha^s * h^u * C^c"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl ZKP {
+    /// r1 = alpha^s * y1^c
+    /// r2 = beta^s * y2^c
+    ///
+    /// Both equations are checked with the same s, which is what ties r1 and r2 to a
+    /// single nonce: they only hold together when s = k1 - c * x = k2 - c * x mod q,
+    /// so a prover who committed to r1 = alpha^k1 and r2 = beta^k2 with k1 != k2
+    /// can satisfy at most one of them.
+    pub fn verify_detailed(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> VerifyDetails {
+        let powers = (
+            ZKP::exponentiate(&self.alpha, s, &self.p),
+            ZKP::exponentiate(&self.beta, s, &self.p),
+        );
+        self.verify_with_powers(
+            &Reducer::new(&self.p, Reduction::Plain),
+            r1,
+            r2,
+            y1,
+            y2,
+            c,
+            powers,
+        )
+    }
+
+    /// `verify_detailed` with (alpha^s, beta^s) already computed, reducing the
+    /// products with `reducer`, which must be built for p
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn verify_with_powers(
+        &self,
+        reducer: &Reducer,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        (mut sol1, mut sol2): (BigUint, BigUint),
+    ) -> VerifyDetails {
+        debug_assert_eq!(reducer.modulus(), &self.p);
+        // reduce each product with the reducer, a modpow by 1 builds a Montgomery
+        // context for nothing
+        sol1 *= ZKP::exponentiate(y1, c, &self.p);
+        let r1_matches = *r1 == reducer.reduce(sol1);
+
+        sol2 *= ZKP::exponentiate(y2, c, &self.p);
+        let r2_matches = *r2 == reducer.reduce(sol2);
+
+        VerifyDetails {
+            r1_matches,
+            r2_matches,
+        }
+    }
+
+    /// Like `verify`, but range checks the inputs first and reports why a proof is rejected
+    pub fn verify_checked(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        self.check_ranges(r1, r2, y1, y2, c, s)?;
+        self.verify_detailed(r1, r2, y1, y2, c, s).into_result()
+    }
+
+    /// Like `verify_checked`, and also checks r1 and r2 are in the order q
+    /// subgroup. A commitment outside of it can still match the algebra when y1
+    /// or y2 are outside too, e.g. -r1 = alpha^s * (-y1)^c for odd c.
+    pub fn verify_strict(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        self.check_ranges(r1, r2, y1, y2, c, s)?;
+        self.check_subgroup(r1, r2)?;
+        self.verify_detailed(r1, r2, y1, y2, c, s).into_result()
+    }
+
+    /// Chance that a prover who doesn't know x passes a single proof whose c
+    /// was drawn uniformly from [0, bound): they can answer at most one
+    /// challenge per commitment, so 1 / bound. A bound above q counts as q,
+    /// c is reduced below it anyway.
+    pub fn soundness_error(&self, bound: &BigUint) -> f64 {
+        assert!(!bound.is_zero(), "the challenge space can't be empty");
+        1.0 / bound.min(&self.q).to_f64().unwrap_or(f64::INFINITY)
+    }
+
+    /// Like `verify_checked` for a c drawn from [0, bound), e.g.
+    /// `ShortChallenges::bound`, returning the `soundness_error` that goes
+    /// with it. A c at or above bound is out of range.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_with_soundness(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+        bound: &BigUint,
+    ) -> Result<f64, VerifyError> {
+        if c >= bound {
+            return Err(VerifyError::OutOfRange("c"));
+        }
+        self.verify_checked(r1, r2, y1, y2, c, s)?;
+        Ok(self.soundness_error(bound))
+    }
+
+    /// n in [1, p) with n^q = 1 mod p, one exponentiation
+    pub fn is_in_subgroup(&self, n: &BigUint) -> bool {
+        !n.is_zero() && *n < self.p && ZKP::exponentiate(n, &self.q, &self.p).is_one()
+    }
+
+    /// c in [0, q), checked on every path that takes c from outside. With y in
+    /// the subgroup y^q = 1, so c = q works like c = 0 and q + 1 like 1: an
+    /// unreduced c would let one answer stand for several challenges.
+    pub fn validate_challenge(&self, c: &BigUint) -> Result<(), VerifyError> {
+        if *c >= self.q {
+            return Err(VerifyError::OutOfRange("c"));
+        }
+        Ok(())
+    }
+
+    fn check_subgroup(&self, r1: &BigUint, r2: &BigUint) -> Result<(), VerifyError> {
+        for (name, element) in [("r1", r1), ("r2", r2)] {
+            if !self.is_in_subgroup(element) {
+                return Err(VerifyError::NotInSubgroup(name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Elements in [1, p), c and s in [0, q). With the `strict` feature r1 and r2
+    /// are also checked to be in the subgroup, at the cost of two exponentiations.
+    pub(crate) fn check_ranges(
+        &self,
+        r1: &BigUint,
+        r2: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+        c: &BigUint,
+        s: &BigUint,
+    ) -> Result<(), VerifyError> {
+        for (name, element) in [("r1", r1), ("r2", r2), ("y1", y1), ("y2", y2)] {
+            if element.is_zero() || *element >= self.p {
+                return Err(VerifyError::OutOfRange(name));
+            }
+        }
+        self.validate_challenge(c)?;
+        if *s >= self.q {
+            return Err(VerifyError::OutOfRange("s"));
+        }
+        if cfg!(feature = "strict") {
+            self.check_subgroup(r1, r2)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::AndProof;
+
+    // x = 6, k = 7, c = 4 over the toy group gives y = (2, 3), r = (8, 4), s = 5
+    fn toy_transcript() -> (ZKP, [BigUint; 6]) {
+        let zkp = ZKP {
+            p: BigUint::from(23u32),
+            q: BigUint::from(11u32),
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+        };
+        (zkp, [8u32, 4, 2, 3, 4, 5].map(BigUint::from))
+    }
+
+    #[test]
+    fn test_verify_checked_accepts_valid_proof() {
+        let (zkp, [r1, r2, y1, y2, c, s]) = toy_transcript();
+        assert_eq!(zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+        assert_eq!(
+            zkp.verify_detailed(&r1, &r2, &y1, &y2, &c, &s),
+            VerifyDetails {
+                r1_matches: true,
+                r2_matches: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_checked_reports_mismatches() {
+        let (zkp, [r1, r2, y1, y2, c, s]) = toy_transcript();
+
+        let r1_fake = ZKP::exponentiate(&zkp.alpha, &BigUint::from(3u32), &zkp.p);
+        assert_eq!(
+            zkp.verify_checked(&r1_fake, &r2, &y1, &y2, &c, &s),
+            Err(VerifyError::R1Mismatch)
+        );
+
+        let r2_fake = ZKP::exponentiate(&zkp.beta, &BigUint::from(3u32), &zkp.p);
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2_fake, &y1, &y2, &c, &s),
+            Err(VerifyError::R2Mismatch)
+        );
+
+        // a wrong s breaks both equations, r1 is reported first
+        let s_fake = BigUint::from(6u32);
+        assert_eq!(
+            zkp.verify_detailed(&r1, &r2, &y1, &y2, &c, &s_fake),
+            VerifyDetails {
+                r1_matches: false,
+                r2_matches: false,
+            }
+        );
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s_fake),
+            Err(VerifyError::R1Mismatch)
+        );
+    }
+
+    #[test]
+    fn test_mismatched_nonces_never_verify() {
+        let (zkp, [_, _, y1, y2, _, _]) = toy_transcript();
+        let x = BigUint::from(6u32);
+
+        // the prover commits to two different nonces and answers each equation on its own
+        let (k1, k2) = (BigUint::from(7u32), BigUint::from(3u32));
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k1, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k2, &zkp.p);
+        for c in (0..11u32).map(BigUint::from) {
+            let s1 = zkp.solve(&k1, &c, &x);
+            let s2 = zkp.solve(&k2, &c, &x);
+            assert_ne!(s1, s2);
+            assert!(zkp.verify_detailed(&r1, &r2, &y1, &y2, &c, &s1).r1_matches);
+            assert!(zkp.verify_detailed(&r1, &r2, &y1, &y2, &c, &s2).r2_matches);
+
+            // but no single s satisfies both
+            for s in (0..11u32).map(BigUint::from) {
+                assert!(!zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_checked_reports_out_of_range() {
+        let (zkp, [r1, r2, y1, y2, c, s]) = toy_transcript();
+
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2, &BigUint::zero(), &y2, &c, &s),
+            Err(VerifyError::OutOfRange("y1"))
+        );
+        assert_eq!(
+            zkp.verify_checked(&r1, &(&r2 + &zkp.p), &y1, &y2, &c, &s),
+            Err(VerifyError::OutOfRange("r2"))
+        );
+        // s + q satisfies the algebra, but is not the canonical answer
+        let s_wrapped = &s + &zkp.q;
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s_wrapped));
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s_wrapped),
+            Err(VerifyError::OutOfRange("s"))
+        );
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2, &y1, &y2, &zkp.q, &s),
+            Err(VerifyError::OutOfRange("c"))
+        );
+    }
+
+    #[test]
+    fn test_unreduced_challenges_are_rejected() {
+        let (zkp, [r1, r2, y1, y2, c, s]) = toy_transcript();
+        for bad in [zkp.q.clone(), &zkp.q + 1u32] {
+            assert_eq!(
+                zkp.validate_challenge(&bad),
+                Err(VerifyError::OutOfRange("c"))
+            );
+        }
+        assert_eq!(zkp.validate_challenge(&(&zkp.q - 1u32)), Ok(()));
+
+        // c + q passes the algebra for the answer to c, but is refused everywhere
+        let wrapped = &c + &zkp.q;
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &wrapped, &s));
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2, &y1, &y2, &wrapped, &s),
+            Err(VerifyError::OutOfRange("c"))
+        );
+        assert!(!bool::from(zkp.verify_ct(&r1, &r2, &y1, &y2, &wrapped, &s)));
+        let and = zkp.prove_and(&7u32.into(), &7u32.into(), &c, &6u32.into(), &6u32.into());
+        assert!(zkp.verify_and(&r1, &r2, &y1, &y2, &c, &and));
+        assert!(!zkp.verify_and(&r1, &r2, &y1, &y2, &wrapped, &and));
+
+        // c = q, for which s = k answers any key
+        let k = BigUint::from(7u32);
+        assert!(!zkp.verify_and(
+            &r1,
+            &r2,
+            &y1,
+            &y2,
+            &zkp.q,
+            &AndProof {
+                s1: k.clone(),
+                s2: k.clone()
+            }
+        ));
+        let compact = crate::CompactProof {
+            c: zkp.q.clone(),
+            s: k,
+        };
+        assert!(!zkp.verify_compact(&y1, &y2, &compact));
+    }
+
+    #[test]
+    fn test_smaller_challenge_spaces_report_larger_errors() {
+        let zkp = ZKP::new();
+        let x = BigUint::from(6u32);
+        let (y1, y2) = (
+            ZKP::exponentiate(&zkp.alpha, &x, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &x, &zkp.p),
+        );
+        let k = ZKP::generate_random_below(&zkp.q);
+        let (r1, r2) = (
+            ZKP::exponentiate(&zkp.alpha, &k, &zkp.p),
+            ZKP::exponentiate(&zkp.beta, &k, &zkp.p),
+        );
+
+        let mut last = 0.0;
+        for bits in [160, 64, 16, 8, 1] {
+            let source = crate::ShortChallenges { bits };
+            let bound = source.bound(&zkp.q);
+            let c = crate::ChallengeSource::challenge(&source, &zkp.q);
+            let s = zkp.solve(&k, &c, &x);
+            let error = zkp
+                .verify_with_soundness(&r1, &r2, &y1, &y2, &c, &s, &bound)
+                .unwrap();
+            assert!(error > last);
+            last = error;
+        }
+        assert_eq!(last, 0.5);
+        assert_eq!(zkp.soundness_error(&BigUint::from(256u32)), 1.0 / 256.0);
+        // q is just under 2^160, bounds past it are capped
+        assert_eq!(
+            zkp.soundness_error(&(BigUint::one() << 1024)),
+            zkp.soundness_error(&zkp.q)
+        );
+
+        // a c the claimed space couldn't have produced
+        let c = BigUint::from(256u32);
+        let s = zkp.solve(&k, &c, &x);
+        assert_eq!(
+            zkp.verify_with_soundness(&r1, &r2, &y1, &y2, &c, &s, &BigUint::from(256u32)),
+            Err(VerifyError::OutOfRange("c"))
+        );
+    }
+
+    #[test]
+    fn test_verify_strict_rejects_elements_outside_the_subgroup() {
+        let (zkp, _) = toy_transcript();
+        let (x, k, c) = (
+            BigUint::from(6u32),
+            BigUint::from(7u32),
+            BigUint::from(5u32),
+        );
+        let s = zkp.solve(&k, &c, &x);
+        let minus = |n: BigUint| &zkp.p - n;
+
+        // negating y1, y2, r1 and r2 keeps the algebra for an odd c, but -1 has
+        // order 2, so the negated elements leave the order 11 subgroup
+        let y1 = minus(ZKP::exponentiate(&zkp.alpha, &x, &zkp.p));
+        let y2 = minus(ZKP::exponentiate(&zkp.beta, &x, &zkp.p));
+        let r1 = minus(ZKP::exponentiate(&zkp.alpha, &k, &zkp.p));
+        let r2 = minus(ZKP::exponentiate(&zkp.beta, &k, &zkp.p));
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+        assert!(!zkp.is_in_subgroup(&r1) && !zkp.is_in_subgroup(&r2));
+
+        assert_eq!(
+            zkp.verify_strict(&r1, &r2, &y1, &y2, &c, &s),
+            Err(VerifyError::NotInSubgroup("r1"))
+        );
+        let r1_member = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        assert_eq!(
+            zkp.verify_strict(&r1_member, &r2, &y1, &y2, &c, &s),
+            Err(VerifyError::NotInSubgroup("r2"))
+        );
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s),
+            if cfg!(feature = "strict") {
+                Err(VerifyError::NotInSubgroup("r1"))
+            } else {
+                Ok(())
+            }
+        );
+
+        let (zkp, [r1, r2, y1, y2, c, s]) = toy_transcript();
+        assert_eq!(zkp.verify_strict(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+    }
+}
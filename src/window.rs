@@ -0,0 +1,96 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
+
+use crate::Commitment;
+
+/// Commitments each user sent lately, to turn away a second challenge for the
+/// same (r1, r2). Answering two challenges with one nonce gives away x, as
+/// s1 - s2 = (c2 - c1) * x. `T` is what a commitment is recognised by, the
+/// pair itself or e.g. its `commit::hash_elements`.
+#[derive(Debug)]
+pub struct CommitmentWindow<T = Commitment> {
+    /// Commitments remembered per user, the oldest are forgotten first
+    capacity: usize,
+    users: HashMap<String, UserWindow<T>>,
+}
+
+#[derive(Debug)]
+struct UserWindow<T> {
+    /// Insertion order, for eviction
+    order: VecDeque<T>,
+    seen: HashSet<T>,
+}
+
+impl<T> Default for UserWindow<T> {
+    fn default() -> Self {
+        UserWindow {
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> CommitmentWindow<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "the window must hold at least one commitment");
+        CommitmentWindow {
+            capacity,
+            users: HashMap::new(),
+        }
+    }
+
+    /// Records the commitment for `user_name`, false if it is already in their window
+    pub fn insert(&mut self, user_name: &str, commitment: T) -> bool {
+        let window = match self.users.get_mut(user_name) {
+            Some(window) => window,
+            None => self.users.entry(user_name.to_string()).or_default(),
+        };
+        if !window.seen.insert(commitment.clone()) {
+            return false;
+        }
+        window.order.push_back(commitment);
+        if window.order.len() > self.capacity {
+            let oldest = window.order.pop_front().expect("the window isn't empty");
+            window.seen.remove(&oldest);
+        }
+        true
+    }
+
+    pub fn contains(&self, user_name: &str, commitment: &T) -> bool {
+        self.users
+            .get(user_name)
+            .is_some_and(|window| window.seen.contains(commitment))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    fn commitment(r1: u32, r2: u32) -> Commitment {
+        Commitment {
+            r1: BigUint::from(r1),
+            r2: BigUint::from(r2),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_commitments_collide() {
+        let mut window = CommitmentWindow::new(2);
+        assert!(window.insert("peggy", commitment(8, 4)));
+        assert!(!window.insert("peggy", commitment(8, 4)));
+        // only the pair counts, and only for the same user
+        assert!(window.insert("peggy", commitment(8, 5)));
+        assert!(window.insert("victor", commitment(8, 4)));
+
+        // (8, 4) is pushed out by the third commitment
+        assert!(window.insert("peggy", commitment(3, 2)));
+        assert!(!window.contains("peggy", &commitment(8, 4)));
+        assert!(window.contains("peggy", &commitment(8, 5)));
+        assert!(window.insert("peggy", commitment(8, 4)));
+    }
+}
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Regenerates the certificates of the mTLS tests: a CA, a server certificate
+# for localhost and client certificates for peggy and mallory, all valid for
+# a century so the fixtures don't expire
+set -e
+cd "$(dirname "$0")"
+days=36500
+
+openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out ca.key
+openssl req -x509 -new -key ca.key -subj "/CN=zkp-chaum-pedersen test CA" -days $days -out ca.pem
+
+issue() {
+    name=$1 subject=$2 extensions=$3
+    openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out "$name.key"
+    openssl req -new -key "$name.key" -subj "$subject" -out "$name.csr"
+    printf '%s\n' "$extensions" > "$name.ext"
+    openssl x509 -req -in "$name.csr" -CA ca.pem -CAkey ca.key -CAcreateserial \
+        -days $days -extfile "$name.ext" -out "$name.pem"
+    rm "$name.csr" "$name.ext"
+}
+
+issue server "/CN=localhost" "subjectAltName=DNS:localhost,IP:127.0.0.1
+extendedKeyUsage=serverAuth"
+issue peggy "/O=Example/CN=peggy" "extendedKeyUsage=clientAuth"
+issue mallory "/O=Example/CN=mallory" "extendedKeyUsage=clientAuth"
+rm -f ca.key ca.srl
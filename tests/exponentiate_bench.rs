@@ -0,0 +1,58 @@
+//! Times `exponentiate_many` against one modpow per exponent, run with
+//! `cargo test --release -- --ignored`.
+//!
+//! `BENCH_ITERATIONS` sets the number of 16 exponent batches per group (default 200).
+
+use std::{env, time::Instant};
+
+use num_bigint::BigUint;
+use zkp_chaum_pedersen::{Group, ZKP};
+
+#[test]
+#[ignore]
+fn bench_exponentiate_many() {
+    let iterations: u32 = env::var("BENCH_ITERATIONS")
+        .map(|value| value.parse().expect("invalid BENCH_ITERATIONS"))
+        .unwrap_or(200);
+
+    for group in [Group::Rfc5114_1024_160, Group::Rfc5114_2048_256] {
+        let zkp = group.zkp();
+        let batches: Vec<Vec<BigUint>> = (0..iterations)
+            .map(|_| {
+                (0..16)
+                    .map(|_| ZKP::generate_random_below(&zkp.q))
+                    .collect()
+            })
+            .collect();
+
+        let start = Instant::now();
+        let one_by_one: Vec<Vec<BigUint>> = batches
+            .iter()
+            .map(|exponents| {
+                exponents
+                    .iter()
+                    .map(|exp| ZKP::exponentiate(&zkp.alpha, exp, &zkp.p))
+                    .collect()
+            })
+            .collect();
+        let modpow = start.elapsed();
+
+        let start = Instant::now();
+        let combed: Vec<Vec<BigUint>> = batches
+            .iter()
+            .map(|exponents| ZKP::exponentiate_many(&zkp.alpha, exponents, &zkp.p))
+            .collect();
+        let comb = start.elapsed();
+
+        assert_eq!(one_by_one, combed);
+        println!(
+            "[{}] {} batches of 16: modpow {:?} ({:?}/batch), exponentiate_many {:?} ({:?}/batch)",
+            group,
+            iterations,
+            modpow,
+            modpow / iterations,
+            comb,
+            comb / iterations
+        );
+    }
+}
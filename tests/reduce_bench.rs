@@ -0,0 +1,66 @@
+//! Times reducing products mod p with each `Reduction`, on their own and inside
+//! `Verifier::verify`, run with `cargo test --release -- --ignored`.
+//!
+//! `BENCH_ITERATIONS` sets the number of products and proofs (default 2000).
+
+use std::{env, time::Instant};
+
+use num_bigint::BigUint;
+use zkp_chaum_pedersen::{Group, Reducer, Reduction, Verifier, ZKP};
+
+#[test]
+#[ignore]
+fn bench_reduction() {
+    let iterations: u32 = env::var("BENCH_ITERATIONS")
+        .map(|value| value.parse().expect("invalid BENCH_ITERATIONS"))
+        .unwrap_or(2000);
+
+    let group = Group::Rfc5114_2048_256;
+    let zkp = group.zkp();
+    let products: Vec<BigUint> = (0..iterations)
+        .map(|_| ZKP::generate_random_below(&zkp.p) * ZKP::generate_random_below(&zkp.p))
+        .collect();
+
+    let x = ZKP::generate_random_below(&zkp.q);
+    let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+    let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+    let proofs: Vec<_> = (0..iterations.min(200))
+        .map(|_| {
+            let k = ZKP::generate_random_below(&zkp.q);
+            let c = ZKP::generate_random_below(&zkp.q);
+            let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+            let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+            let s = zkp.solve(&k, &c, &x);
+            (r1, r2, c, s)
+        })
+        .collect();
+
+    let mut reduced = Vec::new();
+    for reduction in [Reduction::Plain, Reduction::Barrett] {
+        let reducer = Reducer::new(&zkp.p, reduction);
+        let start = Instant::now();
+        let results: Vec<BigUint> = products.iter().map(|x| reducer.reduce(x.clone())).collect();
+        let reduce = start.elapsed();
+        reduced.push(results);
+
+        let verifier = Verifier::new(group.zkp()).with_reduction(reduction);
+        let start = Instant::now();
+        for (r1, r2, c, s) in &proofs {
+            assert_eq!(verifier.verify(r1, r2, &y1, &y2, c, s), Ok(()));
+        }
+        let verify = start.elapsed();
+
+        println!(
+            "[{}] {:?}: {} reductions {:?} ({:?}/reduction), {} verifications {:?} ({:?}/proof)",
+            group,
+            reduction,
+            iterations,
+            reduce,
+            reduce / iterations,
+            proofs.len(),
+            verify,
+            verify / proofs.len() as u32
+        );
+    }
+    assert_eq!(reduced[0], reduced[1]);
+}
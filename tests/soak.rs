@@ -0,0 +1,97 @@
+//! Long-running random prove/verify cycles, run with `cargo test --release -- --ignored`.
+//!
+//! `SOAK_ITERATIONS` sets the number of cycles per group (default 1_000_000) and
+//! `SOAK_SEED` replays a previous run, the seed is printed on any mismatch.
+
+use std::env;
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use zkp_chaum_pedersen::ZKP;
+
+fn env_or<T: std::str::FromStr>(name: &str, default: impl FnOnce() -> T) -> T {
+    env::var(name)
+        .ok()
+        .map(|value| value.parse().ok().expect("invalid soak setting"))
+        .unwrap_or_else(default)
+}
+
+/// Runs the cycles, counting how often each `solve` branch is hit
+fn soak(name: &str, zkp: &ZKP) {
+    let iterations: u64 = env_or("SOAK_ITERATIONS", || 1_000_000);
+    let seed: u64 = env_or("SOAK_SEED", || rand::thread_rng().gen());
+    println!(
+        "[{}] {} iterations with SOAK_SEED={}",
+        name, iterations, seed
+    );
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut branches = [0u64; 2];
+    for i in 0..iterations {
+        let x = rng.gen_biguint_below(&zkp.q);
+        let mut k = rng.gen_biguint_below(&zkp.q);
+        let mut c = rng.gen_biguint_below(&zkp.q);
+        // uniform values almost never take the k >= c * x branch on big groups,
+        // so every other cycle forces a tiny c or an s = 0 answer
+        match i % 4 {
+            2 => c = BigUint::from((i / 4) % 3),
+            3 => k = &c * &x % &zkp.q,
+            _ => {}
+        }
+
+        let y1 = ZKP::exponentiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponentiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponentiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponentiate(&zkp.beta, &k, &zkp.p);
+
+        branches[usize::from(k < &c * &x)] += 1;
+        let s = zkp.solve(&k, &c, &x);
+
+        let context = || {
+            format!(
+                "[{}] iteration {} failed, rerun with SOAK_SEED={} (x = {:x}, k = {:x}, c = {:x}, s = {:x})",
+                name, i, seed, x, k, c, s
+            )
+        };
+        assert!(s < zkp.q, "{}", context());
+        assert_eq!((&s + &c * &x) % &zkp.q, &k % &zkp.q, "{}", context());
+        assert_eq!(
+            zkp.verify_checked(&r1, &r2, &y1, &y2, &c, &s),
+            Ok(()),
+            "{}",
+            context()
+        );
+    }
+
+    println!(
+        "[{}] solve branches: k >= c * x hit {} times, k < c * x hit {} times",
+        name, branches[0], branches[1]
+    );
+    if iterations >= 100 {
+        assert!(
+            branches.iter().all(|&hits| hits > 0),
+            "a solve branch was never hit"
+        );
+    }
+}
+
+#[test]
+#[ignore]
+fn soak_toy_group() {
+    let zkp = ZKP::builder(
+        BigUint::from(23u32),
+        BigUint::from(11u32),
+        BigUint::from(4u32),
+        BigUint::from(9u32),
+    )
+    .allow_insecure()
+    .build()
+    .unwrap();
+    soak("toy", &zkp);
+}
+
+#[test]
+#[ignore]
+fn soak_1024_bits_group() {
+    soak("rfc5114-1024-160", &ZKP::new());
+}
@@ -0,0 +1,166 @@
+//! Compares `verify` with the formulation it replaced, which reduced each
+//! product with a modpow by 1, times `verify_cached` on a retried answer and
+//! the overhead of the constant-time `verify_ct` over `verify`.
+//! Timings with `cargo test --release -- --ignored`.
+//!
+//! `BENCH_ITERATIONS` sets the number of proofs timed per group (default 2_000).
+
+use std::{env, time::Instant};
+
+use num_bigint::BigUint;
+use zkp_chaum_pedersen::{Group, PowerCache, DEFAULT_POWER_CACHE_SIZE, ZKP};
+
+/// `verify` before dropping the modpow by 1
+fn reference_verify(zkp: &ZKP, [r1, r2, y1, y2, c, s]: &[BigUint; 6]) -> bool {
+    let one = BigUint::from(1u32);
+    let sol1 = ZKP::exponentiate(&zkp.alpha, s, &zkp.p) * ZKP::exponentiate(y1, c, &zkp.p);
+    let sol2 = ZKP::exponentiate(&zkp.beta, s, &zkp.p) * ZKP::exponentiate(y2, c, &zkp.p);
+    *r1 == ZKP::exponentiate(&sol1, &one, &zkp.p) && *r2 == ZKP::exponentiate(&sol2, &one, &zkp.p)
+}
+
+/// Honest transcripts, with every third one tampered so both outcomes are covered
+fn transcripts(zkp: &ZKP, count: usize) -> Vec<[BigUint; 6]> {
+    (0..count)
+        .map(|i| {
+            let x = ZKP::generate_random_below(&zkp.q);
+            let k = ZKP::generate_random_below(&zkp.q);
+            let c = ZKP::generate_random_below(&zkp.q);
+            let mut s = zkp.solve(&k, &c, &x);
+            if i % 3 == 0 {
+                s = (s + 1u32) % &zkp.q;
+            }
+            [
+                ZKP::exponentiate(&zkp.alpha, &k, &zkp.p),
+                ZKP::exponentiate(&zkp.beta, &k, &zkp.p),
+                ZKP::exponentiate(&zkp.alpha, &x, &zkp.p),
+                ZKP::exponentiate(&zkp.beta, &x, &zkp.p),
+                c,
+                s,
+            ]
+        })
+        .collect()
+}
+
+fn verify(zkp: &ZKP, [r1, r2, y1, y2, c, s]: &[BigUint; 6]) -> bool {
+    zkp.verify(r1, r2, y1, y2, c, s)
+}
+
+#[test]
+fn verify_matches_reference() {
+    for zkp in [ZKP::new(), Group::Rfc5114_2048_256.zkp()] {
+        for transcript in transcripts(&zkp, 60) {
+            assert_eq!(
+                verify(&zkp, &transcript),
+                reference_verify(&zkp, &transcript)
+            );
+        }
+    }
+}
+
+#[test]
+#[ignore]
+fn bench_verify() {
+    let iterations = env::var("BENCH_ITERATIONS")
+        .map(|value| value.parse().expect("invalid BENCH_ITERATIONS"))
+        .unwrap_or(2_000);
+
+    for group in [Group::Rfc5114_1024_160, Group::Rfc5114_2048_256] {
+        let zkp = group.zkp();
+        let transcripts = transcripts(&zkp, iterations);
+
+        let start = Instant::now();
+        let accepted = transcripts
+            .iter()
+            .filter(|t| reference_verify(&zkp, t))
+            .count();
+        let reference = start.elapsed();
+
+        let start = Instant::now();
+        let accepted_now = transcripts.iter().filter(|t| verify(&zkp, t)).count();
+        let current = start.elapsed();
+
+        assert_eq!(accepted, accepted_now);
+        println!(
+            "[{}] {} proofs: reference {:?} ({:?}/proof), verify {:?} ({:?}/proof)",
+            group,
+            iterations,
+            reference,
+            reference / iterations as u32,
+            current,
+            current / iterations as u32
+        );
+    }
+}
+
+#[test]
+#[ignore]
+fn bench_verify_repeated_s() {
+    let iterations = env::var("BENCH_ITERATIONS")
+        .map(|value| value.parse().expect("invalid BENCH_ITERATIONS"))
+        .unwrap_or(2_000);
+
+    for group in [Group::Rfc5114_1024_160, Group::Rfc5114_2048_256] {
+        let zkp = group.zkp();
+        // the second transcript is an honest one
+        let [r1, r2, y1, y2, c, s] = &transcripts(&zkp, 2)[1];
+        let cache = PowerCache::new(DEFAULT_POWER_CACHE_SIZE);
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            assert_eq!(zkp.verify_checked(r1, r2, y1, y2, c, s), Ok(()));
+        }
+        let uncached = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            assert_eq!(zkp.verify_cached(&cache, r1, r2, y1, y2, c, s), Ok(()));
+        }
+        let cached = start.elapsed();
+
+        println!(
+            "[{}] same s {} times: uncached {:?} ({:?}/proof), cached {:?} ({:?}/proof)",
+            group,
+            iterations,
+            uncached,
+            uncached / iterations as u32,
+            cached,
+            cached / iterations as u32
+        );
+    }
+}
+
+#[test]
+#[ignore]
+fn bench_verify_ct() {
+    let iterations = env::var("BENCH_ITERATIONS")
+        .map(|value| value.parse().expect("invalid BENCH_ITERATIONS"))
+        .unwrap_or(2_000);
+
+    for group in Group::ALL {
+        let zkp = group.zkp();
+        let transcripts = transcripts(&zkp, iterations);
+
+        let start = Instant::now();
+        let accepted = transcripts.iter().filter(|t| verify(&zkp, t)).count();
+        let short_circuit = start.elapsed();
+
+        let start = Instant::now();
+        let accepted_ct = transcripts
+            .iter()
+            .filter(|[r1, r2, y1, y2, c, s]| bool::from(zkp.verify_ct(r1, r2, y1, y2, c, s)))
+            .count();
+        let constant_time = start.elapsed();
+
+        assert_eq!(accepted, accepted_ct);
+        println!(
+            "[{}] {} proofs: verify {:?} ({:?}/proof), verify_ct {:?} ({:?}/proof), overhead {:.2}x",
+            group,
+            iterations,
+            short_circuit,
+            short_circuit / iterations as u32,
+            constant_time,
+            constant_time / iterations as u32,
+            constant_time.as_secs_f64() / short_circuit.as_secs_f64()
+        );
+    }
+}